@@ -81,6 +81,21 @@ fn evaluate_flag_simple(c: &mut Criterion) {
     });
 }
 
+/// E1 with a fresh DataLogic engine built per call, as if the evaluator did not
+/// keep one. The gap to `evaluate_flag_simple` is what reusing the engine saves.
+fn evaluate_flag_simple_fresh_engine(c: &mut Criterion) {
+    let mut evaluator = FlagEvaluator::new(ValidationMode::Permissive);
+    evaluator.update_state(BENCH_CONFIG).unwrap();
+    let context = json!({});
+
+    c.bench_function("evaluate_flag_simple_fresh_engine", |b| {
+        b.iter(|| {
+            black_box(create_evaluator());
+            evaluator.evaluate_flag(black_box("boolFlag"), black_box(context.clone()))
+        })
+    });
+}
+
 fn evaluate_flag_targeting_match(c: &mut Criterion) {
     let mut evaluator = FlagEvaluator::new(ValidationMode::Permissive);
     evaluator.update_state(BENCH_CONFIG).unwrap();
@@ -264,9 +279,39 @@ fn evaluate_flag_complex_targeting_large_ctx(c: &mut Criterion) {
     });
 }
 
+/// B1: Evaluate all flags, serializing each result separately and concatenating.
+fn evaluate_all_concat(c: &mut Criterion) {
+    let mut evaluator = FlagEvaluator::new(ValidationMode::Permissive);
+    evaluator.update_state(BENCH_CONFIG).unwrap();
+    let context = small_context();
+
+    c.bench_function("evaluate_all_concat", |b| {
+        b.iter(|| {
+            let results = evaluator.evaluate_all(black_box(context.clone()));
+            let entries: Vec<String> = results
+                .iter()
+                .map(|(key, result)| format!("{:?}:{}", key, result.to_json_string()))
+                .collect();
+            format!("{{{}}}", entries.join(","))
+        })
+    });
+}
+
+/// B2: Evaluate all flags, serializing every result into a single buffer.
+fn evaluate_all_single_buffer(c: &mut Criterion) {
+    let mut evaluator = FlagEvaluator::new(ValidationMode::Permissive);
+    evaluator.update_state(BENCH_CONFIG).unwrap();
+    let context = small_context();
+
+    c.bench_function("evaluate_all_single_buffer", |b| {
+        b.iter(|| evaluator.evaluate_all_json(black_box(context.clone())))
+    });
+}
+
 criterion_group!(
     benches,
     evaluate_flag_simple,
+    evaluate_flag_simple_fresh_engine,
     evaluate_flag_targeting_match,
     evaluate_flag_targeting_no_match,
     evaluate_flag_complex_targeting,
@@ -281,5 +326,8 @@ criterion_group!(
     evaluate_flag_targeting_large_ctx,
     evaluate_flag_complex_targeting_small_ctx,
     evaluate_flag_complex_targeting_large_ctx,
+    // Batch evaluation
+    evaluate_all_concat,
+    evaluate_all_single_buffer,
 );
 criterion_main!(benches);
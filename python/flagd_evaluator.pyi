@@ -48,7 +48,8 @@ class FlagEvaluator:
             config: Flag configuration in flagd format
 
         Returns:
-            Update response with success status
+            Update response with success status and changed flags; on success
+            also the ``added``, ``removed`` and ``mutated`` flag keys
 
         Raises:
             ValueError: If configuration is invalid
@@ -72,6 +73,23 @@ class FlagEvaluator:
         """
         ...
 
+    def get_flag_metadata(self, flag_key: str) -> Dict[str, Any]:
+        """
+        Get the merged flag-set and flag metadata for a flag.
+
+        Flag-level metadata takes priority over flag-set metadata.
+
+        Args:
+            flag_key: The flag key to look up
+
+        Returns:
+            The merged metadata (empty if the flag has none)
+
+        Raises:
+            KeyError: If flag is not found
+        """
+        ...
+
     def evaluate_bool(
         self,
         flag_key: str,
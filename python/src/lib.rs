@@ -2,7 +2,7 @@
 // which clippy flags as "useless conversion" when used with the ? operator.
 #![allow(clippy::useless_conversion)]
 
-use ::flagd_evaluator::{EvaluationResult, ValidationMode};
+use ::flagd_evaluator::{diff_parsing_results, EvaluationResult, ValidationMode};
 use pyo3::prelude::*;
 use pyo3::types::PyDict;
 use serde_json::{Map, Value};
@@ -157,7 +157,8 @@ impl FlagEvaluator {
     ///
     /// Returns:
     ///     dict: Update response with changed flag keys, pre-evaluated results,
-    ///           required context keys, and flag indices
+    ///           required context keys, and flag indices. On success it also
+    ///           contains the `added`, `removed` and `mutated` flag keys.
     fn update_state(&mut self, py: Python, config: &Bound<'_, PyDict>) -> PyResult<PyObject> {
         // Convert Python dict to JSON Value
         let config_value: Value = pythonize::depythonize(config.as_any())?;
@@ -170,6 +171,9 @@ impl FlagEvaluator {
             ))
         })?;
 
+        // Keep the previous state to report what the update added, removed and mutated
+        let previous = self.inner.get_state().cloned();
+
         // Delegate to the Rust FlagEvaluator
         let response = self.inner.update_state(&config_str).map_err(|e| {
             PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
@@ -194,14 +198,22 @@ impl FlagEvaluator {
         self.flag_indices = response.flag_indices.as_ref().cloned().unwrap_or_default();
 
         // Convert response to Python dict
-        pythonize::pythonize(py, &response)
-            .map(|bound| bound.unbind())
-            .map_err(|e| {
-                PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
-                    "Failed to convert response: {}",
-                    e
-                ))
-            })
+        let dict = pythonize::pythonize(py, &response).map_err(|e| {
+            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                "Failed to convert response: {}",
+                e
+            ))
+        })?;
+
+        if let (true, Some(state)) = (response.success, self.inner.get_state()) {
+            let diff = diff_parsing_results(previous.as_ref(), state);
+            let dict = dict.downcast::<PyDict>()?;
+            dict.set_item("added", diff.added)?;
+            dict.set_item("removed", diff.removed)?;
+            dict.set_item("mutated", diff.mutated)?;
+        }
+
+        Ok(dict.unbind())
     }
 
     /// Evaluate a feature flag
@@ -237,6 +249,35 @@ impl FlagEvaluator {
             })
     }
 
+    /// Get the merged metadata for a flag
+    ///
+    /// Flag-set metadata is merged with flag-level metadata, with flag-level
+    /// metadata taking priority. Internal (`$`-prefixed) flag-set keys are omitted.
+    ///
+    /// Args:
+    ///     flag_key (str): The flag key to look up
+    ///
+    /// Returns:
+    ///     dict: The merged metadata (empty if the flag has none)
+    ///
+    /// Raises:
+    ///     KeyError: If the flag is not found in the current state
+    fn get_flag_metadata(&self, py: Python, flag_key: String) -> PyResult<PyObject> {
+        let metadata = self
+            .inner
+            .get_flag_metadata(&flag_key)
+            .ok_or_else(|| PyErr::new::<pyo3::exceptions::PyKeyError, _>(flag_key.clone()))?;
+
+        pythonize::pythonize(py, &metadata)
+            .map(|bound| bound.unbind())
+            .map_err(|e| {
+                PyErr::new::<pyo3::exceptions::PyValueError, _>(format!(
+                    "Failed to convert metadata: {}",
+                    e
+                ))
+            })
+    }
+
     /// Evaluate a boolean flag
     ///
     /// Args:
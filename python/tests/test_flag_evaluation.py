@@ -82,6 +82,35 @@ def test_flag_with_metadata():
     assert "flagMetadata" in result or "flag_metadata" in result
 
 
+def test_get_flag_metadata_flag_level_wins():
+    """Test that flag-level metadata overrides conflicting flag-set metadata."""
+    from flagd_evaluator import FlagEvaluator
+
+    evaluator = FlagEvaluator()
+    evaluator.update_state({
+        "metadata": {
+            "owner": "flagset-owner",
+            "environment": "production"
+        },
+        "flags": {
+            "metadataFlag": {
+                "state": "ENABLED",
+                "variants": {"on": True, "off": False},
+                "defaultVariant": "on",
+                "metadata": {
+                    "owner": "flag-owner"
+                }
+            }
+        }
+    })
+
+    metadata = evaluator.get_flag_metadata("metadataFlag")
+    assert metadata == {"owner": "flag-owner", "environment": "production"}
+
+    with pytest.raises(KeyError):
+        evaluator.get_flag_metadata("missingFlag")
+
+
 def test_multiple_flags():
     """Test evaluating multiple different flags."""
     from flagd_evaluator import FlagEvaluator
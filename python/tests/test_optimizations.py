@@ -498,3 +498,27 @@ def test_targeting_key_defaults_to_empty_in_filtered_context():
     # With targetingKey -> "present"
     result_with_tk = evaluator.evaluate("tkFlag", {"targetingKey": "user-1"})
     assert result_with_tk["value"] == "has-tk"
+
+
+def test_update_state_reports_diff():
+    """update_state should report added, removed and mutated flags."""
+    from flagd_evaluator import FlagEvaluator
+
+    def flag(default):
+        return {
+            "state": "ENABLED",
+            "variants": {"on": True, "off": False},
+            "defaultVariant": default,
+        }
+
+    evaluator = FlagEvaluator()
+    first = evaluator.update_state({"flags": {"a": flag("on"), "b": flag("on")}})
+    assert first["added"] == ["a", "b"]
+    assert first["removed"] == []
+    assert first["mutated"] == []
+
+    second = evaluator.update_state({"flags": {"a": flag("off"), "c": flag("on")}})
+    assert second["added"] == ["c"]
+    assert second["removed"] == ["b"]
+    assert second["mutated"] == ["a"]
+    assert second["changedFlags"] == ["a", "b", "c"]
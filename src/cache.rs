@@ -0,0 +1,303 @@
+//! Bounded LRU cache for flag evaluation results.
+//!
+//! This module provides an optional in-memory cache used by `FlagEvaluator` to
+//! short-circuit repeated evaluations of the same flag with the same context.
+//! Entries are keyed by flag key, canonicalized context, and state version, so a
+//! call to `update_state` implicitly invalidates every cached result.
+
+use crate::types::EvaluationResult;
+use serde::{Deserialize, Serialize};
+use serde_json::{Number, Value};
+use std::collections::{BTreeMap, HashMap};
+
+/// Largest integer magnitude an f64 can represent exactly (2^53).
+const MAX_EXACT_F64_INT: f64 = 9_007_199_254_740_992.0;
+
+/// Produces a canonical JSON serialization of a context value.
+///
+/// Two semantically equal values always produce the same string:
+/// - Object keys are emitted in sorted order at every nesting level
+/// - Integral floats are normalized to integers (`1.0` becomes `1`, `-0.0` becomes `0`)
+/// - Array order is preserved (arrays are ordered by definition)
+///
+/// The output is valid JSON and is suitable for hashing, caching, and deduplication.
+///
+/// # Example
+///
+/// ```
+/// use flagd_evaluator::cache::canonicalize_context;
+/// use serde_json::json;
+///
+/// let a = canonicalize_context(&json!({"b": 1.0, "a": [true, null]}));
+/// let b = canonicalize_context(&json!({"a": [true, null], "b": 1}));
+/// assert_eq!(a, b);
+/// assert_eq!(a, r#"{"a":[true,null],"b":1}"#);
+/// ```
+pub fn canonicalize_context(value: &Value) -> String {
+    let mut out = String::new();
+    write_canonical(value, &mut out);
+    out
+}
+
+/// Recursively appends the canonical form of `value` to `out`.
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => out.push_str(&canonical_number(n)),
+        Value::String(s) => out.push_str(&serde_json::to_string(s).unwrap_or_default()),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::to_string(key).unwrap_or_default());
+                out.push(':');
+                write_canonical(&map[key], out);
+            }
+            out.push('}');
+        }
+    }
+}
+
+/// Normalizes a JSON number so that equal numeric values serialize identically.
+pub(crate) fn canonical_number(n: &Number) -> String {
+    if let Some(i) = n.as_i64() {
+        return i.to_string();
+    }
+    if let Some(u) = n.as_u64() {
+        return u.to_string();
+    }
+    match n.as_f64() {
+        Some(f) if f.fract() == 0.0 && f.abs() <= MAX_EXACT_F64_INT => (f as i64).to_string(),
+        Some(f) => f.to_string(),
+        None => n.to_string(),
+    }
+}
+
+/// Key identifying a cached evaluation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct CacheKey {
+    flag_key: String,
+    context: String,
+    state_version: u64,
+}
+
+impl CacheKey {
+    /// Creates a cache key for a flag evaluated against a (non-enriched) context.
+    pub(crate) fn new(flag_key: &str, context: &Value, state_version: u64) -> Self {
+        Self {
+            flag_key: flag_key.to_string(),
+            context: canonicalize_context(context),
+            state_version,
+        }
+    }
+}
+
+/// Hit/miss statistics for the evaluation result cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    /// Number of evaluations served from the cache
+    pub hits: u64,
+    /// Number of cacheable evaluations that had to be computed
+    pub misses: u64,
+    /// Number of results currently cached
+    pub size: usize,
+    /// Maximum number of results the cache holds (0 when disabled)
+    pub capacity: usize,
+}
+
+/// Least-recently-used cache of evaluation results.
+#[derive(Debug)]
+pub(crate) struct ResultCache {
+    capacity: usize,
+    /// Cached results with the tick of their last use
+    entries: HashMap<CacheKey, (EvaluationResult, u64)>,
+    /// Last-use tick to key, ordered oldest first
+    recency: BTreeMap<u64, CacheKey>,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl ResultCache {
+    /// Creates an empty cache holding at most `capacity` results.
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Looks up a cached result, marking it as most recently used.
+    pub(crate) fn get(&mut self, key: &CacheKey) -> Option<EvaluationResult> {
+        self.tick += 1;
+        let tick = self.tick;
+        match self.entries.get_mut(key) {
+            Some((result, last_used)) => {
+                self.recency.remove(last_used);
+                self.recency.insert(tick, key.clone());
+                *last_used = tick;
+                self.hits += 1;
+                Some(result.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Stores a result, evicting the least recently used entry if the cache is full.
+    pub(crate) fn insert(&mut self, key: CacheKey, result: EvaluationResult) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.tick += 1;
+        let tick = self.tick;
+        if let Some((_, last_used)) = self.entries.get(&key) {
+            self.recency.remove(last_used);
+        } else if self.entries.len() >= self.capacity {
+            if let Some((_, oldest)) = self.recency.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+
+        self.recency.insert(tick, key.clone());
+        self.entries.insert(key, (result, tick));
+    }
+
+    /// Removes all cached results, keeping the hit/miss counters.
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+
+    /// Returns the current cache statistics.
+    pub(crate) fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            size: self.entries.len(),
+            capacity: self.capacity,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_cache_hit_and_miss() {
+        let mut cache = ResultCache::new(2);
+        let key = CacheKey::new("flag", &json!({"a": 1}), 1);
+
+        assert!(cache.get(&key).is_none());
+        cache.insert(
+            key.clone(),
+            EvaluationResult::targeting_match(json!(true), "on".to_string()),
+        );
+        let hit = cache.get(&key).unwrap();
+        assert_eq!(hit.value, json!(true));
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.size, 1);
+    }
+
+    #[test]
+    fn test_cache_evicts_least_recently_used() {
+        let mut cache = ResultCache::new(2);
+        let a = CacheKey::new("a", &json!({}), 1);
+        let b = CacheKey::new("b", &json!({}), 1);
+        let c = CacheKey::new("c", &json!({}), 1);
+        let result = EvaluationResult::targeting_match(json!(1), "one".to_string());
+
+        cache.insert(a.clone(), result.clone());
+        cache.insert(b.clone(), result.clone());
+        // Touch "a" so "b" becomes the least recently used entry
+        assert!(cache.get(&a).is_some());
+        cache.insert(c.clone(), result);
+
+        assert!(cache.get(&a).is_some());
+        assert!(cache.get(&b).is_none());
+        assert!(cache.get(&c).is_some());
+        assert_eq!(cache.stats().size, 2);
+    }
+
+    #[test]
+    fn test_canonicalize_sorts_nested_keys() {
+        let first: Value =
+            serde_json::from_str(r#"{"user": {"tier": "gold", "age": 30}, "email": "a@b.c"}"#)
+                .unwrap();
+        let second: Value =
+            serde_json::from_str(r#"{"email": "a@b.c", "user": {"age": 30, "tier": "gold"}}"#)
+                .unwrap();
+        assert_eq!(canonicalize_context(&first), canonicalize_context(&second));
+        assert_eq!(
+            canonicalize_context(&first),
+            r#"{"email":"a@b.c","user":{"age":30,"tier":"gold"}}"#
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_normalizes_numbers() {
+        assert_eq!(
+            canonicalize_context(&json!({"n": 1.0, "m": -0.0})),
+            canonicalize_context(&json!({"m": 0, "n": 1}))
+        );
+        assert_eq!(canonicalize_context(&json!(2.5)), "2.5");
+        assert_eq!(canonicalize_context(&json!(u64::MAX)), u64::MAX.to_string());
+    }
+
+    #[test]
+    fn test_canonicalize_arrays_and_objects_in_arrays() {
+        let first = json!({"items": [{"b": 2, "a": 1}, [3.0, "x"]]});
+        let second = json!({"items": [{"a": 1, "b": 2}, [3, "x"]]});
+        assert_eq!(canonicalize_context(&first), canonicalize_context(&second));
+
+        // Array order is significant
+        let reordered = json!({"items": [[3, "x"], {"a": 1, "b": 2}]});
+        assert_ne!(
+            canonicalize_context(&first),
+            canonicalize_context(&reordered)
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_escapes_strings() {
+        let value = json!({"quote\"key": "line\nbreak"});
+        let canonical = canonicalize_context(&value);
+        let reparsed: Value = serde_json::from_str(&canonical).unwrap();
+        assert_eq!(reparsed, value);
+    }
+
+    #[test]
+    fn test_cache_key_ignores_context_key_order() {
+        let first = CacheKey::new("flag", &json!({"a": 1, "b": 2}), 1);
+        let second = CacheKey::new("flag", &json!({"b": 2, "a": 1}), 1);
+        assert_eq!(first, second);
+    }
+}
@@ -4,13 +4,33 @@
 //! and validation mode per-instance, allowing multiple independent evaluators
 //! in the same process without global state issues.
 
-use crate::model::{FeatureFlag, ParsingResult, UpdateStateResponse};
-use crate::operators::create_evaluator;
-use crate::types::{ErrorCode, EvaluationResult, ResolutionReason};
-use crate::validation::validate_flags_config;
+use crate::cache::{canonical_number, CacheKey, CacheStats, ResultCache};
+use crate::model::{
+    diff_parsing_results, encode_bitmap, remove_annotations, ChangeRecord, FeatureFlag,
+    FlagListing, FlagSetDefaults, FlagSetDiff, FlagSummary, HealthStatus, ParsingResult,
+    ShadowReport, UpdateErrorCode, UpdateStateResponse,
+};
+use crate::operators::{
+    create_evaluator, create_evaluator_with_fractional_options, record_bucket_percentile,
+    resolve_operator_aliases, without_numeric_coercion, EmptyKeyPolicy, FractionalHash,
+    FractionalMode, FractionalOptions, FractionalWeights, MatchesNamedOperator,
+    DEFAULT_OPERATOR_ALIASES, MATCHES_NAMED,
+};
+use crate::types::{
+    DefaultReason, ErrorCode, EvaluationResult, EvaluationTrace, FlagType, ResolutionReason,
+    TargetingPresence, TypedEvaluationResult, VariantCode,
+};
+use crate::validation::{
+    find_unknown_keys, is_targeting_operator, validate_flags_config, ValidationResult,
+};
 use datalogic_rs::{CompiledLogic, CompiledNode, DataLogic, OpCode};
 use serde_json::{Map, Value as JsonValue, Value};
-use std::collections::{HashMap, HashSet};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::sync::Mutex;
+
+/// Number of state updates kept by the change history unless configured otherwise.
+pub const DEFAULT_CHANGE_HISTORY_CAPACITY: usize = 32;
 
 /// Validation mode determines how validation errors are handled.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -21,6 +41,37 @@ pub enum ValidationMode {
     Permissive,
 }
 
+/// Determines the value returned when evaluating a disabled flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DisabledReturns {
+    /// Return a null value with the `FLAG_NOT_FOUND` error code (default)
+    #[default]
+    Null,
+    /// Return the value of the flag's default variant, falling back to `Null`
+    /// behavior when the flag has no usable default variant
+    DefaultVariant,
+}
+
+/// Determines how flags with a state other than `ENABLED` or `DISABLED` evaluate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnknownStatePolicy {
+    /// Fail the evaluation with a `PARSE_ERROR` naming the state (default)
+    #[default]
+    Error,
+    /// Evaluate the flag as though it were `DISABLED`
+    Disabled,
+}
+
+/// Determines the reason reported for flags without targeting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StaticReporting {
+    /// Report `STATIC` (default)
+    #[default]
+    Static,
+    /// Report `DEFAULT`, since the default variant was used
+    Default,
+}
+
 /// Instance-based flag evaluator.
 ///
 /// This struct holds flag configuration and validation mode, allowing
@@ -51,20 +102,111 @@ pub enum ValidationMode {
 /// ```
 pub struct FlagEvaluator {
     state: Option<ParsingResult>,
-    validation_mode: ValidationMode,
+    /// Everything the setters configure, restored or copied as a unit
+    options: EvaluatorOptions,
     /// The DataLogic engine with custom operators (created once, reused for all evaluations)
     logic: DataLogic,
     /// Index-to-flag-key mapping for O(1) evaluate_by_index lookups
     flag_index_map: Vec<String>,
+    /// Monotonic version of the flag state, bumped on every successful update or clear
+    state_version: u64,
+    /// The most recent state changes, oldest first
+    change_history: VecDeque<ChangeRecord>,
+    /// Maximum number of entries kept in `change_history`
+    change_history_capacity: usize,
+    /// Optional LRU cache of evaluation results (disabled when `None`)
+    result_cache: Option<Mutex<ResultCache>>,
+    /// Flags whose targeting result is fully determined by the context (safe to cache)
+    cacheable_flags: HashSet<String>,
+    /// Flags whose targeting may read `$flagd.flagMetadata` (only these get it injected)
+    metadata_flags: HashSet<String>,
+    /// The stored configuration as received, if it may be answered compactly
+    stored_config: Option<String>,
+}
+
+/// The options of a `FlagEvaluator`, i.e. everything its setters configure.
+///
+/// Kept together so that `reset_options` and `with_same_options` handle every
+/// option, including ones added later, without listing them one by one.
+#[derive(Debug, Clone)]
+struct EvaluatorOptions {
+    /// How configurations that fail validation are handled
+    validation_mode: ValidationMode,
+    /// Whether keys the schema does not define count as validation errors
+    reject_unknown_keys: bool,
+    /// Alternative operator names, rewritten to their canonical operator on load
+    operator_aliases: BTreeMap<String, String>,
+    /// Whether a targeting rule referencing a missing context variable is an error
+    strict_variables: bool,
+    /// Whether comparisons convert numeric strings to numbers
+    numeric_string_coercion: bool,
+    /// What evaluating a disabled flag returns
+    disabled_returns: DisabledReturns,
+    /// What reason flags without targeting resolve with
+    static_reporting: StaticReporting,
+    /// How flags with an unrecognized state evaluate
+    unknown_state: UnknownStatePolicy,
+    /// Options of the fractional operator, including the seed of random evaluation paths
+    fractional: FractionalOptions,
+    /// Variants selected by a boolean targeting result, as `(true, false)` variant names
+    bool_targeting_maps_to: Option<(String, String)>,
+    /// Maximum accepted configuration size in bytes (unlimited when `None`)
+    max_config_bytes: Option<usize>,
+    /// `$flagd.timestamp` used when the current time is unavailable
+    default_timestamp: u64,
+    /// Context field copied into `targetingKey` before evaluation, if configured
+    targeting_key_field: Option<String>,
+    /// JSON Pointer to the sub-object of the context that rules are evaluated against
+    context_root_pointer: Option<String>,
+    /// Whether `$`-prefixed flag-set metadata is included in results
+    expose_internal_metadata: bool,
+    /// Whether typed evaluations report the configured value when they coerce it
+    report_raw_values: bool,
+    /// Whether re-sending the stored configuration returns a compact response
+    compact_unchanged_updates: bool,
+    /// Whether `update_state` reports changed flags as a bitmap over the flag indices
+    changed_flags_bitmap: bool,
+}
+
+impl Default for EvaluatorOptions {
+    /// The options `FlagEvaluator::new` starts with, in strict validation mode.
+    fn default() -> Self {
+        Self {
+            validation_mode: ValidationMode::Strict,
+            reject_unknown_keys: false,
+            operator_aliases: DEFAULT_OPERATOR_ALIASES
+                .iter()
+                .map(|(alias, canonical)| (alias.to_string(), canonical.to_string()))
+                .collect(),
+            strict_variables: false,
+            numeric_string_coercion: true,
+            disabled_returns: DisabledReturns::default(),
+            static_reporting: StaticReporting::default(),
+            unknown_state: UnknownStatePolicy::default(),
+            fractional: FractionalOptions::default(),
+            bool_targeting_maps_to: None,
+            max_config_bytes: None,
+            default_timestamp: 0,
+            targeting_key_field: None,
+            context_root_pointer: None,
+            expose_internal_metadata: false,
+            report_raw_values: false,
+            compact_unchanged_updates: false,
+            changed_flags_bitmap: false,
+        }
+    }
 }
 
 impl std::fmt::Debug for FlagEvaluator {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("FlagEvaluator")
             .field("state", &self.state)
-            .field("validation_mode", &self.validation_mode)
+            .field("options", &self.options)
             .field("logic", &"<DataLogic>")
             .field("flag_index_map", &self.flag_index_map)
+            .field("state_version", &self.state_version)
+            .field("change_history_len", &self.change_history.len())
+            .field("cache_stats", &self.cache_stats())
             .finish()
     }
 }
@@ -78,9 +220,19 @@ impl FlagEvaluator {
     pub fn new(validation_mode: ValidationMode) -> Self {
         Self {
             state: None,
-            validation_mode,
+            options: EvaluatorOptions {
+                validation_mode,
+                ..EvaluatorOptions::default()
+            },
             logic: create_evaluator(),
             flag_index_map: Vec::new(),
+            state_version: 0,
+            change_history: VecDeque::new(),
+            change_history_capacity: DEFAULT_CHANGE_HISTORY_CAPACITY,
+            result_cache: None,
+            cacheable_flags: HashSet::new(),
+            metadata_flags: HashSet::new(),
+            stored_config: None,
         }
     }
 
@@ -104,67 +256,128 @@ impl FlagEvaluator {
     /// * `Ok(UpdateStateResponse)` - If successful, with changed flag keys
     /// * `Err(String)` - If there was an error
     pub fn update_state(&mut self, json_config: &str) -> Result<UpdateStateResponse, String> {
+        // Reject oversized configurations before doing any parsing work
+        if let Err(e) = self.check_config_size(json_config.len()) {
+            return Ok(UpdateStateResponse::failure(
+                UpdateErrorCode::ConfigTooLarge,
+                e,
+            ));
+        }
+
+        // An identical configuration changes nothing, so skip straight to the answer
+        if self.options.compact_unchanged_updates
+            && self.state.is_some()
+            && self.stored_config.as_deref() == Some(json_config)
+        {
+            return Ok(UpdateStateResponse::unchanged());
+        }
+        let received_config = self
+            .options
+            .compact_unchanged_updates
+            .then(|| json_config.to_string());
+
+        // Operator aliases are spelled canonically before anything reads the rules
+        let resolved = self.resolve_config_aliases(json_config);
+        let json_config: &str = &resolved;
+
         // Validate the configuration
-        let validation_result = validate_flags_config(json_config);
+        let validation_result = self.validate_config(json_config);
 
-        match self.validation_mode {
+        match self.options.validation_mode {
             ValidationMode::Strict => {
                 if let Err(validation_error) = validation_result {
-                    return Ok(UpdateStateResponse {
-                        success: false,
-                        error: Some(validation_error.to_json_string()),
-                        changed_flags: None,
-                        pre_evaluated: None,
-                        required_context_keys: None,
-                        flag_indices: None,
-                    });
+                    return Ok(UpdateStateResponse::failure(
+                        classify_config_error(json_config, UpdateErrorCode::SchemaInvalid),
+                        validation_error.to_json_string(),
+                    ));
                 }
             }
             ValidationMode::Permissive => {
                 if let Err(validation_error) = validation_result {
-                    eprintln!(
+                    crate::platform::warn(&format!(
                         "Warning: Configuration has validation errors: {}",
                         validation_error.to_json_string()
-                    );
+                    ));
                 }
             }
         }
 
         // Parse the configuration
-        let new_parsing_result = match ParsingResult::parse(json_config) {
+        let mut new_parsing_result = match ParsingResult::parse(json_config) {
             Ok(result) => result,
             Err(e) => {
-                return Ok(UpdateStateResponse {
-                    success: false,
-                    error: Some(e),
-                    changed_flags: None,
-                    pre_evaluated: None,
-                    required_context_keys: None,
-                    flag_indices: None,
-                });
+                return Ok(UpdateStateResponse::failure(
+                    classify_config_error(json_config, UpdateErrorCode::SemanticInvalid),
+                    e,
+                ));
             }
         };
 
+        if !self.options.numeric_string_coercion {
+            self.compile_targeting(&mut new_parsing_result);
+        }
+
         // Detect changed flags
-        let changed_flags = self.detect_changed_flags(&new_parsing_result);
+        let diff = diff_parsing_results(self.state.as_ref(), &new_parsing_result);
 
         // Pre-evaluate static and disabled flags (no targeting rules needed)
         let pre_evaluated = self.pre_evaluate_static_flags(&new_parsing_result);
 
         // Build required_context_keys and flag_indices for targeting flags
         let (required_context_keys, flag_indices, index_to_key) =
-            Self::build_optimization_maps(&new_parsing_result);
+            self.build_optimization_maps(&new_parsing_result);
 
         // Store the index-to-key mapping for evaluate_by_index lookups
         self.flag_index_map = index_to_key;
 
+        // Report the change set as a key list, or as a bitmap over the flag indices
+        let (changed_flags, changed_flags_bitmap, removed_flags) =
+            if self.options.changed_flags_bitmap {
+                let changed = diff
+                    .added
+                    .iter()
+                    .chain(&diff.mutated)
+                    .filter_map(|key| flag_indices.get(key).copied());
+                (
+                    None,
+                    Some(encode_bitmap(changed, flag_indices.len())),
+                    Some(diff.removed.clone()),
+                )
+            } else {
+                (Some(diff.changed_flags()), None, None)
+            };
+
+        // Determine which flags can be served from the result cache
+        self.cacheable_flags = new_parsing_result
+            .flags
+            .iter()
+            .filter(|(_, flag)| flag.compiled_targeting.is_some())
+            // Prerequisites may read more than the flag's own targeting
+            .filter(|(_, flag)| flag.prerequisites.is_empty())
+            .filter(|(_, flag)| {
+                flag.targeting
+                    .as_ref()
+                    .is_some_and(is_context_deterministic)
+            })
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        self.metadata_flags = self.metadata_flags_of(&new_parsing_result);
+
         // Store the new state
         self.state = Some(new_parsing_result);
+        self.register_patterns();
+        self.bump_state_version();
+        self.record_change(diff);
+        self.stored_config = received_config;
 
         Ok(UpdateStateResponse {
             success: true,
             error: None,
-            changed_flags: Some(changed_flags),
+            error_code: None,
+            changed_flags,
+            changed_flags_bitmap,
+            removed_flags,
             pre_evaluated: if pre_evaluated.is_empty() {
                 None
             } else {
@@ -180,241 +393,2000 @@ impl FlagEvaluator {
             } else {
                 Some(flag_indices)
             },
+            changed: None,
         })
     }
 
+    /// Updates the flag state from several configuration fragments.
+    ///
+    /// Large deployments may split their flags across files. The fragments are
+    /// merged into one configuration, which is then validated, stored and diffed
+    /// against the current state exactly like a single `update_state` call.
+    ///
+    /// A flag key may only be defined by one fragment. `$evaluators`, `patterns`,
+    /// flag-set `metadata` and `defaults` entries may repeat across fragments only
+    /// with identical values. Any conflict fails the update with `SEMANTIC_INVALID` and
+    /// leaves the current state unchanged. Other top-level keys such as `$schema`
+    /// are taken from the first fragment that has them.
+    pub fn update_state_multi(&mut self, configs: &[&str]) -> Result<UpdateStateResponse, String> {
+        let total_len = configs.iter().map(|config| config.len()).sum();
+        if let Err(e) = self.check_config_size(total_len) {
+            return Ok(UpdateStateResponse::failure(
+                UpdateErrorCode::ConfigTooLarge,
+                e,
+            ));
+        }
+
+        match merge_config_fragments(configs) {
+            Ok(merged) => self.update_state(&merged.to_string()),
+            Err((error_code, message)) => Ok(UpdateStateResponse::failure(error_code, message)),
+        }
+    }
+
     /// Gets a reference to the current flag state.
     pub fn get_state(&self) -> Option<&ParsingResult> {
         self.state.as_ref()
     }
 
+    /// Returns the stored configuration in the normalized form the evaluator uses.
+    ///
+    /// Unlike the configuration passed to `update_state`, `$ref`s are inlined (so
+    /// `$evaluators` is omitted), `$`-prefixed annotations are dropped, and targeting
+    /// shorthands are spelled out: a fractional bucket `["name"]` becomes
+    /// `["name", 1]` and `{"var": ["path"]}` becomes `{"var": "path"}`. Every flag
+    /// lists its `metadata`, and flag-set `metadata`, `defaults` and `patterns` are
+    /// included when set. Keys are sorted, so two effective configurations can be
+    /// diffed directly. Returns `null` if no state is loaded.
+    pub fn effective_config(&self) -> Value {
+        let Some(state) = &self.state else {
+            return Value::Null;
+        };
+
+        let flags: Map<String, Value> = state
+            .flags
+            .iter()
+            .map(|(key, flag)| {
+                let flag = FeatureFlag {
+                    key: None,
+                    targeting: flag.targeting.as_ref().map(normalize_targeting),
+                    compiled_targeting: None,
+                    ..flag.clone()
+                };
+                let flag = serde_json::to_value(&flag).unwrap_or(Value::Null);
+                (key.clone(), flag)
+            })
+            .collect();
+
+        let mut config = Map::new();
+        config.insert("flags".to_string(), Value::Object(flags));
+        if state.disabled {
+            config.insert("state".to_string(), Value::String("DISABLED".to_string()));
+        }
+        if !state.flag_set_metadata.is_empty() {
+            let metadata = state.flag_set_metadata.clone().into_iter().collect();
+            config.insert("metadata".to_string(), Value::Object(metadata));
+        }
+        if state.flag_set_defaults != FlagSetDefaults::default() {
+            let defaults = serde_json::to_value(&state.flag_set_defaults).unwrap_or(Value::Null);
+            config.insert("defaults".to_string(), defaults);
+        }
+        if !state.patterns.is_empty() {
+            let patterns = state
+                .patterns
+                .iter()
+                .map(|(name, regex)| (name.clone(), Value::String(regex.as_str().to_string())))
+                .collect();
+            config.insert("patterns".to_string(), Value::Object(patterns));
+        }
+        Value::Object(config)
+    }
+
+    /// Lists every stored flag with its type, variants, and targeting presence.
+    ///
+    /// No flag is evaluated, so this is cheap enough for admin UIs to call on every
+    /// refresh. Flags are ordered by key. Returns an empty list if no state is loaded.
+    pub fn inventory(&self) -> Vec<FlagSummary> {
+        let Some(state) = &self.state else {
+            return Vec::new();
+        };
+
+        self.flag_index_map
+            .iter()
+            .filter_map(|key| state.flags.get(key).map(|flag| FlagSummary::new(key, flag)))
+            .collect()
+    }
+
+    /// Lists every stored flag with its state, default variant, and type.
+    ///
+    /// The type is that of the default variant's value, named as in type mismatch
+    /// errors. Lighter than `inventory` for hosts that only need to know which flags
+    /// exist. Flags are ordered by key. Returns an empty list if no state is loaded.
+    pub fn list_flags(&self) -> Vec<FlagListing> {
+        let Some(state) = &self.state else {
+            return Vec::new();
+        };
+
+        self.flag_index_map
+            .iter()
+            .filter_map(|key| {
+                let flag = state.flags.get(key)?;
+                let flag_type = flag
+                    .default_variant
+                    .as_ref()
+                    .and_then(|variant| flag.variants.get(variant))
+                    .map(|value| Self::type_name(value).to_string());
+                Some(FlagListing {
+                    key: key.clone(),
+                    state: flag.state.clone(),
+                    default_variant: flag.default_variant.clone(),
+                    flag_type,
+                })
+            })
+            .collect()
+    }
+
+    /// Lists the flags whose targeting references a context attribute.
+    ///
+    /// Each flag's targeting, with `$ref`s expanded, is scanned for `var` references.
+    /// A reference matches if it names the attribute, a path below it (`user.country`
+    /// for `user`), or an object containing it (`user` for `user.country`); a
+    /// `{"var": ""}` reference reads the whole context and matches any attribute.
+    /// Flags are ordered by key. Returns an empty list if no state is loaded.
+    pub fn flags_referencing(&self, attribute: &str) -> Vec<String> {
+        let Some(state) = &self.state else {
+            return Vec::new();
+        };
+
+        self.flag_index_map
+            .iter()
+            .filter(|key| {
+                state
+                    .flags
+                    .get(*key)
+                    .and_then(|flag| flag.targeting.as_ref())
+                    .is_some_and(|targeting| references_attribute(targeting, attribute))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Lists the flags that a partial context can fully resolve.
+    ///
+    /// A flag is evaluable if every attribute its targeting reads through a literal
+    /// `var` path is present in the context (possibly as `null`), after the context
+    /// root, targeting-key field and `$flagd` enrichment are applied as during
+    /// evaluation. `var`s with a default value never block a flag. Flags without
+    /// targeting and disabled flags do not depend on the context and are always
+    /// evaluable. A flag with prerequisites is evaluable only if its prerequisites
+    /// are too. Flags are ordered by key. Returns an empty list if no state is loaded.
+    pub fn evaluable_flags(&self, context: &Value) -> Vec<String> {
+        let Some(state) = &self.state else {
+            return Vec::new();
+        };
+
+        self.flag_index_map
+            .iter()
+            .filter(|key| self.is_evaluable(key, context, state))
+            .cloned()
+            .collect()
+    }
+
+    /// Checks whether a context provides everything a flag and its prerequisites read.
+    fn is_evaluable(&self, key: &str, context: &Value, state: &ParsingResult) -> bool {
+        let Some(flag) = state.flags.get(key) else {
+            return false;
+        };
+        if state.disabled || flag.state != "ENABLED" {
+            return true;
+        }
+        // Prerequisites were checked for cycles when the configuration was loaded
+        if !flag
+            .prerequisites
+            .iter()
+            .all(|prerequisite| self.is_evaluable(&prerequisite.flag, context, state))
+        {
+            return false;
+        }
+        let targeting = match &flag.targeting {
+            Some(targeting) if has_targeting(flag) => targeting,
+            _ => return true,
+        };
+        let flag_metadata = if self.metadata_flags.contains(key) {
+            self.merge_metadata(&state.flag_set_metadata, &flag.metadata)
+        } else {
+            None
+        };
+        let context = self.with_context_root(context.clone());
+        let context = self.enrich_context(key, context, flag_metadata);
+        let context = self.with_targeting_key_field(context);
+        find_missing_variable(targeting, &context).is_none()
+    }
+
+    /// Infers the reasons a flag's evaluation can resolve with, from its structure.
+    ///
+    /// Nothing is evaluated, so reviewers can ask e.g. whether a flag can ever
+    /// resolve with TARGETING_MATCH. Disabled flags resolve with DISABLED; flags
+    /// without targeting with STATIC (or DEFAULT, see `set_static_reporting`); and
+    /// targeted flags with TARGETING_MATCH, ERROR (the rule may fail at runtime),
+    /// and DEFAULT when targeting returns `null`. Flags with prerequisites can also
+    /// resolve with PREREQUISITE_FAILED. Where the default variant would be
+    /// used but the flag has none, FALLBACK is reported instead of DEFAULT, unless a
+    /// flag-set `defaults` value applies. Reasons are listed once each, in
+    /// declaration order. Returns `None` if no state is loaded or the flag does not
+    /// exist.
+    pub fn possible_reasons(&self, flag_key: &str) -> Option<Vec<ResolutionReason>> {
+        let state = self.state.as_ref()?;
+        let flag = state.flags.get(flag_key)?;
+
+        if !state.disabled
+            && !matches!(flag.state.as_str(), "ENABLED" | "DISABLED")
+            && self.options.unknown_state == UnknownStatePolicy::Error
+        {
+            return Some(vec![ResolutionReason::Error]);
+        }
+        if self.is_disabled(state, flag) {
+            return Some(vec![ResolutionReason::Disabled]);
+        }
+
+        // What resolving to the default variant reports
+        let default_reason = match flag.default_variant.as_deref() {
+            None | Some("") => match state.flag_set_defaults.value_for(flag) {
+                Some(_) => ResolutionReason::Default,
+                None => ResolutionReason::Fallback,
+            },
+            Some(name) if !flag.variants.contains_key(name) => ResolutionReason::Error,
+            Some(_) => ResolutionReason::Default,
+        };
+
+        let mut reasons = if !has_targeting(flag) {
+            match &default_reason {
+                ResolutionReason::Default => match self.options.static_reporting {
+                    StaticReporting::Static => vec![ResolutionReason::Static],
+                    StaticReporting::Default => vec![ResolutionReason::Default],
+                },
+                reason => vec![reason.clone()],
+            }
+        } else {
+            let mut reasons = Vec::new();
+            if default_reason == ResolutionReason::Default {
+                reasons.push(ResolutionReason::Default);
+            }
+            reasons.push(ResolutionReason::TargetingMatch);
+            reasons.push(ResolutionReason::Error);
+            if default_reason == ResolutionReason::Fallback {
+                reasons.push(ResolutionReason::Fallback);
+            }
+            reasons
+        };
+        if !flag.prerequisites.is_empty() {
+            match default_reason {
+                ResolutionReason::Default => reasons.push(ResolutionReason::PrerequisiteFailed),
+                reason if !reasons.contains(&reason) => reasons.push(reason),
+                _ => {}
+            }
+        }
+        Some(reasons)
+    }
+
+    /// Returns a flag's variant names sorted ascending.
+    ///
+    /// The position of a name in this list is the variant index reported by
+    /// `evaluate_variant_code`. Returns `None` if no state is loaded or the flag
+    /// does not exist.
+    pub fn variant_names(&self, flag_key: &str) -> Option<Vec<String>> {
+        let flag = self.state.as_ref()?.flags.get(flag_key)?;
+        let mut names: Vec<String> = flag.variants.keys().cloned().collect();
+        names.sort();
+        Some(names)
+    }
+
+    /// Gets the merged metadata for a flag.
+    ///
+    /// Flag-set metadata (with `$`-prefixed internal keys filtered out, unless
+    /// `set_expose_internal_metadata` is enabled) is merged
+    /// with flag-level metadata, with flag-level metadata taking priority. This
+    /// matches the metadata returned with evaluation results.
+    ///
+    /// # Returns
+    /// * `Some(metadata)` - The merged metadata (possibly empty)
+    /// * `None` - If no state is loaded or the flag does not exist
+    pub fn get_flag_metadata(&self, flag_key: &str) -> Option<HashMap<String, JsonValue>> {
+        let state = self.state.as_ref()?;
+        let flag = state.flags.get(flag_key)?;
+        Some(
+            self.merge_metadata(&state.flag_set_metadata, &flag.metadata)
+                .unwrap_or_default(),
+        )
+    }
+
     /// Gets the validation mode for this evaluator.
     pub fn validation_mode(&self) -> ValidationMode {
-        self.validation_mode
+        self.options.validation_mode
     }
 
     /// Sets the validation mode for this evaluator.
     ///
     /// This affects how subsequent `update_state` calls will handle validation errors.
     pub fn set_validation_mode(&mut self, mode: ValidationMode) {
-        self.validation_mode = mode;
+        self.options.validation_mode = mode;
+        // The stored configuration may no longer be accepted as is
+        self.stored_config = None;
     }
 
-    /// Clears the flag state.
-    pub fn clear_state(&mut self) {
-        self.state = None;
-        self.flag_index_map.clear();
+    /// Gets whether unknown configuration keys are validation errors.
+    pub fn reject_unknown_keys(&self) -> bool {
+        self.options.reject_unknown_keys
     }
 
-    // =========================================================================
-    // Core evaluation methods
-    // =========================================================================
-
-    /// Evaluates a feature flag against a context.
+    /// Treats top-level and flag-level keys the schema does not define as validation errors.
     ///
-    /// This is the main evaluation method that handles all flag types.
+    /// Disabled by default, matching the schema, which allows additional properties so
+    /// that a typo such as `defaultVarient` is silently ignored. When enabled, each
+    /// unknown key is reported with its JSON path alongside the schema errors, so
+    /// strict mode rejects the configuration and permissive mode logs a warning.
+    /// `$`-prefixed extensions are always accepted. Applies to `update_state`,
+    /// `evaluate_inline` and `validate_and_evaluate`.
+    pub fn set_reject_unknown_keys(&mut self, reject: bool) {
+        self.options.reject_unknown_keys = reject;
+        // The stored configuration may no longer be accepted as is
+        self.stored_config = None;
+    }
+
+    /// Gets the operator aliases, mapping each alias to its canonical operator.
+    pub fn operator_aliases(&self) -> &BTreeMap<String, String> {
+        &self.options.operator_aliases
+    }
+
+    /// Registers `alias` as another name for the targeting operator `canonical`.
     ///
-    /// # Arguments
-    /// * `flag_key` - The key of the flag to evaluate
-    /// * `context` - The evaluation context (JSON object)
+    /// Targeting rules (including `$evaluators`) may then use either name: aliases
+    /// are rewritten to the canonical operator when a configuration is loaded,
+    /// before validation, so aliased rules pass the schema and evaluate exactly like
+    /// the canonical operator. `DEFAULT_OPERATOR_ALIASES` (`semver`, `startsWith` and
+    /// `endsWith`) are registered initially. Applies to configurations loaded
+    /// afterwards, `evaluate_inline`, `validate_and_evaluate` and
+    /// `evaluate_rule_for_flag`.
     ///
-    /// # Returns
-    /// An EvaluationResult containing the resolved value, variant, reason, and metadata
-    pub fn evaluate_flag(&self, flag_key: &str, context: Value) -> EvaluationResult {
-        self.evaluate_with_type_check(flag_key, context, None, true)
+    /// Fails if `canonical` is not a targeting operator or `alias` already is one.
+    pub fn add_operator_alias(&mut self, alias: &str, canonical: &str) -> Result<(), String> {
+        if !is_targeting_operator(canonical) {
+            return Err(format!("Unknown operator '{}'", canonical));
+        }
+        if is_targeting_operator(alias) {
+            return Err(format!(
+                "Alias '{}' would shadow an existing operator",
+                alias
+            ));
+        }
+        self.options
+            .operator_aliases
+            .insert(alias.to_string(), canonical.to_string());
+        // The stored configuration may no longer be accepted as is
+        self.stored_config = None;
+        Ok(())
     }
 
-    /// Evaluates a boolean flag with type checking.
-    pub fn evaluate_bool(&self, flag_key: &str, context: Value) -> EvaluationResult {
-        self.evaluate_with_type_check(flag_key, context, Some(ExpectedType::Boolean), true)
+    /// Removes an operator alias, returning whether it was registered.
+    pub fn remove_operator_alias(&mut self, alias: &str) -> bool {
+        let removed = self.options.operator_aliases.remove(alias).is_some();
+        if removed {
+            self.stored_config = None;
+        }
+        removed
     }
 
-    /// Evaluates a string flag with type checking.
-    pub fn evaluate_string(&self, flag_key: &str, context: Value) -> EvaluationResult {
-        self.evaluate_with_type_check(flag_key, context, Some(ExpectedType::String), true)
+    /// Rewrites operator aliases in a configuration's targeting and `$evaluators`.
+    ///
+    /// A configuration that mentions no alias, or is not valid JSON, is returned as
+    /// is; validation and parsing then report any problem with it.
+    fn resolve_config_aliases<'a>(&self, json_config: &'a str) -> Cow<'a, str> {
+        let mentions_alias = self
+            .options
+            .operator_aliases
+            .keys()
+            .any(|alias| json_config.contains(&format!("\"{}\"", alias)));
+        if !mentions_alias {
+            return Cow::Borrowed(json_config);
+        }
+        let Ok(mut config) = serde_json::from_str::<Value>(json_config) else {
+            return Cow::Borrowed(json_config);
+        };
+        if let Some(Value::Object(evaluators)) = config.get_mut("$evaluators") {
+            for rule in evaluators.values_mut() {
+                *rule = resolve_operator_aliases(rule, &self.options.operator_aliases);
+            }
+        }
+        if let Some(Value::Object(flags)) = config.get_mut("flags") {
+            for targeting in flags
+                .values_mut()
+                .filter_map(|flag| flag.get_mut("targeting"))
+            {
+                *targeting = resolve_operator_aliases(targeting, &self.options.operator_aliases);
+            }
+        }
+        Cow::Owned(config.to_string())
     }
 
-    /// Evaluates an integer flag with type checking.
-    pub fn evaluate_int(&self, flag_key: &str, context: Value) -> EvaluationResult {
-        self.evaluate_with_type_check(flag_key, context, Some(ExpectedType::Integer), true)
+    /// Validates a configuration, including unknown keys if they are rejected.
+    fn validate_config(&self, json_config: &str) -> Result<(), ValidationResult> {
+        let result = validate_flags_config(json_config);
+        if !self.options.reject_unknown_keys {
+            return result;
+        }
+        let unknown = find_unknown_keys(json_config);
+        match result {
+            Ok(()) if unknown.is_empty() => Ok(()),
+            Ok(()) => Err(ValidationResult::failure(unknown)),
+            Err(mut failure) => {
+                failure.errors.extend(unknown);
+                Err(failure)
+            }
+        }
     }
 
-    /// Evaluates a float flag with type checking.
-    pub fn evaluate_float(&self, flag_key: &str, context: Value) -> EvaluationResult {
-        self.evaluate_with_type_check(flag_key, context, Some(ExpectedType::Float), true)
+    /// Gets whether strict variable resolution is enabled.
+    pub fn strict_variables(&self) -> bool {
+        self.options.strict_variables
     }
 
-    /// Evaluates an object flag with type checking.
-    pub fn evaluate_object(&self, flag_key: &str, context: Value) -> EvaluationResult {
-        self.evaluate_with_type_check(flag_key, context, Some(ExpectedType::Object), true)
+    /// Enables or disables strict variable resolution.
+    ///
+    /// By default, a targeting rule referencing a context variable that is not present
+    /// silently resolves it to `null` (or an empty string in custom operators), which can
+    /// mask misconfigured rules. In strict mode, evaluating a rule that references a
+    /// missing variable returns an `ErrorCode::General` error instead.
+    ///
+    /// Variables with an explicit default (`{"var": ["path", default]}`) are never
+    /// considered missing.
+    pub fn set_strict_variables(&mut self, strict: bool) {
+        self.options.strict_variables = strict;
+        self.clear_result_cache();
     }
 
-    // =========================================================================
-    // Internal evaluation logic
-    // =========================================================================
+    /// Gets whether comparisons convert numeric strings to numbers.
+    pub fn numeric_string_coercion(&self) -> bool {
+        self.options.numeric_string_coercion
+    }
 
-    /// Internal method that handles evaluation with optional type checking.
-    fn evaluate_with_type_check(
-        &self,
-        flag_key: &str,
-        context: Value,
-        expected_type: Option<ExpectedType>,
-        needs_enrichment: bool,
-    ) -> EvaluationResult {
-        // Get flag and metadata from state - avoid cloning the flag!
-        let state = match &self.state {
-            Some(s) => s,
-            None => {
-                return EvaluationResult::error(ErrorCode::General, "No flag configuration loaded");
+    /// Enables or disables numeric string coercion in comparisons.
+    ///
+    /// Enabled by default, matching flagd: when `==`, `!=`, `<`, `<=`, `>` or `>=`
+    /// compares a string with a number, a numeric string such as `"18"` is converted
+    /// to the number, so `{"==": [{"var": "age"}, 18]}` matches `{"age": "18"}`.
+    ///
+    /// When disabled, a string and a number are never equal or ordered: `!=` is true
+    /// and the other comparisons are false, whether or not the string is numeric.
+    /// Comparisons between two numbers or two strings, and all other operators, are
+    /// unaffected. The stored configuration's targeting is recompiled accordingly.
+    pub fn set_numeric_string_coercion(&mut self, coercion: bool) {
+        self.options.numeric_string_coercion = coercion;
+        if let Some(mut state) = self.state.take() {
+            self.compile_targeting(&mut state);
+            self.state = Some(state);
+        }
+        self.clear_result_cache();
+    }
+
+    /// Recompiles every flag's targeting for the current coercion setting.
+    ///
+    /// Rules that fail to compile keep their previous compiled form.
+    fn compile_targeting(&self, parsing_result: &mut ParsingResult) {
+        for flag in parsing_result.flags.values_mut() {
+            let Some(targeting) = &flag.targeting else {
+                continue;
+            };
+            if flag.compiled_targeting.is_none() {
+                continue;
             }
-        };
+            let compiled = if self.options.numeric_string_coercion {
+                self.logic.compile(targeting)
+            } else {
+                self.logic.compile(&without_numeric_coercion(targeting))
+            };
+            if let Ok(compiled) = compiled {
+                flag.compiled_targeting = Some(compiled);
+            }
+        }
+    }
 
-        // Look up flag by reference (no clone!)
-        let flag = match state.flags.get(flag_key) {
-            Some(f) => f,
-            None => {
-                // Flag not found - return flag-set metadata per spec (best effort)
-                let flag_set_metadata =
-                    Self::merge_metadata_flag_set_only(&state.flag_set_metadata);
-                return EvaluationResult {
-                    value: JsonValue::Null,
-                    variant: None,
-                    reason: ResolutionReason::FlagNotFound,
-                    error_code: Some(ErrorCode::FlagNotFound),
-                    error_message: Some(format!("Flag '{}' not found in configuration", flag_key)),
-                    flag_metadata: flag_set_metadata,
-                };
+    /// Gets what evaluating a disabled flag returns.
+    pub fn disabled_returns(&self) -> DisabledReturns {
+        self.options.disabled_returns
+    }
+
+    /// Sets what evaluating a disabled flag returns.
+    ///
+    /// Pre-evaluated results returned by `update_state` reflect the mode at the time of
+    /// the update, so set this before loading the configuration.
+    pub fn set_disabled_returns(&mut self, disabled_returns: DisabledReturns) {
+        self.options.disabled_returns = disabled_returns;
+        self.clear_result_cache();
+    }
+
+    /// Gets what reason flags without targeting resolve with.
+    pub fn static_reporting(&self) -> StaticReporting {
+        self.options.static_reporting
+    }
+
+    /// Sets what reason flags without targeting resolve with.
+    ///
+    /// `Static` (the default) reports `STATIC`. `Default` reports `DEFAULT`, following
+    /// a strict reading of the OpenFeature specification. Pre-evaluated results
+    /// returned by `update_state` reflect the mode at the time of the update, so set
+    /// this before loading the configuration.
+    pub fn set_static_reporting(&mut self, static_reporting: StaticReporting) {
+        self.options.static_reporting = static_reporting;
+        self.clear_result_cache();
+    }
+
+    /// Gets how flags with an unrecognized state evaluate.
+    pub fn unknown_state(&self) -> UnknownStatePolicy {
+        self.options.unknown_state
+    }
+
+    /// Sets how flags with a state other than `ENABLED` or `DISABLED` evaluate.
+    ///
+    /// Such a state, e.g. a typo like `"ENABELD"`, is only accepted by permissive
+    /// validation. `Error` (the default) fails every evaluation of the flag with a
+    /// `PARSE_ERROR`; `Disabled` treats the flag as `DISABLED`. Like disabled flags,
+    /// these flags are pre-evaluated by `update_state` under the policy in effect at
+    /// the time, so set this before loading the configuration.
+    pub fn set_unknown_state(&mut self, unknown_state: UnknownStatePolicy) {
+        self.options.unknown_state = unknown_state;
+        self.clear_result_cache();
+    }
+
+    /// Whether a flag evaluates as disabled, either through the flag-set kill switch
+    /// or its own state under the unknown state policy.
+    fn is_disabled(&self, flag_set: &ParsingResult, flag: &FeatureFlag) -> bool {
+        if flag_set.disabled {
+            return true;
+        }
+        match flag.state.as_str() {
+            "ENABLED" => false,
+            "DISABLED" => true,
+            _ => self.options.unknown_state == UnknownStatePolicy::Disabled,
+        }
+    }
+
+    /// Gets how the fractional operator interprets bucket weights.
+    pub fn fractional_weights(&self) -> FractionalWeights {
+        self.options.fractional.weights
+    }
+
+    /// Sets how the fractional operator interprets bucket weights.
+    ///
+    /// In `Relative` mode (the default), weights are normalized by their total, so
+    /// `[30, 30, 30]` splits evenly. In `Percent` mode, weights must sum to exactly 100
+    /// and any other total makes the evaluation fail with a `PARSE_ERROR`.
+    pub fn set_fractional_weights(&mut self, weights: FractionalWeights) {
+        self.options.fractional.weights = weights;
+        self.rebuild_logic();
+        self.clear_result_cache();
+    }
+
+    /// Gets how the fractional operator maps bucketing keys to buckets.
+    pub fn fractional_mode(&self) -> FractionalMode {
+        self.options.fractional.mode
+    }
+
+    /// Sets how the fractional operator maps bucketing keys to buckets.
+    ///
+    /// `Linear` (the default) buckets exactly like the other flagd implementations.
+    /// `Rendezvous` keeps existing assignments stable when variants are added to or
+    /// removed from a split, but assigns keys differently than `Linear`, so switching
+    /// modes reshuffles users once.
+    pub fn set_fractional_mode(&mut self, mode: FractionalMode) {
+        self.options.fractional.mode = mode;
+        self.rebuild_logic();
+        self.clear_result_cache();
+    }
+
+    /// Gets how the fractional operator handles an empty bucketing key.
+    pub fn fractional_empty_key(&self) -> EmptyKeyPolicy {
+        self.options.fractional.empty_key
+    }
+
+    /// Sets how the fractional operator handles an empty bucketing key.
+    ///
+    /// The key is empty when the context has no `targetingKey` (or the bucketing
+    /// expression evaluates to an empty string). `Deterministic` (the default) hashes
+    /// the empty key, so all such users share one bucket. `Random` assigns them a
+    /// bucket per evaluation, which is not sticky and disables result caching for
+    /// targeted flags. `Error` fails the evaluation with reason `ERROR`.
+    pub fn set_fractional_empty_key(&mut self, policy: EmptyKeyPolicy) {
+        self.options.fractional.empty_key = policy;
+        self.rebuild_logic();
+        self.clear_result_cache();
+    }
+
+    /// Gets the hash function the fractional operator applies to bucketing keys.
+    pub fn fractional_hash(&self) -> FractionalHash {
+        self.options.fractional.hash
+    }
+
+    /// Sets the hash function the fractional operator applies to bucketing keys.
+    ///
+    /// `Murmur3_32` (the default) buckets exactly like the other flagd
+    /// implementations. The alternatives match other ecosystems (see
+    /// `FractionalHash`) but assign keys differently than flagd, so switching hashes
+    /// reshuffles users once.
+    pub fn set_fractional_hash(&mut self, hash: FractionalHash) {
+        self.options.fractional.hash = hash;
+        self.rebuild_logic();
+        self.clear_result_cache();
+    }
+
+    /// Gets the seed of the random evaluation paths, if one is set.
+    pub fn random_seed(&self) -> Option<u64> {
+        self.options.fractional.random_seed
+    }
+
+    /// Makes random evaluation paths reproducible.
+    ///
+    /// Random decisions, currently the buckets assigned under
+    /// `EmptyKeyPolicy::Random`, are drawn from a sequence determined by the seed.
+    /// Setting a seed (or changing another fractional option) restarts the
+    /// sequence, so the same seed reproduces the same decisions for the same
+    /// evaluations. `None` (the default) derives them from the current time.
+    pub fn set_random_seed(&mut self, seed: Option<u64>) {
+        self.options.fractional.random_seed = seed;
+        self.rebuild_logic();
+        self.clear_result_cache();
+    }
+
+    /// Gets the flag metadata key seeding implicit fractional bucketing keys.
+    pub fn fractional_seed_metadata(&self) -> Option<&str> {
+        self.options.fractional.seed_metadata.as_deref()
+    }
+
+    /// Seeds implicit fractional bucketing keys from a flag metadata value.
+    ///
+    /// By default (`None`), a `fractional` rule without an explicit bucketing
+    /// expression hashes the flag key followed by the targeting key. With a key such
+    /// as `"experimentId"`, a flag whose metadata (flag-level or flag-set) has an
+    /// `experimentId` string or number hashes that value instead of its flag key.
+    /// Flags sharing an experiment id then bucket users identically, and giving a
+    /// cloned flag a new id reshuffles its users. Precedence, highest first: an
+    /// explicit bucketing expression in the rule, the metadata value, the flag key.
+    pub fn set_fractional_seed_metadata(&mut self, key: Option<String>) {
+        self.options.fractional.seed_metadata = key;
+        self.rebuild_logic();
+        if let Some(state) = &self.state {
+            self.metadata_flags = self.metadata_flags_of(state);
+        }
+        self.clear_result_cache();
+    }
+
+    /// Lists the flags whose targeting can observe `$flagd.flagMetadata`.
+    ///
+    /// Only these flags pay for metadata injection.
+    fn metadata_flags_of(&self, parsing_result: &ParsingResult) -> HashSet<String> {
+        parsing_result
+            .flags
+            .iter()
+            .filter(|(_, flag)| {
+                flag.targeting
+                    .as_ref()
+                    .is_some_and(|targeting| self.reads_flag_metadata(targeting))
+            })
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// Checks whether a targeting rule can observe `$flagd.flagMetadata`.
+    ///
+    /// With a seed metadata key, the fractional operator reads the metadata too.
+    fn reads_flag_metadata(&self, targeting: &JsonValue) -> bool {
+        references_flag_metadata(targeting)
+            || (self.options.fractional.seed_metadata.is_some() && uses_fractional(targeting))
+    }
+
+    /// Recreates the JSON Logic engine with the current fractional options.
+    fn rebuild_logic(&mut self) {
+        self.logic = create_evaluator_with_fractional_options(self.options.fractional.clone());
+        self.register_patterns();
+    }
+
+    /// Registers the stored configuration's named patterns with `matches_named`.
+    fn register_patterns(&mut self) {
+        let patterns = self
+            .state
+            .as_ref()
+            .map(|state| state.patterns.clone())
+            .unwrap_or_default();
+        self.logic.add_operator(
+            MATCHES_NAMED.to_string(),
+            Box::new(MatchesNamedOperator::new(patterns)),
+        );
+    }
+
+    /// Gets the variants a boolean targeting result maps to, as `(true, false)` names.
+    pub fn bool_targeting_maps_to(&self) -> Option<(&str, &str)> {
+        self.options
+            .bool_targeting_maps_to
+            .as_ref()
+            .map(|(on, off)| (on.as_str(), off.as_str()))
+    }
+
+    /// Maps boolean targeting results to variant names.
+    ///
+    /// By default (`None`), a targeting rule returning `true` or `false` is looked up
+    /// as the variant named `"true"` or `"false"`, which is an error if no such variant
+    /// exists. With a mapping such as `("on", "off")`, `true` selects the first variant
+    /// and `false` the second, so simplified rules like `{"==": [{"var": "tier"}, "gold"]}`
+    /// can be used directly as targeting.
+    pub fn set_bool_targeting_maps_to(&mut self, mapping: Option<(String, String)>) {
+        self.options.bool_targeting_maps_to = mapping;
+        self.clear_result_cache();
+    }
+
+    /// Gets the maximum accepted configuration size in bytes (`None` means unlimited).
+    pub fn max_config_bytes(&self) -> Option<usize> {
+        self.options.max_config_bytes
+    }
+
+    /// Sets the maximum accepted configuration size in bytes.
+    ///
+    /// `update_state` rejects larger configurations before parsing them, leaving the
+    /// current state untouched. `None` (the default) means unlimited.
+    pub fn set_max_config_bytes(&mut self, max_config_bytes: Option<usize>) {
+        self.options.max_config_bytes = max_config_bytes;
+    }
+
+    /// Gets the `$flagd.timestamp` used when the current time is unavailable.
+    pub fn default_timestamp(&self) -> u64 {
+        self.options.default_timestamp
+    }
+
+    /// Sets the `$flagd.timestamp` used when the current time is unavailable.
+    ///
+    /// Without a host clock (or a clock installed via `platform::set_clock`), the
+    /// timestamp is 0 by default, so every time-based rule sees a moment before any
+    /// realistic cutoff. A fixed default such as the build time keeps such rules
+    /// predictable. `health_check` reports when the default is in use.
+    pub fn set_default_timestamp(&mut self, timestamp: u64) {
+        self.options.default_timestamp = timestamp;
+        self.clear_result_cache();
+    }
+
+    /// Gets the context field that feeds `targetingKey`, if configured.
+    pub fn targeting_key_field(&self) -> Option<&str> {
+        self.options.targeting_key_field.as_deref()
+    }
+
+    /// Sets the context field that feeds `targetingKey`.
+    ///
+    /// When the field is present in an evaluation context, its value replaces
+    /// `targetingKey`, so the fractional operator and rules reading `targetingKey`
+    /// use it. This also applies to host-enriched contexts. The field is added to
+    /// `requiredContextKeys`, so set this before loading the configuration.
+    /// `None` (the default) uses `targetingKey` as provided.
+    pub fn set_targeting_key_field(&mut self, field: Option<String>) {
+        self.options.targeting_key_field = field;
+        self.clear_result_cache();
+    }
+
+    /// Gets the JSON Pointer to the sub-object rules are evaluated against, if configured.
+    pub fn context_root_pointer(&self) -> Option<&str> {
+        self.options.context_root_pointer.as_deref()
+    }
+
+    /// Evaluates rules against a sub-object of the context addressed by a JSON Pointer.
+    ///
+    /// For contexts that nest attributes under a prefix, e.g. `{"user": {"email": ...}}`
+    /// with the pointer `/user`, rules can read `{"var": "email"}` directly. `$flagd` and
+    /// `targetingKey` are injected into the sub-object; a host-provided `$flagd` or
+    /// `targetingKey` at the top level is carried over unless the sub-object has its own.
+    /// If the pointer does not address an object, rules see an empty context.
+    ///
+    /// `requiredContextKeys` then lists the pointer's top-level field instead of the
+    /// fields read by the rules, so set this before loading the configuration.
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the pointer is valid (empty or starting with `/`)
+    /// * `Err(String)` - If the pointer is not a valid JSON Pointer
+    pub fn set_context_root_pointer(&mut self, pointer: Option<String>) -> Result<(), String> {
+        if let Some(pointer) = &pointer {
+            if !pointer.is_empty() && !pointer.starts_with('/') {
+                return Err(format!(
+                    "Invalid context root pointer '{}': must be empty or start with '/'",
+                    pointer
+                ));
+            }
+        }
+        self.options.context_root_pointer = pointer;
+        self.clear_result_cache();
+        Ok(())
+    }
+
+    /// Gets whether `$`-prefixed flag-set metadata is included in results.
+    pub fn expose_internal_metadata(&self) -> bool {
+        self.options.expose_internal_metadata
+    }
+
+    /// Includes or omits `$`-prefixed flag-set metadata (e.g. `$flagSetId`) in results.
+    ///
+    /// By default these internal keys are filtered out of `flagMetadata`, from
+    /// `get_flag_metadata`, and from `$flagd.flagMetadata` in targeting. Debugging
+    /// tools can enable this to see them. Pre-evaluated results returned by
+    /// `update_state` reflect the setting at the time of the update.
+    pub fn set_expose_internal_metadata(&mut self, expose: bool) {
+        self.options.expose_internal_metadata = expose;
+        self.clear_result_cache();
+    }
+
+    /// Gets whether typed evaluations report the configured value when they coerce it.
+    pub fn report_raw_values(&self) -> bool {
+        self.options.report_raw_values
+    }
+
+    /// Enables or disables reporting of raw values in typed evaluations.
+    ///
+    /// `evaluate_int` truncates a float variant and `evaluate_float` widens an integer
+    /// one. When enabled, such results also carry the variant as configured in
+    /// `raw_value` (`rawValue` in JSON), so callers can log what was configured
+    /// alongside what was returned. Results that were not coerced are unchanged.
+    pub fn set_report_raw_values(&mut self, report: bool) {
+        self.options.report_raw_values = report;
+        self.clear_result_cache();
+    }
+
+    /// Gets whether re-sending the stored configuration returns a compact response.
+    pub fn compact_unchanged_updates(&self) -> bool {
+        self.options.compact_unchanged_updates
+    }
+
+    /// Returns a compact response when `update_state` receives the stored configuration.
+    ///
+    /// Disabled by default. When enabled, a configuration byte-for-byte identical to
+    /// the last one stored is recognized before any parsing, and the response is
+    /// just `{"success":true,"changed":false}`: no changed flags list, pre-evaluated
+    /// results, context keys or flag indices, which the host keeps
+    /// from the previous response. Any other configuration, including one that only
+    /// differs in formatting, gets the full response. Changing an option that
+    /// affects results or the validation mode makes the next update respond in full.
+    pub fn set_compact_unchanged_updates(&mut self, enabled: bool) {
+        self.options.compact_unchanged_updates = enabled;
+        self.clear_result_cache();
+    }
+
+    /// Gets whether `update_state` reports changed flags as a bitmap.
+    pub fn changed_flags_bitmap(&self) -> bool {
+        self.options.changed_flags_bitmap
+    }
+
+    /// Reports changed flags as a bitmap over the flag indices instead of a key list.
+    ///
+    /// Disabled by default. When enabled, successful `update_state` responses omit
+    /// `changedFlags`; added and mutated flags are set in `changedFlagsBitmap`
+    /// instead, at their position in `flagIndices` (the flag keys sorted ascending),
+    /// and removed flags, which no longer have an index, are listed in
+    /// `removedFlags`. Clients keeping the same flag ordering receive a few bytes
+    /// per hundred flags rather than every changed key.
+    /// `UpdateStateResponse::changed_flag_keys` decodes either form.
+    pub fn set_changed_flags_bitmap(&mut self, enabled: bool) {
+        self.options.changed_flags_bitmap = enabled;
+    }
+
+    /// Returns the `$flagd.timestamp` for the given current time, if known.
+    pub(crate) fn resolve_timestamp(&self, now: Option<u64>) -> u64 {
+        now.unwrap_or(self.options.default_timestamp)
+    }
+
+    /// Reports the evaluator's health.
+    ///
+    /// Degraded conditions, such as a missing clock, are listed in `warnings`
+    /// rather than failing the check.
+    pub fn health_check(&self) -> HealthStatus {
+        self.health_status(crate::current_time().is_some())
+    }
+
+    /// Builds the health status given whether the current time is available.
+    pub(crate) fn health_status(&self, time_available: bool) -> HealthStatus {
+        let mut warnings = Vec::new();
+        if !time_available {
+            warnings.push(format!(
+                "Current time is unavailable; $flagd.timestamp uses the default timestamp {}",
+                self.options.default_timestamp
+            ));
+        }
+
+        HealthStatus {
+            state_loaded: self.state.is_some(),
+            state_version: self.state_version,
+            flag_count: self.flag_index_map.len(),
+            flag_set_disabled: self.state.as_ref().is_some_and(|state| state.disabled),
+            time_available,
+            warnings,
+        }
+    }
+
+    /// Checks a configuration size against the configured maximum.
+    pub(crate) fn check_config_size(&self, len: usize) -> Result<(), String> {
+        match self.options.max_config_bytes {
+            Some(max) if len > max => Err(format!(
+                "Configuration size of {} bytes exceeds the maximum of {} bytes",
+                len, max
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Clears the flag state.
+    pub fn clear_state(&mut self) {
+        let removed = std::mem::take(&mut self.flag_index_map);
+        self.state = None;
+        self.cacheable_flags.clear();
+        self.metadata_flags.clear();
+        self.bump_state_version();
+        self.record_change(FlagSetDiff {
+            removed,
+            ..FlagSetDiff::default()
+        });
+    }
+
+    /// Restores every option to its default, keeping the loaded flags.
+    ///
+    /// Everything a setter configures goes back to what `FlagEvaluator::new` with
+    /// `ValidationMode::Strict` starts with, including the result cache (disabled)
+    /// and the change history capacity. The flag state, state version and kept
+    /// change history stay as they are; use `clear_state` to drop the flags. Cached
+    /// results are discarded, and the stored targeting is recompiled for the
+    /// default numeric string coercion.
+    pub fn reset_options(&mut self) {
+        let defaults = Self::new(ValidationMode::Strict);
+        self.set_validation_mode(defaults.options.validation_mode);
+        self.options = defaults.options;
+        self.set_change_history_capacity(defaults.change_history_capacity);
+        self.result_cache = defaults.result_cache;
+
+        self.rebuild_logic();
+        if let Some(state) = &self.state {
+            self.metadata_flags = self.metadata_flags_of(state);
+        }
+        // Recompiles the stored targeting and clears what is left of the cache
+        self.set_numeric_string_coercion(self.options.numeric_string_coercion);
+    }
+
+    /// Returns the most recent state changes, oldest first.
+    ///
+    /// Every successful `update_state` and every `clear_state` is recorded with the
+    /// flags it added, removed, and mutated. Only the last `change_history_capacity`
+    /// entries are kept.
+    ///
+    /// # Arguments
+    /// * `limit` - Maximum number of entries to return; 0 returns all kept entries
+    pub fn change_history(&self, limit: usize) -> Vec<ChangeRecord> {
+        let skip = match limit {
+            0 => 0,
+            n => self.change_history.len().saturating_sub(n),
+        };
+        self.change_history.iter().skip(skip).cloned().collect()
+    }
+
+    /// Gets the maximum number of entries kept in the change history.
+    pub fn change_history_capacity(&self) -> usize {
+        self.change_history_capacity
+    }
+
+    /// Sets the maximum number of entries kept in the change history.
+    ///
+    /// Older entries beyond the new capacity are dropped; 0 disables the history.
+    pub fn set_change_history_capacity(&mut self, capacity: usize) {
+        self.change_history_capacity = capacity;
+        while self.change_history.len() > capacity {
+            self.change_history.pop_front();
+        }
+    }
+
+    /// Gets the current state version.
+    ///
+    /// The version starts at 0 and is incremented by every successful `update_state`
+    /// and every `clear_state`.
+    pub fn state_version(&self) -> u64 {
+        self.state_version
+    }
+
+    /// Enables, resizes, or disables the evaluation result cache.
+    ///
+    /// When enabled, results of flags whose targeting is fully determined by the
+    /// context are cached per (flag key, context, state version). Flags whose rules
+    /// read `$flagd.timestamp` or the entire context are never cached, and neither
+    /// are evaluations with a pre-enriched context.
+    ///
+    /// # Arguments
+    /// * `capacity` - Maximum number of cached results; 0 disables the cache
+    pub fn set_result_cache_capacity(&mut self, capacity: usize) {
+        self.result_cache = if capacity == 0 {
+            None
+        } else {
+            Some(Mutex::new(ResultCache::new(capacity)))
+        };
+    }
+
+    /// Gets the result cache statistics (all zeros when the cache is disabled).
+    pub fn cache_stats(&self) -> CacheStats {
+        self.result_cache
+            .as_ref()
+            .and_then(|cache| cache.lock().ok().map(|c| c.stats()))
+            .unwrap_or_default()
+    }
+
+    /// Increments the state version and drops all cached results.
+    fn bump_state_version(&mut self) {
+        self.state_version += 1;
+        self.clear_result_cache();
+    }
+
+    /// Drops all cached results (e.g. after an option that affects results changed).
+    fn clear_result_cache(&mut self) {
+        if let Some(cache) = &self.result_cache {
+            if let Ok(mut cache) = cache.lock() {
+                cache.clear();
+            }
+        }
+        // The response to the stored configuration may differ now, so send it in full
+        self.stored_config = None;
+    }
+
+    // =========================================================================
+    // Core evaluation methods
+    // =========================================================================
+
+    /// Evaluates a feature flag against a context.
+    ///
+    /// This is the main evaluation method that handles all flag types.
+    ///
+    /// # Arguments
+    /// * `flag_key` - The key of the flag to evaluate
+    /// * `context` - The evaluation context (JSON object)
+    ///
+    /// # Returns
+    /// An EvaluationResult containing the resolved value, variant, reason, and metadata
+    pub fn evaluate_flag(&self, flag_key: &str, context: Value) -> EvaluationResult {
+        self.evaluate_with_type_check(flag_key, context, None, true)
+    }
+
+    /// Evaluates a flag as though it were enabled, to preview a disabled flag.
+    ///
+    /// A DISABLED flag's targeting and default variant are evaluated exactly as they
+    /// would be once the flag is ENABLED, and a resolved result has reason PREVIEW so
+    /// it is never mistaken for a live decision. Errors and FALLBACK are returned
+    /// as-is. Enabled and missing flags evaluate as with `evaluate_flag`, and so do
+    /// all flags while the flag-set kill switch is on. Preview results are never
+    /// cached.
+    pub fn evaluate_preview(&self, flag_key: &str, context: Value) -> EvaluationResult {
+        let found = self
+            .state
+            .as_ref()
+            .and_then(|state| state.flags.get(flag_key).map(|flag| (state, flag)));
+        let (state, flag) = match found {
+            Some((state, flag)) if !state.disabled && self.is_disabled(state, flag) => {
+                (state, flag)
+            }
+            _ => return self.evaluate_flag(flag_key, context),
+        };
+
+        let enabled = FeatureFlag {
+            state: "ENABLED".to_string(),
+            ..flag.clone()
+        };
+        let mut result =
+            self.evaluate_flag_core(&enabled, flag_key, context, true, state, &mut None);
+        if matches!(
+            result.reason,
+            ResolutionReason::Static | ResolutionReason::Default | ResolutionReason::TargetingMatch
+        ) {
+            result.reason = ResolutionReason::Preview;
+        }
+        result
+    }
+
+    /// Evaluates a feature flag against several contexts merged in priority order.
+    ///
+    /// Follows OpenFeature context merging: attributes of later contexts override
+    /// those of earlier ones (e.g. pass global, client, then invocation context).
+    /// The merge is shallow, so a nested object in a later context replaces the
+    /// whole object rather than being combined with it. Contexts that are not JSON
+    /// objects are ignored. The merged context is then evaluated like
+    /// `evaluate_flag`.
+    pub fn evaluate_merged(&self, flag_key: &str, contexts: &[&Value]) -> EvaluationResult {
+        let mut merged = Map::new();
+        for context in contexts {
+            if let Value::Object(attributes) = context {
+                merged.extend(attributes.iter().map(|(k, v)| (k.clone(), v.clone())));
+            }
+        }
+        self.evaluate_flag(flag_key, Value::Object(merged))
+    }
+
+    /// Resolves a flag to its default variant without evaluating targeting.
+    ///
+    /// The context is neither enriched nor evaluated, so this is cheaper than
+    /// `evaluate_flag` with an empty context when targeting is irrelevant (e.g. to
+    /// display the configured default). Missing, disabled and targeting-less flags
+    /// resolve exactly as through `evaluate_flag`. Targeted flags and flags with
+    /// prerequisites resolve to their default variant with reason DEFAULT, or FALLBACK
+    /// if they have none.
+    pub fn resolve_default(&self, flag_key: &str) -> EvaluationResult {
+        let Some(state) = &self.state else {
+            return EvaluationResult::uninitialized();
+        };
+        let flag = match state.flags.get(flag_key) {
+            Some(flag)
+                if !state.disabled
+                    && flag.state == "ENABLED"
+                    && (has_targeting(flag) || !flag.prerequisites.is_empty()) =>
+            {
+                flag
+            }
+            // These never read the context
+            _ => return self.evaluate_with_type_check(flag_key, Value::Null, None, false),
+        };
+
+        match flag.default_variant.as_deref() {
+            None | Some("") => self.no_default_variant(flag, flag_key, state),
+            Some(default_variant) => match flag.variants.get(default_variant) {
+                Some(value) => self.with_lazy_metadata(
+                    &state.flag_set_metadata,
+                    flag,
+                    EvaluationResult::default_result(value.clone(), default_variant.to_string()),
+                ),
+                None => EvaluationResult::error(
+                    ErrorCode::General,
+                    format!(
+                        "Default variant '{}' not found in flag variants",
+                        default_variant
+                    ),
+                ),
+            },
+        }
+    }
+
+    /// Evaluates every flag in the current state against the same context.
+    ///
+    /// Results are ordered by flag key, which matches the order of `flag_indices`
+    /// in `UpdateStateResponse`. Returns an empty list if no state is loaded.
+    pub fn evaluate_all(&self, context: Value) -> Vec<(String, EvaluationResult)> {
+        self.flag_index_map
+            .iter()
+            .map(|flag_key| {
+                (
+                    flag_key.clone(),
+                    self.evaluate_flag(flag_key, context.clone()),
+                )
+            })
+            .collect()
+    }
+
+    /// Evaluates every flag and serializes the results as a single JSON object
+    /// keyed by flag key.
+    ///
+    /// This is the batch counterpart of `evaluate_all` for hosts that need the
+    /// results as JSON (e.g. across the WASM boundary). See `results_to_json`.
+    pub fn evaluate_all_json(&self, context: Value) -> String {
+        results_to_json(&self.evaluate_all(context))
+    }
+
+    /// Lists the flags added, removed, or mutated after the given state version.
+    ///
+    /// Keys are sorted and deduplicated across updates. Returns `None` if the change
+    /// history no longer reaches back to `version`, or if `version` is newer than the
+    /// current state version, since the changes cannot be determined then.
+    pub fn flags_changed_since(&self, version: u64) -> Option<Vec<String>> {
+        if version > self.state_version {
+            return None;
+        }
+        if version < self.state_version {
+            let oldest = self.change_history.front()?.version;
+            if oldest > version + 1 {
+                return None;
+            }
+        }
+
+        let changed: BTreeSet<&String> = self
+            .change_history
+            .iter()
+            .filter(|record| record.version > version)
+            .flat_map(|record| {
+                record
+                    .added
+                    .iter()
+                    .chain(&record.removed)
+                    .chain(&record.mutated)
+            })
+            .collect();
+        Some(changed.into_iter().cloned().collect())
+    }
+
+    /// Evaluates only the flags that changed after the given state version.
+    ///
+    /// Lets clients refresh just the affected flags after an update. Removed flags
+    /// are included and report `FLAG_NOT_FOUND`, so clients know to drop them. If the
+    /// changes since `version` are unknown (see `flags_changed_since`), every flag is
+    /// evaluated like `evaluate_all`. Results are ordered by flag key.
+    pub fn evaluate_changed_since(
+        &self,
+        version: u64,
+        context: Value,
+    ) -> Vec<(String, EvaluationResult)> {
+        match self.flags_changed_since(version) {
+            Some(flag_keys) => flag_keys
+                .into_iter()
+                .map(|flag_key| {
+                    let result = self.evaluate_flag(&flag_key, context.clone());
+                    (flag_key, result)
+                })
+                .collect(),
+            None => self.evaluate_all(context),
+        }
+    }
+
+    /// Evaluates one flag against each of a sample of contexts and counts the
+    /// results by variant.
+    ///
+    /// Meant for checking a rollout (e.g. a fractional split) against a realistic
+    /// user sample. Results that carry no variant, such as errors or code-default
+    /// fallbacks, are counted under their reason (e.g. `"FALLBACK"`, `"ERROR"`).
+    pub fn evaluate_distribution(
+        &self,
+        flag_key: &str,
+        contexts: &[Value],
+    ) -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        for context in contexts {
+            let result = self.evaluate_flag(flag_key, context.clone());
+            let bucket = match result.variant {
+                Some(variant) => variant,
+                None => serde_json::to_value(&result.reason)
+                    .ok()
+                    .and_then(|reason| reason.as_str().map(str::to_string))
+                    .unwrap_or_default(),
+            };
+            *counts.entry(bucket).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Evaluates every flag as the type inferred from its variants.
+    ///
+    /// Each flag is type checked like `evaluate_bool`, `evaluate_string`, etc. for
+    /// its `FeatureFlag::flag_type`. Flags of `Mixed` type are evaluated without a
+    /// type check. Results are ordered by flag key, like `evaluate_all`.
+    pub fn evaluate_all_typed(&self, context: Value) -> Vec<(String, TypedEvaluationResult)> {
+        let Some(state) = &self.state else {
+            return Vec::new();
+        };
+
+        self.flag_index_map
+            .iter()
+            .filter_map(|flag_key| {
+                let flag_type = state.flags.get(flag_key)?.flag_type();
+                let expected = match flag_type {
+                    FlagType::Boolean => Some(ExpectedType::Boolean),
+                    FlagType::String => Some(ExpectedType::String),
+                    FlagType::Integer => Some(ExpectedType::Integer),
+                    FlagType::Float => Some(ExpectedType::Float),
+                    FlagType::Object => Some(ExpectedType::Object),
+                    FlagType::Mixed => None,
+                };
+                let result =
+                    self.evaluate_with_type_check(flag_key, context.clone(), expected, true);
+                Some((
+                    flag_key.clone(),
+                    TypedEvaluationResult { result, flag_type },
+                ))
+            })
+            .collect()
+    }
+
+    /// Evaluates every flag as its inferred type and serializes the results as a
+    /// single JSON object keyed by flag key.
+    pub fn evaluate_all_typed_json(&self, context: Value) -> String {
+        keyed_json(
+            &self.evaluate_all_typed(context),
+            TypedEvaluationResult::write_json,
+        )
+    }
+
+    /// Evaluates a flag and reports only the selected variant and reason.
+    ///
+    /// This avoids serializing the value, which is all some analytics pipelines
+    /// need. The variant is reported as its index in `variant_names`; see
+    /// `VariantCode` for the packed `u64` encoding.
+    pub fn evaluate_variant_code(&self, flag_key: &str, context: Value) -> VariantCode {
+        let result = self.evaluate_flag(flag_key, context);
+        let variant_index = result.variant.as_ref().and_then(|variant| {
+            let flag = self.state.as_ref()?.flags.get(flag_key)?;
+            flag.variants.contains_key(variant).then(|| {
+                flag.variants
+                    .keys()
+                    .filter(|name| name.as_str() < variant.as_str())
+                    .count() as u32
+            })
+        });
+        VariantCode {
+            variant_index,
+            reason: result.reason,
+            error_code: result.error_code,
+        }
+    }
+
+    /// Evaluates a flag and explains how the result was reached.
+    ///
+    /// The returned trace contains the same result as `evaluate_flag`, plus diagnostic
+    /// details such as whether the flag's targeting was absent, an empty `{}` rule, or
+    /// a real rule. Both absent and empty targeting resolve statically to the default
+    /// variant; the trace is the only place where they are reported distinctly.
+    ///
+    /// # Arguments
+    /// * `flag_key` - The key of the flag to evaluate
+    /// * `context` - The evaluation context (JSON object)
+    pub fn explain_flag(&self, flag_key: &str, context: Value) -> EvaluationTrace {
+        let found = self
+            .state
+            .as_ref()
+            .and_then(|state| state.flags.get(flag_key).map(|flag| (state, flag)));
+        let Some((state, flag)) = found else {
+            return EvaluationTrace {
+                result: self.evaluate_flag(flag_key, context),
+                targeting: None,
+                default_reason: None,
+                bucket_percentile: None,
+            };
+        };
+
+        let targeting = match &flag.targeting {
+            None => TargetingPresence::Absent,
+            Some(JsonValue::Object(map)) if map.is_empty() => TargetingPresence::Empty,
+            Some(_) => TargetingPresence::Rule,
+        };
+
+        let mut default_reason = None;
+        let (result, bucket_percentile) = record_bucket_percentile(|| {
+            self.evaluate_flag_core(flag, flag_key, context, true, state, &mut default_reason)
+        });
+
+        EvaluationTrace {
+            result,
+            targeting: Some(targeting),
+            default_reason,
+            bucket_percentile,
+        }
+    }
+
+    /// Compiles each flag's targeting in a configuration and reports the failures.
+    ///
+    /// Loading a configuration stops at the first rule that fails to compile, so CI
+    /// checks can use this to see every failing flag at once. Rules are prepared as
+    /// `update_state` would (operator aliases, `$ref`s and the numeric string
+    /// coercion setting) and compiled with this evaluator's operators. Nothing is
+    /// stored. Flags are ordered by key; flags without targeting report `None`.
+    ///
+    /// # Errors
+    /// Returns an error if the configuration is not JSON or has no `flags` object.
+    pub fn compile_report(
+        &self,
+        json_config: &str,
+    ) -> Result<Vec<(String, Option<String>)>, String> {
+        let json_config = self.resolve_config_aliases(json_config);
+        let mut config: Value = serde_json::from_str(&json_config)
+            .map_err(|e| format!("Failed to parse JSON: {}", e))?;
+        remove_annotations(&mut config);
+        let flags = config
+            .get("flags")
+            .and_then(Value::as_object)
+            .ok_or_else(|| "Missing 'flags' object in configuration".to_string())?;
+        let evaluators: BTreeMap<String, Value> = config
+            .get("$evaluators")
+            .and_then(Value::as_object)
+            .map(|evaluators| evaluators.clone().into_iter().collect())
+            .unwrap_or_default();
+
+        let mut expanded = BTreeMap::new();
+        let mut report: Vec<(String, Option<String>)> = flags
+            .iter()
+            .map(|(flag_key, flag)| {
+                let error = match flag.get("targeting") {
+                    None => None,
+                    Some(JsonValue::Object(map)) if map.is_empty() => None,
+                    Some(targeting) => {
+                        let mut visited = HashSet::new();
+                        match ParsingResult::resolve_refs(
+                            targeting,
+                            &evaluators,
+                            &mut expanded,
+                            &mut visited,
+                        ) {
+                            Err(e) => Some(e),
+                            Ok(rule) => {
+                                let compiled = if self.options.numeric_string_coercion {
+                                    self.logic.compile(&rule)
+                                } else {
+                                    self.logic.compile(&without_numeric_coercion(&rule))
+                                };
+                                compiled.err().map(|e| e.to_string())
+                            }
+                        }
+                    }
+                };
+                (flag_key.clone(), error)
+            })
+            .collect();
+        report.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(report)
+    }
+
+    /// Evaluates an arbitrary targeting rule as if it were a flag's targeting.
+    ///
+    /// The rule is run against the flag's variants, default variant, state, and
+    /// metadata, with the same context enrichment as `evaluate_flag`, so config
+    /// authors can try out a rule before attaching it to the flag. The stored
+    /// configuration is not modified and results are never cached. `$ref`
+    /// references to shared evaluators are not resolved.
+    ///
+    /// # Arguments
+    /// * `flag_key` - The key of the flag whose variants the rule selects from
+    /// * `rule` - The candidate targeting rule (JSON Logic expression)
+    /// * `context` - The evaluation context (JSON object)
+    pub fn evaluate_rule_for_flag(
+        &self,
+        flag_key: &str,
+        rule: &Value,
+        context: Value,
+    ) -> EvaluationResult {
+        let found = self
+            .state
+            .as_ref()
+            .and_then(|state| state.flags.get(flag_key).map(|flag| (state, flag)));
+        let Some((state, flag)) = found else {
+            return self.evaluate_flag(flag_key, context);
+        };
+        let rule = &resolve_operator_aliases(rule, &self.options.operator_aliases);
+
+        let compiled_targeting = match rule {
+            JsonValue::Object(map) if map.is_empty() => None,
+            _ => {
+                let compiled = if self.options.numeric_string_coercion {
+                    self.logic.compile(rule)
+                } else {
+                    self.logic.compile(&without_numeric_coercion(rule))
+                };
+                match compiled {
+                    Ok(compiled) => Some(compiled),
+                    Err(e) => {
+                        return EvaluationResult::error(
+                            ErrorCode::ParseError,
+                            format!("Failed to compile targeting rule: {}", e),
+                        )
+                    }
+                }
+            }
+        };
+        let candidate = FeatureFlag {
+            targeting: Some(rule.clone()),
+            compiled_targeting,
+            ..flag.clone()
+        };
+
+        // Metadata injection in evaluate_flag_core follows the configured targeting,
+        // so enrich here based on what the candidate rule reads
+        let flag_metadata = if self.reads_flag_metadata(rule) {
+            self.merge_metadata(&state.flag_set_metadata, &flag.metadata)
+        } else {
+            None
+        };
+        let context = self.enrich_context(flag_key, context, flag_metadata);
+
+        self.evaluate_flag_core(&candidate, flag_key, context, false, state, &mut None)
+    }
+
+    /// Evaluates a single flag definition without storing it.
+    ///
+    /// The flag is validated like a configuration containing only this flag (per the
+    /// evaluator's validation mode), then evaluated with the evaluator's options. The
+    /// stored state is neither read nor modified, so this works before any
+    /// `update_state`. An optional `"key"` string in the definition names the flag
+    /// (used for `$flagd.flagKey` and fractional bucketing); it defaults to
+    /// `INLINE_FLAG_KEY`.
+    ///
+    /// # Arguments
+    /// * `flag` - The flag definition, as it would appear under `flags`
+    /// * `context` - The evaluation context (JSON object)
+    pub fn evaluate_inline(&self, flag: &Value, context: Value) -> EvaluationResult {
+        let Some(definition) = flag.as_object() else {
+            return EvaluationResult::error(
+                ErrorCode::ParseError,
+                "Inline flag must be a JSON object",
+            );
+        };
+        let mut definition = definition.clone();
+        let flag_key = match definition.remove("key") {
+            None => INLINE_FLAG_KEY.to_string(),
+            Some(JsonValue::String(key)) => key,
+            Some(_) => {
+                return EvaluationResult::error(
+                    ErrorCode::ParseError,
+                    "Inline flag 'key' must be a string",
+                )
+            }
+        };
+
+        if let Some(targeting) = definition.get_mut("targeting") {
+            *targeting = resolve_operator_aliases(targeting, &self.options.operator_aliases);
+        }
+        let mut flags = Map::new();
+        flags.insert(flag_key.clone(), JsonValue::Object(definition));
+        let config = serde_json::json!({ "flags": flags }).to_string();
+
+        if let Err(validation_error) = self.validate_config(&config) {
+            match self.options.validation_mode {
+                ValidationMode::Strict => {
+                    return EvaluationResult::error(
+                        ErrorCode::ParseError,
+                        format!("Invalid inline flag: {}", validation_error.to_json_string()),
+                    );
+                }
+                ValidationMode::Permissive => crate::platform::warn(&format!(
+                    "Warning: Inline flag has validation errors: {}",
+                    validation_error.to_json_string()
+                )),
+            }
+        }
+
+        let flag_set = match ParsingResult::parse(&config) {
+            Ok(flag_set) => flag_set,
+            Err(e) => {
+                return EvaluationResult::error(
+                    ErrorCode::ParseError,
+                    format!("Invalid inline flag: {}", e),
+                )
+            }
+        };
+        let Some(flag) = flag_set.flags.get(&flag_key) else {
+            return EvaluationResult::flag_not_found(&flag_key);
+        };
+
+        // The inline flag is not in `metadata_flags`, so enrich here
+        let flag_metadata = match &flag.targeting {
+            Some(targeting) if self.reads_flag_metadata(targeting) => {
+                self.merge_metadata(&flag_set.flag_set_metadata, &flag.metadata)
+            }
+            _ => None,
+        };
+        let context = self.enrich_context(&flag_key, context, flag_metadata);
+
+        self.evaluate_flag_core(flag, &flag_key, context, false, &flag_set, &mut None)
+    }
+
+    /// Validates a configuration and evaluates one of its flags, without storing it.
+    ///
+    /// Returns the schema validation result and the evaluation result. In strict mode
+    /// a configuration that fails validation is not evaluated and the result is
+    /// `None`. Otherwise the configuration is loaded into a scratch evaluator with
+    /// this evaluator's options, and a configuration that still fails to load
+    /// resolves to `PARSE_ERROR`. The stored state is neither read nor modified.
+    ///
+    /// # Arguments
+    /// * `json_config` - The configuration, as passed to `update_state`
+    /// * `flag_key` - The flag to evaluate
+    /// * `context` - The evaluation context (JSON object)
+    pub fn validate_and_evaluate(
+        &self,
+        json_config: &str,
+        flag_key: &str,
+        context: Value,
+    ) -> (ValidationResult, Option<EvaluationResult>) {
+        let validation = match self.validate_config(&self.resolve_config_aliases(json_config)) {
+            Ok(()) => ValidationResult::success(),
+            Err(validation_error) => validation_error,
+        };
+        if !validation.valid && self.options.validation_mode == ValidationMode::Strict {
+            return (validation, None);
+        }
+
+        let mut scratch = self.with_same_options();
+        let result = match scratch.update_state(json_config) {
+            Ok(response) if response.success => scratch.evaluate_flag(flag_key, context),
+            Ok(response) => EvaluationResult::error(
+                ErrorCode::ParseError,
+                format!(
+                    "Invalid configuration: {}",
+                    response.error.unwrap_or_default()
+                ),
+            ),
+            Err(e) => EvaluationResult::error(
+                ErrorCode::ParseError,
+                format!("Invalid configuration: {}", e),
+            ),
+        };
+        (validation, Some(result))
+    }
+
+    /// Evaluates contexts against both the current and a candidate configuration.
+    ///
+    /// For rolling out a config change safely: every flag in either configuration
+    /// is evaluated for every context, and a decision counts as changed when the
+    /// value, variant, or error code differs (a reason change alone does not). Flags
+    /// only present in one configuration change for every context. The candidate is
+    /// validated and evaluated with this evaluator's options; the current state is
+    /// not modified.
+    ///
+    /// # Arguments
+    /// * `candidate_config` - The candidate configuration JSON
+    /// * `contexts` - The evaluation contexts to compare
+    ///
+    /// # Returns
+    /// * `Ok(ShadowReport)` - The flags whose decisions would change
+    /// * `Err(String)` - If the candidate configuration is rejected
+    pub fn shadow_eval(
+        &self,
+        candidate_config: &str,
+        contexts: &[Value],
+    ) -> Result<ShadowReport, String> {
+        let mut candidate = self.with_same_options();
+        let response = candidate.update_state(candidate_config)?;
+        if !response.success {
+            return Err(response
+                .error
+                .unwrap_or_else(|| "Candidate configuration was rejected".to_string()));
+        }
+
+        let flag_keys: BTreeSet<&String> = self
+            .flag_index_map
+            .iter()
+            .chain(&candidate.flag_index_map)
+            .collect();
+
+        let mut report = ShadowReport {
+            contexts_evaluated: contexts.len(),
+            ..ShadowReport::default()
+        };
+        for flag_key in flag_keys {
+            for (index, context) in contexts.iter().enumerate() {
+                let current = self.evaluate_flag(flag_key, context.clone());
+                let shadow = candidate.evaluate_flag(flag_key, context.clone());
+                if current.value != shadow.value
+                    || current.variant != shadow.variant
+                    || current.error_code != shadow.error_code
+                {
+                    let diff = report.flags.entry(flag_key.clone()).or_default();
+                    diff.changed += 1;
+                    diff.changed_contexts.push(index);
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// Creates an evaluator with the same options, but no state, history, or cache.
+    fn with_same_options(&self) -> Self {
+        let mut evaluator = Self::new(self.options.validation_mode);
+        evaluator.options = self.options.clone();
+        evaluator.rebuild_logic();
+        evaluator
+    }
+
+    /// Evaluates a boolean flag with type checking.
+    pub fn evaluate_bool(&self, flag_key: &str, context: Value) -> EvaluationResult {
+        self.evaluate_with_type_check(flag_key, context, Some(ExpectedType::Boolean), true)
+    }
+
+    /// Evaluates a string flag with type checking.
+    pub fn evaluate_string(&self, flag_key: &str, context: Value) -> EvaluationResult {
+        self.evaluate_with_type_check(flag_key, context, Some(ExpectedType::String), true)
+    }
+
+    /// Evaluates an integer flag with type checking.
+    pub fn evaluate_int(&self, flag_key: &str, context: Value) -> EvaluationResult {
+        self.evaluate_with_type_check(flag_key, context, Some(ExpectedType::Integer), true)
+    }
+
+    /// Evaluates a float flag with type checking.
+    pub fn evaluate_float(&self, flag_key: &str, context: Value) -> EvaluationResult {
+        self.evaluate_with_type_check(flag_key, context, Some(ExpectedType::Float), true)
+    }
+
+    /// Evaluates an object flag with type checking.
+    pub fn evaluate_object(&self, flag_key: &str, context: Value) -> EvaluationResult {
+        self.evaluate_with_type_check(flag_key, context, Some(ExpectedType::Object), true)
+    }
+
+    // =========================================================================
+    // Internal evaluation logic
+    // =========================================================================
+
+    /// Internal method that handles evaluation with optional type checking.
+    fn evaluate_with_type_check(
+        &self,
+        flag_key: &str,
+        context: Value,
+        expected_type: Option<ExpectedType>,
+        needs_enrichment: bool,
+    ) -> EvaluationResult {
+        // Get flag and metadata from state - avoid cloning the flag!
+        let state = match &self.state {
+            Some(s) => s,
+            None => {
+                return EvaluationResult::uninitialized();
+            }
+        };
+
+        // Look up flag by reference (no clone!)
+        let flag = match state.flags.get(flag_key) {
+            Some(f) => f,
+            None => {
+                // Flag not found - return flag-set metadata per spec (best effort)
+                let flag_set_metadata = self.merge_metadata_flag_set_only(&state.flag_set_metadata);
+                return EvaluationResult {
+                    value: JsonValue::Null,
+                    variant: None,
+                    reason: ResolutionReason::FlagNotFound,
+                    error_code: Some(ErrorCode::FlagNotFound),
+                    error_message: Some(format!("Flag '{}' not found in configuration", flag_key)),
+                    flag_metadata: flag_set_metadata,
+                    raw_value: None,
+                };
+            }
+        };
+
+        // Only cache evaluations that are fully determined by the caller's context
+        let cache_key = match &self.result_cache {
+            Some(_)
+                if needs_enrichment
+                    && self.cacheable_flags.contains(flag_key)
+                    && self.options.fractional.empty_key != EmptyKeyPolicy::Random =>
+            {
+                Some(CacheKey::new(flag_key, &context, self.state_version))
+            }
+            _ => None,
+        };
+        let cached = cache_key.as_ref().and_then(|key| {
+            let cache = self.result_cache.as_ref()?;
+            cache.lock().ok()?.get(key)
+        });
+
+        // Perform the evaluation
+        let result = match cached {
+            Some(result) => result,
+            None => {
+                let result = self.evaluate_flag_core(
+                    flag,
+                    flag_key,
+                    context,
+                    needs_enrichment,
+                    state,
+                    &mut None,
+                );
+                if let (Some(key), Some(cache)) = (cache_key, &self.result_cache) {
+                    if let Ok(mut cache) = cache.lock() {
+                        cache.insert(key, result.clone());
+                    }
+                }
+                result
             }
         };
 
-        // Perform the evaluation
-        let result = self.evaluate_flag_core(
-            flag,
-            flag_key,
-            context,
-            needs_enrichment,
-            &state.flag_set_metadata,
-        );
-
         // Apply type checking if requested
         match expected_type {
-            Some(expected) => self.apply_type_check(result, expected),
+            Some(expected) => self.apply_type_check(flag_key, result, expected),
             None => result,
         }
     }
 
     /// Core flag evaluation logic.
+    ///
+    /// `default_reason` is set when the result is not a targeting match, explaining why
+    /// targeting did not select a variant (used by `explain_flag`).
     fn evaluate_flag_core(
         &self,
         flag: &FeatureFlag,
         flag_key: &str,
         context: Value,
         needs_enrichment: bool,
-        flag_set_metadata: &HashMap<String, JsonValue>,
+        flag_set: &ParsingResult,
+        default_reason: &mut Option<DefaultReason>,
     ) -> EvaluationResult {
+        let flag_set_metadata = &flag_set.flag_set_metadata;
+
+        if !flag_set.disabled
+            && !matches!(flag.state.as_str(), "ENABLED" | "DISABLED")
+            && self.options.unknown_state == UnknownStatePolicy::Error
+        {
+            return EvaluationResult::error(
+                ErrorCode::ParseError,
+                format!("flag: {} has unknown state '{}'", flag_key, flag.state),
+            );
+        }
+
         // Check if flag is disabled - still return metadata per spec
-        if flag.state == "DISABLED" {
-            let merged_metadata = Self::merge_metadata(flag_set_metadata, &flag.metadata);
+        if self.is_disabled(flag_set, flag) {
+            *default_reason = Some(DefaultReason::Disabled);
+            if self.options.disabled_returns == DisabledReturns::DefaultVariant {
+                let default = flag
+                    .default_variant
+                    .as_ref()
+                    .and_then(|name| flag.variants.get(name).map(|value| (name, value)));
+                if let Some((name, value)) = default {
+                    let result = EvaluationResult::disabled(value.clone(), name.clone());
+                    return self.with_lazy_metadata(flag_set_metadata, flag, result);
+                }
+            }
             return EvaluationResult {
                 value: JsonValue::Null,
                 variant: None,
                 reason: ResolutionReason::Disabled,
                 error_code: Some(ErrorCode::FlagNotFound),
                 error_message: Some(format!("flag: {} is disabled", flag_key)),
-                flag_metadata: merged_metadata,
+                flag_metadata: self.merge_metadata(flag_set_metadata, &flag.metadata),
+                raw_value: None,
             };
         }
 
-        // Check if there's no targeting rule or if it's an empty object "{}"
-        let is_empty_targeting = match &flag.targeting {
-            None => true,
-            Some(JsonValue::Object(map)) if map.is_empty() => true,
-            _ => false,
-        };
+        // Targeting only applies once every prerequisite resolves as required
+        if !flag.prerequisites.is_empty()
+            && !self.prerequisites_met(flag, &context, needs_enrichment, flag_set)
+        {
+            *default_reason = Some(DefaultReason::PrerequisiteFailed);
+            return self.prerequisite_failed(flag, flag_key, flag_set);
+        }
 
-        if is_empty_targeting {
+        if !has_targeting(flag) {
+            *default_reason = Some(DefaultReason::NoTargeting);
             return match flag.default_variant.as_ref() {
-                None => EvaluationResult::fallback(flag_key),
-                Some(value) if value.is_empty() => EvaluationResult::fallback(flag_key),
+                None => self.no_default_variant(flag, flag_key, flag_set),
+                Some(value) if value.is_empty() => {
+                    self.no_default_variant(flag, flag_key, flag_set)
+                }
                 Some(default_variant) => match flag.variants.get(default_variant) {
                     Some(value) => {
-                        let result =
-                            EvaluationResult::static_result(value.clone(), default_variant.clone());
+                        let result = match self.options.static_reporting {
+                            StaticReporting::Static => EvaluationResult::static_result(
+                                value.clone(),
+                                default_variant.clone(),
+                            ),
+                            StaticReporting::Default => EvaluationResult::default_result(
+                                value.clone(),
+                                default_variant.clone(),
+                            ),
+                        };
                         // Lazy metadata: only merge if there's actually metadata
-                        Self::with_lazy_metadata(flag_set_metadata, &flag.metadata, result)
+                        self.with_lazy_metadata(flag_set_metadata, flag, result)
+                    }
+                    None => {
+                        *default_reason = Some(DefaultReason::VariantNotFound);
+                        EvaluationResult::error(
+                            ErrorCode::General,
+                            format!(
+                                "Default variant '{}' not found in flag variants",
+                                default_variant
+                            ),
+                        )
                     }
-                    None => EvaluationResult::error(
-                        ErrorCode::General,
-                        format!(
-                            "Default variant '{}' not found in flag variants",
-                            default_variant
-                        ),
-                    ),
                 },
             };
         }
 
         // Conditionally enrich the context
+        let flag_metadata = if self.metadata_flags.contains(flag_key) {
+            self.merge_metadata(flag_set_metadata, &flag.metadata)
+        } else {
+            None
+        };
+        let context = self.with_context_root(context);
         let eval_context = if needs_enrichment {
-            Self::enrich_context(flag_key, context)
+            self.enrich_context(flag_key, context, flag_metadata)
         } else {
-            context
+            Self::with_flag_metadata(context, flag_metadata)
         };
+        let eval_context = self.with_targeting_key_field(eval_context);
+
+        // In strict mode, a rule referencing a missing variable is an error
+        if self.options.strict_variables {
+            if let Some(missing) = flag
+                .targeting
+                .as_ref()
+                .and_then(|t| find_missing_variable(t, &eval_context))
+            {
+                return EvaluationResult::error(
+                    ErrorCode::General,
+                    format!("variable '{}' not found", missing),
+                );
+            }
+        }
 
         // Evaluate targeting using the instance's DataLogic engine
-        let eval_result = if let Some(ref compiled) = flag.compiled_targeting {
+        let eval_result = match (&flag.compiled_targeting, &flag.targeting) {
             // Fast path: use pre-compiled targeting with evaluate_owned (no JSON serialization)
-            self.logic.evaluate_owned(compiled, eval_context)
-        } else {
-            // Fallback: compile at runtime (for flags created without pre-compilation)
-            let targeting = flag.targeting.as_ref().unwrap();
-            let rule_str = targeting.to_string();
-            let context_str = eval_context.to_string();
-            self.logic.evaluate_json(&rule_str, &context_str)
+            (Some(compiled), _) => self.logic.evaluate_owned(compiled, eval_context),
+            // Fallback: compile at runtime (for flags built outside `ParsingResult::parse`)
+            (None, Some(targeting)) => {
+                let rule_str = targeting.to_string();
+                let context_str = eval_context.to_string();
+                self.logic.evaluate_json(&rule_str, &context_str)
+            }
+            (None, None) => {
+                return EvaluationResult::error(
+                    ErrorCode::General,
+                    format!("Flag '{}' has no targeting to evaluate", flag_key),
+                )
+            }
         };
 
         match eval_result {
             Ok(result) => {
                 // Check if targeting returned null - this means use default variant
                 if result.is_null() {
+                    *default_reason = Some(DefaultReason::TargetingReturnedNull);
                     return match flag.default_variant.as_ref() {
-                        None => EvaluationResult::fallback(flag_key),
-                        Some(value) if value.is_empty() => EvaluationResult::fallback(flag_key),
+                        None => self.no_default_variant(flag, flag_key, flag_set),
+                        Some(value) if value.is_empty() => {
+                            self.no_default_variant(flag, flag_key, flag_set)
+                        }
                         Some(default_variant) => match flag.variants.get(default_variant) {
                             Some(value) => {
                                 let result = EvaluationResult::default_result(
                                     value.clone(),
                                     default_variant.clone(),
                                 );
-                                Self::with_lazy_metadata(flag_set_metadata, &flag.metadata, result)
+                                self.with_lazy_metadata(flag_set_metadata, flag, result)
+                            }
+                            None => {
+                                *default_reason = Some(DefaultReason::VariantNotFound);
+                                EvaluationResult::error(
+                                    ErrorCode::General,
+                                    format!(
+                                        "Default variant '{}' not found in flag variants",
+                                        default_variant
+                                    ),
+                                )
                             }
-                            None => EvaluationResult::error(
-                                ErrorCode::General,
-                                format!(
-                                    "Default variant '{}' not found in flag variants",
-                                    default_variant
-                                ),
-                            ),
                         },
                     };
                 }
 
-                // The result should be a variant name (string)
+                // Optionally map a boolean result to the configured variants
+                let result = match (&self.options.bool_targeting_maps_to, result) {
+                    (Some((on, off)), JsonValue::Bool(b)) => {
+                        JsonValue::String(if b { on } else { off }.clone())
+                    }
+                    (_, result) => result,
+                };
+
+                // The result should be a variant name (string). Numbers and booleans can
+                // still name variants such as "1" or "true"; objects and arrays cannot.
                 // Optimization: avoid clone if result is already a String
+                let result_type = Self::type_name(&result);
                 let variant_name = match result {
                     JsonValue::String(s) => s,
-                    other => match other.as_str() {
-                        Some(s) => s.to_string(),
-                        None => other.to_string().trim_matches('"').to_string(),
-                    },
+                    // Arithmetic in targeting may turn `20` into `20.0`; an integral float
+                    // names the integer variant unless a variant or alias has its exact spelling
+                    JsonValue::Number(n) => {
+                        let spelled = n.to_string();
+                        let integral = n.is_f64() && n.as_f64().is_some_and(|f| f.fract() == 0.0);
+                        if integral
+                            && !flag.variants.contains_key(&spelled)
+                            && !flag.variant_aliases.contains_key(&spelled)
+                        {
+                            canonical_number(&n)
+                        } else {
+                            spelled
+                        }
+                    }
+                    JsonValue::Bool(b) => b.to_string(),
+                    _ => {
+                        *default_reason = Some(DefaultReason::InvalidTargetingResult);
+                        return Self::invalid_targeting_result(flag_key, result_type);
+                    }
                 };
 
                 // Check for empty variant name
                 if variant_name.is_empty() {
                     return match flag.default_variant.as_ref() {
-                        None => EvaluationResult::fallback(flag_key),
+                        None => self.no_default_variant(flag, flag_key, flag_set),
                         Some(default_variant) if default_variant.is_empty() => {
-                            EvaluationResult::fallback(flag_key)
+                            self.no_default_variant(flag, flag_key, flag_set)
                         }
                         Some(_) => EvaluationResult::error(
                             ErrorCode::General,
@@ -426,19 +2398,34 @@ impl FlagEvaluator {
                     };
                 }
 
-                // Look up the variant value
+                // Look up the variant value, under its current name if the rule used an alias
+                let variant_name = match flag.variant_aliases.get(&variant_name) {
+                    Some(target) => target.clone(),
+                    None => variant_name,
+                };
                 match flag.variants.get(&variant_name) {
+                    // The value is the variant's as stored, so an integer variant stays an
+                    // integer whatever number type the targeting computed along the way
                     Some(value) => {
                         let result = EvaluationResult::targeting_match(value.clone(), variant_name);
-                        Self::with_lazy_metadata(flag_set_metadata, &flag.metadata, result)
+                        self.with_lazy_metadata(flag_set_metadata, flag, result)
+                    }
+                    // Booleans keep the plain variant lookup error (see
+                    // `set_bool_targeting_maps_to`); only numbers get the type error
+                    None if matches!(result_type, "integer" | "float") => {
+                        *default_reason = Some(DefaultReason::InvalidTargetingResult);
+                        Self::invalid_targeting_result(flag_key, result_type)
+                    }
+                    None => {
+                        *default_reason = Some(DefaultReason::VariantNotFound);
+                        EvaluationResult::error(
+                            ErrorCode::General,
+                            format!(
+                                "Targeting rule returned variant '{}' which is not defined in flag variants",
+                                variant_name
+                            ),
+                        )
                     }
-                    None => EvaluationResult::error(
-                        ErrorCode::General,
-                        format!(
-                            "Targeting rule returned variant '{}' which is not defined in flag variants",
-                            variant_name
-                        ),
-                    ),
                 }
             }
             Err(e) => {
@@ -447,10 +2434,100 @@ impl FlagEvaluator {
         }
     }
 
+    /// Builds the error for a targeting result that does not name a variant.
+    fn invalid_targeting_result(flag_key: &str, result_type: &str) -> EvaluationResult {
+        EvaluationResult::error(
+            ErrorCode::General,
+            format!(
+                "Targeting rule for flag '{}' must resolve to a variant name string, got {}",
+                flag_key, result_type
+            ),
+        )
+    }
+
+    /// Resolves a flag that has no default variant.
+    ///
+    /// Uses the flag set's configured default for the flag's type if there is one,
+    /// otherwise returns FALLBACK so the caller's code default applies.
+    fn no_default_variant(
+        &self,
+        flag: &FeatureFlag,
+        flag_key: &str,
+        flag_set: &ParsingResult,
+    ) -> EvaluationResult {
+        match flag_set.flag_set_defaults.value_for(flag) {
+            Some(value) => self.with_lazy_metadata(
+                &flag_set.flag_set_metadata,
+                flag,
+                EvaluationResult::flag_set_default(value),
+            ),
+            None => EvaluationResult::fallback(flag_key),
+        }
+    }
+
+    /// Checks whether every prerequisite of a flag resolves to its required variant.
+    ///
+    /// Prerequisites are evaluated against the same context as the flag. A disabled
+    /// prerequisite is never met, even if it resolves to the required variant.
+    fn prerequisites_met(
+        &self,
+        flag: &FeatureFlag,
+        context: &Value,
+        needs_enrichment: bool,
+        flag_set: &ParsingResult,
+    ) -> bool {
+        flag.prerequisites.iter().all(|prerequisite| {
+            let Some(required) = flag_set.flags.get(&prerequisite.flag) else {
+                return false;
+            };
+            let result = self.evaluate_flag_core(
+                required,
+                &prerequisite.flag,
+                context.clone(),
+                needs_enrichment,
+                flag_set,
+                &mut None,
+            );
+            result.reason != ResolutionReason::Disabled
+                && result.variant.as_deref()
+                    == Some(required.resolve_variant_name(&prerequisite.variant))
+        })
+    }
+
+    /// Resolves a flag whose prerequisites are not met to its default variant.
+    fn prerequisite_failed(
+        &self,
+        flag: &FeatureFlag,
+        flag_key: &str,
+        flag_set: &ParsingResult,
+    ) -> EvaluationResult {
+        match flag.default_variant.as_deref() {
+            None | Some("") => self.no_default_variant(flag, flag_key, flag_set),
+            Some(default_variant) => match flag.variants.get(default_variant) {
+                Some(value) => self.with_lazy_metadata(
+                    &flag_set.flag_set_metadata,
+                    flag,
+                    EvaluationResult::prerequisite_failed(
+                        value.clone(),
+                        default_variant.to_string(),
+                    ),
+                ),
+                None => EvaluationResult::error(
+                    ErrorCode::General,
+                    format!(
+                        "Default variant '{}' not found in flag variants",
+                        default_variant
+                    ),
+                ),
+            },
+        }
+    }
+
     /// Applies type checking to an evaluation result.
     fn apply_type_check(
         &self,
-        mut result: EvaluationResult,
+        flag_key: &str,
+        result: EvaluationResult,
         expected: ExpectedType,
     ) -> EvaluationResult {
         // If there's already an error or special status, return it as-is
@@ -467,90 +2544,100 @@ impl FlagEvaluator {
                 if result.value.is_boolean() {
                     result
                 } else {
-                    EvaluationResult::error(
-                        ErrorCode::TypeMismatch,
-                        format!(
-                            "Flag value has incorrect type. Expected boolean, got {}",
-                            Self::type_name(&result.value)
-                        ),
-                    )
+                    Self::type_mismatch(flag_key, &result, "boolean")
                 }
             }
             ExpectedType::String => {
                 if result.value.is_string() {
                     result
                 } else {
-                    EvaluationResult::error(
-                        ErrorCode::TypeMismatch,
-                        format!(
-                            "Flag value has incorrect type. Expected string, got {}",
-                            Self::type_name(&result.value)
-                        ),
-                    )
+                    Self::type_mismatch(flag_key, &result, "string")
                 }
             }
             ExpectedType::Integer => {
                 // Type coercion: float to integer (Java-compatible behavior)
                 if result.value.is_f64() {
                     if let Some(f) = result.value.as_f64() {
-                        result.value = JsonValue::Number(serde_json::Number::from(f as i64));
-                        return result;
+                        let coerced = JsonValue::Number(serde_json::Number::from(f as i64));
+                        return self.with_coerced_value(result, coerced);
                     }
                 }
                 if result.value.is_i64() || result.value.is_u64() {
                     result
                 } else {
-                    EvaluationResult::error(
-                        ErrorCode::TypeMismatch,
-                        format!(
-                            "Flag value has incorrect type. Expected integer, got {}",
-                            Self::type_name(&result.value)
-                        ),
-                    )
+                    Self::type_mismatch(flag_key, &result, "integer")
                 }
             }
             ExpectedType::Float => {
                 // Type coercion: integer to float (Java-compatible behavior)
                 if result.value.is_i64() || result.value.is_u64() {
-                    if let Some(i) = result.value.as_i64() {
-                        if let Some(num) = serde_json::Number::from_f64(i as f64) {
-                            result.value = JsonValue::Number(num);
-                        }
-                    } else if let Some(u) = result.value.as_u64() {
-                        if let Some(num) = serde_json::Number::from_f64(u as f64) {
-                            result.value = JsonValue::Number(num);
-                        }
-                    }
-                    return result;
+                    let widened = match result.value.as_i64() {
+                        Some(i) => serde_json::Number::from_f64(i as f64),
+                        None => result
+                            .value
+                            .as_u64()
+                            .and_then(|u| serde_json::Number::from_f64(u as f64)),
+                    };
+                    return match widened {
+                        Some(num) => self.with_coerced_value(result, JsonValue::Number(num)),
+                        None => result,
+                    };
                 }
                 if result.value.is_number() {
                     result
                 } else {
-                    EvaluationResult::error(
-                        ErrorCode::TypeMismatch,
-                        format!(
-                            "Flag value has incorrect type. Expected float, got {}",
-                            Self::type_name(&result.value)
-                        ),
-                    )
+                    Self::type_mismatch(flag_key, &result, "float")
                 }
             }
             ExpectedType::Object => {
                 if result.value.is_object() {
                     result
                 } else {
-                    EvaluationResult::error(
-                        ErrorCode::TypeMismatch,
-                        format!(
-                            "Flag value has incorrect type. Expected object, got {}",
-                            Self::type_name(&result.value)
-                        ),
-                    )
+                    Self::type_mismatch(flag_key, &result, "object")
                 }
             }
         }
     }
 
+    /// Replaces a result's value with its coerced form, keeping the configured value
+    /// in `raw_value` when raw value reporting is enabled.
+    fn with_coerced_value(
+        &self,
+        mut result: EvaluationResult,
+        coerced: JsonValue,
+    ) -> EvaluationResult {
+        let raw = std::mem::replace(&mut result.value, coerced);
+        if self.options.report_raw_values {
+            result.raw_value = Some(raw);
+        }
+        result
+    }
+
+    /// Builds the `TYPE_MISMATCH` error for a result of the wrong type.
+    ///
+    /// The message names the flag and, if one was resolved, the variant whose value
+    /// has the wrong type, e.g. `Flag value has incorrect type. Expected boolean, got
+    /// string (flag 'banner', variant 'on')`.
+    fn type_mismatch(
+        flag_key: &str,
+        result: &EvaluationResult,
+        expected: &str,
+    ) -> EvaluationResult {
+        let source = match &result.variant {
+            Some(variant) => format!("flag '{}', variant '{}'", flag_key, variant),
+            None => format!("flag '{}'", flag_key),
+        };
+        EvaluationResult::error(
+            ErrorCode::TypeMismatch,
+            format!(
+                "Flag value has incorrect type. Expected {}, got {} ({})",
+                expected,
+                Self::type_name(&result.value),
+                source
+            ),
+        )
+    }
+
     // =========================================================================
     // Helper methods
     // =========================================================================
@@ -566,25 +2653,27 @@ impl FlagEvaluator {
         let mut results = HashMap::new();
 
         for (flag_key, flag) in &parsing_result.flags {
-            // Pre-evaluate disabled flags
-            if flag.state == "DISABLED" {
+            // Pre-evaluate disabled flags, and flags with an unknown state
+            if parsing_result.disabled || flag.state != "ENABLED" {
                 let result = self.evaluate_flag_core(
                     flag,
                     flag_key,
                     Value::Object(Map::new()),
                     false,
-                    &parsing_result.flag_set_metadata,
+                    parsing_result,
+                    &mut None,
                 );
                 results.insert(flag_key.clone(), result);
                 continue;
             }
 
-            // Pre-evaluate static flags (no targeting rules)
-            let is_static = match &flag.targeting {
-                None => true,
-                Some(JsonValue::Object(map)) if map.is_empty() => true,
-                _ => false,
-            };
+            // Pre-evaluate static flags (no targeting rules or prerequisites)
+            let is_static = flag.prerequisites.is_empty()
+                && match &flag.targeting {
+                    None => true,
+                    Some(JsonValue::Object(map)) if map.is_empty() => true,
+                    _ => false,
+                };
 
             if is_static {
                 let result = self.evaluate_flag_core(
@@ -592,7 +2681,8 @@ impl FlagEvaluator {
                     flag_key,
                     Value::Object(Map::new()),
                     false,
-                    &parsing_result.flag_set_metadata,
+                    parsing_result,
+                    &mut None,
                 );
                 results.insert(flag_key.clone(), result);
             }
@@ -601,60 +2691,51 @@ impl FlagEvaluator {
         results
     }
 
-    /// Detects which flags have changed between the current and new state.
-    fn detect_changed_flags(&self, new_state: &ParsingResult) -> Vec<String> {
-        let mut changed_keys = HashSet::new();
-
-        match &self.state {
-            None => {
-                // No previous state, all flags are new
-                for key in new_state.flags.keys() {
-                    changed_keys.insert(key.clone());
-                }
-            }
-            Some(old) => {
-                // Check for added and mutated flags
-                for (key, new_flag) in &new_state.flags {
-                    match old.flags.get(key) {
-                        None => {
-                            changed_keys.insert(key.clone());
-                        }
-                        Some(old_flag) => {
-                            if new_flag.is_different_from(old_flag) {
-                                changed_keys.insert(key.clone());
-                            }
-                        }
-                    }
-                }
-
-                // Check for removed flags
-                for key in old.flags.keys() {
-                    if !new_state.flags.contains_key(key) {
-                        changed_keys.insert(key.clone());
-                    }
-                }
-            }
+    /// Appends a change record for the current state version, evicting the oldest.
+    fn record_change(&mut self, diff: FlagSetDiff) {
+        if self.change_history_capacity == 0 {
+            return;
         }
-
-        let mut result: Vec<String> = changed_keys.into_iter().collect();
-        result.sort();
-        result
+        while self.change_history.len() >= self.change_history_capacity {
+            self.change_history.pop_front();
+        }
+        self.change_history.push_back(ChangeRecord {
+            version: self.state_version,
+            timestamp: self.resolve_timestamp(crate::current_time()),
+            added: diff.added,
+            removed: diff.removed,
+            mutated: diff.mutated,
+        });
     }
 
     /// Enriches the evaluation context with standard flagd fields.
-    fn enrich_context(flag_key: &str, context: Value) -> Value {
+    ///
+    /// The merged flag metadata, when present, is exposed as `$flagd.flagMetadata` so
+    /// targeting rules can branch on it (e.g. `{"var": "$flagd.flagMetadata.tier"}`).
+    fn enrich_context(
+        &self,
+        flag_key: &str,
+        context: Value,
+        flag_metadata: Option<HashMap<String, JsonValue>>,
+    ) -> Value {
         let mut enriched = match context {
             Value::Object(obj) => obj,
             _ => Map::new(),
         };
 
         // Get current Unix timestamp (seconds since epoch)
-        let timestamp = crate::get_current_time();
+        let timestamp = self.resolve_timestamp(crate::current_time());
 
         // Create $flagd object with nested properties
         let mut flagd_props = Map::new();
         flagd_props.insert("flagKey".to_string(), Value::String(flag_key.to_string()));
         flagd_props.insert("timestamp".to_string(), Value::Number(timestamp.into()));
+        if let Some(metadata) = flag_metadata {
+            flagd_props.insert(
+                "flagMetadata".to_string(),
+                Value::Object(metadata.into_iter().collect()),
+            );
+        }
 
         // Add $flagd object to context
         enriched.insert("$flagd".to_string(), Value::Object(flagd_props));
@@ -667,15 +2748,71 @@ impl FlagEvaluator {
         Value::Object(enriched)
     }
 
+    /// Selects the sub-object addressed by the context root pointer, if configured.
+    ///
+    /// Top-level `$flagd` and `targetingKey` entries are carried over into it.
+    fn with_context_root(&self, mut context: Value) -> Value {
+        let Some(pointer) = self.options.context_root_pointer.as_deref() else {
+            return context;
+        };
+
+        let mut selected = match context.pointer_mut(pointer).map(Value::take) {
+            Some(Value::Object(obj)) => obj,
+            _ => Map::new(),
+        };
+        if let Value::Object(root) = &mut context {
+            for key in ["$flagd", "targetingKey"] {
+                if let Some(value) = root.remove(key) {
+                    selected.entry(key).or_insert(value);
+                }
+            }
+        }
+        Value::Object(selected)
+    }
+
+    /// Copies the configured targeting-key field into `targetingKey`, if present.
+    fn with_targeting_key_field(&self, mut context: Value) -> Value {
+        if let (Some(field), Some(obj)) =
+            (&self.options.targeting_key_field, context.as_object_mut())
+        {
+            if let Some(value) = obj.get(field).cloned() {
+                obj.insert("targetingKey".to_string(), value);
+            }
+        }
+        context
+    }
+
+    /// Adds `$flagd.flagMetadata` to a host-enriched context.
+    ///
+    /// Hosts pre-enrich `$flagd.flagKey` and `$flagd.timestamp` but do not know the
+    /// merged flag metadata, so it is injected here when the host hasn't provided it.
+    fn with_flag_metadata(
+        mut context: Value,
+        flag_metadata: Option<HashMap<String, JsonValue>>,
+    ) -> Value {
+        if let (Some(metadata), Some(Value::Object(flagd))) = (
+            flag_metadata,
+            context
+                .as_object_mut()
+                .and_then(|obj| obj.get_mut("$flagd")),
+        ) {
+            flagd
+                .entry("flagMetadata")
+                .or_insert_with(|| Value::Object(metadata.into_iter().collect()));
+        }
+        context
+    }
+
     /// Merges flag-set metadata with flag-level metadata.
     fn merge_metadata(
+        &self,
         flag_set_metadata: &HashMap<String, JsonValue>,
         flag_metadata: &HashMap<String, JsonValue>,
     ) -> Option<HashMap<String, JsonValue>> {
         // Filter out internal fields (those starting with $) from flag-set metadata
         let filtered_flag_set: HashMap<String, JsonValue> = flag_set_metadata
             .iter()
-            .filter(|(key, _)| !key.starts_with('$'))
+            .filter(|(key, _)| self.is_exposed_metadata_key(key))
             .map(|(k, v)| (k.clone(), v.clone()))
             .collect();
 
@@ -695,14 +2832,22 @@ impl FlagEvaluator {
         Some(merged)
     }
 
+    /// Returns whether a flag-set metadata key is included in results.
+    ///
+    /// Internal (`$`-prefixed) keys are only included when exposed via the option.
+    fn is_exposed_metadata_key(&self, key: &str) -> bool {
+        self.options.expose_internal_metadata || !key.starts_with('$')
+    }
+
     /// Merges only flag-set metadata (no flag-level metadata).
     /// Used in flag-not-found paths to avoid creating an empty HashMap.
     fn merge_metadata_flag_set_only(
+        &self,
         flag_set_metadata: &HashMap<String, JsonValue>,
     ) -> Option<HashMap<String, JsonValue>> {
         let filtered: HashMap<String, JsonValue> = flag_set_metadata
             .iter()
-            .filter(|(key, _)| !key.starts_with('$'))
+            .filter(|(key, _)| self.is_exposed_metadata_key(key))
             .map(|(k, v)| (k.clone(), v.clone()))
             .collect();
         if filtered.is_empty() {
@@ -713,19 +2858,35 @@ impl FlagEvaluator {
     }
 
     /// Lazy metadata attachment - only merges metadata if there's actually metadata to merge.
-    /// This avoids the cost of creating HashMaps when both sources are empty.
+    /// This avoids the cost of creating HashMaps when all sources are empty.
+    ///
+    /// The metadata of the result's variant (if any) is merged last, so variant-level
+    /// keys override flag-level keys, which override flag-set keys.
     fn with_lazy_metadata(
+        &self,
         flag_set_metadata: &HashMap<String, JsonValue>,
-        flag_metadata: &HashMap<String, JsonValue>,
+        flag: &FeatureFlag,
         result: EvaluationResult,
     ) -> EvaluationResult {
-        // Fast path: if both are empty, skip merging entirely
-        if flag_set_metadata.is_empty() && flag_metadata.is_empty() {
+        let variant_metadata = result
+            .variant
+            .as_ref()
+            .and_then(|variant| flag.variant_metadata.get(variant))
+            .filter(|metadata| !metadata.is_empty());
+
+        // Fast path: if all are empty, skip merging entirely
+        if flag_set_metadata.is_empty() && flag.metadata.is_empty() && variant_metadata.is_none() {
             return result;
         }
 
         // Only merge if there's actual metadata
-        match Self::merge_metadata(flag_set_metadata, flag_metadata) {
+        let mut merged = self.merge_metadata(flag_set_metadata, &flag.metadata);
+        if let Some(variant_metadata) = variant_metadata {
+            merged
+                .get_or_insert_with(HashMap::new)
+                .extend(variant_metadata.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+        match merged {
             Some(metadata) => result.with_metadata(metadata),
             None => result,
         }
@@ -736,6 +2897,10 @@ impl FlagEvaluator {
     /// This is a fast path that avoids flag key string handling by using O(1) Vec lookup.
     /// The context is expected to be pre-enriched with `$flagd.*` and `targetingKey` by the host.
     pub fn evaluate_flag_by_index(&self, index: u32, context: Value) -> EvaluationResult {
+        if self.state.is_none() {
+            return EvaluationResult::uninitialized();
+        }
+
         let flag_key = match self.flag_index_map.get(index as usize) {
             Some(key) => key.clone(),
             None => {
@@ -768,6 +2933,7 @@ impl FlagEvaluator {
     /// Returns (required_context_keys, flag_indices, index_to_key_vec).
     #[allow(clippy::type_complexity)]
     fn build_optimization_maps(
+        &self,
         parsing_result: &ParsingResult,
     ) -> (
         HashMap<String, Vec<String>>,
@@ -789,9 +2955,22 @@ impl FlagEvaluator {
             flag_indices.insert((*flag_key).clone(), index as u32);
             index_to_key.push((*flag_key).clone());
 
-            // Extract required context keys for flags with compiled targeting
-            if let Some(ref compiled) = flag.compiled_targeting {
-                if let Some(keys) = extract_required_context_keys(compiled) {
+            // Extract required context keys for flags with compiled targeting. Flags
+            // with prerequisites get the whole context, which the prerequisites may read.
+            if let Some(compiled) = flag
+                .compiled_targeting
+                .as_ref()
+                .filter(|_| flag.prerequisites.is_empty())
+            {
+                if let Some(mut keys) = extract_required_context_keys(compiled) {
+                    // The configured field feeds targetingKey, so the host must send it too
+                    if let Some(field) = &self.options.targeting_key_field {
+                        keys.insert(field.clone());
+                    }
+                    // Rules read below the context root, so the host must send all of it
+                    if let Some(root_key) = self.context_root_key() {
+                        keys = HashSet::from(["targetingKey".to_string(), root_key]);
+                    }
                     let mut sorted_keys: Vec<String> = keys.into_iter().collect();
                     sorted_keys.sort();
                     required_context_keys.insert((*flag_key).clone(), sorted_keys);
@@ -803,6 +2982,13 @@ impl FlagEvaluator {
         (required_context_keys, flag_indices, index_to_key)
     }
 
+    /// Returns the top-level context field addressed by the context root pointer.
+    fn context_root_key(&self) -> Option<String> {
+        let pointer = self.options.context_root_pointer.as_deref()?;
+        let segment = pointer.strip_prefix('/')?.split('/').next()?;
+        Some(segment.replace("~1", "/").replace("~0", "~"))
+    }
+
     /// Helper function to get a human-readable type name from a JSON value.
     fn type_name(value: &JsonValue) -> &'static str {
         match value {
@@ -838,6 +3024,304 @@ enum ExpectedType {
     Object,
 }
 
+/// Classifies a rejected configuration, reporting `InvalidJson` if it doesn't even parse.
+///
+/// Only called on failure paths, so the extra syntax check costs nothing on success.
+fn classify_config_error(json_config: &str, fallback: UpdateErrorCode) -> UpdateErrorCode {
+    match serde_json::from_str::<serde::de::IgnoredAny>(json_config) {
+        Ok(_) => fallback,
+        Err(_) => UpdateErrorCode::InvalidJson,
+    }
+}
+
+/// Merges configuration fragments for `FlagEvaluator::update_state_multi`.
+fn merge_config_fragments(configs: &[&str]) -> Result<JsonValue, (UpdateErrorCode, String)> {
+    let mut merged = Map::new();
+    for (index, config) in configs.iter().enumerate() {
+        let fragment = match serde_json::from_str::<JsonValue>(config) {
+            Ok(JsonValue::Object(fragment)) => fragment,
+            Ok(_) => {
+                return Err((
+                    UpdateErrorCode::SemanticInvalid,
+                    format!("Fragment {} is not a JSON object", index),
+                ))
+            }
+            Err(e) => {
+                return Err((
+                    UpdateErrorCode::InvalidJson,
+                    format!("Failed to parse fragment {}: {}", index, e),
+                ))
+            }
+        };
+
+        for (key, value) in fragment {
+            if !merged.contains_key(&key) {
+                merged.insert(key, value);
+                continue;
+            }
+            let (Some(JsonValue::Object(existing)), JsonValue::Object(entries)) =
+                (merged.get_mut(&key), value)
+            else {
+                continue;
+            };
+            // Other top-level keys keep the first fragment's value
+            if !matches!(
+                key.as_str(),
+                "flags" | "$evaluators" | "metadata" | "defaults" | "patterns"
+            ) {
+                continue;
+            }
+            for (name, entry) in entries {
+                match existing.get(&name) {
+                    None => {
+                        existing.insert(name, entry);
+                    }
+                    Some(_) if key == "flags" && !name.starts_with('$') => {
+                        return Err((
+                            UpdateErrorCode::SemanticInvalid,
+                            format!("Flag '{}' in fragment {} is already defined", name, index),
+                        ))
+                    }
+                    Some(previous) if *previous != entry && !name.starts_with('$') => {
+                        return Err((
+                            UpdateErrorCode::SemanticInvalid,
+                            format!(
+                                "'{}' in {} of fragment {} conflicts with an earlier fragment",
+                                name, key, index
+                            ),
+                        ))
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+    Ok(JsonValue::Object(merged))
+}
+
+/// Flag key used by `FlagEvaluator::evaluate_inline` when the definition has no `key`.
+pub const INLINE_FLAG_KEY: &str = "inline";
+
+/// Rough serialized size of one keyed result, used to pre-size batch buffers.
+const ESTIMATED_RESULT_BYTES: usize = 128;
+
+/// Serializes keyed results as a JSON object (`{"flagKey": {...}, ...}`).
+pub(crate) fn results_to_json(results: &[(String, EvaluationResult)]) -> String {
+    keyed_json(results, EvaluationResult::write_json)
+}
+
+/// Serializes keyed values as a JSON object, appending each value with `write`.
+///
+/// Every result is written into one buffer pre-sized for the whole batch, instead
+/// of serializing each result to its own `String` and concatenating. For N flags
+/// this replaces N+1 allocations (plus the copies and regrowth of the concatenated
+/// string) with a single allocation in the common case.
+pub(crate) fn keyed_json<T>(results: &[(String, T)], write: impl Fn(&T, &mut Vec<u8>)) -> String {
+    let mut out = Vec::with_capacity(2 + results.len() * ESTIMATED_RESULT_BYTES);
+    out.push(b'{');
+    for (i, (flag_key, result)) in results.iter().enumerate() {
+        if i > 0 {
+            out.push(b',');
+        }
+        // Serializing a str into a Vec cannot fail
+        let _ = serde_json::to_writer(&mut out, flag_key);
+        out.push(b':');
+        write(result, &mut out);
+    }
+    out.push(b'}');
+    // serde_json only ever writes valid UTF-8
+    String::from_utf8(out).unwrap_or_default()
+}
+
+/// Checks whether a flag has a targeting rule (absent and `{}` both mean none).
+fn has_targeting(flag: &FeatureFlag) -> bool {
+    match &flag.targeting {
+        None => false,
+        Some(JsonValue::Object(map)) => !map.is_empty(),
+        Some(_) => true,
+    }
+}
+
+/// Checks whether a targeting rule's result is fully determined by the caller's context.
+///
+/// Returns `false` if the rule reads `$flagd.timestamp` (time-based), reads the whole
+/// `$flagd` object, or uses a `var` path that cannot be determined statically
+/// (empty path or computed path), since any of these may observe the timestamp.
+/// Fractional bucketing is deterministic: it only depends on the flag key and context.
+fn is_context_deterministic(targeting: &JsonValue) -> bool {
+    match targeting {
+        JsonValue::Object(obj) => {
+            if let Some(var) = obj.get("var") {
+                let path = match var {
+                    JsonValue::String(s) => Some(s.as_str()),
+                    JsonValue::Array(items) => items.first().and_then(|v| v.as_str()),
+                    _ => None,
+                };
+                match path {
+                    Some(p) if p.is_empty() || p == "$flagd" => return false,
+                    Some(p) if p.starts_with("$flagd.timestamp") => return false,
+                    Some(_) => {}
+                    None => return false,
+                }
+            }
+            obj.values().all(is_context_deterministic)
+        }
+        JsonValue::Array(items) => items.iter().all(is_context_deterministic),
+        _ => true,
+    }
+}
+
+/// Checks whether a targeting rule may read `$flagd.flagMetadata`.
+///
+/// Returns `true` for literal paths under `$flagd.flagMetadata`, reads of the whole
+/// `$flagd` object or context, and computed paths that cannot be determined statically.
+fn references_flag_metadata(targeting: &JsonValue) -> bool {
+    match targeting {
+        JsonValue::Object(obj) => {
+            if let Some(var) = obj.get("var") {
+                let path = match var {
+                    JsonValue::String(s) => Some(s.as_str()),
+                    JsonValue::Array(items) => items.first().and_then(|v| v.as_str()),
+                    _ => None,
+                };
+                match path {
+                    Some(p) if p.is_empty() || p == "$flagd" => return true,
+                    Some(p) if p.starts_with("$flagd.flagMetadata") => return true,
+                    Some(_) => {}
+                    None => return true,
+                }
+            }
+            obj.values().any(references_flag_metadata)
+        }
+        JsonValue::Array(items) => items.iter().any(references_flag_metadata),
+        _ => false,
+    }
+}
+
+/// Spells out targeting shorthands, see `FlagEvaluator::effective_config`.
+fn normalize_targeting(rule: &JsonValue) -> JsonValue {
+    match rule {
+        JsonValue::Object(obj) => obj
+            .iter()
+            .map(|(key, value)| {
+                let value = match (key.as_str(), value) {
+                    ("var", JsonValue::Array(path)) if path.len() == 1 => {
+                        normalize_targeting(&path[0])
+                    }
+                    ("fractional", JsonValue::Array(args)) => args
+                        .iter()
+                        .map(|arg| match arg {
+                            JsonValue::Array(bucket) if bucket.len() == 1 => {
+                                JsonValue::Array(vec![normalize_targeting(&bucket[0]), 1.into()])
+                            }
+                            arg => normalize_targeting(arg),
+                        })
+                        .collect(),
+                    (_, value) => normalize_targeting(value),
+                };
+                (key.clone(), value)
+            })
+            .collect::<Map<String, JsonValue>>()
+            .into(),
+        JsonValue::Array(items) => items.iter().map(normalize_targeting).collect(),
+        _ => rule.clone(),
+    }
+}
+
+/// Checks whether a targeting rule uses the `fractional` operator.
+fn uses_fractional(targeting: &JsonValue) -> bool {
+    match targeting {
+        JsonValue::Object(obj) => obj
+            .iter()
+            .any(|(key, value)| key == "fractional" || uses_fractional(value)),
+        JsonValue::Array(items) => items.iter().any(uses_fractional),
+        _ => false,
+    }
+}
+
+/// Finds the first statically referenced variable path that is absent from the context.
+///
+/// Only literal `var` paths are checked; variables with an explicit default value
+/// (`{"var": ["path", default]}`) and computed paths are skipped. Path segments are
+/// separated by `.` and numeric segments index into arrays. A variable that is present
+/// with a `null` value is not considered missing.
+fn find_missing_variable(targeting: &JsonValue, context: &JsonValue) -> Option<String> {
+    match targeting {
+        JsonValue::Object(obj) => {
+            if let Some(var) = obj.get("var") {
+                let path = match var {
+                    JsonValue::String(s) => Some(s.as_str()),
+                    JsonValue::Array(items) if items.len() == 1 => items[0].as_str(),
+                    _ => None,
+                };
+                if let Some(path) = path.filter(|p| !p.is_empty()) {
+                    if !variable_exists(context, path) {
+                        return Some(path.to_string());
+                    }
+                }
+            }
+            obj.values()
+                .find_map(|value| find_missing_variable(value, context))
+        }
+        JsonValue::Array(items) => items
+            .iter()
+            .find_map(|item| find_missing_variable(item, context)),
+        _ => None,
+    }
+}
+
+/// Checks whether a dot-separated variable path resolves in the context.
+fn variable_exists(context: &JsonValue, path: &str) -> bool {
+    let mut current = context;
+    for segment in path.split('.') {
+        let next = match current {
+            JsonValue::Object(obj) => obj.get(segment),
+            JsonValue::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        };
+        match next {
+            Some(value) => current = value,
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Checks whether a targeting rule contains a `var` reference overlapping an attribute.
+///
+/// See `FlagEvaluator::flags_referencing` for which paths overlap.
+fn references_attribute(rule: &JsonValue, attribute: &str) -> bool {
+    match rule {
+        JsonValue::Object(obj) => obj.iter().any(|(key, value)| {
+            let path = match (key.as_str(), value) {
+                ("var", JsonValue::Array(items)) => items.first(),
+                ("var", path) => Some(path),
+                _ => None,
+            }
+            .map(|path| match path {
+                JsonValue::String(path) => path.clone(),
+                JsonValue::Null => String::new(),
+                path => path.to_string(),
+            });
+            let overlaps = path.is_some_and(|path| {
+                path.is_empty()
+                    || path == attribute
+                    || path
+                        .strip_prefix(attribute)
+                        .is_some_and(|rest| rest.starts_with('.'))
+                    || attribute
+                        .strip_prefix(path.as_str())
+                        .is_some_and(|rest| rest.starts_with('.'))
+            });
+            overlaps || references_attribute(value, attribute)
+        }),
+        JsonValue::Array(items) => items
+            .iter()
+            .any(|item| references_attribute(item, attribute)),
+        _ => false,
+    }
+}
+
 /// Extracts the set of user-context keys that a compiled targeting rule references.
 ///
 /// Returns `None` if the rule uses `{"var": ""}` (entire context access),
@@ -20,7 +20,44 @@
 //!
 //! - `evaluate_logic`: Evaluates JSON Logic rules directly
 //! - `update_state`: Updates the feature flag configuration state
+//! - `update_state_bin`: Like `update_state`, returning only the changed flags in a compact binary form
+//! - `update_state_with_events`: Like `update_state`, plus the provider events to emit
+//! - `update_state_multi`: Like `update_state`, merging several configuration fragments
 //! - `evaluate`: Evaluates a feature flag against context (requires prior `update_state` call)
+//! - `context_begin`, `context_set`, `evaluate_with_builder`: Evaluates against a context
+//!   built from typed key/value pairs instead of JSON
+//! - `evaluate_safe`: Like `evaluate`, returning a structured error for any input
+//! - `evaluate_all`: Evaluates every flag against one context in a single call
+//! - `evaluate_all_typed`: Like `evaluate_all`, resolving each flag as its inferred type
+//! - `evaluate_changed_since`: Like `evaluate_all`, for the flags changed since a state version
+//! - `evaluate_distribution`: Counts the variants one flag resolves to over a sample of contexts
+//! - `evaluate_rule_for_flag`: Evaluates a candidate targeting rule against a flag's variants
+//! - `evaluate_inline`: Evaluates a single flag definition without storing it
+//! - `validate_and_evaluate`: Validates a configuration and evaluates one of its flags without storing it
+//! - `lint_config`: Reports likely mistakes in a configuration without storing it
+//! - `compile_report`: Compiles each flag's targeting in a configuration and reports the failures
+//! - `validate_targeting`: Validates a single targeting rule
+//! - `inventory`: Lists every stored flag with its type and variants
+//! - `list_flags`: Lists every stored flag's key, state, default variant and type
+//! - `effective_config`: Returns the stored configuration with `$ref`s inlined and shorthands spelled out
+//! - `flags_referencing`: Lists the flags whose targeting references a context attribute
+//! - `evaluable_flags`: Lists the flags a partial context can fully resolve
+//! - `set_reject_unknown_keys`: Reports configuration keys the schema does not define as validation errors
+//! - `set_default_timestamp`: Sets the `$flagd.timestamp` used when the host provides no time
+//! - `set_compact_unchanged_updates`: Answers an unchanged `update_state` with a compact response
+//! - `set_changed_flags_bitmap`: Reports `update_state` changes as a bitmap over the flag indices
+//! - `reset_options`: Restores every option to its default, keeping the loaded flags
+//! - `add_operator_alias`: Registers another name for a targeting operator
+//! - `set_targeting_key_field`: Sets the context field that feeds `targetingKey`
+//! - `get_change_history`: Lists the flags added, removed, and mutated by recent updates
+//! - `health_check`: Reports whether state is loaded and whether the host time is available
+//! - `evaluate_variant_code`: Evaluates a flag to a packed variant index and reason, without JSON
+//! - `get_variant_names`: Lists a flag's sorted variant names to decode variant indices
+//! - `possible_reasons`: Lists the reasons a flag can resolve with, inferred from its structure
+//! - `resolve_default`: Resolves a flag to its default variant without evaluating targeting
+//! - `evaluate_preview`: Evaluates a disabled flag as though it were enabled
+//! - `result_schema`: Returns the JSON Schema of the evaluation result
+//! - `set_result_framing`: Prefixes single-result responses with a status byte
 //! - `wasm_alloc`: Allocate memory from WASM linear memory
 //! - `wasm_dealloc`: Free allocated memory
 //!
@@ -36,11 +73,19 @@
 //! // 6. Free allocated memory
 //! ```
 
+use std::cell::RefCell;
 use std::panic;
-use std::sync::Once;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Mutex, MutexGuard, Once, PoisonError};
 
 static PANIC_HOOK_INIT: Once = Once::new();
 
+thread_local! {
+    /// Location of the most recent panic on this thread, recorded by the panic hook
+    /// so that `catch_unwind` handlers can report it.
+    static LAST_PANIC_LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
 // WASM is single-threaded, so we can use RefCell for better semantics.
 // For native targets (testing, library usage), we use Mutex for thread safety.
 
@@ -77,18 +122,21 @@ mod wasm_evaluator {
 #[cfg(not(target_family = "wasm"))]
 mod wasm_evaluator {
     use super::*;
-    use std::sync::{Mutex, OnceLock};
+    use std::sync::{Mutex, OnceLock, PoisonError};
 
     static WASM_EVALUATOR: OnceLock<Mutex<evaluator::FlagEvaluator>> = OnceLock::new();
 
     /// Get or initialize the global evaluator instance (thread-safe for native).
+    ///
+    /// A panic caught while the evaluator was borrowed poisons the mutex; the
+    /// evaluator is still used afterwards rather than failing every later call.
     pub fn with_evaluator<F, R>(f: F) -> R
     where
         F: FnOnce(&mut evaluator::FlagEvaluator) -> R,
     {
         let mutex = WASM_EVALUATOR
             .get_or_init(|| Mutex::new(evaluator::FlagEvaluator::new(ValidationMode::Strict)));
-        let mut guard = mutex.lock().unwrap();
+        let mut guard = mutex.lock().unwrap_or_else(PoisonError::into_inner);
         f(&mut guard)
     }
 }
@@ -109,6 +157,10 @@ extern "C" {
     fn host_get_current_time() -> u64;
 }
 
+/// Whether the host time function works, probed on the first call to `get_current_time`.
+#[cfg(target_family = "wasm")]
+static HOST_CLOCK: platform::HostClock = platform::HostClock::new();
+
 /// Initialize panic hook to prevent unreachable instructions in WASM
 fn init_panic_hook() {
     PANIC_HOOK_INIT.call_once(|| {
@@ -121,68 +173,127 @@ fn init_panic_hook() {
                 "Unknown panic"
             };
 
-            let location = if let Some(location) = panic_info.location() {
+            let location = panic_info.location().map(|location| {
                 format!(
-                    " at {}:{}:{}",
+                    "{}:{}:{}",
                     location.file(),
                     location.line(),
                     location.column()
                 )
-            } else {
-                String::new()
-            };
+            });
+            let _ = LAST_PANIC_LOCATION.try_with(|last| *last.borrow_mut() = location.clone());
 
             // This will be visible in Chicory's error output
-            eprintln!("PANIC in WASM module: {}{}", msg, location);
+            match location {
+                Some(location) => eprintln!("PANIC in WASM module: {} at {}", msg, location),
+                None => eprintln!("PANIC in WASM module: {}", msg),
+            }
         }));
     });
 }
 
+/// Builds the error message for a panic caught during evaluation.
+///
+/// Includes the location recorded by the panic hook, so WASM crashes can be traced
+/// back to the source without access to the host's stderr.
+fn evaluation_panic_message(panic_err: &(dyn std::any::Any + Send)) -> String {
+    let msg = if let Some(s) = panic_err.downcast_ref::<&str>() {
+        s
+    } else if let Some(s) = panic_err.downcast_ref::<String>() {
+        s.as_str()
+    } else {
+        "unknown error"
+    };
+
+    match LAST_PANIC_LOCATION.with(|last| last.borrow_mut().take()) {
+        Some(location) => format!("Evaluation panic: {} at {}", msg, location),
+        None => format!("Evaluation panic: {}", msg),
+    }
+}
+
+pub mod cache;
 pub mod error;
 pub mod evaluator;
+pub mod lint;
 pub mod memory;
 pub mod model;
 pub mod operators;
+pub mod platform;
 pub mod types;
 pub mod validation;
+#[cfg(all(feature = "notify", not(target_family = "wasm")))]
+pub mod watcher;
 
 /// Gets the current Unix timestamp in seconds.
 ///
-/// This function attempts to call the host-provided `get_current_time_unix_seconds` function.
-/// If the host doesn't provide this function (linking error), or if calling it fails,
-/// it defaults to returning 0.
+/// A clock installed with `platform::set_clock` takes precedence. Otherwise this
+/// function attempts to call the host-provided `get_current_time_unix_seconds` function.
+/// The first call probes it: if it fails or returns 0 (a host stub), the host clock is
+/// remembered as unavailable and 0 is returned from then on without calling it again.
+/// Native builds use the system clock when the `os` feature is enabled, and return 0
+/// without it.
 ///
 /// # Returns
 /// Unix timestamp in seconds, or 0 if unavailable
 pub fn get_current_time() -> u64 {
+    current_time().unwrap_or(0)
+}
+
+/// Gets the current Unix timestamp in seconds, or `None` if no clock is available.
+pub(crate) fn current_time() -> Option<u64> {
+    if let Some(now) = platform::injected_time() {
+        return Some(now);
+    }
+
     #[cfg(target_family = "wasm")]
     {
-        // In WASM, try to call the host function
-        // If it's not provided, this will cause a link error that we catch
-        std::panic::catch_unwind(|| unsafe { host_get_current_time() }).unwrap_or(0)
+        // In WASM, call the host function unless a previous call showed it is unavailable
+        // SAFETY: The host function takes no arguments and returns a plain integer
+        let now = HOST_CLOCK.now(|| unsafe { host_get_current_time() });
+        (now != 0).then_some(now)
     }
-    #[cfg(not(target_family = "wasm"))]
+    #[cfg(all(not(target_family = "wasm"), feature = "os"))]
     {
         // In native code (tests, CLI), use SystemTime
         std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .map(|d| d.as_secs())
-            .unwrap_or(0)
+            .ok()
+    }
+    #[cfg(all(not(target_family = "wasm"), not(feature = "os")))]
+    {
+        // Without an OS clock, the embedder must install one via platform::set_clock
+        None
     }
 }
 
-use serde_json::Value;
+use serde_json::{Map, Value};
 
+pub use cache::{canonicalize_context, CacheStats};
 pub use error::{ErrorType, EvaluatorError};
-pub use evaluator::{FlagEvaluator, ValidationMode};
+pub use evaluator::{
+    DisabledReturns, FlagEvaluator, StaticReporting, UnknownStatePolicy, ValidationMode,
+};
 pub use memory::{
     bytes_to_memory, pack_ptr_len, string_from_memory, string_to_memory, unpack_ptr_len,
     wasm_alloc, wasm_dealloc,
 };
-pub use model::{FeatureFlag, ParsingResult, UpdateStateResponse};
-pub use operators::create_evaluator;
-pub use types::{ErrorCode, EvaluationResult, ResolutionReason};
-pub use validation::{validate_flags_config, ValidationError, ValidationResult};
+pub use model::{
+    diff_parsing_results, ChangeRecord, FeatureFlag, FlagListing, FlagSetDefaults, FlagSetDiff,
+    FlagSummary, HealthStatus, ParsingResult, ProviderEvent, ProviderEventType, ShadowFlagDiff,
+    ShadowReport, UpdateErrorCode, UpdateStateResponse, UpdateStateWithEventsResponse,
+};
+pub use operators::{
+    create_evaluator, EmptyKeyPolicy, FractionalHash, FractionalMode, FractionalOptions,
+    FractionalWeights,
+};
+pub use types::{
+    DefaultReason, ErrorCode, EvaluationResult, EvaluationTrace, FlagType, ResolutionReason,
+    ResultFraming, TargetingPresence, TypedEvaluationResult, VariantCode,
+};
+pub use validation::{
+    find_unknown_keys, validate_flags_config, validate_targeting, ValidationError, ValidationResult,
+};
 
 /// Re-exports for external access to allocation functions.
 ///
@@ -213,10 +324,13 @@ pub extern "C" fn dealloc(ptr: *mut u8, len: u32) {
 /// ```json
 /// {
 ///   "success": true|false,
-///   "error": null|"error message"
+///   "error": null|"error message",
+///   "errorCode": "INVALID_ARGUMENT"
 /// }
 /// ```
 ///
+/// `errorCode` is only present on failure.
+///
 /// # Example (from Java via Chicory)
 /// ```java
 /// // Set to permissive mode (1)
@@ -232,16 +346,21 @@ pub extern "C" fn dealloc(ptr: *mut u8, len: u32) {
 /// - The caller will free the returned memory using `dealloc`
 #[export_name = "set_validation_mode"]
 pub extern "C" fn set_validation_mode_wasm(mode: u32) -> u64 {
+    string_to_memory(&set_validation_mode_internal(mode))
+}
+
+/// Internal implementation of set_validation_mode.
+fn set_validation_mode_internal(mode: u32) -> String {
     let validation_mode = match mode {
         0 => ValidationMode::Strict,
         1 => ValidationMode::Permissive,
         _ => {
-            let response = serde_json::json!({
+            return serde_json::json!({
                 "success": false,
-                "error": "Invalid validation mode. Use 0 for Strict or 1 for Permissive."
+                "error": "Invalid validation mode. Use 0 for Strict or 1 for Permissive.",
+                "errorCode": UpdateErrorCode::InvalidArgument
             })
             .to_string();
-            return string_to_memory(&response);
         }
     };
 
@@ -250,6 +369,43 @@ pub extern "C" fn set_validation_mode_wasm(mode: u32) -> u64 {
         eval.set_validation_mode(validation_mode);
     });
 
+    serde_json::json!({
+        "success": true,
+        "error": null
+    })
+    .to_string()
+}
+
+/// Treats configuration keys the schema does not define as validation errors (WASM export).
+///
+/// See `FlagEvaluator::set_reject_unknown_keys`. With it enabled, a typo such as
+/// `defaultVarient` in a flag is reported at `/flags/<flag>/defaultVarient`: strict
+/// mode rejects the configuration and permissive mode logs a warning. `$`-prefixed
+/// extensions are always accepted. Disabled by default.
+///
+/// # Arguments
+/// * `enabled` - 0 to disable, any other value to enable
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the response JSON string.
+///
+/// # Response Format
+/// ```json
+/// {
+///   "success": true,
+///   "error": null
+/// }
+/// ```
+///
+/// # Safety
+/// The caller will free the returned memory using `dealloc`
+#[export_name = "set_reject_unknown_keys"]
+pub extern "C" fn set_reject_unknown_keys_wasm(enabled: u32) -> u64 {
+    wasm_evaluator::with_evaluator(|eval| {
+        eval.set_reject_unknown_keys(enabled != 0);
+    });
+
     let response = serde_json::json!({
         "success": true,
         "error": null
@@ -259,1225 +415,7873 @@ pub extern "C" fn set_validation_mode_wasm(mode: u32) -> u64 {
     string_to_memory(&response)
 }
 
-/// Updates the feature flag state with a new configuration.
+/// Sets the maximum configuration size accepted by `update_state` (WASM export).
 ///
-/// This function parses the provided JSON configuration and stores it in
-/// thread-local storage for later evaluation. It also detects which flags
-/// have changed by comparing the new configuration with the previous state.
+/// Configurations larger than the limit are rejected before they are read or parsed,
+/// protecting the instance from a host pushing an unexpectedly huge configuration.
 ///
 /// # Arguments
-/// * `config_ptr` - Pointer to the JSON configuration string in WASM memory
-/// * `config_len` - Length of the JSON configuration string
+/// * `max_bytes` - Maximum configuration size in bytes, or 0 for unlimited (the default)
 ///
 /// # Returns
 /// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
-/// of the response JSON string. The response includes a list of changed flag keys.
+/// of the response JSON string.
 ///
 /// # Response Format
 /// ```json
 /// {
-///   "success": true|false,
-///   "error": null|"error message",
-///   "changedFlags": ["flag1", "flag2", ...]
+///   "success": true,
+///   "error": null
 /// }
 /// ```
 ///
-/// The `changedFlags` array contains the keys of all flags that were:
-/// - Added (present in new config but not in old)
-/// - Removed (present in old config but not in new)
-/// - Mutated (default variant, targeting rules, or metadata changed)
-///
 /// # Safety
-/// The caller must ensure:
-/// - `config_ptr` points to valid memory
-/// - The memory region is valid UTF-8
-/// - The caller will free the returned memory using `dealloc`
-#[no_mangle]
-pub extern "C" fn update_state(config_ptr: *const u8, config_len: u32) -> u64 {
-    let response = update_state_internal(config_ptr, config_len);
-    string_to_memory(&response)
-}
+/// The caller will free the returned memory using `dealloc`
+#[export_name = "set_max_config_bytes"]
+pub extern "C" fn set_max_config_bytes_wasm(max_bytes: u32) -> u64 {
+    let max_config_bytes = match max_bytes {
+        0 => None,
+        n => Some(n as usize),
+    };
 
-/// Internal implementation of update_state.
-fn update_state_internal(config_ptr: *const u8, config_len: u32) -> String {
-    // Initialize panic hook for better error messages
-    init_panic_hook();
+    wasm_evaluator::with_evaluator(|eval| {
+        eval.set_max_config_bytes(max_config_bytes);
+    });
 
-    // SAFETY: The caller guarantees valid memory regions
-    let config_str = match unsafe { string_from_memory(config_ptr, config_len) } {
-        Ok(s) => s,
-        Err(e) => {
-            return serde_json::json!({
-                "success": false,
-                "error": format!("Failed to read configuration: {}", e),
-                "changedFlags": null
-            })
-            .to_string()
-        }
-    };
+    let response = serde_json::json!({
+        "success": true,
+        "error": null
+    })
+    .to_string();
 
-    // Parse and store the configuration using the singleton evaluator
+    string_to_memory(&response)
+}
+
+/// Sets the `$flagd.timestamp` used when the host provides no time (WASM export).
+///
+/// See `FlagEvaluator::set_default_timestamp`. The default is 0.
+///
+/// # Arguments
+/// * `timestamp` - Unix timestamp in seconds
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the response JSON string.
+///
+/// # Response Format
+/// ```json
+/// {
+///   "success": true,
+///   "error": null
+/// }
+/// ```
+///
+/// # Safety
+/// The caller will free the returned memory using `dealloc`
+#[export_name = "set_default_timestamp"]
+pub extern "C" fn set_default_timestamp_wasm(timestamp: u64) -> u64 {
     wasm_evaluator::with_evaluator(|eval| {
-        match eval.update_state(&config_str) {
-            Ok(response) => {
-                // Convert UpdateStateResponse to JSON
-                serde_json::to_string(&response).unwrap_or_else(|e| {
-                    serde_json::json!({
-                        "success": false,
-                        "error": format!("Failed to serialize response: {}", e),
-                        "changedFlags": null
-                    })
-                    .to_string()
-                })
-            }
-            Err(e) => serde_json::json!({
-                "success": false,
-                "error": e,
-                "changedFlags": null
-            })
-            .to_string(),
-        }
+        eval.set_default_timestamp(timestamp);
+    });
+
+    let response = serde_json::json!({
+        "success": true,
+        "error": null
     })
+    .to_string();
+
+    string_to_memory(&response)
 }
 
-/// Evaluates a feature flag against the provided context.
+/// Enables compact responses for unchanged configurations (WASM export).
 ///
-/// This function retrieves a flag from the previously stored state (set via `update_state`)
-/// and evaluates it against the provided context.
+/// See `FlagEvaluator::set_compact_unchanged_updates`. Hosts polling a configuration
+/// that rarely changes can enable this so that re-sending the stored configuration
+/// to `update_state` returns `{"success":true,"changed":false}` instead of the full
+/// response. Disabled by default.
 ///
 /// # Arguments
-/// * `flag_key_ptr` - Pointer to the flag key string in WASM memory
-/// * `flag_key_len` - Length of the flag key string
-/// * `context_ptr` - Pointer to the evaluation context JSON string in WASM memory
-/// * `context_len` - Length of the evaluation context JSON string
+/// * `enabled` - 0 to disable, any other value to enable
 ///
 /// # Returns
 /// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
-/// of the EvaluationResult JSON string.
+/// of the response JSON string.
 ///
 /// # Response Format
-/// The response matches the flagd provider specification:
 /// ```json
 /// {
-///   "value": <resolved_value>,
-///   "variant": "variant_name",
-///   "reason": "STATIC"|"DEFAULT"|"TARGETING_MATCH"|"DISABLED"|"ERROR"|"FLAG_NOT_FOUND",
-///   "errorCode": "FLAG_NOT_FOUND"|"PARSE_ERROR"|"TYPE_MISMATCH"|"GENERAL",
-///   "errorMessage": "error description"
+///   "success": true,
+///   "error": null
 /// }
 /// ```
 ///
 /// # Safety
-/// The caller must ensure:
-/// - `flag_key_ptr` and `context_ptr` point to valid memory
-/// - The memory regions are valid UTF-8
-/// - The caller will free the returned memory using `dealloc`
-/// - The input buffers (flag_key and context) are freed by this function - caller should NOT dealloc them
-/// - For empty context, pass context_ptr=0 and context_len=0 to skip allocation entirely
-#[no_mangle]
-pub extern "C" fn evaluate(
-    flag_key_ptr: *mut u8,
-    flag_key_len: u32,
-    context_ptr: *mut u8,
-    context_len: u32,
-) -> u64 {
-    let result = evaluate_internal(
-        flag_key_ptr as *const u8,
-        flag_key_len,
-        context_ptr as *const u8,
-        context_len,
-    );
+/// The caller will free the returned memory using `dealloc`
+#[export_name = "set_compact_unchanged_updates"]
+pub extern "C" fn set_compact_unchanged_updates_wasm(enabled: u32) -> u64 {
+    wasm_evaluator::with_evaluator(|eval| {
+        eval.set_compact_unchanged_updates(enabled != 0);
+    });
 
-    // Free input buffers - caller no longer needs to call dealloc for these
-    // This saves 2 WASM calls per evaluation
-    wasm_dealloc(flag_key_ptr, flag_key_len);
-    // Only dealloc context if it was actually allocated (non-null pointer with length > 0)
-    if !context_ptr.is_null() && context_len > 0 {
-        wasm_dealloc(context_ptr, context_len);
-    }
+    let response = serde_json::json!({
+        "success": true,
+        "error": null
+    })
+    .to_string();
 
-    string_to_memory(&result.to_json_string())
+    string_to_memory(&response)
 }
 
-/// Evaluates a feature flag using pre-allocated buffers (no input deallocation).
+/// Reports changed flags as a bitmap over the flag indices (WASM export).
 ///
-/// This is a high-performance variant designed for use with pre-allocated buffers.
-/// Unlike `evaluate` and `evaluate_binary`, this function does NOT deallocate the
-/// input buffers, allowing them to be reused across multiple evaluations.
+/// See `FlagEvaluator::set_changed_flags_bitmap`. Hosts that keep the flag ordering
+/// of `flagIndices` can enable this so that `update_state` returns
+/// `changedFlagsBitmap` and `removedFlags` instead of the `changedFlags` key list,
+/// which keeps responses small for large flag sets with few changes. Bit `i` of the
+/// bitmap (least significant bit of byte `i / 8`, bytes as hex pairs) stands for
+/// the flag with index `i`. Disabled by default.
 ///
 /// # Arguments
-/// * `flag_key_ptr` - Pointer to the flag key string in WASM memory
-/// * `flag_key_len` - Length of the flag key string
-/// * `context_ptr` - Pointer to the evaluation context JSON string in WASM memory
-/// * `context_len` - Length of the evaluation context JSON string
+/// * `enabled` - 0 to disable, any other value to enable
 ///
 /// # Returns
 /// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
-/// of the JSON-encoded EvaluationResult string.
+/// of the response JSON string.
+///
+/// # Response Format
+/// ```json
+/// {
+///   "success": true,
+///   "error": null
+/// }
+/// ```
 ///
 /// # Safety
-/// The caller must ensure:
-/// - `flag_key_ptr` and `context_ptr` point to valid memory
-/// - The memory regions are valid UTF-8
-/// - The caller manages the input buffer lifecycle (they are NOT freed by this function)
-/// - The caller will free the returned result memory using `dealloc`
-/// - For empty context, pass context_ptr=0 and context_len=0
-#[no_mangle]
-pub extern "C" fn evaluate_reusable(
-    flag_key_ptr: *const u8,
-    flag_key_len: u32,
-    context_ptr: *const u8,
-    context_len: u32,
-) -> u64 {
-    let result = evaluate_internal(flag_key_ptr, flag_key_len, context_ptr, context_len);
+/// The caller will free the returned memory using `dealloc`
+#[export_name = "set_changed_flags_bitmap"]
+pub extern "C" fn set_changed_flags_bitmap_wasm(enabled: u32) -> u64 {
+    wasm_evaluator::with_evaluator(|eval| {
+        eval.set_changed_flags_bitmap(enabled != 0);
+    });
 
-    // NOTE: We do NOT deallocate input buffers here - caller manages them
-    // This allows buffer reuse across multiple evaluations
+    let response = serde_json::json!({
+        "success": true,
+        "error": null
+    })
+    .to_string();
 
-    // Return JSON string (simple and sufficient for small result payloads)
-    string_to_memory(&result.to_json_string())
+    string_to_memory(&response)
 }
 
-/// Evaluates a feature flag by numeric index with pre-enriched context.
+/// Sets the context field that feeds `targetingKey` (WASM export).
 ///
-/// This is a high-performance variant that:
-/// - Uses O(1) Vec index lookup instead of string-based HashMap lookup
-/// - Expects the context to be pre-enriched with `$flagd.*` and `targetingKey` by the host
-/// - Does NOT deallocate input buffers (caller manages them)
+/// See `FlagEvaluator::set_targeting_key_field`. Hosts whose contexts identify users by
+/// another field (e.g. `userId`) can use it for fractional bucketing without renaming it.
+/// Set this before `update_state` so `requiredContextKeys` includes the field.
 ///
 /// # Arguments
-/// * `flag_index` - Numeric index from the `flagIndices` map returned by `update_state`
-/// * `context_ptr` - Pointer to the pre-enriched evaluation context JSON string
-/// * `context_len` - Length of the context string
+/// * `field_ptr` - Pointer to the field name string in WASM memory
+/// * `field_len` - Length of the field name string (must not be 0)
 ///
 /// # Returns
 /// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
-/// of the JSON-encoded EvaluationResult string.
+/// of the response JSON string.
+///
+/// # Response Format
+/// ```json
+/// {
+///   "success": true,
+///   "error": null
+/// }
+/// ```
+///
+/// `errorCode` is only present on failure.
 ///
 /// # Safety
 /// The caller must ensure:
-/// - `context_ptr` points to valid memory (or is null with context_len=0)
-/// - The memory region is valid UTF-8
-/// - The caller manages the input buffer lifecycle (NOT freed by this function)
-/// - The caller will free the returned result memory using `dealloc`
-#[no_mangle]
-pub extern "C" fn evaluate_by_index(
-    flag_index: u32,
-    context_ptr: *const u8,
-    context_len: u32,
-) -> u64 {
-    let result = evaluate_by_index_internal(flag_index, context_ptr, context_len);
-    string_to_memory(&result.to_json_string())
+/// - `field_ptr` points to valid UTF-8 memory
+/// - The caller manages the input buffer lifecycle (it is NOT freed by this function)
+/// - The caller will free the returned memory using `dealloc`
+#[export_name = "set_targeting_key_field"]
+pub extern "C" fn set_targeting_key_field_wasm(field_ptr: *const u8, field_len: u32) -> u64 {
+    string_to_memory(&set_targeting_key_field_internal(field_ptr, field_len))
 }
 
-/// Internal implementation of evaluate_by_index.
-fn evaluate_by_index_internal(
-    flag_index: u32,
-    context_ptr: *const u8,
-    context_len: u32,
-) -> EvaluationResult {
+/// Internal implementation of set_targeting_key_field.
+fn set_targeting_key_field_internal(field_ptr: *const u8, field_len: u32) -> String {
+    // SAFETY: The caller guarantees valid memory regions
+    let field = match unsafe { string_from_memory(field_ptr, field_len) } {
+        Ok(field) if !field.is_empty() => field,
+        Ok(_) => {
+            return serde_json::json!({
+                "success": false,
+                "error": "Targeting key field must not be empty",
+                "errorCode": UpdateErrorCode::InvalidArgument
+            })
+            .to_string()
+        }
+        Err(e) => {
+            return serde_json::json!({
+                "success": false,
+                "error": format!("Failed to read targeting key field: {}", e),
+                "errorCode": UpdateErrorCode::InvalidInput
+            })
+            .to_string()
+        }
+    };
+
+    wasm_evaluator::with_evaluator(|eval| {
+        eval.set_targeting_key_field(Some(field));
+    });
+
+    serde_json::json!({
+        "success": true,
+        "error": null
+    })
+    .to_string()
+}
+
+/// Registers another name for a targeting operator (WASM export).
+///
+/// See `FlagEvaluator::add_operator_alias`. Configurations loaded afterwards may use
+/// the alias wherever the canonical operator is accepted, e.g. `semver` for `sem_ver`.
+/// The default aliases are `semver`, `startsWith` and `endsWith`.
+///
+/// # Arguments
+/// * `alias_ptr` - Pointer to the alias name string in WASM memory
+/// * `alias_len` - Length of the alias name string
+/// * `canonical_ptr` - Pointer to the canonical operator name string in WASM memory
+/// * `canonical_len` - Length of the canonical operator name string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the response JSON string.
+///
+/// # Response Format
+/// ```json
+/// {
+///   "success": true|false,
+///   "error": null|"error message",
+///   "errorCode": "INVALID_ARGUMENT"
+/// }
+/// ```
+///
+/// `errorCode` is only present on failure: `INVALID_ARGUMENT` when the canonical
+/// name is not an operator or the alias already is one.
+///
+/// # Safety
+/// The caller must ensure:
+/// - Both pointers point to valid UTF-8 memory
+/// - The caller manages the input buffer lifecycle (it is NOT freed by this function)
+/// - The caller will free the returned memory using `dealloc`
+#[export_name = "add_operator_alias"]
+pub extern "C" fn add_operator_alias_wasm(
+    alias_ptr: *const u8,
+    alias_len: u32,
+    canonical_ptr: *const u8,
+    canonical_len: u32,
+) -> u64 {
+    string_to_memory(&add_operator_alias_internal(
+        alias_ptr,
+        alias_len,
+        canonical_ptr,
+        canonical_len,
+    ))
+}
+
+/// Internal implementation of add_operator_alias.
+fn add_operator_alias_internal(
+    alias_ptr: *const u8,
+    alias_len: u32,
+    canonical_ptr: *const u8,
+    canonical_len: u32,
+) -> String {
+    // SAFETY: The caller guarantees valid memory regions
+    let names = unsafe {
+        (
+            string_from_memory(alias_ptr, alias_len),
+            string_from_memory(canonical_ptr, canonical_len),
+        )
+    };
+    let (alias, canonical) = match names {
+        (Ok(alias), Ok(canonical)) => (alias, canonical),
+        (Err(e), _) | (_, Err(e)) => {
+            return serde_json::json!({
+                "success": false,
+                "error": format!("Failed to read operator alias: {}", e),
+                "errorCode": UpdateErrorCode::InvalidInput
+            })
+            .to_string()
+        }
+    };
+
+    match wasm_evaluator::with_evaluator(|eval| eval.add_operator_alias(&alias, &canonical)) {
+        Ok(()) => serde_json::json!({
+            "success": true,
+            "error": null
+        })
+        .to_string(),
+        Err(e) => serde_json::json!({
+            "success": false,
+            "error": e,
+            "errorCode": UpdateErrorCode::InvalidArgument
+        })
+        .to_string(),
+    }
+}
+
+/// Framing of the single-result evaluation exports, as a `ResultFraming` discriminant.
+static RESULT_FRAMING: AtomicU8 = AtomicU8::new(0);
+
+/// Returns the framing set through `set_result_framing`.
+fn result_framing() -> ResultFraming {
+    match RESULT_FRAMING.load(Ordering::Relaxed) {
+        1 => ResultFraming::StatusPrefixed,
+        _ => ResultFraming::Json,
+    }
+}
+
+/// Writes an evaluation result to WASM memory using the current framing.
+fn result_to_memory(result: &EvaluationResult) -> u64 {
+    bytes_to_memory(&result.to_framed_bytes(result_framing()))
+}
+
+/// Sets how single-result evaluation exports frame their response (WASM export).
+///
+/// With status prefixing, `evaluate`, `evaluate_reusable`, `evaluate_by_index`,
+/// `evaluate_rule_for_flag`, `evaluate_inline`, `resolve_default` and
+/// `evaluate_preview` return one status byte before the result JSON, so hosts can
+/// branch on errors without parsing it:
+///
+/// ```text
+/// [status] [EvaluationResult JSON]
+/// status: 0 = success, 1 = FLAG_NOT_FOUND, 2 = PARSE_ERROR, 3 = TYPE_MISMATCH, 4 = GENERAL
+/// ```
+///
+/// The returned length includes the status byte.
+///
+/// # Arguments
+/// * `mode` - Framing: 0 = JSON only (the default), 1 = status byte followed by JSON
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the response JSON string.
+///
+/// # Response Format
+/// ```json
+/// {
+///   "success": true|false,
+///   "error": null|"error message",
+///   "errorCode": "INVALID_ARGUMENT"
+/// }
+/// ```
+///
+/// `errorCode` is only present on failure.
+///
+/// # Safety
+/// The caller will free the returned memory using `dealloc`
+#[export_name = "set_result_framing"]
+pub extern "C" fn set_result_framing_wasm(mode: u32) -> u64 {
+    string_to_memory(&set_result_framing_internal(mode))
+}
+
+/// Internal implementation of set_result_framing.
+fn set_result_framing_internal(mode: u32) -> String {
+    let framing =
+        match mode {
+            0 => ResultFraming::Json,
+            1 => ResultFraming::StatusPrefixed,
+            _ => return serde_json::json!({
+                "success": false,
+                "error": "Invalid result framing. Use 0 for JSON or 1 for status-prefixed JSON.",
+                "errorCode": UpdateErrorCode::InvalidArgument
+            })
+            .to_string(),
+        };
+    RESULT_FRAMING.store(framing as u8, Ordering::Relaxed);
+
+    serde_json::json!({
+        "success": true,
+        "error": null
+    })
+    .to_string()
+}
+
+/// Restores every option to its default, keeping the loaded flags (WASM export).
+///
+/// See `FlagEvaluator::reset_options`. Also resets the framing set through
+/// `set_result_framing` to plain JSON, so the module behaves as freshly
+/// instantiated apart from its flag state, which `update_state` replaces.
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the response JSON string.
+///
+/// # Response Format
+/// ```json
+/// {
+///   "success": true,
+///   "error": null
+/// }
+/// ```
+///
+/// # Safety
+/// The caller will free the returned memory using `dealloc`
+#[export_name = "reset_options"]
+pub extern "C" fn reset_options_wasm() -> u64 {
+    string_to_memory(&reset_options_internal())
+}
+
+/// Internal implementation of reset_options.
+fn reset_options_internal() -> String {
+    wasm_evaluator::with_evaluator(|eval| eval.reset_options());
+    RESULT_FRAMING.store(ResultFraming::Json as u8, Ordering::Relaxed);
+
+    serde_json::json!({
+        "success": true,
+        "error": null
+    })
+    .to_string()
+}
+
+/// Reports the health of the evaluator (WASM export).
+///
+/// See `FlagEvaluator::health_check`. A host that does not provide
+/// `get_current_time_unix_seconds` shows up as `"timeAvailable": false` with a warning.
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the response JSON string.
+///
+/// # Response Format
+/// ```json
+/// {
+///   "stateLoaded": true,
+///   "stateVersion": 3,
+///   "flagCount": 12,
+///   "flagSetDisabled": false,
+///   "timeAvailable": false,
+///   "warnings": ["Current time is unavailable; $flagd.timestamp uses the default timestamp 0"]
+/// }
+/// ```
+///
+/// # Safety
+/// The caller will free the returned memory using `dealloc`
+#[export_name = "health_check"]
+pub extern "C" fn health_check_wasm() -> u64 {
+    string_to_memory(&health_check_internal())
+}
+
+/// Internal implementation of health_check.
+fn health_check_internal() -> String {
     init_panic_hook();
 
     let result = std::panic::catch_unwind(|| {
-        wasm_evaluator::with_evaluator(|eval| {
-            if eval.get_state().is_none() {
-                return EvaluationResult::error(
-                    ErrorCode::FlagNotFound,
-                    "Flag state not initialized. Call update_state first.",
-                );
+        wasm_evaluator::with_evaluator(|eval| serde_json::to_string(&eval.health_check()))
+    });
+
+    match result {
+        Ok(Ok(json)) => json,
+        _ => r#"{"error":"Failed to build health status"}"#.to_string(),
+    }
+}
+
+/// Updates the feature flag state with a new configuration.
+///
+/// This function parses the provided JSON configuration and stores it in
+/// thread-local storage for later evaluation. It also detects which flags
+/// have changed by comparing the new configuration with the previous state.
+///
+/// # Arguments
+/// * `config_ptr` - Pointer to the JSON configuration string in WASM memory
+/// * `config_len` - Length of the JSON configuration string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the response JSON string. The response includes a list of changed flag keys.
+///
+/// # Response Format
+/// ```json
+/// {
+///   "success": true|false,
+///   "error": null|"error message",
+///   "errorCode": "INVALID_JSON"|"SCHEMA_INVALID"|"SEMANTIC_INVALID"|"CONFIG_TOO_LARGE"|"INVALID_INPUT",
+///   "changedFlags": ["flag1", "flag2", ...]
+/// }
+/// ```
+///
+/// `error` is a human-readable message for display; branch on `errorCode` instead,
+/// which is only present on failure.
+///
+/// The `changedFlags` array contains the keys of all flags that were:
+/// - Added (present in new config but not in old)
+/// - Removed (present in old config but not in new)
+/// - Mutated (default variant, targeting rules, or metadata changed)
+///
+/// # Safety
+/// The caller must ensure:
+/// - `config_ptr` points to valid memory
+/// - The memory region is valid UTF-8
+/// - The caller will free the returned memory using `dealloc`
+#[no_mangle]
+pub extern "C" fn update_state(config_ptr: *const u8, config_len: u32) -> u64 {
+    let response = update_state_internal(config_ptr, config_len);
+    string_to_memory(&response)
+}
+
+/// Internal implementation of update_state.
+fn update_state_internal(config_ptr: *const u8, config_len: u32) -> String {
+    let response = apply_config(config_ptr, config_len);
+    serialize_update_response(&response)
+}
+
+/// Updates the feature flag state and returns the changed flags in a compact binary form.
+///
+/// Behaves exactly like `update_state`, but encodes only the change set, which avoids
+/// the JSON envelope for hosts that sync configurations at high frequency. See
+/// `UpdateStateResponse::to_binary` for the wire format, summarized here (all
+/// integers are little-endian `u32`, strings are a byte length followed by UTF-8):
+///
+/// ```text
+/// success: [0x01] [count] ([key_len] [key bytes])*count
+/// failure: [0x00] [code_len] [code bytes] [message_len] [message bytes]
+/// ```
+///
+/// # Arguments
+/// * `config_ptr` - Pointer to the JSON configuration string in WASM memory
+/// * `config_len` - Length of the JSON configuration string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the binary response.
+///
+/// # Safety
+/// The caller must ensure:
+/// - `config_ptr` points to valid memory
+/// - The memory region is valid UTF-8
+/// - The caller will free the returned memory using `dealloc`
+#[export_name = "update_state_bin"]
+pub extern "C" fn update_state_bin_wasm(config_ptr: *const u8, config_len: u32) -> u64 {
+    bytes_to_memory(&update_state_bin_internal(config_ptr, config_len))
+}
+
+/// Internal implementation of update_state_bin.
+fn update_state_bin_internal(config_ptr: *const u8, config_len: u32) -> Vec<u8> {
+    apply_config(config_ptr, config_len).to_binary()
+}
+
+/// Updates the feature flag state and returns the provider events to emit.
+///
+/// Behaves exactly like `update_state`, but the response also contains an `events`
+/// array derived from the diff, so providers don't need to build events themselves.
+/// An event is only included when the update succeeded and at least one flag changed.
+///
+/// # Arguments
+/// * `config_ptr` - Pointer to the JSON configuration string in WASM memory
+/// * `config_len` - Length of the JSON configuration string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the response JSON string.
+///
+/// # Response Format
+/// All fields of the `update_state` response, plus:
+/// ```json
+/// {
+///   "success": true,
+///   "changedFlags": ["flag1", "flag2"],
+///   "events": [
+///     {"type": "PROVIDER_CONFIGURATION_CHANGED", "flags": ["flag1", "flag2"]}
+///   ]
+/// }
+/// ```
+///
+/// # Safety
+/// The caller must ensure:
+/// - `config_ptr` points to valid memory
+/// - The memory region is valid UTF-8
+/// - The caller will free the returned memory using `dealloc`
+#[export_name = "update_state_with_events"]
+pub extern "C" fn update_state_with_events_wasm(config_ptr: *const u8, config_len: u32) -> u64 {
+    let response = update_state_with_events_internal(config_ptr, config_len);
+    string_to_memory(&response)
+}
+
+/// Internal implementation of update_state_with_events.
+fn update_state_with_events_internal(config_ptr: *const u8, config_len: u32) -> String {
+    let response = UpdateStateWithEventsResponse::from(apply_config(config_ptr, config_len));
+    serialize_update_response(&response)
+}
+
+/// Updates the feature flag state from several configuration fragments.
+///
+/// See `FlagEvaluator::update_state_multi`. The fragments are passed as one JSON
+/// array of configuration objects, e.g. `[{"flags": {...}}, {"flags": {...}}]`, and
+/// merged into a single state. A flag defined by two fragments, or `$evaluators`
+/// with the same name but different definitions, fail the update with
+/// `SEMANTIC_INVALID`.
+///
+/// # Arguments
+/// * `configs_ptr` - Pointer to the JSON array of configurations in WASM memory
+/// * `configs_len` - Length of the JSON array string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the response JSON string, in the same format as `update_state`.
+///
+/// # Safety
+/// The caller must ensure:
+/// - `configs_ptr` points to valid memory
+/// - The memory region is valid UTF-8
+/// - The caller will free the returned memory using `dealloc`
+#[export_name = "update_state_multi"]
+pub extern "C" fn update_state_multi_wasm(configs_ptr: *const u8, configs_len: u32) -> u64 {
+    let response = update_state_multi_internal(configs_ptr, configs_len);
+    string_to_memory(&response)
+}
+
+/// Internal implementation of update_state_multi.
+fn update_state_multi_internal(configs_ptr: *const u8, configs_len: u32) -> String {
+    init_panic_hook();
+
+    // SAFETY: The caller guarantees valid memory regions
+    let response = match unsafe { string_from_memory(configs_ptr, configs_len) } {
+        Err(e) => UpdateStateResponse::failure(
+            UpdateErrorCode::InvalidInput,
+            format!("Failed to read configurations: {}", e),
+        ),
+        Ok(configs) => match serde_json::from_str::<Vec<Value>>(&configs) {
+            Err(e) => UpdateStateResponse::failure(
+                UpdateErrorCode::InvalidJson,
+                format!("Failed to parse configuration array: {}", e),
+            ),
+            Ok(configs) => {
+                let configs: Vec<String> = configs.iter().map(Value::to_string).collect();
+                let configs: Vec<&str> = configs.iter().map(String::as_str).collect();
+                wasm_evaluator::with_evaluator(|eval| {
+                    eval.update_state_multi(&configs).unwrap_or_else(|e| {
+                        UpdateStateResponse::failure(UpdateErrorCode::SemanticInvalid, e)
+                    })
+                })
+            }
+        },
+    };
+    serialize_update_response(&response)
+}
+
+/// Reads a configuration from WASM memory and stores it in the singleton evaluator.
+fn apply_config(config_ptr: *const u8, config_len: u32) -> UpdateStateResponse {
+    // Initialize panic hook for better error messages
+    init_panic_hook();
+
+    // Reject oversized configurations before copying them out of WASM memory
+    if let Err(e) =
+        wasm_evaluator::with_evaluator(|eval| eval.check_config_size(config_len as usize))
+    {
+        return UpdateStateResponse::failure(UpdateErrorCode::ConfigTooLarge, e);
+    }
+
+    // SAFETY: The caller guarantees valid memory regions
+    let config_str = match unsafe { string_from_memory(config_ptr, config_len) } {
+        Ok(s) => s,
+        Err(e) => {
+            return UpdateStateResponse::failure(
+                UpdateErrorCode::InvalidInput,
+                format!("Failed to read configuration: {}", e),
+            )
+        }
+    };
+
+    // Parse and store the configuration using the singleton evaluator
+    wasm_evaluator::with_evaluator(|eval| {
+        eval.update_state(&config_str)
+            .unwrap_or_else(|e| UpdateStateResponse::failure(UpdateErrorCode::SemanticInvalid, e))
+    })
+}
+
+/// Serializes an update response, reporting serialization failures as a failed update.
+fn serialize_update_response<T: serde::Serialize>(response: &T) -> String {
+    serde_json::to_string(response).unwrap_or_else(|e| {
+        serde_json::json!({
+            "success": false,
+            "error": format!("Failed to serialize response: {}", e),
+            "changedFlags": null
+        })
+        .to_string()
+    })
+}
+
+/// Lints a flag configuration without modifying the stored state.
+///
+/// In addition to schema validation errors, this reports configurations that are
+/// valid but likely mistakes (unused evaluators, duplicate variant values, suspicious
+/// fractional weights, missing default variant). The result does not depend on the
+/// current validation mode.
+///
+/// # Arguments
+/// * `config_ptr` - Pointer to the JSON configuration string in WASM memory
+/// * `config_len` - Length of the JSON configuration string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the response JSON string.
+///
+/// # Response Format
+/// ```json
+/// [
+///   {"severity": "error"|"warning"|"info", "path": "/flags/myFlag", "message": "..."}
+/// ]
+/// ```
+///
+/// # Safety
+/// The caller must ensure:
+/// - `config_ptr` points to valid memory
+/// - The memory region is valid UTF-8
+/// - The caller will free the returned memory using `dealloc`
+#[export_name = "lint_config"]
+pub extern "C" fn lint_config_wasm(config_ptr: *const u8, config_len: u32) -> u64 {
+    let response = lint_config_internal(config_ptr, config_len);
+    string_to_memory(&response)
+}
+
+/// Internal implementation of lint_config.
+fn lint_config_internal(config_ptr: *const u8, config_len: u32) -> String {
+    init_panic_hook();
+
+    // SAFETY: The caller guarantees valid memory regions
+    let lints = match unsafe { string_from_memory(config_ptr, config_len) } {
+        Ok(config_str) => lint::lint_config(&config_str),
+        Err(e) => vec![lint::LintWarning {
+            severity: lint::LintSeverity::Error,
+            path: String::new(),
+            message: format!("Failed to read configuration: {}", e),
+        }],
+    };
+
+    serde_json::to_string(&lints).unwrap_or_else(|_| "[]".to_string())
+}
+
+/// Compiles each flag's targeting in a configuration without storing it.
+///
+/// See `FlagEvaluator::compile_report`. Unlike `update_state`, which stops at the
+/// first rule that fails to compile, every flag is reported, so CI checks can list
+/// all failures at once. Rules are compiled with the current evaluator's options.
+///
+/// # Arguments
+/// * `config_ptr` - Pointer to the JSON configuration string in WASM memory
+/// * `config_len` - Length of the JSON configuration string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the response JSON string.
+///
+/// # Response Format
+/// ```json
+/// {
+///   "flags": {
+///     "goodFlag": null,
+///     "badFlag": "Evaluator 'missing' not found in $evaluators"
+///   }
+/// }
+/// ```
+///
+/// If the configuration cannot be read, the response is `{"error": "..."}`.
+///
+/// # Safety
+/// The caller must ensure:
+/// - `config_ptr` points to valid memory
+/// - The memory region is valid UTF-8
+/// - The caller will free the returned memory using `dealloc`
+#[export_name = "compile_report"]
+pub extern "C" fn compile_report_wasm(config_ptr: *const u8, config_len: u32) -> u64 {
+    string_to_memory(&compile_report_internal(config_ptr, config_len))
+}
+
+/// Internal implementation of compile_report.
+fn compile_report_internal(config_ptr: *const u8, config_len: u32) -> String {
+    init_panic_hook();
+
+    let result = std::panic::catch_unwind(|| {
+        // SAFETY: The caller guarantees valid memory regions
+        let config = unsafe { string_from_memory(config_ptr, config_len) }
+            .map_err(|e| format!("Failed to read configuration: {}", e))?;
+        wasm_evaluator::with_evaluator(|eval| eval.compile_report(&config))
+    });
+
+    let response = match result {
+        Ok(Ok(report)) => {
+            let flags: serde_json::Map<String, Value> = report
+                .into_iter()
+                .map(|(key, error)| (key, error.map_or(Value::Null, Value::String)))
+                .collect();
+            serde_json::json!({ "flags": flags })
+        }
+        Ok(Err(error)) => serde_json::json!({ "error": error }),
+        Err(panic_err) => serde_json::json!({ "error": evaluation_panic_message(&*panic_err) }),
+    };
+    response.to_string()
+}
+
+/// Validates a single targeting rule without a flag or stored state.
+///
+/// See `validate_targeting`. Reports unknown operators, wrong argument counts and
+/// `$ref`s that do not name one of the given evaluators.
+///
+/// # Arguments
+/// * `rule_ptr` - Pointer to the targeting rule JSON string in WASM memory
+/// * `rule_len` - Length of the targeting rule JSON string
+/// * `evaluators_ptr` - Pointer to the `$evaluators` JSON object in WASM memory
+/// * `evaluators_len` - Length of the `$evaluators` JSON string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the response JSON string.
+///
+/// # Response Format
+/// ```json
+/// {
+///   "valid": false,
+///   "errors": [{"path": "/if/0", "message": "Unknown operator 'equals'"}]
+/// }
+/// ```
+///
+/// # Safety
+/// The caller must ensure:
+/// - `rule_ptr` and `evaluators_ptr` point to valid memory
+/// - The memory regions are valid UTF-8
+/// - The caller will free the returned memory using `dealloc`
+/// - Without evaluators, pass evaluators_ptr=0 and evaluators_len=0
+#[export_name = "validate_targeting"]
+pub extern "C" fn validate_targeting_wasm(
+    rule_ptr: *const u8,
+    rule_len: u32,
+    evaluators_ptr: *const u8,
+    evaluators_len: u32,
+) -> u64 {
+    let response = validate_targeting_internal(rule_ptr, rule_len, evaluators_ptr, evaluators_len);
+    string_to_memory(&response.to_json_string())
+}
+
+/// Internal implementation of validate_targeting.
+fn validate_targeting_internal(
+    rule_ptr: *const u8,
+    rule_len: u32,
+    evaluators_ptr: *const u8,
+    evaluators_len: u32,
+) -> ValidationResult {
+    let read_json = |ptr: *const u8, len: u32, what: &str| {
+        // SAFETY: The caller guarantees valid memory regions
+        let s = unsafe { string_from_memory(ptr, len) }
+            .map_err(|e| format!("Failed to read {}: {}", what, e))?;
+        serde_json::from_str::<Value>(&s)
+            .map_err(|e| format!("Failed to parse {} JSON: {}", what, e))
+    };
+    let failure =
+        |message: String| ValidationResult::failure(vec![ValidationError::new("", message)]);
+
+    let rule = match read_json(rule_ptr, rule_len, "rule") {
+        Ok(rule) => rule,
+        Err(e) => return failure(e),
+    };
+    let evaluators = if evaluators_ptr.is_null() || evaluators_len == 0 {
+        None
+    } else {
+        match read_json(evaluators_ptr, evaluators_len, "evaluators") {
+            Ok(evaluators) => Some(evaluators),
+            Err(e) => return failure(e),
+        }
+    };
+
+    validate_targeting(&rule, evaluators.as_ref())
+}
+
+/// Returns the most recent state changes, oldest first.
+///
+/// See `FlagEvaluator::change_history`. Each successful `update_state` is recorded with
+/// the flags it added, removed, and mutated, keyed by the resulting state version.
+///
+/// # Arguments
+/// * `limit` - Maximum number of entries to return; 0 returns all kept entries
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the response JSON string.
+///
+/// # Response Format
+/// ```json
+/// [
+///   {
+///     "version": 4,
+///     "timestamp": 1735689600,
+///     "added": ["newFlag"],
+///     "removed": [],
+///     "mutated": ["myFlag"]
+///   }
+/// ]
+/// ```
+///
+/// # Safety
+/// The caller will free the returned memory using `dealloc`
+#[export_name = "get_change_history"]
+pub extern "C" fn get_change_history_wasm(limit: u32) -> u64 {
+    string_to_memory(&get_change_history_internal(limit))
+}
+
+/// Internal implementation of get_change_history.
+fn get_change_history_internal(limit: u32) -> String {
+    init_panic_hook();
+
+    let result = std::panic::catch_unwind(|| {
+        wasm_evaluator::with_evaluator(|eval| {
+            serde_json::to_string(&eval.change_history(limit as usize))
+        })
+    });
+
+    match result {
+        Ok(Ok(json)) => json,
+        _ => "[]".to_string(),
+    }
+}
+
+/// Lists every stored flag without evaluating it.
+///
+/// See `FlagEvaluator::inventory`. Returns an empty array if no state is loaded.
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the response JSON string.
+///
+/// # Response Format
+/// ```json
+/// [
+///   {
+///     "key": "myFlag",
+///     "state": "ENABLED",
+///     "type": "BOOLEAN",
+///     "variants": ["off", "on"],
+///     "hasTargeting": true,
+///     "defaultVariant": "off"
+///   }
+/// ]
+/// ```
+///
+/// # Safety
+/// The caller will free the returned memory using `dealloc`
+#[export_name = "inventory"]
+pub extern "C" fn inventory_wasm() -> u64 {
+    string_to_memory(&inventory_internal())
+}
+
+/// Internal implementation of inventory.
+fn inventory_internal() -> String {
+    init_panic_hook();
+
+    let result = std::panic::catch_unwind(|| {
+        wasm_evaluator::with_evaluator(|eval| serde_json::to_string(&eval.inventory()))
+    });
+
+    match result {
+        Ok(Ok(json)) => json,
+        _ => "[]".to_string(),
+    }
+}
+
+/// Lists every stored flag's key, state, default variant and type (WASM export).
+///
+/// See `FlagEvaluator::list_flags`. Lets hosts discover the loaded flags after
+/// `update_state` without keeping a copy of the configuration. The type is inferred
+/// from the default variant's value. Returns an empty array if no state is loaded.
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the response JSON string.
+///
+/// # Response Format
+/// ```json
+/// [
+///   {
+///     "key": "myFlag",
+///     "state": "ENABLED",
+///     "defaultVariant": "on",
+///     "type": "boolean"
+///   }
+/// ]
+/// ```
+///
+/// # Safety
+/// The caller will free the returned memory using `dealloc`
+#[export_name = "list_flags"]
+pub extern "C" fn list_flags_wasm() -> u64 {
+    string_to_memory(&list_flags_internal())
+}
+
+/// Internal implementation of list_flags.
+fn list_flags_internal() -> String {
+    init_panic_hook();
+
+    let result = std::panic::catch_unwind(|| {
+        wasm_evaluator::with_evaluator(|eval| serde_json::to_string(&eval.list_flags()))
+    });
+
+    match result {
+        Ok(Ok(json)) => json,
+        _ => "[]".to_string(),
+    }
+}
+
+/// Returns the stored configuration in normalized form (WASM export).
+///
+/// See `FlagEvaluator::effective_config`. `$ref`s are inlined, annotations dropped
+/// and targeting shorthands spelled out, with keys sorted, so operators can review
+/// and diff what the evaluator actually uses. Returns `null` if no state is loaded.
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the response JSON string.
+///
+/// # Response Format
+/// ```json
+/// {
+///   "flags": {
+///     "myFlag": {
+///       "state": "ENABLED",
+///       "defaultVariant": "off",
+///       "variants": {"off": false, "on": true},
+///       "targeting": {"fractional": [["on", 1], ["off", 1]]},
+///       "metadata": {}
+///     }
+///   }
+/// }
+/// ```
+///
+/// # Safety
+/// The caller will free the returned memory using `dealloc`
+#[export_name = "effective_config"]
+pub extern "C" fn effective_config_wasm() -> u64 {
+    string_to_memory(&effective_config_internal())
+}
+
+/// Internal implementation of effective_config.
+fn effective_config_internal() -> String {
+    init_panic_hook();
+
+    let result = std::panic::catch_unwind(|| {
+        wasm_evaluator::with_evaluator(|eval| eval.effective_config().to_string())
+    });
+
+    result.unwrap_or_else(|_| "null".to_string())
+}
+
+/// Lists the flags whose targeting references a context attribute.
+///
+/// See `FlagEvaluator::flags_referencing`. Dotted paths match nested attributes and
+/// the objects containing them. Returns an empty array if no state is loaded.
+///
+/// # Arguments
+/// * `attribute_ptr` - Pointer to the attribute name (e.g. `country`) in WASM memory
+/// * `attribute_len` - Length of the attribute name
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the response JSON string, e.g. `["checkoutFlag","regionFlag"]`.
+///
+/// # Safety
+/// The caller must ensure:
+/// - `attribute_ptr` points to valid UTF-8 memory
+/// - The caller manages the input buffer lifecycle (it is NOT freed by this function)
+/// - The caller will free the returned memory using `dealloc`
+#[export_name = "flags_referencing"]
+pub extern "C" fn flags_referencing_wasm(attribute_ptr: *const u8, attribute_len: u32) -> u64 {
+    string_to_memory(&flags_referencing_internal(attribute_ptr, attribute_len))
+}
+
+/// Internal implementation of flags_referencing.
+fn flags_referencing_internal(attribute_ptr: *const u8, attribute_len: u32) -> String {
+    init_panic_hook();
+
+    let result = std::panic::catch_unwind(|| {
+        // SAFETY: The caller guarantees valid memory regions
+        let attribute = unsafe { string_from_memory(attribute_ptr, attribute_len) }.ok()?;
+        let flags = wasm_evaluator::with_evaluator(|eval| eval.flags_referencing(&attribute));
+        serde_json::to_string(&flags).ok()
+    });
+
+    match result {
+        Ok(Some(json)) => json,
+        _ => "[]".to_string(),
+    }
+}
+
+/// Lists the flags that a partial context can fully resolve.
+///
+/// See `FlagEvaluator::evaluable_flags`. Hosts building up a context progressively
+/// can resolve these flags now and defer the rest until more attributes are known.
+/// Flags without targeting and disabled flags are always included. Returns an empty
+/// array if no state is loaded or the context is not valid JSON.
+///
+/// # Arguments
+/// * `context_ptr` - Pointer to the evaluation context JSON string in WASM memory
+/// * `context_len` - Length of the evaluation context JSON string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the response JSON string, e.g. `["regionFlag","staticFlag"]`.
+///
+/// # Safety
+/// The caller must ensure:
+/// - `context_ptr` points to valid UTF-8 memory
+/// - The caller manages the input buffer lifecycle (it is NOT freed by this function)
+/// - The caller will free the returned memory using `dealloc`
+#[export_name = "evaluable_flags"]
+pub extern "C" fn evaluable_flags_wasm(context_ptr: *const u8, context_len: u32) -> u64 {
+    string_to_memory(&evaluable_flags_internal(context_ptr, context_len))
+}
+
+/// Internal implementation of evaluable_flags.
+fn evaluable_flags_internal(context_ptr: *const u8, context_len: u32) -> String {
+    init_panic_hook();
+
+    let result = std::panic::catch_unwind(|| {
+        // SAFETY: The caller guarantees valid memory regions
+        let context = unsafe { string_from_memory(context_ptr, context_len) }.ok()?;
+        let context: Value = serde_json::from_str(&context).ok()?;
+        let flags = wasm_evaluator::with_evaluator(|eval| eval.evaluable_flags(&context));
+        serde_json::to_string(&flags).ok()
+    });
+
+    match result {
+        Ok(Some(json)) => json,
+        _ => "[]".to_string(),
+    }
+}
+
+/// Evaluates a feature flag against the provided context.
+///
+/// This function retrieves a flag from the previously stored state (set via `update_state`)
+/// and evaluates it against the provided context.
+///
+/// # Arguments
+/// * `flag_key_ptr` - Pointer to the flag key string in WASM memory
+/// * `flag_key_len` - Length of the flag key string
+/// * `context_ptr` - Pointer to the evaluation context JSON string in WASM memory
+/// * `context_len` - Length of the evaluation context JSON string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the EvaluationResult JSON string.
+///
+/// # Response Format
+/// The response matches the flagd provider specification:
+/// ```json
+/// {
+///   "value": <resolved_value>,
+///   "variant": "variant_name",
+///   "reason": "STATIC"|"DEFAULT"|"TARGETING_MATCH"|"DISABLED"|"ERROR"|"FLAG_NOT_FOUND",
+///   "errorCode": "FLAG_NOT_FOUND"|"PARSE_ERROR"|"TYPE_MISMATCH"|"GENERAL",
+///   "errorMessage": "error description"
+/// }
+/// ```
+///
+/// # Safety
+/// The caller must ensure:
+/// - `flag_key_ptr` and `context_ptr` point to valid memory
+/// - The memory regions are valid UTF-8
+/// - The caller will free the returned memory using `dealloc`
+/// - The input buffers (flag_key and context) are freed by this function - caller should NOT dealloc them
+/// - For empty context, pass context_ptr=0 and context_len=0 to skip allocation entirely
+#[no_mangle]
+pub extern "C" fn evaluate(
+    flag_key_ptr: *mut u8,
+    flag_key_len: u32,
+    context_ptr: *mut u8,
+    context_len: u32,
+) -> u64 {
+    let result = evaluate_internal(
+        flag_key_ptr as *const u8,
+        flag_key_len,
+        context_ptr as *const u8,
+        context_len,
+    );
+
+    // Free input buffers - caller no longer needs to call dealloc for these
+    // This saves 2 WASM calls per evaluation
+    wasm_dealloc(flag_key_ptr, flag_key_len);
+    // Only dealloc context if it was actually allocated (non-null pointer with length > 0)
+    if !context_ptr.is_null() && context_len > 0 {
+        wasm_dealloc(context_ptr, context_len);
+    }
+
+    result_to_memory(&result)
+}
+
+/// Evaluates a feature flag using pre-allocated buffers (no input deallocation).
+///
+/// This is a high-performance variant designed for use with pre-allocated buffers.
+/// Unlike `evaluate` and `evaluate_binary`, this function does NOT deallocate the
+/// input buffers, allowing them to be reused across multiple evaluations.
+///
+/// # Arguments
+/// * `flag_key_ptr` - Pointer to the flag key string in WASM memory
+/// * `flag_key_len` - Length of the flag key string
+/// * `context_ptr` - Pointer to the evaluation context JSON string in WASM memory
+/// * `context_len` - Length of the evaluation context JSON string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the JSON-encoded EvaluationResult string.
+///
+/// # Safety
+/// The caller must ensure:
+/// - `flag_key_ptr` and `context_ptr` point to valid memory
+/// - The memory regions are valid UTF-8
+/// - The caller manages the input buffer lifecycle (they are NOT freed by this function)
+/// - The caller will free the returned result memory using `dealloc`
+/// - For empty context, pass context_ptr=0 and context_len=0
+#[no_mangle]
+pub extern "C" fn evaluate_reusable(
+    flag_key_ptr: *const u8,
+    flag_key_len: u32,
+    context_ptr: *const u8,
+    context_len: u32,
+) -> u64 {
+    let result = evaluate_internal(flag_key_ptr, flag_key_len, context_ptr, context_len);
+
+    // NOTE: We do NOT deallocate input buffers here - caller manages them
+    // This allows buffer reuse across multiple evaluations
+
+    // Return JSON string (simple and sufficient for small result payloads)
+    result_to_memory(&result)
+}
+
+/// Evaluates a feature flag, returning a structured error for any input (WASM export).
+///
+/// Intended for hosts evaluating untrusted flag keys and contexts. Behaves like
+/// `evaluate_reusable`, except that:
+/// - Any bytes are accepted: invalid UTF-8, malformed JSON, and contexts that are not
+///   JSON objects resolve to `PARSE_ERROR` instead of being treated as empty
+/// - The response is always the `EvaluationResult` JSON, whatever the result framing
+///
+/// The release build aborts on panic, so `catch_unwind` cannot turn a panic into an
+/// error inside WASM. The guarantee therefore rests on the evaluation path not
+/// panicking, which the fuzz tests check; natively, a panic is still reported as
+/// `GENERAL`.
+///
+/// # Arguments
+/// * `flag_key_ptr` - Pointer to the flag key string in WASM memory
+/// * `flag_key_len` - Length of the flag key string
+/// * `context_ptr` - Pointer to the evaluation context JSON string in WASM memory
+/// * `context_len` - Length of the evaluation context JSON string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the JSON-encoded EvaluationResult string.
+///
+/// # Safety
+/// The caller must ensure:
+/// - `flag_key_ptr` and `context_ptr` point to memory of the given lengths
+/// - The caller manages the input buffer lifecycle (they are NOT freed by this function)
+/// - The caller will free the returned result memory using `dealloc`
+/// - For empty context, pass context_ptr=0 and context_len=0
+#[export_name = "evaluate_safe"]
+pub extern "C" fn evaluate_safe_wasm(
+    flag_key_ptr: *const u8,
+    flag_key_len: u32,
+    context_ptr: *const u8,
+    context_len: u32,
+) -> u64 {
+    let result = evaluate_safe_internal(flag_key_ptr, flag_key_len, context_ptr, context_len);
+    string_to_memory(&result.to_json_string())
+}
+
+/// Internal implementation of evaluate_safe.
+fn evaluate_safe_internal(
+    flag_key_ptr: *const u8,
+    flag_key_len: u32,
+    context_ptr: *const u8,
+    context_len: u32,
+) -> EvaluationResult {
+    init_panic_hook();
+
+    let result = std::panic::catch_unwind(|| {
+        // SAFETY: The caller guarantees valid memory regions
+        let flag_key = match unsafe { string_from_memory(flag_key_ptr, flag_key_len) } {
+            Ok(s) => s,
+            Err(e) => {
+                return EvaluationResult::error(
+                    ErrorCode::ParseError,
+                    format!("Failed to read flag key: {}", e),
+                )
+            }
+        };
+        let context = match read_batch_context(context_ptr, context_len) {
+            Ok(context @ (Value::Object(_) | Value::Null)) => context,
+            Ok(_) => {
+                return EvaluationResult::error(
+                    ErrorCode::ParseError,
+                    "Context must be a JSON object",
+                )
+            }
+            Err(message) => return EvaluationResult::error(ErrorCode::ParseError, message),
+        };
+
+        wasm_evaluator::with_evaluator(|eval| {
+            if eval.get_state().is_none() {
+                return EvaluationResult::uninitialized();
+            }
+            eval.evaluate_flag(&flag_key, context)
+        })
+    });
+
+    result.unwrap_or_else(|panic_err| {
+        EvaluationResult::error(ErrorCode::General, evaluation_panic_message(&*panic_err))
+    })
+}
+
+/// Evaluates a feature flag by numeric index with pre-enriched context.
+///
+/// This is a high-performance variant that:
+/// - Uses O(1) Vec index lookup instead of string-based HashMap lookup
+/// - Expects the context to be pre-enriched with `$flagd.*` and `targetingKey` by the host
+/// - Does NOT deallocate input buffers (caller manages them)
+///
+/// # Arguments
+/// * `flag_index` - Numeric index from the `flagIndices` map returned by `update_state`
+/// * `context_ptr` - Pointer to the pre-enriched evaluation context JSON string
+/// * `context_len` - Length of the context string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the JSON-encoded EvaluationResult string.
+///
+/// # Safety
+/// The caller must ensure:
+/// - `context_ptr` points to valid memory (or is null with context_len=0)
+/// - The memory region is valid UTF-8
+/// - The caller manages the input buffer lifecycle (NOT freed by this function)
+/// - The caller will free the returned result memory using `dealloc`
+#[no_mangle]
+pub extern "C" fn evaluate_by_index(
+    flag_index: u32,
+    context_ptr: *const u8,
+    context_len: u32,
+) -> u64 {
+    let result = evaluate_by_index_internal(flag_index, context_ptr, context_len);
+    result_to_memory(&result)
+}
+
+/// Internal implementation of evaluate_by_index.
+fn evaluate_by_index_internal(
+    flag_index: u32,
+    context_ptr: *const u8,
+    context_len: u32,
+) -> EvaluationResult {
+    init_panic_hook();
+
+    let result = std::panic::catch_unwind(|| {
+        wasm_evaluator::with_evaluator(|eval| {
+            if eval.get_state().is_none() {
+                return EvaluationResult::uninitialized();
+            }
+
+            // Parse context (pre-enriched by host)
+            let context: Value = if context_ptr.is_null() || context_len == 0 {
+                Value::Null
+            } else {
+                // SAFETY: The caller guarantees valid memory regions
+                let context_str = match unsafe { string_from_memory(context_ptr, context_len) } {
+                    Ok(s) => s,
+                    Err(e) => {
+                        return EvaluationResult::error(
+                            ErrorCode::ParseError,
+                            format!("Failed to read context: {}", e),
+                        )
+                    }
+                };
+
+                match serde_json::from_str(&context_str) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        return EvaluationResult::error(
+                            ErrorCode::ParseError,
+                            format!("Failed to parse context JSON: {}", e),
+                        )
+                    }
+                }
+            };
+
+            eval.evaluate_flag_by_index(flag_index, context)
+        })
+    });
+
+    result.unwrap_or_else(|panic_err| {
+        EvaluationResult::error(ErrorCode::General, evaluation_panic_message(&*panic_err))
+    })
+}
+
+/// Context assembled by `context_begin` and `context_set` for `evaluate_with_builder`.
+static CONTEXT_BUILDER: Mutex<Option<Map<String, Value>>> = Mutex::new(None);
+
+/// Locks the context builder, recovering it if a caught panic poisoned the lock.
+fn context_builder() -> MutexGuard<'static, Option<Map<String, Value>>> {
+    CONTEXT_BUILDER
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Starts a new evaluation context for `evaluate_with_builder` (WASM export).
+///
+/// Hosts that build contexts incrementally can set attributes one at a time with
+/// `context_set` instead of serializing the context to JSON. Any previously built
+/// context is discarded.
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the response JSON string.
+///
+/// # Response Format
+/// ```json
+/// {
+///   "success": true,
+///   "error": null
+/// }
+/// ```
+///
+/// # Safety
+/// The caller will free the returned memory using `dealloc`
+#[export_name = "context_begin"]
+pub extern "C" fn context_begin_wasm() -> u64 {
+    string_to_memory(&context_begin_internal())
+}
+
+/// Internal implementation of context_begin.
+fn context_begin_internal() -> String {
+    *context_builder() = Some(Map::new());
+
+    serde_json::json!({
+        "success": true,
+        "error": null
+    })
+    .to_string()
+}
+
+/// Sets one attribute of the context started by `context_begin` (WASM export).
+///
+/// The value is encoded as a one-byte type tag followed by its text:
+///
+/// ```text
+/// s<text>          string, e.g. "sgold"
+/// n<number>        number, e.g. "n42" or "n-1.5"
+/// btrue | bfalse   boolean
+/// ```
+///
+/// Setting an attribute again replaces its value. The key is stored as is, so a
+/// dotted key such as `user.tier` is a single top-level attribute.
+///
+/// # Arguments
+/// * `key_ptr` - Pointer to the attribute name string in WASM memory
+/// * `key_len` - Length of the attribute name string
+/// * `value_ptr` - Pointer to the tagged value string in WASM memory
+/// * `value_len` - Length of the tagged value string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the response JSON string.
+///
+/// # Response Format
+/// ```json
+/// {
+///   "success": true|false,
+///   "error": null|"error message",
+///   "errorCode": "INVALID_INPUT"|"INVALID_ARGUMENT"
+/// }
+/// ```
+///
+/// `errorCode` is only present on failure.
+///
+/// # Safety
+/// The caller must ensure:
+/// - `key_ptr` and `value_ptr` point to valid UTF-8 memory
+/// - The caller manages the input buffer lifecycle (they are NOT freed by this function)
+/// - The caller will free the returned memory using `dealloc`
+#[export_name = "context_set"]
+pub extern "C" fn context_set_wasm(
+    key_ptr: *const u8,
+    key_len: u32,
+    value_ptr: *const u8,
+    value_len: u32,
+) -> u64 {
+    string_to_memory(&context_set_internal(
+        key_ptr, key_len, value_ptr, value_len,
+    ))
+}
+
+/// Internal implementation of context_set.
+fn context_set_internal(
+    key_ptr: *const u8,
+    key_len: u32,
+    value_ptr: *const u8,
+    value_len: u32,
+) -> String {
+    // SAFETY: The caller guarantees valid memory regions
+    let read = unsafe {
+        (
+            string_from_memory(key_ptr, key_len),
+            string_from_memory(value_ptr, value_len),
+        )
+    };
+    let (key, tagged) = match read {
+        (Ok(key), Ok(tagged)) => (key, tagged),
+        (Err(e), _) | (_, Err(e)) => {
+            return serde_json::json!({
+                "success": false,
+                "error": format!("Failed to read context attribute: {}", e),
+                "errorCode": UpdateErrorCode::InvalidInput
+            })
+            .to_string()
+        }
+    };
+
+    let value = match parse_tagged_value(&tagged) {
+        Ok(value) => value,
+        Err(e) => {
+            return serde_json::json!({
+                "success": false,
+                "error": format!("Invalid value for context attribute '{}': {}", key, e),
+                "errorCode": UpdateErrorCode::InvalidArgument
+            })
+            .to_string()
+        }
+    };
+
+    context_builder()
+        .get_or_insert_with(Map::new)
+        .insert(key, value);
+
+    serde_json::json!({
+        "success": true,
+        "error": null
+    })
+    .to_string()
+}
+
+/// Decodes a `context_set` value from its type tag and text.
+fn parse_tagged_value(tagged: &str) -> Result<Value, String> {
+    let Some(tag) = tagged.chars().next() else {
+        return Err("missing type tag".to_string());
+    };
+    let text = &tagged[tag.len_utf8()..];
+    match tag {
+        's' => Ok(Value::String(text.to_string())),
+        'n' => text
+            .parse::<serde_json::Number>()
+            .map(Value::Number)
+            .map_err(|_| format!("'{}' is not a number", text)),
+        'b' => match text {
+            "true" => Ok(Value::Bool(true)),
+            "false" => Ok(Value::Bool(false)),
+            _ => Err(format!("'{}' is not a boolean", text)),
+        },
+        _ => Err(format!(
+            "unknown type tag '{}', expected 's', 'n' or 'b'",
+            tag
+        )),
+    }
+}
+
+/// Evaluates a feature flag against the context built with `context_set` (WASM export).
+///
+/// Behaves like `evaluate` with the built context as the context JSON. The context
+/// is kept, so several flags can be evaluated against it until the next
+/// `context_begin`. Without a prior `context_begin`, the context is empty.
+///
+/// # Arguments
+/// * `flag_key_ptr` - Pointer to the flag key string in WASM memory
+/// * `flag_key_len` - Length of the flag key string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the JSON-encoded EvaluationResult string.
+///
+/// # Safety
+/// The caller must ensure:
+/// - `flag_key_ptr` points to valid UTF-8 memory
+/// - The caller manages the input buffer lifecycle (it is NOT freed by this function)
+/// - The caller will free the returned result memory using `dealloc`
+#[export_name = "evaluate_with_builder"]
+pub extern "C" fn evaluate_with_builder_wasm(flag_key_ptr: *const u8, flag_key_len: u32) -> u64 {
+    result_to_memory(&evaluate_with_builder_internal(flag_key_ptr, flag_key_len))
+}
+
+/// Internal implementation of evaluate_with_builder.
+fn evaluate_with_builder_internal(flag_key_ptr: *const u8, flag_key_len: u32) -> EvaluationResult {
+    init_panic_hook();
+
+    let result = std::panic::catch_unwind(|| {
+        // SAFETY: The caller guarantees valid memory regions
+        let flag_key = match unsafe { string_from_memory(flag_key_ptr, flag_key_len) } {
+            Ok(s) => s,
+            Err(e) => {
+                return EvaluationResult::error(
+                    ErrorCode::ParseError,
+                    format!("Failed to read flag key: {}", e),
+                )
+            }
+        };
+        let context = context_builder().clone().unwrap_or_default();
+
+        wasm_evaluator::with_evaluator(|eval| {
+            if eval.get_state().is_none() {
+                return EvaluationResult::uninitialized();
+            }
+            eval.evaluate_flag(&flag_key, Value::Object(context))
+        })
+    });
+
+    result.unwrap_or_else(|panic_err| {
+        EvaluationResult::error(ErrorCode::General, evaluation_panic_message(&*panic_err))
+    })
+}
+
+/// Evaluates every flag in the current state against the provided context.
+///
+/// All results are serialized into a single buffer, so batch callers avoid one
+/// allocation and FFI round trip per flag. Flags are ordered by key. If the
+/// context cannot be read or parsed, every flag reports the `PARSE_ERROR` result.
+///
+/// # Arguments
+/// * `context_ptr` - Pointer to the evaluation context JSON string in WASM memory
+/// * `context_len` - Length of the evaluation context JSON string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of a JSON object mapping each flag key to its EvaluationResult.
+///
+/// # Response Format
+/// ```json
+/// {
+///   "flagA": {"value": true, "variant": "on", "reason": "STATIC"},
+///   "flagB": {"value": "blue", "variant": "blue", "reason": "TARGETING_MATCH"}
+/// }
+/// ```
+///
+/// # Safety
+/// The caller must ensure:
+/// - `context_ptr` points to valid memory (or is null with context_len=0)
+/// - The memory region is valid UTF-8
+/// - The caller manages the input buffer lifecycle (NOT freed by this function)
+/// - The caller will free the returned result memory using `dealloc`
+#[export_name = "evaluate_all"]
+pub extern "C" fn evaluate_all_wasm(context_ptr: *const u8, context_len: u32) -> u64 {
+    string_to_memory(&evaluate_all_internal(context_ptr, context_len))
+}
+
+/// Internal implementation of evaluate_all.
+fn evaluate_all_internal(context_ptr: *const u8, context_len: u32) -> String {
+    init_panic_hook();
+
+    let result = std::panic::catch_unwind(|| {
+        wasm_evaluator::with_evaluator(|eval| match read_batch_context(context_ptr, context_len) {
+            Ok(context) => eval.evaluate_all_json(context),
+            Err(message) => {
+                let error = EvaluationResult::error(ErrorCode::ParseError, message);
+                let mut flag_keys: Vec<&String> = eval
+                    .get_state()
+                    .map(|state| state.flags.keys().collect())
+                    .unwrap_or_default();
+                flag_keys.sort();
+                let results: Vec<(String, EvaluationResult)> = flag_keys
+                    .into_iter()
+                    .map(|key| (key.clone(), error.clone()))
+                    .collect();
+                evaluator::results_to_json(&results)
+            }
+        })
+    });
+
+    result.unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Evaluates every flag in the current state as the type inferred from its variants.
+///
+/// Like `evaluate_all`, but each flag is type checked against the type of its
+/// variants and the result carries a `type` field (`BOOLEAN`, `STRING`, `INTEGER`,
+/// `FLOAT`, `OBJECT`, or `MIXED` when the variants don't share a type). This lets a
+/// host fetch a fully-typed snapshot in one call.
+///
+/// # Arguments
+/// * `context_ptr` - Pointer to the evaluation context JSON string in WASM memory
+/// * `context_len` - Length of the evaluation context JSON string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of a JSON object mapping each flag key to its typed result.
+///
+/// # Response Format
+/// ```json
+/// {
+///   "flagA": {"value": true, "variant": "on", "reason": "STATIC", "type": "BOOLEAN"},
+///   "flagB": {"value": "blue", "variant": "blue", "reason": "TARGETING_MATCH", "type": "STRING"}
+/// }
+/// ```
+///
+/// # Safety
+/// The caller must ensure:
+/// - `context_ptr` points to valid memory (or is null with context_len=0)
+/// - The memory region is valid UTF-8
+/// - The caller manages the input buffer lifecycle (NOT freed by this function)
+/// - The caller will free the returned result memory using `dealloc`
+#[export_name = "evaluate_all_typed"]
+pub extern "C" fn evaluate_all_typed_wasm(context_ptr: *const u8, context_len: u32) -> u64 {
+    string_to_memory(&evaluate_all_typed_internal(context_ptr, context_len))
+}
+
+/// Internal implementation of evaluate_all_typed.
+fn evaluate_all_typed_internal(context_ptr: *const u8, context_len: u32) -> String {
+    init_panic_hook();
+
+    let result = std::panic::catch_unwind(|| {
+        wasm_evaluator::with_evaluator(|eval| match read_batch_context(context_ptr, context_len) {
+            Ok(context) => eval.evaluate_all_typed_json(context),
+            Err(message) => {
+                let error = EvaluationResult::error(ErrorCode::ParseError, message);
+                let mut results: Vec<(String, TypedEvaluationResult)> = eval
+                    .get_state()
+                    .map(|state| {
+                        state
+                            .flags
+                            .iter()
+                            .map(|(key, flag)| {
+                                let typed = TypedEvaluationResult {
+                                    result: error.clone(),
+                                    flag_type: flag.flag_type(),
+                                };
+                                (key.clone(), typed)
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                results.sort_by(|a, b| a.0.cmp(&b.0));
+                evaluator::keyed_json(&results, TypedEvaluationResult::write_json)
+            }
+        })
+    });
+
+    result.unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Evaluates only the flags that changed after a given state version.
+///
+/// Combines the change history with batch evaluation so clients can refresh just
+/// the flags affected by recent updates. See `FlagEvaluator::evaluate_changed_since`.
+/// Removed flags report `FLAG_NOT_FOUND`. If the version is not a number, is newer
+/// than the current state, or is older than the kept change history, every flag is
+/// evaluated like `evaluate_all`. The current version is reported by `health_check`.
+///
+/// # Arguments
+/// * `version_ptr` - Pointer to the state version as a decimal string in WASM memory
+/// * `version_len` - Length of the state version string
+/// * `context_ptr` - Pointer to the evaluation context JSON string in WASM memory
+/// * `context_len` - Length of the evaluation context JSON string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of a JSON object mapping each changed flag key to its EvaluationResult.
+///
+/// # Response Format
+/// ```json
+/// {
+///   "flagA": {"value": true, "variant": "on", "reason": "STATIC"},
+///   "removedFlag": {"value": null, "reason": "ERROR", "errorCode": "FLAG_NOT_FOUND", "errorMessage": "..."}
+/// }
+/// ```
+///
+/// # Safety
+/// The caller must ensure:
+/// - `version_ptr` points to valid UTF-8 memory
+/// - `context_ptr` points to valid memory (or is null with context_len=0)
+/// - The caller manages the input buffer lifecycle (NOT freed by this function)
+/// - The caller will free the returned result memory using `dealloc`
+#[export_name = "evaluate_changed_since"]
+pub extern "C" fn evaluate_changed_since_wasm(
+    version_ptr: *const u8,
+    version_len: u32,
+    context_ptr: *const u8,
+    context_len: u32,
+) -> u64 {
+    string_to_memory(&evaluate_changed_since_internal(
+        version_ptr,
+        version_len,
+        context_ptr,
+        context_len,
+    ))
+}
+
+/// Internal implementation of evaluate_changed_since.
+fn evaluate_changed_since_internal(
+    version_ptr: *const u8,
+    version_len: u32,
+    context_ptr: *const u8,
+    context_len: u32,
+) -> String {
+    init_panic_hook();
+
+    let result = std::panic::catch_unwind(|| {
+        // SAFETY: The caller guarantees valid memory regions
+        let version = unsafe { string_from_memory(version_ptr, version_len) }
+            .ok()
+            .and_then(|version| version.trim().parse::<u64>().ok());
+
+        wasm_evaluator::with_evaluator(|eval| match read_batch_context(context_ptr, context_len) {
+            Ok(context) => match version {
+                Some(version) => {
+                    evaluator::results_to_json(&eval.evaluate_changed_since(version, context))
+                }
+                None => eval.evaluate_all_json(context),
+            },
+            Err(message) => {
+                let error = EvaluationResult::error(ErrorCode::ParseError, message);
+                let flag_keys = version
+                    .and_then(|version| eval.flags_changed_since(version))
+                    .unwrap_or_else(|| {
+                        let mut flag_keys: Vec<String> = eval
+                            .get_state()
+                            .map(|state| state.flags.keys().cloned().collect())
+                            .unwrap_or_default();
+                        flag_keys.sort();
+                        flag_keys
+                    });
+                let results: Vec<(String, EvaluationResult)> = flag_keys
+                    .into_iter()
+                    .map(|key| (key, error.clone()))
+                    .collect();
+                evaluator::results_to_json(&results)
+            }
+        })
+    });
+
+    result.unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Evaluates one flag against a sample of contexts and counts the results by variant.
+///
+/// Meant for checking a rollout (e.g. a fractional split) against a realistic user
+/// sample. Results without a variant are counted under their reason. See
+/// `FlagEvaluator::evaluate_distribution`.
+///
+/// # Arguments
+/// * `flag_key_ptr` - Pointer to the flag key string in WASM memory
+/// * `flag_key_len` - Length of the flag key string
+/// * `contexts_ptr` - Pointer to a JSON array of evaluation contexts in WASM memory
+/// * `contexts_len` - Length of the contexts JSON string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the response JSON string.
+///
+/// # Response Format
+/// ```json
+/// {"counts": {"on": 698, "off": 302}}
+/// ```
+/// or `{"error": "..."}` if the inputs cannot be read.
+///
+/// # Safety
+/// The caller must ensure:
+/// - Both pointers point to valid memory
+/// - The memory regions are valid UTF-8
+/// - The caller manages the input buffer lifecycle (NOT freed by this function)
+/// - The caller will free the returned result memory using `dealloc`
+#[export_name = "evaluate_distribution"]
+pub extern "C" fn evaluate_distribution_wasm(
+    flag_key_ptr: *const u8,
+    flag_key_len: u32,
+    contexts_ptr: *const u8,
+    contexts_len: u32,
+) -> u64 {
+    string_to_memory(&evaluate_distribution_internal(
+        flag_key_ptr,
+        flag_key_len,
+        contexts_ptr,
+        contexts_len,
+    ))
+}
+
+/// Internal implementation of evaluate_distribution.
+fn evaluate_distribution_internal(
+    flag_key_ptr: *const u8,
+    flag_key_len: u32,
+    contexts_ptr: *const u8,
+    contexts_len: u32,
+) -> String {
+    init_panic_hook();
+
+    let result = std::panic::catch_unwind(|| {
+        // SAFETY: The caller guarantees valid memory regions
+        let flag_key = unsafe { string_from_memory(flag_key_ptr, flag_key_len) }
+            .map_err(|e| format!("Failed to read flag key: {}", e))?;
+        // SAFETY: The caller guarantees valid memory regions
+        let contexts = unsafe { string_from_memory(contexts_ptr, contexts_len) }
+            .map_err(|e| format!("Failed to read contexts: {}", e))?;
+        let contexts: Vec<Value> = serde_json::from_str(&contexts)
+            .map_err(|e| format!("Failed to parse contexts JSON array: {}", e))?;
+        let counts =
+            wasm_evaluator::with_evaluator(|eval| eval.evaluate_distribution(&flag_key, &contexts));
+        Ok::<_, String>(serde_json::json!({ "counts": counts }))
+    });
+
+    let response = match result {
+        Ok(Ok(response)) => response,
+        Ok(Err(message)) => serde_json::json!({ "error": message }),
+        Err(_) => serde_json::json!({ "error": "Distribution evaluation panicked" }),
+    };
+    response.to_string()
+}
+
+/// Reads and parses an evaluation context passed by pointer (null when empty).
+fn read_batch_context(context_ptr: *const u8, context_len: u32) -> Result<Value, String> {
+    if context_ptr.is_null() || context_len == 0 {
+        return Ok(Value::Null);
+    }
+
+    // SAFETY: The caller guarantees valid memory regions
+    match unsafe { string_from_memory(context_ptr, context_len) } {
+        Ok(s) => {
+            serde_json::from_str(&s).map_err(|e| format!("Failed to parse context JSON: {}", e))
+        }
+        Err(e) => Err(format!("Failed to read context: {}", e)),
+    }
+}
+
+/// Evaluates a candidate targeting rule against a stored flag's variants.
+///
+/// Runs the rule as if it were the flag's targeting, without modifying the stored
+/// configuration, so config authors can test rules in isolation. See
+/// `FlagEvaluator::evaluate_rule_for_flag`.
+///
+/// # Arguments
+/// * `flag_key_ptr` - Pointer to the flag key string in WASM memory
+/// * `flag_key_len` - Length of the flag key string
+/// * `rule_ptr` - Pointer to the targeting rule JSON string in WASM memory
+/// * `rule_len` - Length of the targeting rule JSON string
+/// * `context_ptr` - Pointer to the evaluation context JSON string in WASM memory
+/// * `context_len` - Length of the evaluation context JSON string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the JSON-encoded EvaluationResult string.
+///
+/// # Safety
+/// The caller must ensure:
+/// - `flag_key_ptr`, `rule_ptr` and `context_ptr` point to valid memory
+/// - The memory regions are valid UTF-8
+/// - The caller manages the input buffer lifecycle (they are NOT freed by this function)
+/// - The caller will free the returned result memory using `dealloc`
+/// - For empty context, pass context_ptr=0 and context_len=0
+#[export_name = "evaluate_rule_for_flag"]
+pub extern "C" fn evaluate_rule_for_flag_wasm(
+    flag_key_ptr: *const u8,
+    flag_key_len: u32,
+    rule_ptr: *const u8,
+    rule_len: u32,
+    context_ptr: *const u8,
+    context_len: u32,
+) -> u64 {
+    let result = evaluate_rule_for_flag_internal(
+        flag_key_ptr,
+        flag_key_len,
+        rule_ptr,
+        rule_len,
+        context_ptr,
+        context_len,
+    );
+    result_to_memory(&result)
+}
+
+/// Internal implementation of evaluate_rule_for_flag.
+fn evaluate_rule_for_flag_internal(
+    flag_key_ptr: *const u8,
+    flag_key_len: u32,
+    rule_ptr: *const u8,
+    rule_len: u32,
+    context_ptr: *const u8,
+    context_len: u32,
+) -> EvaluationResult {
+    init_panic_hook();
+
+    let result = std::panic::catch_unwind(|| {
+        wasm_evaluator::with_evaluator(|eval| {
+            if eval.get_state().is_none() {
+                return EvaluationResult::uninitialized();
+            }
+
+            // SAFETY: The caller guarantees valid memory regions
+            let flag_key = match unsafe { string_from_memory(flag_key_ptr, flag_key_len) } {
+                Ok(s) => s,
+                Err(e) => {
+                    return EvaluationResult::error(
+                        ErrorCode::ParseError,
+                        format!("Failed to read flag key: {}", e),
+                    )
+                }
+            };
+
+            let rule_str = match unsafe { string_from_memory(rule_ptr, rule_len) } {
+                Ok(s) => s,
+                Err(e) => {
+                    return EvaluationResult::error(
+                        ErrorCode::ParseError,
+                        format!("Failed to read rule: {}", e),
+                    )
+                }
+            };
+            let rule: Value = match serde_json::from_str(&rule_str) {
+                Ok(v) => v,
+                Err(e) => {
+                    return EvaluationResult::error(
+                        ErrorCode::ParseError,
+                        format!("Failed to parse rule JSON: {}", e),
+                    )
+                }
+            };
+
+            let context: Value = if context_ptr.is_null() || context_len == 0 {
+                Value::Null
+            } else {
+                let context_str = match unsafe { string_from_memory(context_ptr, context_len) } {
+                    Ok(s) => s,
+                    Err(e) => {
+                        return EvaluationResult::error(
+                            ErrorCode::ParseError,
+                            format!("Failed to read context: {}", e),
+                        )
+                    }
+                };
+
+                match serde_json::from_str(&context_str) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        return EvaluationResult::error(
+                            ErrorCode::ParseError,
+                            format!("Failed to parse context JSON: {}", e),
+                        )
+                    }
+                }
+            };
+
+            eval.evaluate_rule_for_flag(&flag_key, &rule, context)
+        })
+    });
+
+    result.unwrap_or_else(|panic_err| {
+        EvaluationResult::error(ErrorCode::General, evaluation_panic_message(&*panic_err))
+    })
+}
+
+/// Evaluates a single flag definition without storing it.
+///
+/// Parses and validates the flag, then evaluates it against the context without
+/// reading or modifying the stored state, so it works before `update_state`. This
+/// suits "try this flag config" UIs. See `FlagEvaluator::evaluate_inline`.
+///
+/// # Arguments
+/// * `flag_ptr` - Pointer to the flag definition JSON string in WASM memory
+/// * `flag_len` - Length of the flag definition JSON string
+/// * `context_ptr` - Pointer to the evaluation context JSON string in WASM memory
+/// * `context_len` - Length of the evaluation context JSON string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the JSON-encoded EvaluationResult string.
+///
+/// # Safety
+/// The caller must ensure:
+/// - `flag_ptr` and `context_ptr` point to valid memory
+/// - The memory regions are valid UTF-8
+/// - The caller manages the input buffer lifecycle (they are NOT freed by this function)
+/// - The caller will free the returned result memory using `dealloc`
+/// - For empty context, pass context_ptr=0 and context_len=0
+#[export_name = "evaluate_inline"]
+pub extern "C" fn evaluate_inline_wasm(
+    flag_ptr: *const u8,
+    flag_len: u32,
+    context_ptr: *const u8,
+    context_len: u32,
+) -> u64 {
+    let result = evaluate_inline_internal(flag_ptr, flag_len, context_ptr, context_len);
+    result_to_memory(&result)
+}
+
+/// Internal implementation of evaluate_inline.
+fn evaluate_inline_internal(
+    flag_ptr: *const u8,
+    flag_len: u32,
+    context_ptr: *const u8,
+    context_len: u32,
+) -> EvaluationResult {
+    init_panic_hook();
+
+    let result = std::panic::catch_unwind(|| {
+        // SAFETY: The caller guarantees valid memory regions
+        let flag_str = match unsafe { string_from_memory(flag_ptr, flag_len) } {
+            Ok(s) => s,
+            Err(e) => {
+                return EvaluationResult::error(
+                    ErrorCode::ParseError,
+                    format!("Failed to read flag: {}", e),
+                )
+            }
+        };
+        let flag: Value = match serde_json::from_str(&flag_str) {
+            Ok(v) => v,
+            Err(e) => {
+                return EvaluationResult::error(
+                    ErrorCode::ParseError,
+                    format!("Failed to parse flag JSON: {}", e),
+                )
+            }
+        };
+
+        let context = match read_batch_context(context_ptr, context_len) {
+            Ok(context) => context,
+            Err(message) => return EvaluationResult::error(ErrorCode::ParseError, message),
+        };
+
+        wasm_evaluator::with_evaluator(|eval| eval.evaluate_inline(&flag, context))
+    });
+
+    result.unwrap_or_else(|panic_err| {
+        EvaluationResult::error(ErrorCode::General, evaluation_panic_message(&*panic_err))
+    })
+}
+
+/// Validates a configuration and evaluates one of its flags in a single call.
+///
+/// For tooling that tests a configuration against sample contexts: the configuration
+/// is validated and, unless validation fails in strict mode, evaluated without being
+/// stored. See `FlagEvaluator::validate_and_evaluate`.
+///
+/// # Arguments
+/// * `config_ptr` - Pointer to the configuration JSON string in WASM memory
+/// * `config_len` - Length of the configuration JSON string
+/// * `flag_key_ptr` - Pointer to the flag key string in WASM memory
+/// * `flag_key_len` - Length of the flag key string
+/// * `context_ptr` - Pointer to the evaluation context JSON string in WASM memory
+/// * `context_len` - Length of the evaluation context JSON string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the response JSON string.
+///
+/// # Response Format
+/// ```json
+/// {
+///   "validation": {"valid": true, "errors": []},
+///   "result": {"value": true, "variant": "on", "reason": "STATIC", ...}
+/// }
+/// ```
+///
+/// `result` is `null` when validation fails in strict mode.
+///
+/// # Safety
+/// The caller must ensure:
+/// - `config_ptr`, `flag_key_ptr` and `context_ptr` point to valid memory
+/// - The memory regions are valid UTF-8
+/// - The caller manages the input buffer lifecycle (they are NOT freed by this function)
+/// - The caller will free the returned memory using `dealloc`
+/// - For empty context, pass context_ptr=0 and context_len=0
+#[export_name = "validate_and_evaluate"]
+pub extern "C" fn validate_and_evaluate_wasm(
+    config_ptr: *const u8,
+    config_len: u32,
+    flag_key_ptr: *const u8,
+    flag_key_len: u32,
+    context_ptr: *const u8,
+    context_len: u32,
+) -> u64 {
+    string_to_memory(&validate_and_evaluate_internal(
+        config_ptr,
+        config_len,
+        flag_key_ptr,
+        flag_key_len,
+        context_ptr,
+        context_len,
+    ))
+}
+
+/// Internal implementation of validate_and_evaluate.
+fn validate_and_evaluate_internal(
+    config_ptr: *const u8,
+    config_len: u32,
+    flag_key_ptr: *const u8,
+    flag_key_len: u32,
+    context_ptr: *const u8,
+    context_len: u32,
+) -> String {
+    init_panic_hook();
+
+    let result = std::panic::catch_unwind(|| {
+        // SAFETY: The caller guarantees valid memory regions
+        let config = match unsafe { string_from_memory(config_ptr, config_len) } {
+            Ok(s) => s,
+            Err(e) => {
+                let error = ValidationError::new("", format!("Failed to read config: {}", e));
+                return (ValidationResult::failure(vec![error]), None);
+            }
+        };
+        // SAFETY: The caller guarantees valid memory regions
+        let flag_key = match unsafe { string_from_memory(flag_key_ptr, flag_key_len) } {
+            Ok(s) => s,
+            Err(e) => {
+                let result = EvaluationResult::error(
+                    ErrorCode::ParseError,
+                    format!("Failed to read flag key: {}", e),
+                );
+                return (ValidationResult::success(), Some(result));
+            }
+        };
+        let context = match read_batch_context(context_ptr, context_len) {
+            Ok(context) => context,
+            Err(message) => {
+                let result = EvaluationResult::error(ErrorCode::ParseError, message);
+                return (ValidationResult::success(), Some(result));
+            }
+        };
+
+        wasm_evaluator::with_evaluator(|eval| {
+            eval.validate_and_evaluate(&config, &flag_key, context)
+        })
+    });
+
+    let (validation, result) = result.unwrap_or_else(|panic_err| {
+        let result =
+            EvaluationResult::error(ErrorCode::General, evaluation_panic_message(&*panic_err));
+        (ValidationResult::success(), Some(result))
+    });
+    serde_json::json!({
+        "validation": validation,
+        "result": result
+    })
+    .to_string()
+}
+
+/// Evaluates a feature flag and returns only its variant and reason as an integer.
+///
+/// No JSON is produced and nothing is allocated for the result, which suits analytics
+/// pipelines that only record which variant was served. See `VariantCode` for the
+/// full layout:
+///
+/// - bits 32..64: index of the variant in the names returned by `get_variant_names`,
+///   or `0xFFFFFFFF` if no variant was selected
+/// - bits 8..16: error code (0 = none, 1 = FLAG_NOT_FOUND, 2 = PARSE_ERROR,
+///   3 = TYPE_MISMATCH, 4 = GENERAL)
+/// - bits 0..8: reason (0 = STATIC, 1 = DEFAULT, 2 = TARGETING_MATCH, 3 = DISABLED,
+///   4 = ERROR, 5 = FLAG_NOT_FOUND, 6 = FALLBACK, 7 = PREVIEW, 8 = PREREQUISITE_FAILED)
+///
+/// # Arguments
+/// * `flag_key_ptr` - Pointer to the flag key string in WASM memory
+/// * `flag_key_len` - Length of the flag key string
+/// * `context_ptr` - Pointer to the evaluation context JSON string in WASM memory
+/// * `context_len` - Length of the evaluation context JSON string
+///
+/// # Returns
+/// The packed variant code (not a pointer).
+///
+/// # Safety
+/// The caller must ensure:
+/// - `flag_key_ptr` and `context_ptr` point to valid memory
+/// - The memory regions are valid UTF-8
+/// - The caller manages the input buffer lifecycle (they are NOT freed by this function)
+/// - For empty context, pass context_ptr=0 and context_len=0
+#[export_name = "evaluate_variant_code"]
+pub extern "C" fn evaluate_variant_code_wasm(
+    flag_key_ptr: *const u8,
+    flag_key_len: u32,
+    context_ptr: *const u8,
+    context_len: u32,
+) -> u64 {
+    evaluate_variant_code_internal(flag_key_ptr, flag_key_len, context_ptr, context_len).pack()
+}
+
+/// Internal implementation of evaluate_variant_code.
+fn evaluate_variant_code_internal(
+    flag_key_ptr: *const u8,
+    flag_key_len: u32,
+    context_ptr: *const u8,
+    context_len: u32,
+) -> VariantCode {
+    init_panic_hook();
+
+    let error = |error_code| VariantCode {
+        variant_index: None,
+        reason: ResolutionReason::Error,
+        error_code: Some(error_code),
+    };
+
+    let result = std::panic::catch_unwind(|| {
+        // SAFETY: The caller guarantees valid memory regions
+        let Ok(flag_key) = (unsafe { string_from_memory(flag_key_ptr, flag_key_len) }) else {
+            return error(ErrorCode::ParseError);
+        };
+        let Ok(context) = read_batch_context(context_ptr, context_len) else {
+            return error(ErrorCode::ParseError);
+        };
+
+        wasm_evaluator::with_evaluator(|eval| eval.evaluate_variant_code(&flag_key, context))
+    });
+
+    result.unwrap_or_else(|_| error(ErrorCode::General))
+}
+
+/// Returns a flag's variant names sorted ascending, for decoding `evaluate_variant_code`.
+///
+/// The variant index in a packed variant code is the position of the variant in
+/// this list. Returns `null` if the flag does not exist or no state is loaded.
+///
+/// # Arguments
+/// * `flag_key_ptr` - Pointer to the flag key string in WASM memory
+/// * `flag_key_len` - Length of the flag key string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the response JSON string, e.g. `["off","on"]`.
+///
+/// # Safety
+/// The caller must ensure:
+/// - `flag_key_ptr` points to valid UTF-8 memory
+/// - The caller manages the input buffer lifecycle (it is NOT freed by this function)
+/// - The caller will free the returned memory using `dealloc`
+#[export_name = "get_variant_names"]
+pub extern "C" fn get_variant_names_wasm(flag_key_ptr: *const u8, flag_key_len: u32) -> u64 {
+    string_to_memory(&get_variant_names_internal(flag_key_ptr, flag_key_len))
+}
+
+/// Internal implementation of get_variant_names.
+fn get_variant_names_internal(flag_key_ptr: *const u8, flag_key_len: u32) -> String {
+    init_panic_hook();
+
+    let result = std::panic::catch_unwind(|| {
+        // SAFETY: The caller guarantees valid memory regions
+        let flag_key = unsafe { string_from_memory(flag_key_ptr, flag_key_len) }.ok()?;
+        let names = wasm_evaluator::with_evaluator(|eval| eval.variant_names(&flag_key))?;
+        serde_json::to_string(&names).ok()
+    });
+
+    match result {
+        Ok(Some(json)) => json,
+        _ => "null".to_string(),
+    }
+}
+
+/// Lists the reasons a flag can resolve with, inferred from its structure.
+///
+/// See `FlagEvaluator::possible_reasons`. Nothing is evaluated. Returns `null` if
+/// the flag does not exist or no state is loaded.
+///
+/// # Arguments
+/// * `flag_key_ptr` - Pointer to the flag key string in WASM memory
+/// * `flag_key_len` - Length of the flag key string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the response JSON string, e.g. `["DEFAULT","TARGETING_MATCH","ERROR"]`.
+///
+/// # Safety
+/// The caller must ensure:
+/// - `flag_key_ptr` points to valid UTF-8 memory
+/// - The caller manages the input buffer lifecycle (it is NOT freed by this function)
+/// - The caller will free the returned memory using `dealloc`
+#[export_name = "possible_reasons"]
+pub extern "C" fn possible_reasons_wasm(flag_key_ptr: *const u8, flag_key_len: u32) -> u64 {
+    string_to_memory(&possible_reasons_internal(flag_key_ptr, flag_key_len))
+}
+
+/// Internal implementation of possible_reasons.
+fn possible_reasons_internal(flag_key_ptr: *const u8, flag_key_len: u32) -> String {
+    init_panic_hook();
+
+    let result = std::panic::catch_unwind(|| {
+        // SAFETY: The caller guarantees valid memory regions
+        let flag_key = unsafe { string_from_memory(flag_key_ptr, flag_key_len) }.ok()?;
+        let reasons = wasm_evaluator::with_evaluator(|eval| eval.possible_reasons(&flag_key))?;
+        serde_json::to_string(&reasons).ok()
+    });
+
+    match result {
+        Ok(Some(json)) => json,
+        _ => "null".to_string(),
+    }
+}
+
+/// Resolves a flag to its default variant, skipping context enrichment and targeting.
+///
+/// This is cheaper than `evaluate` with an empty context for hosts that only need
+/// the statically configured default (e.g. for display). Targeted flags resolve with
+/// reason `DEFAULT`, or `FALLBACK` if they have no default variant; other flags
+/// resolve exactly as through `evaluate`.
+///
+/// # Arguments
+/// * `flag_key_ptr` - Pointer to the flag key string in WASM memory
+/// * `flag_key_len` - Length of the flag key string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the JSON-encoded EvaluationResult string.
+///
+/// # Safety
+/// The caller must ensure:
+/// - `flag_key_ptr` points to valid UTF-8 memory
+/// - The caller manages the input buffer lifecycle (it is NOT freed by this function)
+/// - The caller will free the returned memory using `dealloc`
+#[export_name = "resolve_default"]
+pub extern "C" fn resolve_default_wasm(flag_key_ptr: *const u8, flag_key_len: u32) -> u64 {
+    let result = resolve_default_internal(flag_key_ptr, flag_key_len);
+    result_to_memory(&result)
+}
+
+/// Internal implementation of resolve_default.
+fn resolve_default_internal(flag_key_ptr: *const u8, flag_key_len: u32) -> EvaluationResult {
+    init_panic_hook();
+
+    let result = std::panic::catch_unwind(|| {
+        // SAFETY: The caller guarantees valid memory regions
+        let flag_key = match unsafe { string_from_memory(flag_key_ptr, flag_key_len) } {
+            Ok(s) => s,
+            Err(e) => {
+                return EvaluationResult::error(
+                    ErrorCode::ParseError,
+                    format!("Failed to read flag key: {}", e),
+                )
+            }
+        };
+
+        wasm_evaluator::with_evaluator(|eval| eval.resolve_default(&flag_key))
+    });
+
+    result.unwrap_or_else(|panic_err| {
+        EvaluationResult::error(ErrorCode::General, evaluation_panic_message(&*panic_err))
+    })
+}
+
+/// Evaluates a flag as though it were enabled, to preview a disabled flag.
+///
+/// A DISABLED flag's targeting and default variant are evaluated as if the flag were
+/// ENABLED, and resolved results have reason `PREVIEW`. Other flags evaluate exactly
+/// as through `evaluate`. The stored configuration is not modified.
+///
+/// # Arguments
+/// * `flag_key_ptr` - Pointer to the flag key string in WASM memory
+/// * `flag_key_len` - Length of the flag key string
+/// * `context_ptr` - Pointer to the evaluation context JSON string in WASM memory
+/// * `context_len` - Length of the evaluation context JSON string
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the JSON-encoded EvaluationResult string.
+///
+/// # Safety
+/// The caller must ensure:
+/// - `flag_key_ptr` and `context_ptr` point to valid memory
+/// - The memory regions are valid UTF-8
+/// - The caller manages the input buffer lifecycle (they are NOT freed by this function)
+/// - The caller will free the returned memory using `dealloc`
+/// - For empty context, pass context_ptr=0 and context_len=0
+#[export_name = "evaluate_preview"]
+pub extern "C" fn evaluate_preview_wasm(
+    flag_key_ptr: *const u8,
+    flag_key_len: u32,
+    context_ptr: *const u8,
+    context_len: u32,
+) -> u64 {
+    let result = evaluate_preview_internal(flag_key_ptr, flag_key_len, context_ptr, context_len);
+    result_to_memory(&result)
+}
+
+/// Internal implementation of evaluate_preview.
+fn evaluate_preview_internal(
+    flag_key_ptr: *const u8,
+    flag_key_len: u32,
+    context_ptr: *const u8,
+    context_len: u32,
+) -> EvaluationResult {
+    init_panic_hook();
+
+    let result = std::panic::catch_unwind(|| {
+        // SAFETY: The caller guarantees valid memory regions
+        let flag_key = match unsafe { string_from_memory(flag_key_ptr, flag_key_len) } {
+            Ok(s) => s,
+            Err(e) => {
+                return EvaluationResult::error(
+                    ErrorCode::ParseError,
+                    format!("Failed to read flag key: {}", e),
+                )
+            }
+        };
+        let context = match read_batch_context(context_ptr, context_len) {
+            Ok(context) => context,
+            Err(e) => return EvaluationResult::error(ErrorCode::ParseError, e),
+        };
+
+        wasm_evaluator::with_evaluator(|eval| eval.evaluate_preview(&flag_key, context))
+    });
+
+    result.unwrap_or_else(|panic_err| {
+        EvaluationResult::error(ErrorCode::General, evaluation_panic_message(&*panic_err))
+    })
+}
+
+/// Returns the JSON Schema of the evaluation result envelope.
+///
+/// The schema describes the JSON returned by `evaluate` and the other single-flag
+/// exports (`value`, `variant`, `reason`, `errorCode`, `errorMessage`,
+/// `flagMetadata`), for tooling that generates host bindings. It does not depend on
+/// the stored state.
+///
+/// # Returns
+/// A packed u64 containing the pointer (upper 32 bits) and length (lower 32 bits)
+/// of the JSON Schema (draft-07) string.
+///
+/// # Safety
+/// The caller will free the returned memory using `dealloc`
+#[export_name = "result_schema"]
+pub extern "C" fn result_schema_wasm() -> u64 {
+    string_to_memory(&result_schema_internal())
+}
+
+/// Internal implementation of result_schema.
+fn result_schema_internal() -> String {
+    EvaluationResult::json_schema().to_string()
+}
+
+/// Internal implementation of evaluate.
+fn evaluate_internal(
+    flag_key_ptr: *const u8,
+    flag_key_len: u32,
+    context_ptr: *const u8,
+    context_len: u32,
+) -> EvaluationResult {
+    // Initialize panic hook for better error messages
+    init_panic_hook();
+
+    // Catch any panics and convert them to error responses
+    let result = std::panic::catch_unwind(|| {
+        wasm_evaluator::with_evaluator(|eval| {
+            // Check if state is initialized
+            if eval.get_state().is_none() {
+                return EvaluationResult::uninitialized();
+            }
+
+            // SAFETY: The caller guarantees valid memory regions
+            let flag_key = match unsafe { string_from_memory(flag_key_ptr, flag_key_len) } {
+                Ok(s) => s,
+                Err(e) => {
+                    return EvaluationResult::error(
+                        ErrorCode::ParseError,
+                        format!("Failed to read flag key: {}", e),
+                    )
+                }
+            };
+
+            let flag = eval.get_state().unwrap().flags.get(&flag_key);
+
+            // Parse protobuf context
+            let context: Value = if context_ptr.is_null()
+                || context_len == 0
+                || flag.is_some_and(|f| f.targeting.is_none())
+            {
+                Value::Null
+            } else {
+                let context_str = match unsafe { string_from_memory(context_ptr, context_len) } {
+                    Ok(s) => s,
+                    Err(e) => {
+                        return EvaluationResult::error(
+                            ErrorCode::ParseError,
+                            format!("Failed to read context: {}", e),
+                        )
+                    }
+                };
+
+                match serde_json::from_str(&context_str) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        return EvaluationResult::error(
+                            ErrorCode::ParseError,
+                            format!("Failed to parse context JSON: {}", e),
+                        )
+                    }
+                }
+            };
+
+            // Evaluate using the evaluator instance
+            eval.evaluate_flag(&flag_key, context)
+        })
+    });
+
+    result.unwrap_or_else(|panic_err| {
+        EvaluationResult::error(ErrorCode::General, evaluation_panic_message(&*panic_err))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // ============================================================================
+    // Library tests using FlagEvaluator directly
+    // ============================================================================
+
+    #[test]
+    fn test_evaluator_update_state_and_evaluate() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "flags": {
+                "boolFlag": {
+                    "state": "ENABLED",
+                    "variants": {
+                        "on": true,
+                        "off": false
+                    },
+                    "defaultVariant": "off"
+                }
+            }
+        }"#;
+
+        let response = evaluator.update_state(config).unwrap();
+        assert!(response.success);
+
+        let result = evaluator.evaluate_bool("boolFlag", json!({}));
+        assert_eq!(result.value, json!(false));
+        assert_eq!(result.variant, Some("off".to_string()));
+        assert_eq!(result.reason, ResolutionReason::Static);
+    }
+
+    #[test]
+    fn test_evaluator_int_flag() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "flags": {
+                "intFlag": {
+                    "state": "ENABLED",
+                    "variants": {
+                        "small": 10,
+                        "large": 100
+                    },
+                    "defaultVariant": "small"
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+
+        let result = evaluator.evaluate_int("intFlag", json!({}));
+        assert_eq!(result.value, json!(10));
+        assert_eq!(result.variant, Some("small".to_string()));
+    }
+
+    #[test]
+    fn test_evaluator_float_flag() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "flags": {
+                "floatFlag": {
+                    "state": "ENABLED",
+                    "variants": {
+                        "pi": 3.14,
+                        "e": 2.71
+                    },
+                    "defaultVariant": "pi"
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+
+        let result = evaluator.evaluate_float("floatFlag", json!({}));
+        assert_eq!(result.value, json!(3.14));
+        assert_eq!(result.variant, Some("pi".to_string()));
+    }
+
+    #[test]
+    fn test_evaluator_string_flag() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "flags": {
+                "stringFlag": {
+                    "state": "ENABLED",
+                    "variants": {
+                        "hello": "Hello, World!",
+                        "goodbye": "Goodbye!"
+                    },
+                    "defaultVariant": "hello"
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+
+        let result = evaluator.evaluate_string("stringFlag", json!({}));
+        assert_eq!(result.value, json!("Hello, World!"));
+        assert_eq!(result.variant, Some("hello".to_string()));
+    }
+
+    #[test]
+    fn test_evaluator_object_flag() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "flags": {
+                "objectFlag": {
+                    "state": "ENABLED",
+                    "variants": {
+                        "config1": {"key": "value1"},
+                        "config2": {"key": "value2"}
+                    },
+                    "defaultVariant": "config1"
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+
+        let result = evaluator.evaluate_flag("objectFlag", json!({}));
+        assert_eq!(result.value, json!({"key": "value1"}));
+        assert_eq!(result.variant, Some("config1".to_string()));
+    }
+
+    #[test]
+    fn test_evaluator_with_targeting() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "flags": {
+                "targetedFlag": {
+                    "state": "ENABLED",
+                    "variants": {
+                        "admin": "admin-value",
+                        "user": "user-value"
+                    },
+                    "defaultVariant": "user",
+                    "targeting": {
+                        "if": [
+                            {"==": [{"var": "role"}, "admin"]},
+                            "admin",
+                            "user"
+                        ]
+                    }
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+
+        // Test admin role
+        let result_admin = evaluator.evaluate_flag("targetedFlag", json!({"role": "admin"}));
+        assert_eq!(result_admin.value, json!("admin-value"));
+        assert_eq!(result_admin.variant, Some("admin".to_string()));
+        assert_eq!(result_admin.reason, ResolutionReason::TargetingMatch);
+
+        // Test user role
+        let result_user = evaluator.evaluate_flag("targetedFlag", json!({"role": "user"}));
+        assert_eq!(result_user.value, json!("user-value"));
+        assert_eq!(result_user.variant, Some("user".to_string()));
+        assert_eq!(result_user.reason, ResolutionReason::TargetingMatch);
+    }
+
+    #[test]
+    fn test_evaluator_disabled_flag() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "flags": {
+                "disabledFlag": {
+                    "state": "DISABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on"
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+
+        let result = evaluator.evaluate_bool("disabledFlag", json!({}));
+        assert_eq!(result.value, Value::Null);
+        assert_eq!(result.reason, ResolutionReason::Disabled);
+        assert_eq!(result.error_code, Some(ErrorCode::FlagNotFound));
+    }
+
+    #[test]
+    fn test_evaluator_flag_not_found() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "flags": {
+                "existingFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on"
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+
+        let result = evaluator.evaluate_flag("nonexistentFlag", json!({}));
+        assert_eq!(result.reason, ResolutionReason::FlagNotFound);
+        assert_eq!(result.error_code, Some(ErrorCode::FlagNotFound));
+    }
+
+    #[test]
+    fn test_comment_annotations_load_in_strict_mode() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        let config = r#"{
+            "$schema": "https://flagd.dev/schema/v0/flags.json",
+            "$comment": "Flags for the checkout team",
+            "$evaluators": {
+                "$comment": "Shared rules",
+                "isGold": {"==": [{"var": "tier"}, "gold"]}
+            },
+            "flags": {
+                "$comment": "Keep flags sorted",
+                "checkout": {
+                    "$comment": "Remove after the Q3 rollout",
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"$ref": "isGold"}, "on", null]}
+                }
+            }
+        }"#;
+
+        let response = evaluator.update_state(config).unwrap();
+        assert!(response.success, "{:?}", response.error);
+        assert_eq!(response.changed_flags, Some(vec!["checkout".to_string()]));
+        assert!(!evaluator
+            .get_state()
+            .unwrap()
+            .flags
+            .contains_key("$comment"));
+
+        let result = evaluator.evaluate_bool("checkout", json!({"tier": "gold"}));
+        assert_eq!(result.value, json!(true));
+        assert!(lint::lint_config(config).is_empty());
+    }
+
+    #[test]
+    fn test_evaluate_inline() {
+        let evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let static_flag = json!({
+            "state": "ENABLED",
+            "variants": {"red": "#f00", "blue": "#00f"},
+            "defaultVariant": "blue"
+        });
+        let result = evaluator.evaluate_inline(&static_flag, json!({}));
+        assert_eq!(result.value, json!("#00f"));
+        assert_eq!(result.variant, Some("blue".to_string()));
+        assert_eq!(result.reason, ResolutionReason::Static);
+
+        let targeting_flag = json!({
+            "state": "ENABLED",
+            "variants": {"red": "#f00", "blue": "#00f"},
+            "defaultVariant": "blue",
+            "targeting": {"if": [{"==": [{"var": "tier"}, "gold"]}, "red", null]}
+        });
+        let result = evaluator.evaluate_inline(&targeting_flag, json!({"tier": "gold"}));
+        assert_eq!(result.value, json!("#f00"));
+        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+        let result = evaluator.evaluate_inline(&targeting_flag, json!({"tier": "basic"}));
+        assert_eq!(result.value, json!("#00f"));
+        assert_eq!(result.reason, ResolutionReason::Default);
+
+        // Invalid definitions are rejected and nothing is stored
+        let invalid = json!({"state": "SOMETIMES", "variants": {"on": true}});
+        let result = evaluator.evaluate_inline(&invalid, json!({}));
+        assert_eq!(result.reason, ResolutionReason::Error);
+        assert_eq!(result.error_code, Some(ErrorCode::ParseError));
+        assert!(result
+            .error_message
+            .unwrap()
+            .starts_with("Invalid inline flag"));
+        assert!(evaluator.get_state().is_none());
+    }
+
+    #[test]
+    fn test_default_timestamp_when_time_unavailable() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        let config = r#"{
+            "flags": {
+                "launchFlag": {
+                    "state": "ENABLED",
+                    "variants": {"launched": true, "pending": false},
+                    "defaultVariant": "pending",
+                    "targeting": {
+                        "if": [{">=": [{"var": "$flagd.timestamp"}, 1750000000]}, "launched", "pending"]
+                    }
+                }
+            }
+        }"#;
+        evaluator.update_state(config).unwrap();
+
+        let evaluate_without_clock = |evaluator: &FlagEvaluator| {
+            let timestamp = evaluator.resolve_timestamp(None);
+            let context = json!({"$flagd": {"flagKey": "launchFlag", "timestamp": timestamp}});
+            evaluator.evaluate_flag_pre_enriched("launchFlag", context)
+        };
+
+        // Without a clock or a default, every rule sees the Unix epoch
+        assert_eq!(evaluator.resolve_timestamp(None), 0);
+        assert_eq!(
+            evaluate_without_clock(&evaluator).variant.as_deref(),
+            Some("pending")
+        );
+
+        evaluator.set_default_timestamp(1_800_000_000);
+        assert_eq!(evaluator.resolve_timestamp(None), 1_800_000_000);
+        assert_eq!(evaluator.resolve_timestamp(Some(42)), 42);
+        for _ in 0..2 {
+            assert_eq!(
+                evaluate_without_clock(&evaluator).variant.as_deref(),
+                Some("launched")
+            );
+        }
+
+        let health = evaluator.health_status(false);
+        assert!(health.state_loaded);
+        assert_eq!(health.flag_count, 1);
+        assert!(!health.time_available);
+        assert_eq!(
+            health.warnings,
+            vec![
+                "Current time is unavailable; $flagd.timestamp uses the default timestamp 1800000000"
+            ]
+        );
+
+        // Native tests run with the system clock
+        let health = evaluator.health_check();
+        assert!(health.time_available);
+        assert!(health.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_targeting_key_field() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        evaluator.set_targeting_key_field(Some("userId".to_string()));
+        let config = r#"{
+            "flags": {
+                "splitFlag": {
+                    "state": "ENABLED",
+                    "variants": {"a": "a", "b": "b"},
+                    "defaultVariant": "a",
+                    "targeting": {"fractional": [["a", 50], ["b", 50]]}
+                },
+                "keyFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"==": [{"var": "targetingKey"}, "user-7"]}, "on", "off"]}
+                }
+            }
+        }"#;
+        let response = evaluator.update_state(config).unwrap();
+        let keys = response.required_context_keys.unwrap();
+        assert_eq!(keys["splitFlag"], vec!["targetingKey", "userId"]);
+
+        let mut reference = FlagEvaluator::new(ValidationMode::Strict);
+        reference.update_state(config).unwrap();
+        for i in 0..20 {
+            let user = format!("user-{}", i);
+            assert_eq!(
+                evaluator
+                    .evaluate_flag("splitFlag", json!({"userId": user}))
+                    .variant,
+                reference
+                    .evaluate_flag("splitFlag", json!({"targetingKey": user}))
+                    .variant
+            );
+        }
+
+        let result = evaluator.evaluate_flag("keyFlag", json!({"userId": "user-7"}));
+        assert_eq!(result.value, json!(true));
+
+        // Without the field, targetingKey is used as provided
+        let result = evaluator.evaluate_flag("keyFlag", json!({"targetingKey": "user-7"}));
+        assert_eq!(result.value, json!(true));
+    }
+
+    #[test]
+    fn test_context_root_pointer() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        assert!(evaluator
+            .set_context_root_pointer(Some("user".to_string()))
+            .is_err());
+        evaluator
+            .set_context_root_pointer(Some("/request/user".to_string()))
+            .unwrap();
+
+        let config = r#"{
+            "flags": {
+                "adminFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {
+                        "if": [
+                            {"and": [
+                                {"==": [{"var": "email"}, "admin@example.com"]},
+                                {"==": [{"var": "$flagd.flagKey"}, "adminFlag"]},
+                                {"==": [{"var": "targetingKey"}, "user-1"]}
+                            ]},
+                            "on",
+                            "off"
+                        ]
+                    }
+                }
+            }
+        }"#;
+        let response = evaluator.update_state(config).unwrap();
+        assert_eq!(
+            response.required_context_keys.unwrap()["adminFlag"],
+            vec!["request", "targetingKey"]
+        );
+
+        let context = json!({
+            "email": "someone@example.com",
+            "request": {"user": {"email": "admin@example.com", "targetingKey": "user-1"}}
+        });
+        let result = evaluator.evaluate_flag("adminFlag", context);
+        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+        assert_eq!(result.value, json!(true));
+
+        // A top-level targetingKey is carried into the sub-object
+        let context = json!({
+            "targetingKey": "user-1",
+            "request": {"user": {"email": "admin@example.com"}}
+        });
+        assert_eq!(
+            evaluator.evaluate_flag("adminFlag", context).value,
+            json!(true)
+        );
+
+        // Host-enriched contexts keep their top-level $flagd
+        let context = json!({
+            "$flagd": {"flagKey": "adminFlag", "timestamp": 0},
+            "targetingKey": "user-1",
+            "request": {"user": {"email": "admin@example.com"}}
+        });
+        let result = evaluator.evaluate_flag_pre_enriched("adminFlag", context);
+        assert_eq!(result.value, json!(true));
+
+        // Rules see an empty context when the pointer does not resolve to an object
+        let context = json!({"email": "admin@example.com", "targetingKey": "user-1"});
+        let result = evaluator.evaluate_flag("adminFlag", context);
+        assert_eq!(result.value, json!(false));
+    }
+
+    /// Decodes an `update_state_bin` response into the equivalent JSON fields.
+    fn decode_update_bin(bytes: &[u8]) -> Value {
+        let mut pos = 1;
+        let read_u32 = |pos: &mut usize| {
+            let value = u32::from_le_bytes(bytes[*pos..*pos + 4].try_into().unwrap());
+            *pos += 4;
+            value as usize
+        };
+        let read_str = |pos: &mut usize| {
+            let len = read_u32(pos);
+            let s = std::str::from_utf8(&bytes[*pos..*pos + len])
+                .unwrap()
+                .to_string();
+            *pos += len;
+            s
+        };
+
+        let decoded = if bytes[0] == 1 {
+            let count = read_u32(&mut pos);
+            let keys: Vec<String> = (0..count).map(|_| read_str(&mut pos)).collect();
+            json!({"success": true, "changedFlags": keys})
+        } else {
+            let code = read_str(&mut pos);
+            let message = read_str(&mut pos);
+            json!({"success": false, "errorCode": code, "error": message})
+        };
+        assert_eq!(pos, bytes.len());
+        decoded
+    }
+
+    #[test]
+    fn test_update_state_binary_response() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        let updates = [
+            r#"{"flags": {"a": {"state": "ENABLED", "variants": {"on": true}, "defaultVariant": "on"}, "ключ": {"state": "ENABLED", "variants": {"on": true}, "defaultVariant": "on"}}}"#,
+            r#"{"flags": {"a": {"state": "ENABLED", "variants": {"on": true}, "defaultVariant": "on"}, "ключ": {"state": "ENABLED", "variants": {"on": true}, "defaultVariant": "on"}}}"#,
+            r#"{"flags": {"b": {"state": "ENABLED", "variants": {"on": true}, "defaultVariant": "on"}}}"#,
+            r#"{"flags": {"b": {"state": "BROKEN"}}}"#,
+        ];
+
+        for update in updates {
+            let response = evaluator.update_state(update).unwrap();
+            let json = serde_json::to_value(&response).unwrap();
+            let decoded = decode_update_bin(&response.to_binary());
+            for field in ["success", "changedFlags", "errorCode", "error"] {
+                if !decoded[field].is_null() {
+                    assert_eq!(decoded[field], json[field], "field {}", field);
+                }
+            }
+        }
+
+        let response = evaluator.update_state(updates[3]).unwrap();
+        assert_eq!(
+            decode_update_bin(&response.to_binary())["errorCode"],
+            json!("SCHEMA_INVALID")
+        );
+        let response = evaluator.update_state(updates[1]).unwrap();
+        assert_eq!(
+            decode_update_bin(&response.to_binary())["changedFlags"],
+            json!(["a", "b", "ключ"])
+        );
+    }
+
+    #[test]
+    fn test_diff_parsing_results() {
+        let parse =
+            |flags: &str| ParsingResult::parse(&format!(r#"{{"flags": {}}}"#, flags)).unwrap();
+        let first = parse(
+            r#"{"a": {"state": "ENABLED", "variants": {"on": true}, "defaultVariant": "on"},
+                "b": {"state": "ENABLED", "variants": {"on": true}, "defaultVariant": "on"}}"#,
+        );
+        let second = parse(
+            r#"{"a": {"state": "DISABLED", "variants": {"on": true}, "defaultVariant": "on"},
+                "c": {"state": "ENABLED", "variants": {"on": true}, "defaultVariant": "on"}}"#,
+        );
+
+        let diff = diff_parsing_results(None, &first);
+        assert_eq!(diff.added, vec!["a", "b"]);
+        assert!(diff.removed.is_empty() && diff.mutated.is_empty());
+
+        let diff = diff_parsing_results(Some(&first), &second);
+        assert_eq!(
+            diff,
+            FlagSetDiff {
+                added: vec!["c".to_string()],
+                removed: vec!["b".to_string()],
+                mutated: vec!["a".to_string()],
+            }
+        );
+        assert_eq!(diff.changed_flags(), vec!["a", "b", "c"]);
+
+        assert!(diff_parsing_results(Some(&second), &second).is_empty());
+
+        // update_state reports the same change set
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        evaluator
+            .update_state(r#"{"flags": {"a": {"state": "ENABLED", "variants": {"on": true}, "defaultVariant": "on"}, "b": {"state": "ENABLED", "variants": {"on": true}, "defaultVariant": "on"}}}"#)
+            .unwrap();
+        let response = evaluator
+            .update_state(r#"{"flags": {"a": {"state": "DISABLED", "variants": {"on": true}, "defaultVariant": "on"}, "c": {"state": "ENABLED", "variants": {"on": true}, "defaultVariant": "on"}}}"#)
+            .unwrap();
+        assert_eq!(response.changed_flags, Some(diff.changed_flags()));
+    }
+
+    #[test]
+    fn test_evaluate_changed_since() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        evaluator.set_change_history_capacity(2);
+        let flag = |default: &str| {
+            format!(
+                r#"{{"state": "ENABLED", "variants": {{"on": true, "off": false}}, "defaultVariant": "{}"}}"#,
+                default
+            )
+        };
+        let update = |evaluator: &mut FlagEvaluator, flags: &[(&str, &str)]| {
+            let flags: Vec<String> = flags
+                .iter()
+                .map(|(key, default)| format!(r#""{}": {}"#, key, flag(default)))
+                .collect();
+            let config = format!(r#"{{"flags": {{{}}}}}"#, flags.join(", "));
+            assert!(evaluator.update_state(&config).unwrap().success);
+        };
+        let keys = |results: Vec<(String, EvaluationResult)>| {
+            results.into_iter().map(|(key, _)| key).collect::<Vec<_>>()
+        };
+
+        update(&mut evaluator, &[("a", "on"), ("b", "on"), ("c", "on")]);
+        let version = evaluator.state_version();
+        assert!(keys(evaluator.evaluate_changed_since(version, json!({}))).is_empty());
+
+        // Only the mutated flag is evaluated after a partial update
+        update(&mut evaluator, &[("a", "on"), ("b", "off"), ("c", "on")]);
+        let results = evaluator.evaluate_changed_since(version, json!({}));
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0, "b");
+        assert_eq!(results[0].1.value, json!(false));
+
+        // Changes accumulate across updates, and removed flags report FLAG_NOT_FOUND
+        update(&mut evaluator, &[("a", "on"), ("b", "off"), ("d", "on")]);
+        let results = evaluator.evaluate_changed_since(version, json!({}));
+        assert_eq!(keys(results.clone()), vec!["b", "c", "d"]);
+        assert_eq!(results[1].1.error_code, Some(ErrorCode::FlagNotFound));
+
+        // A version older than the kept history or newer than the state evaluates all
+        assert_eq!(evaluator.flags_changed_since(version - 1), None);
+        assert_eq!(
+            keys(evaluator.evaluate_changed_since(version - 1, json!({}))),
+            vec!["a", "b", "d"]
+        );
+        assert_eq!(
+            keys(evaluator.evaluate_changed_since(version + 10, json!({}))),
+            vec!["a", "b", "d"]
+        );
+    }
+
+    #[test]
+    fn test_change_history() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        evaluator.set_change_history_capacity(3);
+        assert!(evaluator.change_history(0).is_empty());
+
+        let flag = |default: &str| {
+            format!(
+                r#"{{"state": "ENABLED", "variants": {{"on": true, "off": false}}, "defaultVariant": "{}"}}"#,
+                default
+            )
+        };
+        let updates = [
+            format!(
+                r#"{{"flags": {{"a": {}, "b": {}}}}}"#,
+                flag("on"),
+                flag("on")
+            ),
+            format!(
+                r#"{{"flags": {{"a": {}, "c": {}}}}}"#,
+                flag("off"),
+                flag("on")
+            ),
+            format!(
+                r#"{{"flags": {{"a": {}, "c": {}}}}}"#,
+                flag("off"),
+                flag("on")
+            ),
+            format!(r#"{{"flags": {{"c": {}}}}}"#, flag("off")),
+        ];
+        for update in &updates {
+            assert!(evaluator.update_state(update).unwrap().success);
+        }
+        // A rejected update is not recorded
+        assert!(!evaluator.update_state(r#"{"flags": 1}"#).unwrap().success);
+
+        let history = evaluator.change_history(0);
+        let versions: Vec<u64> = history.iter().map(|r| r.version).collect();
+        assert_eq!(versions, vec![2, 3, 4]);
+        assert!(history.iter().all(|r| r.timestamp > 0));
+
+        let names = |keys: &[&str]| keys.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+        assert_eq!(history[0].added, names(&["c"]));
+        assert_eq!(history[0].removed, names(&["b"]));
+        assert_eq!(history[0].mutated, names(&["a"]));
+        assert!(history[1].added.is_empty() && history[1].mutated.is_empty());
+        assert_eq!(history[2].removed, names(&["a"]));
+        assert_eq!(history[2].mutated, names(&["c"]));
+
+        assert_eq!(evaluator.change_history(1), vec![history[2].clone()]);
+
+        evaluator.clear_state();
+        let last = evaluator.change_history(1).pop().unwrap();
+        assert_eq!(last.version, 5);
+        assert_eq!(last.removed, names(&["c"]));
+        assert_eq!(evaluator.change_history(0).len(), 3);
+    }
+
+    #[test]
+    fn test_evaluator_inventory() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        assert!(evaluator.inventory().is_empty());
+
+        let config = r#"{
+            "flags": {
+                "boolFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"==": [{"var": "tier"}, "gold"]}, "on", null]}
+                },
+                "colorFlag": {
+                    "state": "DISABLED",
+                    "variants": {"red": "red", "blue": "blue"},
+                    "defaultVariant": "red",
+                    "targeting": {}
+                },
+                "mixedFlag": {
+                    "state": "ENABLED",
+                    "variants": {"num": 1, "obj": {"a": 1}}
+                }
+            }
+        }"#;
+        evaluator.update_state(config).unwrap();
+
+        let inventory = evaluator.inventory();
+        let keys: Vec<&str> = inventory.iter().map(|s| s.key.as_str()).collect();
+        assert_eq!(keys, vec!["boolFlag", "colorFlag", "mixedFlag"]);
+
+        assert_eq!(
+            inventory[0],
+            FlagSummary {
+                key: "boolFlag".to_string(),
+                state: "ENABLED".to_string(),
+                flag_type: FlagType::Boolean,
+                variants: vec!["off".to_string(), "on".to_string()],
+                has_targeting: true,
+                default_variant: Some("off".to_string()),
+            }
+        );
+        assert_eq!(inventory[1].state, "DISABLED");
+        assert_eq!(inventory[1].flag_type, FlagType::String);
+        assert!(!inventory[1].has_targeting);
+        assert_eq!(inventory[2].flag_type, FlagType::Mixed);
+        assert_eq!(inventory[2].default_variant, None);
+
+        assert_eq!(
+            serde_json::to_value(&inventory[2]).unwrap(),
+            json!({
+                "key": "mixedFlag",
+                "state": "ENABLED",
+                "type": "MIXED",
+                "variants": ["num", "obj"],
+                "hasTargeting": false
+            })
+        );
+    }
+
+    #[test]
+    fn test_evaluator_uninitialized_differs_from_flag_not_found() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let result = evaluator.evaluate_flag("anyFlag", json!({}));
+        assert_eq!(result.reason, ResolutionReason::Error);
+        assert_eq!(result.error_code, Some(ErrorCode::General));
+        assert_eq!(result.error_message.as_deref(), Some("state uninitialized"));
+        let by_index = evaluator.evaluate_flag_by_index(0, json!({}));
+        assert_eq!(
+            by_index.error_message.as_deref(),
+            Some("state uninitialized")
+        );
+
+        evaluator.update_state(r#"{"flags": {}}"#).unwrap();
+        let result = evaluator.evaluate_bool("anyFlag", json!({}));
+        assert_eq!(result.reason, ResolutionReason::FlagNotFound);
+        assert_eq!(result.error_code, Some(ErrorCode::FlagNotFound));
+
+        evaluator.clear_state();
+        let result = evaluator.evaluate_bool("anyFlag", json!({}));
+        assert_eq!(result.error_message.as_deref(), Some("state uninitialized"));
+    }
+
+    #[test]
+    fn test_evaluator_invalid_json_config() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let invalid_config = r#"{"flags": invalid}"#;
+        let result = evaluator.update_state(invalid_config);
+        assert!(result.is_ok()); // Returns Ok with error in response
+        let response = result.unwrap();
+        assert!(!response.success);
+        assert!(response.error.is_some());
+    }
+
+    #[test]
+    fn test_evaluator_fractional_targeting() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Permissive);
+
+        let config = r#"{
+            "flags": {
+                "abTestFlag": {
+                    "state": "ENABLED",
+                    "variants": {
+                        "control": "control-experience",
+                        "treatment": "treatment-experience"
+                    },
+                    "defaultVariant": "control",
+                    "targeting": {
+                        "fractional": [
+                            ["control", 50],
+                            ["treatment", 50]
+                        ]
+                    }
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+
+        let result = evaluator.evaluate_flag("abTestFlag", json!({"targetingKey": "user-123"}));
+        assert!(
+            result.value == json!("control-experience")
+                || result.value == json!("treatment-experience")
+        );
+        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+    }
+
+    #[test]
+    fn test_evaluator_missing_targeting_key() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Permissive);
+
+        let config = r#"{
+            "flags": {
+                "testFlag": {
+                    "state": "ENABLED",
+                    "variants": {
+                        "a": "variant-a",
+                        "b": "variant-b"
+                    },
+                    "defaultVariant": "a",
+                    "targeting": {
+                        "fractional": [
+                            ["a", 50],
+                            ["b", 50]
+                        ]
+                    }
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+
+        // Missing targetingKey - should use empty string
+        let result = evaluator.evaluate_flag("testFlag", json!({}));
+        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+        assert!(result.value == json!("variant-a") || result.value == json!("variant-b"));
+    }
+
+    #[test]
+    fn test_evaluator_unknown_variant_from_targeting() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "flags": {
+                "brokenFlag": {
+                    "state": "ENABLED",
+                    "variants": {
+                        "valid": "valid-value"
+                    },
+                    "defaultVariant": "valid",
+                    "targeting": {
+                        "if": [
+                            true,
+                            "unknown-variant",
+                            "valid"
+                        ]
+                    }
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+
+        let result = evaluator.evaluate_flag("brokenFlag", json!({}));
+        assert_eq!(result.reason, ResolutionReason::Error);
+        assert_eq!(result.error_code, Some(ErrorCode::General));
+    }
+
+    #[test]
+    fn test_evaluator_complex_targeting() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "flags": {
+                "complexFlag": {
+                    "state": "ENABLED",
+                    "variants": {
+                        "premium": "premium-tier",
+                        "standard": "standard-tier",
+                        "basic": "basic-tier"
+                    },
+                    "defaultVariant": "basic",
+                    "targeting": {
+                        "if": [
+                            {"starts_with": [{"var": "email"}, "admin@"]},
+                            "premium",
+                            {
+                                "if": [
+                                    {"sem_ver": [{"var": "appVersion"}, ">=", "2.0.0"]},
+                                    "standard",
+                                    "basic"
+                                ]
+                            }
+                        ]
+                    }
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+
+        // Admin email
+        let result_admin = evaluator.evaluate_flag(
+            "complexFlag",
+            json!({"email": "admin@example.com", "appVersion": "1.0.0"}),
+        );
+        assert_eq!(result_admin.value, json!("premium-tier"));
+
+        // Non-admin with new version
+        let result_standard = evaluator.evaluate_flag(
+            "complexFlag",
+            json!({"email": "user@example.com", "appVersion": "2.1.0"}),
+        );
+        assert_eq!(result_standard.value, json!("standard-tier"));
+
+        // Non-admin with old version
+        let result_basic = evaluator.evaluate_flag(
+            "complexFlag",
+            json!({"email": "user@example.com", "appVersion": "1.5.0"}),
+        );
+        assert_eq!(result_basic.value, json!("basic-tier"));
+    }
+
+    #[test]
+    fn test_evaluator_flagd_properties() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "flags": {
+                "enrichedFlag": {
+                    "state": "ENABLED",
+                    "variants": {
+                        "verified": "properties-present",
+                        "failed": "properties-missing"
+                    },
+                    "defaultVariant": "failed",
+                    "targeting": {
+                        "if": [
+                            {
+                                "and": [
+                                    {"==": [{"var": "$flagd.flagKey"}, "enrichedFlag"]},
+                                    {">": [{"var": "$flagd.timestamp"}, 0]}
+                                ]
+                            },
+                            "verified",
+                            "failed"
+                        ]
+                    }
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+
+        let result = evaluator.evaluate_flag("enrichedFlag", json!({}));
+        assert_eq!(result.value, json!("properties-present"));
+        assert_eq!(result.variant, Some("verified".to_string()));
+    }
+
+    #[test]
+    fn test_evaluator_type_checking_bool() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "flags": {
+                "stringFlag": {
+                    "state": "ENABLED",
+                    "variants": {"val": "string-value", "alt": "alternative-value"},
+                    "defaultVariant": "val"
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+
+        let result = evaluator.evaluate_bool("stringFlag", json!({}));
+        assert_eq!(result.reason, ResolutionReason::Error);
+        assert_eq!(result.error_code, Some(ErrorCode::TypeMismatch));
+        assert_eq!(
+            result.error_message.as_deref(),
+            Some(
+                "Flag value has incorrect type. Expected boolean, got string \
+                 (flag 'stringFlag', variant 'val')"
+            )
+        );
+    }
+
+    #[test]
+    fn test_evaluator_type_checking_string() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "flags": {
+                "boolFlag": {
+                    "state": "ENABLED",
+                    "variants": {"val": true, "alt": false},
+                    "defaultVariant": "val"
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+
+        let result = evaluator.evaluate_string("stringFlag", json!({}));
+        assert_eq!(result.reason, ResolutionReason::FlagNotFound);
+    }
+
+    #[test]
+    fn test_evaluator_type_checking_int() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "flags": {
+                "floatFlag": {
+                    "state": "ENABLED",
+                    "variants": {"val": 3.14, "alt": 2.71},
+                    "defaultVariant": "val"
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+
+        // Integer evaluation should accept floats via coercion
+        let result = evaluator.evaluate_int("floatFlag", json!({}));
+        assert_eq!(result.value, json!(3));
+    }
+
+    #[test]
+    fn test_evaluator_type_checking_float() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "flags": {
+                "intFlag": {
+                    "state": "ENABLED",
+                    "variants": {"val": 42, "alt": 100},
+                    "defaultVariant": "val"
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+
+        // Float evaluation should accept integers via coercion
+        let result = evaluator.evaluate_float("intFlag", json!({}));
+        assert_eq!(result.value, json!(42.0));
+    }
+
+    #[test]
+    fn test_evaluator_reports_raw_values() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        evaluator.set_report_raw_values(true);
+
+        let config = r#"{
+            "flags": {
+                "floatFlag": {
+                    "state": "ENABLED",
+                    "variants": {"val": 3.7},
+                    "defaultVariant": "val"
+                },
+                "intFlag": {
+                    "state": "ENABLED",
+                    "variants": {"val": 42},
+                    "defaultVariant": "val"
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+
+        // Integer from float
+        let result = evaluator.evaluate_int("floatFlag", json!({}));
+        assert_eq!(result.value, json!(3));
+        assert_eq!(result.raw_value, Some(json!(3.7)));
+        let serialized = serde_json::to_value(&result).unwrap();
+        assert_eq!(serialized["value"], json!(3));
+        assert_eq!(serialized["rawValue"], json!(3.7));
+
+        // Float from integer
+        let result = evaluator.evaluate_float("intFlag", json!({}));
+        assert_eq!(result.value, json!(42.0));
+        assert_eq!(result.raw_value, Some(json!(42)));
+        let serialized = serde_json::to_value(&result).unwrap();
+        assert_eq!(serialized["value"], json!(42.0));
+        assert_eq!(serialized["rawValue"], json!(42));
+
+        // No coercion, no raw value
+        let result = evaluator.evaluate_int("intFlag", json!({}));
+        assert_eq!(result.raw_value, None);
+
+        // Omitted when reporting is disabled
+        evaluator.set_report_raw_values(false);
+        let result = evaluator.evaluate_int("floatFlag", json!({}));
+        assert_eq!(result.value, json!(3));
+        assert_eq!(result.raw_value, None);
+        assert!(serde_json::to_value(&result)
+            .unwrap()
+            .get("rawValue")
+            .is_none());
+    }
+
+    #[test]
+    fn test_evaluator_type_checking_object() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "flags": {
+                "stringFlag": {
+                    "state": "ENABLED",
+                    "variants": {"val": "string-value", "alt": "alternative-value"},
+                    "defaultVariant": "val"
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+
+        let result = evaluator.evaluate_object("stringFlag", json!({}));
+        assert_eq!(result.reason, ResolutionReason::Error);
+        assert_eq!(result.error_code, Some(ErrorCode::TypeMismatch));
+        let message = result.error_message.unwrap();
+        assert!(message.contains("Expected object, got string"));
+        assert!(message.contains("variant 'val'"));
+    }
+
+    #[test]
+    fn test_evaluator_all_types_flag_not_found() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "flags": {
+                "existingFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on"
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+
+        let bool_result = evaluator.evaluate_bool("missingFlag", json!({}));
+        assert_eq!(bool_result.reason, ResolutionReason::FlagNotFound);
+
+        let string_result = evaluator.evaluate_string("missingFlag", json!({}));
+        assert_eq!(string_result.reason, ResolutionReason::FlagNotFound);
+
+        let int_result = evaluator.evaluate_int("missingFlag", json!({}));
+        assert_eq!(int_result.reason, ResolutionReason::FlagNotFound);
+
+        let float_result = evaluator.evaluate_float("missingFlag", json!({}));
+        assert_eq!(float_result.reason, ResolutionReason::FlagNotFound);
+
+        let object_result = evaluator.evaluate_object("missingFlag", json!({}));
+        assert_eq!(object_result.reason, ResolutionReason::FlagNotFound);
+    }
+
+    #[test]
+    fn test_evaluator_disabled_flags_all_types() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "flags": {
+                "disabledBool": {
+                    "state": "DISABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on"
+                },
+                "disabledString": {
+                    "state": "DISABLED",
+                    "variants": {"val": "text", "alt": "alternative"},
+                    "defaultVariant": "val"
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+
+        let bool_result = evaluator.evaluate_bool("disabledBool", json!({}));
+        assert_eq!(bool_result.value, Value::Null);
+        assert_eq!(bool_result.reason, ResolutionReason::Disabled);
+
+        let string_result = evaluator.evaluate_string("disabledString", json!({}));
+        assert_eq!(string_result.value, Value::Null);
+        assert_eq!(string_result.reason, ResolutionReason::Disabled);
+    }
+
+    #[test]
+    fn test_fractional_weights_modes() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "flags": {
+                "splitFlag": {
+                    "state": "ENABLED",
+                    "variants": {"a": "variant-a", "b": "variant-b", "c": "variant-c"},
+                    "defaultVariant": "a",
+                    "targeting": {
+                        "fractional": [["a", 30], ["b", 30], ["c", 30]]
+                    }
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+        assert_eq!(evaluator.fractional_weights(), FractionalWeights::Relative);
+
+        // Relative mode: 30/30/30 is normalized to an even split
+        let mut seen = std::collections::HashSet::new();
+        for i in 0..100 {
+            let context = json!({"targetingKey": format!("user-{}", i)});
+            let result = evaluator.evaluate_flag("splitFlag", context);
+            assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+            seen.insert(result.variant.unwrap());
+        }
+        assert_eq!(seen.len(), 3);
+
+        // Percent mode: weights not summing to 100 are an error
+        evaluator.set_fractional_weights(FractionalWeights::Percent);
+        let result = evaluator.evaluate_flag("splitFlag", json!({"targetingKey": "user-1"}));
+        assert_eq!(result.reason, ResolutionReason::Error);
+        assert_eq!(result.error_code, Some(ErrorCode::ParseError));
+        assert!(result
+            .error_message
+            .unwrap()
+            .contains("must sum to 100 in percent mode, got 90"));
+    }
+
+    #[test]
+    fn test_fractional_rendezvous_mode() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        assert_eq!(evaluator.fractional_mode(), FractionalMode::Linear);
+        evaluator.set_fractional_mode(FractionalMode::Rendezvous);
+
+        let config = |variants: &str| {
+            format!(
+                r#"{{
+                "flags": {{
+                    "splitFlag": {{
+                        "state": "ENABLED",
+                        "variants": {{"a": "a", "b": "b", "c": "c"}},
+                        "defaultVariant": "a",
+                        "targeting": {{"fractional": [{}]}}
+                    }}
+                }}
+            }}"#,
+                variants
+            )
+        };
+
+        evaluator
+            .update_state(&config(r#"["a", 50], ["b", 50]"#))
+            .unwrap();
+        let before: Vec<String> = (0..200)
+            .map(|i| {
+                let context = json!({"targetingKey": format!("user-{}", i)});
+                evaluator
+                    .evaluate_flag("splitFlag", context)
+                    .variant
+                    .unwrap()
+            })
+            .collect();
+
+        evaluator
+            .update_state(&config(r#"["a", 50], ["b", 50], ["c", 50]"#))
+            .unwrap();
+        for (i, old) in before.iter().enumerate() {
+            let context = json!({"targetingKey": format!("user-{}", i)});
+            let new = evaluator
+                .evaluate_flag("splitFlag", context)
+                .variant
+                .unwrap();
+            assert!(
+                new == *old || new == "c",
+                "user-{} moved {} -> {}",
+                i,
+                old,
+                new
+            );
+        }
+    }
+
+    #[test]
+    fn test_fractional_seed_from_flag_metadata() {
+        let config = r#"{
+            "flags": {
+                "checkoutA": {
+                    "state": "ENABLED",
+                    "variants": {"a": "a", "b": "b"},
+                    "defaultVariant": "a",
+                    "metadata": {"experimentId": "exp-1"},
+                    "targeting": {"fractional": [["a", 50], ["b", 50]]}
+                },
+                "checkoutClone": {
+                    "state": "ENABLED",
+                    "variants": {"a": "a", "b": "b"},
+                    "defaultVariant": "a",
+                    "metadata": {"experimentId": "exp-1"},
+                    "targeting": {"fractional": [["a", 50], ["b", 50]]}
+                },
+                "checkoutB": {
+                    "state": "ENABLED",
+                    "variants": {"a": "a", "b": "b"},
+                    "defaultVariant": "a",
+                    "metadata": {"experimentId": "exp-2"},
+                    "targeting": {"fractional": [["a", 50], ["b", 50]]}
+                },
+                "explicitKey": {
+                    "state": "ENABLED",
+                    "variants": {"a": "a", "b": "b"},
+                    "defaultVariant": "a",
+                    "metadata": {"experimentId": "exp-1"},
+                    "targeting": {"fractional": [{"var": "email"}, ["a", 50], ["b", 50]]}
+                }
+            }
+        }"#;
+        let buckets = [json!(["a", 50]), json!(["b", 50])];
+        let variant = |evaluator: &FlagEvaluator, flag: &str, user: &str| {
+            let context = json!({"targetingKey": user, "email": format!("{}@example.com", user)});
+            evaluator.evaluate_flag(flag, context).variant.unwrap()
+        };
+
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        assert_eq!(evaluator.fractional_seed_metadata(), None);
+        evaluator.update_state(config).unwrap();
+
+        // By default the flag key seeds the bucketing key
+        let user = "user-1";
+        let (expected, _) = operators::bucket_of(user, "checkoutA", &buckets).unwrap();
+        assert_eq!(expected, "a");
+        assert_eq!(variant(&evaluator, "checkoutA", user), expected);
+
+        evaluator.set_fractional_seed_metadata(Some("experimentId".to_string()));
+        let mut reshuffled = false;
+        for i in 0..50 {
+            let user = format!("user-{}", i);
+            let (exp1, _) = operators::bucket_of(&user, "exp-1", &buckets).unwrap();
+            let (exp2, _) = operators::bucket_of(&user, "exp-2", &buckets).unwrap();
+
+            // The experiment id replaces the flag key, so a clone with the same id
+            // buckets identically and a different id reshuffles
+            assert_eq!(variant(&evaluator, "checkoutA", &user), exp1);
+            assert_eq!(variant(&evaluator, "checkoutClone", &user), exp1);
+            assert_eq!(variant(&evaluator, "checkoutB", &user), exp2);
+            reshuffled |= exp1 != exp2;
+
+            // An explicit bucketing expression takes precedence over the metadata
+            let (explicit, _) =
+                operators::bucket_of(&format!("{}@example.com", user), "", &buckets).unwrap();
+            assert_eq!(variant(&evaluator, "explicitKey", &user), explicit);
+        }
+        assert!(reshuffled);
+
+        // Pinned assignments: user-2 lands in "a" for exp-1 and "b" for exp-2, and
+        // its explicit email key lands in "a"
+        assert_eq!(variant(&evaluator, "checkoutA", "user-2"), "a");
+        assert_eq!(variant(&evaluator, "checkoutClone", "user-2"), "a");
+        assert_eq!(variant(&evaluator, "checkoutB", "user-2"), "b");
+        assert_eq!(variant(&evaluator, "explicitKey", "user-2"), "a");
+        assert_eq!(variant(&evaluator, "explicitKey", "user-4"), "b");
+    }
+
+    #[test]
+    fn test_fractional_empty_key_policy() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        assert_eq!(
+            evaluator.fractional_empty_key(),
+            EmptyKeyPolicy::Deterministic
+        );
+
+        let config = r#"{
+            "flags": {
+                "splitFlag": {
+                    "state": "ENABLED",
+                    "variants": {"a": "a", "b": "b"},
+                    "defaultVariant": "a",
+                    "targeting": {"fractional": [["a", 50], ["b", 50]]}
+                }
+            }
+        }"#;
+        evaluator.update_state(config).unwrap();
+
+        // Deterministic: the empty key is hashed like any other
+        let buckets = [json!(["a", 50]), json!(["b", 50])];
+        let (expected, _) = operators::bucket_of("", "splitFlag", &buckets).unwrap();
+        assert_eq!(expected, "a");
+        for _ in 0..10 {
+            let result = evaluator.evaluate_flag("splitFlag", json!({"plan": "free"}));
+            assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+            assert_eq!(result.variant.as_deref(), Some(expected.as_str()));
+        }
+
+        // Error: the evaluation fails cleanly
+        evaluator.set_fractional_empty_key(EmptyKeyPolicy::Error);
+        let result = evaluator.evaluate_flag("splitFlag", json!({"plan": "free"}));
+        assert_eq!(result.reason, ResolutionReason::Error);
+        assert!(result
+            .error_message
+            .unwrap()
+            .contains("Fractional bucketing key is empty"));
+        let result = evaluator.evaluate_flag("splitFlag", json!({"targetingKey": "user-1"}));
+        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+
+        // Random: keyless users are spread across buckets
+        evaluator.set_fractional_empty_key(EmptyKeyPolicy::Random);
+        let mut seen = std::collections::HashSet::new();
+        for _ in 0..100 {
+            let result = evaluator.evaluate_flag("splitFlag", json!({"plan": "free"}));
+            assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+            seen.insert(result.variant.unwrap());
+        }
+        assert_eq!(seen.len(), 2);
+    }
+
+    #[test]
+    fn test_random_seed_reproduces_random_decisions() {
+        let config = r#"{
+            "flags": {
+                "splitFlag": {
+                    "state": "ENABLED",
+                    "variants": {"a": "a", "b": "b"},
+                    "defaultVariant": "a",
+                    "targeting": {"fractional": [["a", 50], ["b", 50]]}
+                }
+            }
+        }"#;
+        let decisions = |evaluator: &FlagEvaluator| -> Vec<String> {
+            (0..50)
+                .map(|_| {
+                    let result = evaluator.evaluate_flag("splitFlag", json!({"plan": "free"}));
+                    result.variant.unwrap()
+                })
+                .collect()
+        };
+
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        assert_eq!(evaluator.random_seed(), None);
+        evaluator.set_fractional_empty_key(EmptyKeyPolicy::Random);
+        evaluator.set_random_seed(Some(42));
+        evaluator.update_state(config).unwrap();
+        let first = decisions(&evaluator);
+        // Still random: both buckets are used
+        assert!(first.iter().any(|v| v == "a") && first.iter().any(|v| v == "b"));
+
+        // Another evaluator with the same seed makes the same decisions
+        let mut other = FlagEvaluator::new(ValidationMode::Strict);
+        other.set_random_seed(Some(42));
+        other.set_fractional_empty_key(EmptyKeyPolicy::Random);
+        other.update_state(config).unwrap();
+        assert_eq!(decisions(&other), first);
+
+        // Reseeding restarts the sequence; a different seed gives another one
+        evaluator.set_random_seed(Some(42));
+        assert_eq!(decisions(&evaluator), first);
+        evaluator.set_random_seed(Some(7));
+        assert_ne!(decisions(&evaluator), first);
+    }
+
+    #[test]
+    fn test_evaluate_rule_for_flag_matches_configured_targeting() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        let config = r#"{
+            "metadata": {"region": "eu"},
+            "flags": {
+                "tiered": {
+                    "state": "ENABLED",
+                    "variants": {"gold": "gold-value", "basic": "basic-value"},
+                    "defaultVariant": "basic",
+                    "targeting": {"if": [{"==": [{"var": "tier"}, "gold"]}, "gold", null]}
+                }
+            }
+        }"#;
+        evaluator.update_state(config).unwrap();
+        let configured = json!({"if": [{"==": [{"var": "tier"}, "gold"]}, "gold", null]});
+
+        for context in [
+            json!({"tier": "gold"}),
+            json!({"tier": "silver"}),
+            json!({}),
+        ] {
+            let actual = evaluator.evaluate_flag("tiered", context.clone());
+            let candidate = evaluator.evaluate_rule_for_flag("tiered", &configured, context);
+            assert_eq!(
+                serde_json::to_value(candidate).unwrap(),
+                serde_json::to_value(actual).unwrap()
+            );
+        }
+
+        // A different rule selects from the same variants without touching the state
+        let rule =
+            json!({"if": [{"==": [{"var": "$flagd.flagMetadata.region"}, "eu"]}, "gold", "basic"]});
+        let result = evaluator.evaluate_rule_for_flag("tiered", &rule, json!({}));
+        assert_eq!(result.value, json!("gold-value"));
+        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+        let result = evaluator.evaluate_flag("tiered", json!({}));
+        assert_eq!(result.value, json!("basic-value"));
+
+        let result = evaluator.evaluate_rule_for_flag("tiered", &json!("missing"), json!({}));
+        assert_eq!(result.reason, ResolutionReason::Error);
+        assert!(result.error_message.unwrap().contains("'missing'"));
+
+        let result = evaluator.evaluate_rule_for_flag("tiered", &json!({}), json!({}));
+        assert_eq!(result.reason, ResolutionReason::Static);
+
+        let result = evaluator.evaluate_rule_for_flag("nope", &rule, json!({}));
+        assert_eq!(result.reason, ResolutionReason::FlagNotFound);
+    }
+
+    #[test]
+    fn test_variant_metadata_overrides_flag_metadata() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        let config = r#"{
+            "metadata": {"team": "platform", "owner": "flag-set"},
+            "flags": {
+                "checkout": {
+                    "state": "ENABLED",
+                    "variants": {"new": "new-flow", "old": "old-flow"},
+                    "defaultVariant": "old",
+                    "targeting": {"if": [{"==": [{"var": "beta"}, true]}, "new", null]},
+                    "metadata": {"owner": "flag", "experiment": "none"},
+                    "variantMetadata": {
+                        "new": {"experiment": "checkout-v2", "cohort": 2}
+                    }
+                }
+            }
+        }"#;
+        let response = evaluator.update_state(config).unwrap();
+        assert!(response.success, "{:?}", response.error);
+
+        let result = evaluator.evaluate_flag("checkout", json!({"beta": true}));
+        assert_eq!(result.variant.as_deref(), Some("new"));
+        let metadata = result.flag_metadata.unwrap();
+        assert_eq!(metadata.get("experiment"), Some(&json!("checkout-v2")));
+        assert_eq!(metadata.get("cohort"), Some(&json!(2)));
+        assert_eq!(metadata.get("owner"), Some(&json!("flag")));
+        assert_eq!(metadata.get("team"), Some(&json!("platform")));
+
+        // A variant without metadata only gets flag and flag-set metadata
+        let result = evaluator.evaluate_flag("checkout", json!({"beta": false}));
+        assert_eq!(result.variant.as_deref(), Some("old"));
+        let metadata = result.flag_metadata.unwrap();
+        assert_eq!(metadata.get("experiment"), Some(&json!("none")));
+        assert!(!metadata.contains_key("cohort"));
+
+        // Variant metadata values must be booleans, strings, or numbers
+        let invalid = config.replace(r#""cohort": 2"#, r#""cohort": [2]"#);
+        let response = evaluator.update_state(&invalid).unwrap();
+        assert!(!response.success);
+        assert_eq!(response.error_code, Some(UpdateErrorCode::SchemaInvalid));
+    }
+
+    #[test]
+    fn test_metadata_precedence_variant_over_flag_over_flag_set() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        let config = r#"{
+            "metadata": {"arm": "flag-set", "source": "flag-set", "team": "growth"},
+            "flags": {
+                "pricing": {
+                    "state": "ENABLED",
+                    "variants": {"control": 10, "treatment": 8, "holdout": 12},
+                    "defaultVariant": "control",
+                    "targeting": {
+                        "if": [
+                            {"==": [{"var": "group"}, "a"]}, "treatment",
+                            {"==": [{"var": "group"}, "b"]}, "holdout",
+                            null
+                        ]
+                    },
+                    "metadata": {"arm": "flag", "source": "flag"},
+                    "variantMetadata": {
+                        "treatment": {"arm": "treatment"},
+                        "control": {"arm": "control"}
+                    }
+                }
+            }
+        }"#;
+        let response = evaluator.update_state(config).unwrap();
+        assert!(response.success, "{:?}", response.error);
+
+        // Targeting match: the variant's key wins over both flag and flag-set keys
+        let result = evaluator.evaluate_flag("pricing", json!({"group": "a"}));
+        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+        let metadata = result.flag_metadata.unwrap();
+        assert_eq!(metadata.get("arm"), Some(&json!("treatment")));
+        assert_eq!(metadata.get("source"), Some(&json!("flag")));
+        assert_eq!(metadata.get("team"), Some(&json!("growth")));
+
+        // Falling back to the default variant still applies its metadata
+        let result = evaluator.evaluate_flag("pricing", json!({"group": "c"}));
+        assert_eq!(result.variant.as_deref(), Some("control"));
+        let metadata = result.flag_metadata.unwrap();
+        assert_eq!(metadata.get("arm"), Some(&json!("control")));
+        assert_eq!(metadata.get("source"), Some(&json!("flag")));
+
+        // A variant without metadata falls back to the flag's key
+        let result = evaluator.evaluate_flag("pricing", json!({"group": "b"}));
+        assert_eq!(result.variant.as_deref(), Some("holdout"));
+        let metadata = result.flag_metadata.unwrap();
+        assert_eq!(metadata.get("arm"), Some(&json!("flag")));
+        assert_eq!(metadata.get("team"), Some(&json!("growth")));
+
+        // Without flag metadata, the flag-set key is used for variants without metadata
+        let config = config.replace(r#""metadata": {"arm": "flag", "source": "flag"},"#, "");
+        let response = evaluator.update_state(&config).unwrap();
+        assert!(response.success, "{:?}", response.error);
+        let result = evaluator.evaluate_flag("pricing", json!({"group": "b"}));
+        let metadata = result.flag_metadata.unwrap();
+        assert_eq!(metadata.get("arm"), Some(&json!("flag-set")));
+        assert_eq!(metadata.get("source"), Some(&json!("flag-set")));
+        let result = evaluator.evaluate_flag("pricing", json!({"group": "a"}));
+        let metadata = result.flag_metadata.unwrap();
+        assert_eq!(metadata.get("arm"), Some(&json!("treatment")));
+    }
+
+    #[test]
+    fn test_flag_set_defaults_for_flags_without_default_variant() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "defaults": {"boolean": false, "number": 0},
+            "metadata": {"owner": "platform"},
+            "flags": {
+                "boolNoDefault": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false}
+                },
+                "boolNullTargeting": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "targeting": {"if": [{"==": [{"var": "tier"}, "gold"]}, "on", null]}
+                },
+                "stringNoDefault": {
+                    "state": "ENABLED",
+                    "variants": {"a": "alpha", "b": "beta"}
+                },
+                "numberNoDefault": {
+                    "state": "ENABLED",
+                    "variants": {"low": 1, "high": 2.5}
+                }
+            }
+        }"#;
+        let response = evaluator.update_state(config).unwrap();
+        assert!(response.success, "{:?}", response.error);
+
+        let result = evaluator.evaluate_bool("boolNoDefault", json!({}));
+        assert_eq!(result.value, json!(false));
+        assert_eq!(result.variant, None);
+        assert_eq!(result.reason, ResolutionReason::Default);
+        assert_eq!(result.error_code, None);
+        assert_eq!(
+            result.flag_metadata.unwrap().get("owner"),
+            Some(&json!("platform"))
+        );
+
+        let result = evaluator.evaluate_flag("boolNullTargeting", json!({"tier": "silver"}));
+        assert_eq!(result.value, json!(false));
+        assert_eq!(result.reason, ResolutionReason::Default);
+        let result = evaluator.evaluate_flag("boolNullTargeting", json!({"tier": "gold"}));
+        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+
+        let result = evaluator.evaluate_flag("numberNoDefault", json!({}));
+        assert_eq!(result.value, json!(0));
+        assert_eq!(result.reason, ResolutionReason::Default);
+
+        // No policy for strings: keep the FALLBACK behavior
+        let result = evaluator.evaluate_string("stringNoDefault", json!({}));
+        assert_eq!(result.reason, ResolutionReason::Fallback);
+        assert_eq!(result.value, Value::Null);
+
+        // Without a policy, flags without a default variant fall back as before
+        let without_policy = config.replace(r#""defaults": {"boolean": false, "number": 0},"#, "");
+        evaluator.update_state(&without_policy).unwrap();
+        let result = evaluator.evaluate_bool("boolNoDefault", json!({}));
+        assert_eq!(result.reason, ResolutionReason::Fallback);
+
+        // A default of the wrong type is rejected
+        let invalid = config.replace(r#""boolean": false"#, r#""boolean": "no""#);
+        let response = evaluator.update_state(&invalid).unwrap();
+        assert!(!response.success);
+        evaluator.set_validation_mode(ValidationMode::Permissive);
+        let response = evaluator.update_state(&invalid).unwrap();
+        assert!(!response.success);
+        assert!(response.error.unwrap().contains("Invalid 'defaults'"));
+    }
+
+    #[test]
+    fn test_disabled_returns_modes() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "flags": {
+                "disabledFlag": {
+                    "state": "DISABLED",
+                    "variants": {"on": "on-value", "off": "off-value"},
+                    "defaultVariant": "off",
+                    "metadata": {"owner": "team-a"}
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+        assert_eq!(evaluator.disabled_returns(), DisabledReturns::Null);
+
+        // Default mode: null value, unchanged from previous behavior
+        let result = evaluator.evaluate_flag("disabledFlag", json!({}));
+        assert_eq!(result.value, Value::Null);
+        assert_eq!(result.variant, None);
+        assert_eq!(result.reason, ResolutionReason::Disabled);
+        assert_eq!(result.error_code, Some(ErrorCode::FlagNotFound));
+
+        // DefaultVariant mode: the default variant's value is returned
+        evaluator.set_disabled_returns(DisabledReturns::DefaultVariant);
+        let result = evaluator.evaluate_string("disabledFlag", json!({}));
+        assert_eq!(result.value, json!("off-value"));
+        assert_eq!(result.variant, Some("off".to_string()));
+        assert_eq!(result.reason, ResolutionReason::Disabled);
+        assert_eq!(result.error_code, None);
+        assert_eq!(
+            result.flag_metadata.unwrap().get("owner"),
+            Some(&json!("team-a"))
+        );
+    }
+
+    #[test]
+    fn test_compile_report() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        let stored = r#"{"flags": {"stored": {"state": "ENABLED", "variants": {"on": true}, "defaultVariant": "on"}}}"#;
+        evaluator.update_state(stored).unwrap();
+        let version = evaluator.state_version();
+
+        let config = r#"{
+            "$evaluators": {"isGold": {"==": [{"var": "tier"}, "gold"]}},
+            "flags": {
+                "goodFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"$ref": "isGold"}, "on", "off"]}
+                },
+                "badFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"$ref": "missing"}, "on", "off"]}
+                },
+                "staticFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true},
+                    "defaultVariant": "on"
+                }
+            }
+        }"#;
+        assert_eq!(
+            evaluator.compile_report(config).unwrap(),
+            vec![
+                (
+                    "badFlag".to_string(),
+                    Some("Evaluator 'missing' not found in $evaluators".to_string())
+                ),
+                ("goodFlag".to_string(), None),
+                ("staticFlag".to_string(), None),
+            ]
+        );
+
+        // The stored state is untouched
+        assert_eq!(evaluator.state_version(), version);
+        assert!(evaluator.get_state().unwrap().flags.contains_key("stored"));
+
+        assert!(evaluator.compile_report("{not json").is_err());
+        assert_eq!(
+            evaluator.compile_report(r#"{"metadata": {}}"#).unwrap_err(),
+            "Missing 'flags' object in configuration"
+        );
+    }
+
+    #[test]
+    fn test_flag_set_kill_switch() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        let config = |state: &str| {
+            format!(
+                r#"{{
+                    {}
+                    "flags": {{
+                        "staticFlag": {{
+                            "state": "ENABLED",
+                            "variants": {{"on": true, "off": false}},
+                            "defaultVariant": "on"
+                        }},
+                        "targetedFlag": {{
+                            "state": "ENABLED",
+                            "variants": {{"gold": "gold", "basic": "basic"}},
+                            "defaultVariant": "basic",
+                            "targeting": {{"if": [{{"==": [{{"var": "tier"}}, "gold"]}}, "gold"]}}
+                        }}
+                    }}
+                }}"#,
+                state
+            )
+        };
+        let context = json!({"tier": "gold"});
+
+        evaluator.update_state(&config("")).unwrap();
+        assert!(!evaluator.health_check().flag_set_disabled);
+        assert_eq!(
+            evaluator
+                .evaluate_flag("targetedFlag", context.clone())
+                .reason,
+            ResolutionReason::TargetingMatch
+        );
+
+        // Turning the kill switch on disables and reports every flag as changed
+        let response = evaluator
+            .update_state(&config(r#""state": "DISABLED","#))
+            .unwrap();
+        assert!(response.success);
+        assert_eq!(
+            response.changed_flags,
+            Some(vec!["staticFlag".to_string(), "targetedFlag".to_string()])
+        );
+        assert!(evaluator.health_check().flag_set_disabled);
+        for (key, result) in evaluator.evaluate_all(context.clone()) {
+            assert_eq!(result.reason, ResolutionReason::Disabled, "{}", key);
+            assert_eq!(result.value, Value::Null, "{}", key);
+        }
+        assert_eq!(
+            evaluator.possible_reasons("targetedFlag"),
+            Some(vec![ResolutionReason::Disabled])
+        );
+        assert_eq!(
+            evaluator
+                .evaluate_preview("targetedFlag", context.clone())
+                .reason,
+            ResolutionReason::Disabled
+        );
+        assert_eq!(evaluator.effective_config()["state"], json!("DISABLED"));
+
+        // Re-enabling restores the flags' own states
+        let response = evaluator
+            .update_state(&config(r#""state": "ENABLED","#))
+            .unwrap();
+        assert_eq!(response.changed_flags.map(|flags| flags.len()), Some(2));
+        assert!(!evaluator.health_check().flag_set_disabled);
+        assert_eq!(
+            evaluator
+                .evaluate_flag("targetedFlag", context.clone())
+                .reason,
+            ResolutionReason::TargetingMatch
+        );
+        assert_eq!(
+            evaluator.evaluate_flag("staticFlag", context).reason,
+            ResolutionReason::Static
+        );
+
+        let response = evaluator
+            .update_state(&config(r#""state": "OFF","#))
+            .unwrap();
+        assert!(!response.success);
+    }
+
+    #[test]
+    fn test_static_reporting_modes() {
+        let config = r#"{
+            "flags": {
+                "staticFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on"
+                },
+                "targetedFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"==": [{"var": "tier"}, "gold"]}, "on"]}
+                }
+            }
+        }"#;
+
+        // Static mode (default): flags without targeting report STATIC
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        assert_eq!(evaluator.static_reporting(), StaticReporting::Static);
+        let response = evaluator.update_state(config).unwrap();
+        assert_eq!(
+            response.pre_evaluated.unwrap()["staticFlag"].reason,
+            ResolutionReason::Static
+        );
+        let result = evaluator.evaluate_bool("staticFlag", json!({}));
+        assert_eq!(result.value, json!(true));
+        assert_eq!(result.reason, ResolutionReason::Static);
+
+        // Default mode: the same flag reports DEFAULT, including when pre-evaluated
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        evaluator.set_static_reporting(StaticReporting::Default);
+        let response = evaluator.update_state(config).unwrap();
+        assert_eq!(
+            response.pre_evaluated.unwrap()["staticFlag"].reason,
+            ResolutionReason::Default
+        );
+        let result = evaluator.evaluate_bool("staticFlag", json!({}));
+        assert_eq!(result.value, json!(true));
+        assert_eq!(result.variant, Some("on".to_string()));
+        assert_eq!(result.reason, ResolutionReason::Default);
+
+        // Targeted flags are unaffected
+        let result = evaluator.evaluate_bool("targetedFlag", json!({"tier": "gold"}));
+        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+    }
+
+    #[test]
+    fn test_possible_reasons() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        assert_eq!(evaluator.possible_reasons("staticFlag"), None);
+
+        let config = r#"{
+            "flags": {
+                "staticFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on"
+                },
+                "targetedFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"==": [{"var": "tier"}, "gold"]}, "on"]}
+                },
+                "noDefaultFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "targeting": {"if": [{"==": [{"var": "tier"}, "gold"]}, "on"]}
+                },
+                "disabledFlag": {
+                    "state": "DISABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on",
+                    "targeting": {"if": [{"==": [{"var": "tier"}, "gold"]}, "on"]}
+                }
+            }
+        }"#;
+        evaluator.update_state(config).unwrap();
+
+        assert_eq!(
+            evaluator.possible_reasons("staticFlag"),
+            Some(vec![ResolutionReason::Static])
+        );
+        assert_eq!(
+            evaluator.possible_reasons("targetedFlag"),
+            Some(vec![
+                ResolutionReason::Default,
+                ResolutionReason::TargetingMatch,
+                ResolutionReason::Error
+            ])
+        );
+        assert_eq!(
+            evaluator.possible_reasons("noDefaultFlag"),
+            Some(vec![
+                ResolutionReason::TargetingMatch,
+                ResolutionReason::Error,
+                ResolutionReason::Fallback
+            ])
+        );
+        assert_eq!(
+            evaluator.possible_reasons("disabledFlag"),
+            Some(vec![ResolutionReason::Disabled])
+        );
+        assert_eq!(evaluator.possible_reasons("missingFlag"), None);
+
+        // The inference agrees with actual evaluations
+        assert_eq!(
+            evaluator.evaluate_bool("staticFlag", json!({})).reason,
+            ResolutionReason::Static
+        );
+        assert_eq!(
+            evaluator.evaluate_bool("noDefaultFlag", json!({})).reason,
+            ResolutionReason::Fallback
+        );
+        assert_eq!(
+            evaluator
+                .evaluate_bool("targetedFlag", json!({"tier": "gold"}))
+                .reason,
+            ResolutionReason::TargetingMatch
+        );
+
+        evaluator.set_static_reporting(StaticReporting::Default);
+        assert_eq!(
+            evaluator.possible_reasons("staticFlag"),
+            Some(vec![ResolutionReason::Default])
+        );
+    }
+
+    #[test]
+    fn test_reject_unknown_keys() {
+        let config = r#"{
+            "flags": {
+                "checkout": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "defaultVarient": "on",
+                    "$owner": "payments"
+                }
+            }
+        }"#;
+
+        // Disabled by default: the typo is ignored and the flag resolves to "off"
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        assert!(!evaluator.reject_unknown_keys());
+        assert!(evaluator.update_state(config).unwrap().success);
+
+        // Strict mode rejects the configuration, pointing at the flag's unknown key
+        evaluator.set_reject_unknown_keys(true);
+        let response = evaluator.update_state(config).unwrap();
+        assert!(!response.success);
+        assert_eq!(response.error_code, Some(UpdateErrorCode::SchemaInvalid));
+        let errors = ValidationResult::from_json_string(&response.error.unwrap())
+            .unwrap()
+            .errors;
+        assert_eq!(
+            errors,
+            vec![ValidationError::new(
+                "/flags/checkout/defaultVarient",
+                "Unknown property 'defaultVarient' in flag 'checkout'"
+            )]
+        );
+        let fixed = config.replace(r#""defaultVarient": "on","#, "");
+        assert!(evaluator.update_state(&fixed).unwrap().success);
+
+        // Permissive mode only warns
+        evaluator.set_validation_mode(ValidationMode::Permissive);
+        assert!(evaluator.update_state(config).unwrap().success);
+
+        // validate_and_evaluate reports the unknown key too
+        let (validation, result) = evaluator.validate_and_evaluate(config, "checkout", json!({}));
+        assert!(!validation.valid);
+        assert_eq!(validation.errors[0].path, "/flags/checkout/defaultVarient");
+        assert_eq!(result.unwrap().value, json!(false));
+    }
+
+    #[test]
+    fn test_compact_unchanged_updates() {
+        let config = r#"{"flags": {"a": {"state": "ENABLED", "variants": {"on": true}, "defaultVariant": "on"}}}"#;
+        let changed = r#"{"flags": {"b": {"state": "ENABLED", "variants": {"on": true}, "defaultVariant": "on"}}}"#;
+
+        // Disabled by default: an unchanged config gets the full response
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        assert!(!evaluator.compact_unchanged_updates());
+        evaluator.update_state(config).unwrap();
+        let response = evaluator.update_state(config).unwrap();
+        assert_eq!(response.changed_flags, Some(vec![]));
+        assert!(response.pre_evaluated.is_some());
+
+        evaluator.set_compact_unchanged_updates(true);
+        // The first update after enabling is answered in full
+        let response = evaluator.update_state(config).unwrap();
+        assert!(response.flag_indices.is_some());
+        assert_eq!(response.changed, None);
+
+        // Unchanged: minimal response, state untouched
+        let version = evaluator.state_version();
+        let response = evaluator.update_state(config).unwrap();
+        assert_eq!(
+            serde_json::to_value(&response).unwrap(),
+            json!({"success": true, "changed": false})
+        );
+        assert_eq!(evaluator.state_version(), version);
+
+        // Changed: the detailed response is kept
+        let response = evaluator.update_state(changed).unwrap();
+        assert!(response.success);
+        assert_eq!(response.changed, None);
+        let mut flags = response.changed_flags.unwrap();
+        flags.sort();
+        assert_eq!(flags, vec!["a", "b"]);
+        assert!(response.pre_evaluated.is_some());
+
+        // Changing an option that affects results forces a full response
+        evaluator.set_static_reporting(StaticReporting::Default);
+        let response = evaluator.update_state(changed).unwrap();
+        assert_eq!(response.changed, None);
+        assert!(response.pre_evaluated.is_some());
+    }
+
+    #[test]
+    fn test_changed_flags_bitmap() {
+        let config = |keys: &[String], on: &[&str]| {
+            let flags: Map<String, Value> = keys
+                .iter()
+                .map(|key| {
+                    let default = if on.contains(&key.as_str()) {
+                        "on"
+                    } else {
+                        "off"
+                    };
+                    let flag = json!({
+                        "state": "ENABLED",
+                        "variants": {"on": true, "off": false},
+                        "defaultVariant": default
+                    });
+                    (key.clone(), flag)
+                })
+                .collect();
+            json!({ "flags": flags }).to_string()
+        };
+        let old_keys: Vec<String> = (0..20).map(|i| format!("f{:02}", i)).collect();
+        let mut new_keys = old_keys.clone();
+        new_keys.retain(|key| key != "f10");
+        new_keys.push("f20".to_string());
+
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        assert!(!evaluator.changed_flags_bitmap());
+        evaluator.set_changed_flags_bitmap(true);
+        evaluator.update_state(&config(&old_keys, &[])).unwrap();
+
+        // f03 and f17 change, f10 is removed and f20 added
+        let response = evaluator
+            .update_state(&config(&new_keys, &["f03", "f17"]))
+            .unwrap();
+        assert!(response.success);
+        assert_eq!(response.changed_flags, None);
+        let indices = response.flag_indices.clone().unwrap();
+        assert_eq!(
+            (indices["f03"], indices["f17"], indices["f20"]),
+            (3, 16, 19)
+        );
+        // Bits 3, 16 and 19 over 20 flags
+        assert_eq!(response.changed_flags_bitmap.as_deref(), Some("080009"));
+        assert_eq!(response.removed_flags, Some(vec!["f10".to_string()]));
+        let serialized = serde_json::to_value(&response).unwrap();
+        assert_eq!(serialized["changedFlagsBitmap"], json!("080009"));
+        assert!(serialized.get("changedFlags").is_none());
+
+        // Decoding gives the key list reported without the bitmap
+        let expected = vec!["f03", "f10", "f17", "f20"];
+        assert_eq!(response.changed_flag_keys(), expected);
+        let events = UpdateStateWithEventsResponse::from(response).events;
+        assert_eq!(events[0].flags, expected);
+
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        evaluator.update_state(&config(&old_keys, &[])).unwrap();
+        let response = evaluator
+            .update_state(&config(&new_keys, &["f03", "f17"]))
+            .unwrap();
+        assert_eq!(
+            response.changed_flags,
+            Some(expected.iter().map(|k| k.to_string()).collect())
+        );
+        assert_eq!(response.changed_flags_bitmap, None);
+        assert_eq!(response.removed_flags, None);
+    }
+
+    #[test]
+    fn test_effective_config() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        assert_eq!(evaluator.effective_config(), Value::Null);
+
+        let config = r#"{
+            "$schema": "https://flagd.dev/schema/v0/flags.json",
+            "$comment": "checkout experiments",
+            "$evaluators": {
+                "isStaff": {"ends_with": [{"var": ["email"]}, "@example.com"]}
+            },
+            "metadata": {"flagSetId": "checkout"},
+            "flags": {
+                "newCheckout": {
+                    "state": "ENABLED",
+                    "$comment": "rolling out",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {
+                        "if": [
+                            {"$ref": "isStaff"},
+                            "on",
+                            {"fractional": [["on"], ["off", 3]]}
+                        ]
+                    }
+                }
+            }
+        }"#;
+        evaluator.update_state(config).unwrap();
+
+        assert_eq!(
+            evaluator.effective_config(),
+            json!({
+                "metadata": {"flagSetId": "checkout"},
+                "flags": {
+                    "newCheckout": {
+                        "state": "ENABLED",
+                        "variants": {"on": true, "off": false},
+                        "defaultVariant": "off",
+                        "targeting": {
+                            "if": [
+                                {"ends_with": [{"var": "email"}, "@example.com"]},
+                                "on",
+                                {"fractional": [["on", 1], ["off", 3]]}
+                            ]
+                        },
+                        "metadata": {}
+                    }
+                }
+            })
+        );
+
+        // The normalized configuration evaluates like the original
+        let mut normalized = FlagEvaluator::new(ValidationMode::Strict);
+        normalized
+            .update_state(&evaluator.effective_config().to_string())
+            .unwrap();
+        for email in ["ana@example.com", "bob@example.org", "cy@example.net"] {
+            let context = json!({"email": email, "targetingKey": email});
+            assert_eq!(
+                normalized
+                    .evaluate_bool("newCheckout", context.clone())
+                    .variant,
+                evaluator.evaluate_bool("newCheckout", context).variant
+            );
+        }
+    }
+
+    #[test]
+    fn test_reset_options() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        let config = r#"{
+            "flags": {
+                "ageGate": {
+                    "state": "ENABLED",
+                    "variants": {"adult": "adult", "minor": "minor"},
+                    "defaultVariant": "minor",
+                    "targeting": {"if": [{">=": [{"var": "age"}, 18]}, "adult"]}
+                },
+                "staticFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on"
+                }
+            }
+        }"#;
+        evaluator.update_state(config).unwrap();
+        let version = evaluator.state_version();
+
+        evaluator.set_validation_mode(ValidationMode::Permissive);
+        evaluator.set_strict_variables(true);
+        evaluator.set_numeric_string_coercion(false);
+        evaluator.set_static_reporting(StaticReporting::Default);
+        evaluator.set_max_config_bytes(Some(16));
+        evaluator.set_default_timestamp(42);
+        evaluator.set_targeting_key_field(Some("userId".to_string()));
+        evaluator.set_report_raw_values(true);
+        evaluator.set_result_cache_capacity(8);
+        assert_eq!(
+            evaluator
+                .evaluate_string("ageGate", json!({"age": "21"}))
+                .variant,
+            Some("minor".to_string())
+        );
+
+        evaluator.reset_options();
+
+        let defaults = FlagEvaluator::new(ValidationMode::Strict);
+        assert_eq!(evaluator.validation_mode(), ValidationMode::Strict);
+        assert_eq!(evaluator.strict_variables(), defaults.strict_variables());
+        assert!(evaluator.numeric_string_coercion());
+        assert_eq!(evaluator.static_reporting(), StaticReporting::Static);
+        assert_eq!(evaluator.max_config_bytes(), None);
+        assert_eq!(evaluator.default_timestamp(), 0);
+        assert_eq!(evaluator.targeting_key_field(), None);
+        assert!(!evaluator.report_raw_values());
+        assert_eq!(evaluator.cache_stats(), CacheStats::default());
+
+        // The flags stay loaded, and targeting follows the restored defaults
+        assert_eq!(evaluator.state_version(), version);
+        assert_eq!(
+            evaluator
+                .evaluate_string("ageGate", json!({"age": "21"}))
+                .variant,
+            Some("adult".to_string())
+        );
+        assert_eq!(
+            evaluator.evaluate_bool("staticFlag", json!({})).reason,
+            ResolutionReason::Static
+        );
+    }
+
+    #[test]
+    fn test_update_state_multi() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let first = r#"{
+            "$evaluators": {"isGold": {"==": [{"var": "tier"}, "gold"]}},
+            "flags": {
+                "goldFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"$ref": "isGold"}, "on", "off"]}
+                }
+            }
+        }"#;
+        let second = r#"{
+            "$evaluators": {"isGold": {"==": [{"var": "tier"}, "gold"]}},
+            "flags": {
+                "bannerFlag": {
+                    "state": "ENABLED",
+                    "variants": {"big": "big", "small": "small"},
+                    "defaultVariant": "small",
+                    "targeting": {"if": [{"$ref": "isGold"}, "big", "small"]}
+                }
+            }
+        }"#;
+
+        // Both fragments are merged into one state and diffed once
+        let response = evaluator.update_state_multi(&[first, second]).unwrap();
+        assert!(response.success);
+        let mut changed = response.changed_flags.unwrap();
+        changed.sort();
+        assert_eq!(changed, vec!["bannerFlag", "goldFlag"]);
+        let context = json!({"tier": "gold"});
+        assert_eq!(
+            evaluator.evaluate_bool("goldFlag", context.clone()).value,
+            json!(true)
+        );
+        assert_eq!(
+            evaluator.evaluate_string("bannerFlag", context).value,
+            json!("big")
+        );
+
+        // Reloading the same fragments changes nothing
+        let response = evaluator.update_state_multi(&[first, second]).unwrap();
+        assert_eq!(response.changed_flags.unwrap(), Vec::<String>::new());
+
+        // A flag defined twice is rejected and the state is kept
+        let response = evaluator.update_state_multi(&[first, first]).unwrap();
+        assert!(!response.success);
+        assert_eq!(response.error_code, Some(UpdateErrorCode::SemanticInvalid));
+        assert!(response
+            .error
+            .unwrap()
+            .contains("Flag 'goldFlag' in fragment 1 is already defined"));
+        assert_eq!(evaluator.get_state().unwrap().flags.len(), 2);
+
+        // So is an evaluator with conflicting definitions
+        let conflicting = second.replace("\"gold\"]}}", "\"silver\"]}}");
+        let response = evaluator
+            .update_state_multi(&[first, &conflicting])
+            .unwrap();
+        assert!(!response.success);
+        assert!(response.error.unwrap().contains("'isGold' in $evaluators"));
+    }
+
+    #[test]
+    fn test_flags_referencing() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        assert!(evaluator.flags_referencing("country").is_empty());
+
+        let config = r#"{
+            "$evaluators": {
+                "inEurope": {"in": [{"var": "country"}, ["DE", "FR"]]}
+            },
+            "flags": {
+                "euFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"$ref": "inEurope"}, "on", "off"]}
+                },
+                "nestedFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"==": [{"var": "user.country"}, "DE"]}, "on", "off"]}
+                },
+                "userFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"has": [{"var": "user"}]}, "on", "off"]}
+                },
+                "emailFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"==": [{"var": "email"}, "a@b.c"]}, "on", "off"]}
+                },
+                "staticFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on"
+                }
+            }
+        }"#;
+        evaluator.update_state(config).unwrap();
+
+        // The $ref is expanded, so the shared evaluator's attribute is found
+        assert_eq!(evaluator.flags_referencing("country"), vec!["euFlag"]);
+        // A nested attribute and the object containing it both match
+        assert_eq!(
+            evaluator.flags_referencing("user"),
+            vec!["nestedFlag", "userFlag"]
+        );
+        assert_eq!(
+            evaluator.flags_referencing("user.country"),
+            vec!["nestedFlag", "userFlag"]
+        );
+        // A shared prefix without a dot is a different attribute
+        assert!(evaluator.flags_referencing("use").is_empty());
+        assert_eq!(evaluator.flags_referencing("email"), vec!["emailFlag"]);
+    }
+
+    #[test]
+    fn test_evaluable_flags() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        assert!(evaluator.evaluable_flags(&json!({})).is_empty());
+
+        let config = r#"{
+            "flags": {
+                "regionFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"==": [{"var": "country"}, "DE"]}, "on", "off"]}
+                },
+                "planFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [
+                        {"and": [
+                            {"==": [{"var": "user.plan"}, "pro"]},
+                            {"<": [{"var": "$flagd.timestamp"}, 4102444800]}
+                        ]},
+                        "on",
+                        "off"
+                    ]}
+                },
+                "tierFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"==": [{"var": ["tier", "free"]}, "gold"]}, "on", "off"]}
+                },
+                "disabledFlag": {
+                    "state": "DISABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"==": [{"var": "country"}, "DE"]}, "on", "off"]}
+                },
+                "staticFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on"
+                }
+            }
+        }"#;
+        evaluator.update_state(config).unwrap();
+
+        // Missing country: only the flag that needs it is excluded
+        assert_eq!(
+            evaluator.evaluable_flags(&json!({"user": {"plan": "pro"}})),
+            vec!["disabledFlag", "planFlag", "staticFlag", "tierFlag"]
+        );
+        // Nested attributes must be present themselves
+        assert_eq!(
+            evaluator.evaluable_flags(&json!({"country": "DE", "user": {}})),
+            vec!["disabledFlag", "regionFlag", "staticFlag", "tierFlag"]
+        );
+        assert_eq!(
+            evaluator.evaluable_flags(&json!({"country": "DE", "user": {"plan": null}})),
+            vec![
+                "disabledFlag",
+                "planFlag",
+                "regionFlag",
+                "staticFlag",
+                "tierFlag"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_flag_state() {
+        let config = r#"{
+            "flags": {
+                "typoFlag": {
+                    "state": "ENABELD",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on",
+                    "targeting": {"if": [{"==": [{"var": "tier"}, "gold"]}, "on", "off"]}
+                },
+                "pausedFlag": {
+                    "state": "PAUSED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on"
+                }
+            }
+        }"#;
+
+        // Error (default): an unrecognized state is never treated as enabled
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Permissive);
+        assert_eq!(evaluator.unknown_state(), UnknownStatePolicy::Error);
+        let response = evaluator.update_state(config).unwrap();
+        assert!(response.success);
+        for flag in ["typoFlag", "pausedFlag"] {
+            let result = evaluator.evaluate_bool(flag, json!({"tier": "gold"}));
+            assert_eq!(result.reason, ResolutionReason::Error, "{}", flag);
+            assert_eq!(result.error_code, Some(ErrorCode::ParseError));
+            assert!(result.error_message.unwrap().contains("unknown state"));
+        }
+        let pre_evaluated = response.pre_evaluated.unwrap();
+        assert_eq!(pre_evaluated["pausedFlag"].reason, ResolutionReason::Error);
+        assert_eq!(pre_evaluated["typoFlag"].reason, ResolutionReason::Error);
+
+        // Disabled: the flags evaluate like DISABLED flags
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Permissive);
+        evaluator.set_unknown_state(UnknownStatePolicy::Disabled);
+        evaluator.update_state(config).unwrap();
+        for flag in ["typoFlag", "pausedFlag"] {
+            let result = evaluator.evaluate_bool(flag, json!({"tier": "gold"}));
+            assert_eq!(result.reason, ResolutionReason::Disabled, "{}", flag);
+            assert_eq!(result.value, Value::Null);
+        }
+
+        // Strict validation rejects the config outright
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        assert!(!evaluator.update_state(config).unwrap().success);
+    }
+
+    #[test]
+    fn test_numeric_string_coercion() {
+        let config = r#"{
+            "flags": {
+                "exactAge": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"==": [{"var": "age"}, 18]}, "on", "off"]}
+                },
+                "adult": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{">=": [{"var": "age"}, 18]}, "on", "off"]}
+                }
+            }
+        }"#;
+
+        // Coercion is on by default: "18" compares as the number 18
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        assert!(evaluator.numeric_string_coercion());
+        evaluator.update_state(config).unwrap();
+        for flag in ["exactAge", "adult"] {
+            let result = evaluator.evaluate_bool(flag, json!({"age": "18"}));
+            assert_eq!(result.value, json!(true), "{}", flag);
+        }
+
+        // Without coercion a numeric string never matches a number
+        evaluator.set_numeric_string_coercion(false);
+        for flag in ["exactAge", "adult"] {
+            let result = evaluator.evaluate_bool(flag, json!({"age": "18"}));
+            assert_eq!(result.value, json!(false), "{}", flag);
+            assert_eq!(result.variant, Some("off".to_string()));
+
+            let result = evaluator.evaluate_bool(flag, json!({"age": 18}));
+            assert_eq!(result.value, json!(true), "{}", flag);
+        }
+
+        // The option also applies to configs loaded afterwards
+        evaluator.update_state(config).unwrap();
+        let result = evaluator.evaluate_bool("adult", json!({"age": "30"}));
+        assert_eq!(result.value, json!(false));
+    }
+
+    #[test]
+    fn test_explain_distinguishes_absent_and_empty_targeting() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "flags": {
+                "noTargeting": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on"
+                },
+                "emptyTargeting": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on",
+                    "targeting": {}
+                },
+                "ruleTargeting": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on",
+                    "targeting": {"if": [true, "off", "on"]}
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+
+        let absent = evaluator.explain_flag("noTargeting", json!({}));
+        let empty = evaluator.explain_flag("emptyTargeting", json!({}));
+        assert_eq!(absent.targeting, Some(TargetingPresence::Absent));
+        assert_eq!(empty.targeting, Some(TargetingPresence::Empty));
+
+        // Both still resolve identically to the default variant
+        for trace in [&absent, &empty] {
+            assert_eq!(trace.result.value, json!(true));
+            assert_eq!(trace.result.variant, Some("on".to_string()));
+            assert_eq!(trace.result.reason, ResolutionReason::Static);
+        }
+
+        let rule = evaluator.explain_flag("ruleTargeting", json!({}));
+        assert_eq!(rule.targeting, Some(TargetingPresence::Rule));
+        assert_eq!(rule.result.variant, Some("off".to_string()));
+
+        let missing = evaluator.explain_flag("missing", json!({}));
+        assert_eq!(missing.targeting, None);
+        assert_eq!(missing.result.reason, ResolutionReason::FlagNotFound);
+
+        let serialized = serde_json::to_value(&empty).unwrap();
+        assert_eq!(serialized["targeting"], json!("empty"));
+        assert_eq!(serialized["result"]["reason"], json!("STATIC"));
+    }
+
+    #[test]
+    fn test_numeric_targeting_preserves_integer_variants() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        let config = r#"{
+            "flags": {
+                "limit": {
+                    "state": "ENABLED",
+                    "variants": {"10": 10, "20": 20, "2.5": 2.5},
+                    "defaultVariant": "10",
+                    "targeting": {"*": [{"var": "multiplier"}, 10]}
+                }
+            }
+        }"#;
+        evaluator.update_state(config).unwrap();
+
+        // An integer variant selected through arithmetic resolves to the stored integer
+        let result = evaluator.evaluate_int("limit", json!({"multiplier": 2}));
+        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+        assert_eq!(result.variant.as_deref(), Some("20"));
+        assert!(
+            result.value.is_i64(),
+            "expected integer, got {}",
+            result.value
+        );
+        assert!(result.to_json_string().contains(r#""value":20,"#));
+        let result = evaluator.evaluate_flag("limit", json!({"multiplier": 2}));
+        assert!(result.value.is_i64());
+
+        // Non-integral results select by their exact spelling
+        let result = evaluator.evaluate_flag("limit", json!({"multiplier": 0.25}));
+        assert_eq!(result.variant.as_deref(), Some("2.5"));
+        assert!(result.value.is_f64());
+    }
+
+    #[test]
+    fn test_integral_float_targeting_result_selects_integer_variant() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        let config = r#"{
+            "flags": {
+                "picked": {
+                    "state": "ENABLED",
+                    "variants": {"1": 1, "20": 20, "2.5": 2.5},
+                    "defaultVariant": "1",
+                    "targeting": {"var": "pick"}
+                },
+                "spelled": {
+                    "state": "ENABLED",
+                    "variants": {"1": "integer", "1.0": "float"},
+                    "defaultVariant": "1",
+                    "targeting": {"var": "pick"}
+                }
+            }
+        }"#;
+        evaluator.update_state(config).unwrap();
+
+        // A float intermediate such as 20.0 selects the variant "20" and keeps its
+        // stored integer value
+        for pick in [json!(20), json!(20.0)] {
+            let result = evaluator.evaluate_int("picked", json!({ "pick": pick }));
+            assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+            assert_eq!(result.variant.as_deref(), Some("20"));
+            assert!(
+                result.value.is_i64(),
+                "expected integer, got {}",
+                result.value
+            );
+        }
+
+        // Non-integral floats still select by their spelling
+        let result = evaluator.evaluate_flag("picked", json!({"pick": 2.5}));
+        assert_eq!(result.variant.as_deref(), Some("2.5"));
+
+        // A variant spelled exactly like the float wins over the integer one
+        let result = evaluator.evaluate_flag("spelled", json!({"pick": 1.0}));
+        assert_eq!(result.variant.as_deref(), Some("1.0"));
+        let result = evaluator.evaluate_flag("spelled", json!({"pick": 1}));
+        assert_eq!(result.variant.as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn test_bool_targeting_maps_to_variants() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        let config = r#"{
+            "flags": {
+                "goldOnly": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"==": [{"var": "tier"}, "gold"]}
+                }
+            }
+        }"#;
+        evaluator.update_state(config).unwrap();
+
+        // Default: the boolean is looked up as a variant name and is not found
+        let result = evaluator.evaluate_bool("goldOnly", json!({"tier": "gold"}));
+        assert_eq!(result.reason, ResolutionReason::Error);
+        assert!(result.error_message.unwrap().contains("'true'"));
+        assert_eq!(evaluator.bool_targeting_maps_to(), None);
+
+        evaluator.set_bool_targeting_maps_to(Some(("on".to_string(), "off".to_string())));
+        assert_eq!(evaluator.bool_targeting_maps_to(), Some(("on", "off")));
+
+        let result = evaluator.evaluate_bool("goldOnly", json!({"tier": "gold"}));
+        assert_eq!(result.value, json!(true));
+        assert_eq!(result.variant.as_deref(), Some("on"));
+        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+
+        let result = evaluator.evaluate_bool("goldOnly", json!({"tier": "silver"}));
+        assert_eq!(result.value, json!(false));
+        assert_eq!(result.variant.as_deref(), Some("off"));
+        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+
+        // A mapping to a variant the flag doesn't define is still an error
+        evaluator.set_bool_targeting_maps_to(Some(("yes".to_string(), "no".to_string())));
+        let result = evaluator.evaluate_bool("goldOnly", json!({"tier": "gold"}));
+        assert_eq!(result.reason, ResolutionReason::Error);
+        assert!(result.error_message.unwrap().contains("'yes'"));
+    }
+
+    #[test]
+    fn test_explain_reports_bucket_percentile() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        let config = r#"{
+            "flags": {
+                "split": {
+                    "state": "ENABLED",
+                    "variants": {"a": "a", "b": "b"},
+                    "defaultVariant": "a",
+                    "targeting": {"fractional": [["a", 50], ["b", 50]]}
+                },
+                "plain": {
+                    "state": "ENABLED",
+                    "variants": {"a": "a"},
+                    "defaultVariant": "a",
+                    "targeting": {"if": [true, "a"]}
+                }
+            }
+        }"#;
+        evaluator.update_state(config).unwrap();
+        let context = json!({"targetingKey": "user-42"});
+
+        let first = evaluator.explain_flag("split", context.clone());
+        let second = evaluator.explain_flag("split", context);
+        let percentile = first.bucket_percentile.unwrap();
+        assert!((0.0..100.0).contains(&percentile));
+        assert_eq!(second.bucket_percentile, Some(percentile));
+
+        // Matches the percentile used to pick the bucket
+        let buckets = [json!("a"), json!(50), json!("b"), json!(50)];
+        let (bucket, expected) = operators::bucket_of("user-42", "split", &buckets).unwrap();
+        assert_eq!(percentile, expected);
+        assert_eq!(first.result.variant, Some(bucket));
+
+        let serialized = serde_json::to_value(&first).unwrap();
+        assert_eq!(serialized["bucketPercentile"], json!(percentile));
+
+        // Only present when a fractional rule ran
+        let trace = evaluator.explain_flag("plain", json!({"targetingKey": "user-42"}));
+        assert_eq!(trace.bucket_percentile, None);
+        assert!(serde_json::to_value(&trace)
+            .unwrap()
+            .get("bucketPercentile")
+            .is_none());
+    }
+
+    #[test]
+    fn test_explain_default_reason() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Permissive);
+
+        let config = r#"{
+            "flags": {
+                "nullTargeting": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"==": [{"var": "tier"}, "gold"]}, "on", null]}
+                },
+                "staticFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off"
+                },
+                "unknownVariant": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [true, "missing", "off"]}
+                },
+                "disabledFlag": {
+                    "state": "DISABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off"
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+
+        let cases = [
+            ("nullTargeting", DefaultReason::TargetingReturnedNull),
+            ("staticFlag", DefaultReason::NoTargeting),
+            ("unknownVariant", DefaultReason::VariantNotFound),
+            ("disabledFlag", DefaultReason::Disabled),
+        ];
+        for (flag_key, expected) in cases {
+            let trace = evaluator.explain_flag(flag_key, json!({}));
+            assert_eq!(trace.default_reason, Some(expected), "flag {}", flag_key);
+        }
+
+        // A targeting match has no default reason
+        let trace = evaluator.explain_flag("nullTargeting", json!({"tier": "gold"}));
+        assert_eq!(trace.result.reason, ResolutionReason::TargetingMatch);
+        assert_eq!(trace.default_reason, None);
+
+        let serialized = serde_json::to_value(
+            evaluator.explain_flag("nullTargeting", json!({"tier": "silver"})),
+        )
+        .unwrap();
+        assert_eq!(
+            serialized["defaultReason"],
+            json!("targeting_returned_null")
+        );
+    }
+
+    #[test]
+    fn test_targeting_returning_non_variant_value() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        let config = r#"{
+            "flags": {
+                "numberFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [true, 42, "off"]}
+                },
+                "objectFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"var": "obj"}
+                },
+                "numericVariants": {
+                    "state": "ENABLED",
+                    "variants": {"1": "one", "2": "two"},
+                    "defaultVariant": "1",
+                    "targeting": {"+": [{"var": "count"}, 1]}
+                }
+            }
+        }"#;
+        evaluator.update_state(config).unwrap();
+
+        let result = evaluator.evaluate_flag("numberFlag", json!({}));
+        assert_eq!(result.reason, ResolutionReason::Error);
+        assert_eq!(result.error_code, Some(ErrorCode::General));
+        assert_eq!(
+            result.error_message.as_deref(),
+            Some("Targeting rule for flag 'numberFlag' must resolve to a variant name string, got integer")
+        );
+
+        let trace = evaluator.explain_flag("objectFlag", json!({"obj": {"variant": "on"}}));
+        assert_eq!(trace.result.reason, ResolutionReason::Error);
+        assert_eq!(
+            trace.result.error_message.as_deref(),
+            Some("Targeting rule for flag 'objectFlag' must resolve to a variant name string, got object")
+        );
+        assert_eq!(
+            trace.default_reason,
+            Some(DefaultReason::InvalidTargetingResult)
+        );
+
+        // Numbers that name a variant still select it
+        let result = evaluator.evaluate_flag("numericVariants", json!({"count": 1}));
+        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+        assert_eq!(result.value, json!("two"));
+    }
+
+    #[test]
+    fn test_targeting_on_flag_metadata() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "metadata": {"tier": "free", "environment": "production"},
+            "flags": {
+                "premiumFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "metadata": {"tier": "premium"},
+                    "targeting": {
+                        "if": [{"==": [{"var": "$flagd.flagMetadata.tier"}, "premium"]}, "on", "off"]
+                    }
+                },
+                "freeFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {
+                        "if": [{"==": [{"var": "$flagd.flagMetadata.tier"}, "premium"]}, "on", "off"]
+                    }
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+
+        // Flag-level metadata overrides flag-set metadata
+        let result = evaluator.evaluate_bool("premiumFlag", json!({}));
+        assert_eq!(result.value, json!(true));
+        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+
+        // Flag-set metadata is inherited
+        let result = evaluator.evaluate_bool("freeFlag", json!({}));
+        assert_eq!(result.value, json!(false));
+    }
+
+    #[test]
+    fn test_evaluate_preview() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        let config = r#"{
+            "flags": {
+                "previewFlag": {
+                    "state": "DISABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"==": [{"var": "tier"}, "gold"]}, "on"]}
+                },
+                "liveFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on"
+                }
+            }
+        }"#;
+        evaluator.update_state(config).unwrap();
+
+        // Normal evaluation of the disabled flag is unchanged
+        let result = evaluator.evaluate_flag("previewFlag", json!({"tier": "gold"}));
+        assert_eq!(result.reason, ResolutionReason::Disabled);
+        assert_eq!(result.value, json!(null));
+
+        let result = evaluator.evaluate_preview("previewFlag", json!({"tier": "gold"}));
+        assert_eq!(result.value, json!(true));
+        assert_eq!(result.variant, Some("on".to_string()));
+        assert_eq!(result.reason, ResolutionReason::Preview);
+
+        let result = evaluator.evaluate_preview("previewFlag", json!({"tier": "silver"}));
+        assert_eq!(result.value, json!(false));
+        assert_eq!(result.reason, ResolutionReason::Preview);
+
+        // Enabled flags are evaluated normally
+        let result = evaluator.evaluate_preview("liveFlag", json!({}));
+        assert_eq!(result.reason, ResolutionReason::Static);
+    }
+
+    #[test]
+    fn test_evaluate_merged() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        let config = r#"{
+            "flags": {
+                "planFlag": {
+                    "state": "ENABLED",
+                    "variants": {"premium": "premium", "basic": "basic"},
+                    "defaultVariant": "basic",
+                    "targeting": {
+                        "if": [
+                            {"and": [
+                                {"==": [{"var": "plan"}, "pro"]},
+                                {"==": [{"var": "region"}, "eu"]}
+                            ]},
+                            "premium",
+                            "basic"
+                        ]
+                    }
+                }
+            }
+        }"#;
+        evaluator.update_state(config).unwrap();
+
+        let global = json!({"plan": "free", "region": "eu"});
+        let client = json!({"plan": "pro"});
+        let invocation = json!({"targetingKey": "user-1"});
+
+        // The client context overrides the global plan
+        let result = evaluator.evaluate_merged("planFlag", &[&global, &client, &invocation]);
+        assert_eq!(result.value, json!("premium"));
+        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+
+        // Reversing the priority lets the global plan win
+        let result = evaluator.evaluate_merged("planFlag", &[&client, &global]);
+        assert_eq!(result.value, json!("basic"));
+
+        // Non-object contexts are ignored
+        let result = evaluator.evaluate_merged("planFlag", &[&global, &client, &json!(null)]);
+        assert_eq!(result.value, json!("premium"));
+    }
+
+    #[test]
+    fn test_shadow_eval() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        let config = |tiers: &str| {
+            format!(
+                r#"{{
+                    "flags": {{
+                        "tierFlag": {{
+                            "state": "ENABLED",
+                            "variants": {{"on": true, "off": false}},
+                            "defaultVariant": "off",
+                            "targeting": {{"if": [{{"in": [{{"var": "tier"}}, {}]}}, "on", "off"]}}
+                        }},
+                        "stableFlag": {{
+                            "state": "ENABLED",
+                            "variants": {{"on": true, "off": false}},
+                            "defaultVariant": "on"
+                        }}
+                    }}
+                }}"#,
+                tiers
+            )
+        };
+        evaluator.update_state(&config(r#"["gold"]"#)).unwrap();
+
+        let contexts = vec![
+            json!({"tier": "gold"}),
+            json!({"tier": "silver"}),
+            json!({"tier": "bronze"}),
+            json!({"tier": "silver"}),
+        ];
+        let report = evaluator
+            .shadow_eval(&config(r#"["gold", "silver"]"#), &contexts)
+            .unwrap();
+
+        assert_eq!(report.contexts_evaluated, 4);
+        assert_eq!(report.flags.len(), 1);
+        let diff = &report.flags["tierFlag"];
+        assert_eq!(diff.changed, 2);
+        assert_eq!(diff.changed_contexts, vec![1, 3]);
+
+        // The current state is untouched
+        let result = evaluator.evaluate_bool("tierFlag", json!({"tier": "silver"}));
+        assert_eq!(result.value, json!(false));
+
+        // An identical candidate changes nothing
+        let report = evaluator
+            .shadow_eval(&config(r#"["gold"]"#), &contexts)
+            .unwrap();
+        assert!(report.is_empty());
+
+        assert!(evaluator.shadow_eval("{not json", &contexts).is_err());
+    }
+
+    const PREREQUISITE_CONFIG: &str = r#"{
+        "flags": {
+            "newCheckout": {
+                "state": "ENABLED",
+                "variants": {"on": true, "off": false},
+                "defaultVariant": "off",
+                "targeting": {"if": [{"==": [{"var": "tier"}, "gold"]}, "on"]}
+            },
+            "expressShipping": {
+                "state": "ENABLED",
+                "variants": {"on": true, "off": false},
+                "defaultVariant": "off",
+                "prerequisites": [{"flag": "newCheckout", "variant": "on"}],
+                "targeting": {"if": [{"==": [{"var": "country"}, "DE"]}, "on"]}
+            },
+            "shippingBanner": {
+                "state": "ENABLED",
+                "variants": {"show": "visible", "hide": "hidden"},
+                "defaultVariant": "show",
+                "prerequisites": [{"flag": "expressShipping", "variant": "on"}]
+            }
+        }
+    }"#;
+
+    #[test]
+    fn test_prerequisites_satisfied() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        let response = evaluator.update_state(PREREQUISITE_CONFIG).unwrap();
+        assert!(response.success, "{:?}", response.error);
+
+        let context = json!({"tier": "gold", "country": "DE"});
+        let result = evaluator.evaluate_bool("expressShipping", context.clone());
+        assert_eq!(result.value, json!(true));
+        assert_eq!(result.variant, Some("on".to_string()));
+        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+
+        // Prerequisites chain: shippingBanner needs expressShipping, which needs newCheckout
+        let result = evaluator.evaluate_string("shippingBanner", context);
+        assert_eq!(result.value, json!("visible"));
+        assert_eq!(result.reason, ResolutionReason::Static);
+
+        // A met prerequisite leaves the flag's own targeting in charge
+        let result = evaluator.evaluate_bool("expressShipping", json!({"tier": "gold"}));
+        assert_eq!(result.variant, Some("off".to_string()));
+        assert_eq!(result.reason, ResolutionReason::Default);
+    }
+
+    #[test]
+    fn test_prerequisites_unsatisfied() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        let response = evaluator.update_state(PREREQUISITE_CONFIG).unwrap();
+        assert!(response.success, "{:?}", response.error);
+
+        // Flags with prerequisites depend on the context, so they are not pre-evaluated
+        let pre_evaluated = response.pre_evaluated.unwrap_or_default();
+        assert!(!pre_evaluated.contains_key("shippingBanner"));
+
+        // newCheckout resolves to "off", so expressShipping's targeting is skipped
+        let context = json!({"tier": "silver", "country": "DE"});
+        let result = evaluator.evaluate_bool("expressShipping", context.clone());
+        assert_eq!(result.value, json!(false));
+        assert_eq!(result.variant, Some("off".to_string()));
+        assert_eq!(result.reason, ResolutionReason::PrerequisiteFailed);
+        assert_eq!(result.error_code, None);
+        let serialized = serde_json::to_value(&result).unwrap();
+        assert_eq!(serialized["reason"], json!("PREREQUISITE_FAILED"));
+
+        // The failure propagates down the chain
+        let result = evaluator.evaluate_string("shippingBanner", context);
+        assert_eq!(result.value, json!("visible"));
+        assert_eq!(result.reason, ResolutionReason::PrerequisiteFailed);
+
+        // A disabled prerequisite is never met
+        let disabled = PREREQUISITE_CONFIG.replacen("\"ENABLED\"", "\"DISABLED\"", 1);
+        evaluator.update_state(&disabled).unwrap();
+        let result =
+            evaluator.evaluate_bool("expressShipping", json!({"tier": "gold", "country": "DE"}));
+        assert_eq!(result.reason, ResolutionReason::PrerequisiteFailed);
+
+        assert_eq!(
+            evaluator.possible_reasons("expressShipping"),
+            Some(vec![
+                ResolutionReason::Default,
+                ResolutionReason::TargetingMatch,
+                ResolutionReason::Error,
+                ResolutionReason::PrerequisiteFailed
+            ])
+        );
+    }
+
+    #[test]
+    fn test_prerequisite_changes_mark_dependents_changed() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        evaluator.update_state(PREREQUISITE_CONFIG).unwrap();
+
+        let updated = PREREQUISITE_CONFIG.replace("\"gold\"", "\"platinum\"");
+        let response = evaluator.update_state(&updated).unwrap();
+        assert_eq!(
+            response.changed_flags,
+            Some(vec![
+                "expressShipping".to_string(),
+                "newCheckout".to_string(),
+                "shippingBanner".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_prerequisite_cycle_is_rejected() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        let config = r#"{
+            "flags": {
+                "a": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on",
+                    "prerequisites": [{"flag": "b", "variant": "on"}]
+                },
+                "b": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on",
+                    "prerequisites": [{"flag": "a", "variant": "on"}]
+                }
+            }
+        }"#;
+
+        let response = evaluator.update_state(config).unwrap();
+        assert!(!response.success);
+        assert_eq!(response.error_code, Some(UpdateErrorCode::SemanticInvalid));
+        assert_eq!(
+            response.error.as_deref(),
+            Some("Circular prerequisite detected: a -> b -> a")
+        );
+        assert!(evaluator.get_state().is_none());
+    }
+
+    #[test]
+    fn test_variant_aliases() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        // "blue" and "green" were renamed to "control" and "treatment"
+        let config = r#"{
+            "flags": {
+                "buttonColor": {
+                    "state": "ENABLED",
+                    "variants": {"control": "navy", "treatment": "lime"},
+                    "variantAliases": {"blue": "control", "green": "treatment"},
+                    "defaultVariant": "control",
+                    "targeting": {
+                        "if": [
+                            {"==": [{"var": "beta"}, true]},
+                            "green",
+                            {"fractional": [["blue", 50], ["green", 50]]}
+                        ]
+                    }
+                },
+                "greenBanner": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on",
+                    "prerequisites": [{"flag": "buttonColor", "variant": "green"}]
+                }
+            }
+        }"#;
+        let response = evaluator.update_state(config).unwrap();
+        assert!(response.success, "{:?}", response.error);
+
+        // The old name resolves to the renamed variant's value
+        let result = evaluator.evaluate_string("buttonColor", json!({"beta": true}));
+        assert_eq!(result.value, json!("lime"));
+        assert_eq!(result.variant, Some("treatment".to_string()));
+        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+
+        // Fractional buckets keep their old names, so users stay in their bucket
+        for i in 0..20 {
+            let context = json!({"beta": false, "targetingKey": format!("user-{}", i)});
+            let result = evaluator.evaluate_string("buttonColor", context);
+            assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+            let expected = match result.variant.as_deref() {
+                Some("control") => json!("navy"),
+                Some("treatment") => json!("lime"),
+                other => panic!("unexpected variant {:?}", other),
+            };
+            assert_eq!(result.value, expected);
+        }
+
+        // Prerequisites may name the alias too
+        let result = evaluator.evaluate_bool("greenBanner", json!({"beta": true}));
+        assert_eq!(result.reason, ResolutionReason::Static);
+    }
+
+    #[test]
+    fn test_variant_aliases_must_target_variants() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        let config = |aliases: Value| {
+            json!({
+                "flags": {
+                    "flag": {
+                        "state": "ENABLED",
+                        "variants": {"on": true, "off": false},
+                        "variantAliases": aliases,
+                        "defaultVariant": "on"
+                    }
+                }
+            })
+            .to_string()
+        };
+
+        let response = evaluator
+            .update_state(&config(json!({"enabled": "yes"})))
+            .unwrap();
+        assert!(!response.success);
+        assert_eq!(
+            response.error.as_deref(),
+            Some("Variant alias 'enabled' in flag 'flag' refers to unknown variant 'yes'")
+        );
+
+        let response = evaluator
+            .update_state(&config(json!({"on": "off"})))
+            .unwrap();
+        assert!(!response.success);
+        assert_eq!(
+            response.error.as_deref(),
+            Some("Variant alias 'on' in flag 'flag' shadows an existing variant")
+        );
+    }
+
+    #[test]
+    fn test_resolve_default() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        assert_eq!(
+            evaluator.resolve_default("staticFlag").error_message,
+            Some("state uninitialized".to_string())
+        );
+
+        let config = r#"{
+            "metadata": {"team": "checkout"},
+            "flags": {
+                "staticFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on"
+                },
+                "targetedFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [true, "on", "off"]}
+                },
+                "noDefaultFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "targeting": {"if": [true, "on", "off"]}
+                }
+            }
+        }"#;
+        evaluator.update_state(config).unwrap();
+
+        // A flag without targeting resolves exactly as a full evaluation
+        assert_eq!(
+            evaluator.resolve_default("staticFlag").to_json_string(),
+            evaluator
+                .evaluate_flag("staticFlag", json!({}))
+                .to_json_string()
+        );
+
+        // Targeting is ignored entirely
+        assert_eq!(
+            evaluator.evaluate_flag("targetedFlag", json!({})).value,
+            json!(true)
+        );
+        let result = evaluator.resolve_default("targetedFlag");
+        assert_eq!(result.value, json!(false));
+        assert_eq!(result.variant, Some("off".to_string()));
+        assert_eq!(result.reason, ResolutionReason::Default);
+        assert_eq!(
+            result.flag_metadata.unwrap().get("team"),
+            Some(&json!("checkout"))
+        );
+
+        let result = evaluator.resolve_default("noDefaultFlag");
+        assert_eq!(result.reason, ResolutionReason::Fallback);
+
+        let result = evaluator.resolve_default("missingFlag");
+        assert_eq!(result.reason, ResolutionReason::FlagNotFound);
+    }
+
+    #[test]
+    fn test_expose_internal_metadata() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Permissive);
+        assert!(!evaluator.expose_internal_metadata());
+
+        let config = r#"{
+            "metadata": {"$flagSetId": "internal-set", "team": "checkout"},
+            "flags": {
+                "myFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on"
+                }
+            }
+        }"#;
+        evaluator.update_state(config).unwrap();
+
+        // Internal keys are filtered out by default
+        let metadata = evaluator
+            .evaluate_bool("myFlag", json!({}))
+            .flag_metadata
+            .unwrap();
+        assert_eq!(metadata.get("team"), Some(&json!("checkout")));
+        assert!(!metadata.contains_key("$flagSetId"));
+        assert!(!evaluator
+            .get_flag_metadata("myFlag")
+            .unwrap()
+            .contains_key("$flagSetId"));
+
+        evaluator.set_expose_internal_metadata(true);
+        let metadata = evaluator
+            .evaluate_bool("myFlag", json!({}))
+            .flag_metadata
+            .unwrap();
+        assert_eq!(metadata.get("team"), Some(&json!("checkout")));
+        assert_eq!(metadata.get("$flagSetId"), Some(&json!("internal-set")));
+        assert_eq!(
+            evaluator
+                .get_flag_metadata("myFlag")
+                .unwrap()
+                .get("$flagSetId"),
+            Some(&json!("internal-set"))
+        );
+    }
+
+    #[test]
+    fn test_max_config_bytes() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        assert_eq!(evaluator.max_config_bytes(), None);
+
+        let config = r#"{"flags": {"myFlag": {"state": "ENABLED", "variants": {"on": true}, "defaultVariant": "on"}}}"#;
+        evaluator.set_max_config_bytes(Some(config.len()));
+        assert!(evaluator.update_state(config).unwrap().success);
+
+        evaluator.set_max_config_bytes(Some(config.len() - 1));
+        let response = evaluator.update_state(config).unwrap();
+        assert!(!response.success);
+        assert!(response.error.unwrap().contains("exceeds the maximum"));
+        assert!(evaluator.get_state().unwrap().flags.contains_key("myFlag"));
+    }
+
+    #[test]
+    fn test_evaluate_all_json_matches_individual_results() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        let config = r#"{
+            "flags": {
+                "boolFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on"
+                },
+                "targetedFlag": {
+                    "state": "ENABLED",
+                    "variants": {"admin": "admin-value", "user": "user-value"},
+                    "defaultVariant": "user",
+                    "targeting": {"if": [{"==": [{"var": "role"}, "admin"]}, "admin", "user"]}
+                },
+                "disabledFlag": {
+                    "state": "DISABLED",
+                    "variants": {"on": true},
+                    "defaultVariant": "on"
+                },
+                "odd\"key": {
+                    "state": "ENABLED",
+                    "variants": {"obj": {"nested": [1, 2]}},
+                    "defaultVariant": "obj",
+                    "metadata": {"team": "core"}
+                }
             }
+        }"#;
+        evaluator.update_state(config).unwrap();
+        let context = json!({"role": "admin", "targetingKey": "user-1"});
 
-            // Parse context (pre-enriched by host)
-            let context: Value = if context_ptr.is_null() || context_len == 0 {
-                Value::Null
-            } else {
-                // SAFETY: The caller guarantees valid memory regions
-                let context_str = match unsafe { string_from_memory(context_ptr, context_len) } {
-                    Ok(s) => s,
-                    Err(e) => {
-                        return EvaluationResult::error(
-                            ErrorCode::ParseError,
-                            format!("Failed to read context: {}", e),
-                        )
-                    }
-                };
+        let batched: Value =
+            serde_json::from_str(&evaluator.evaluate_all_json(context.clone())).unwrap();
+        let batched = batched.as_object().unwrap();
+        assert_eq!(batched.len(), 4);
 
-                match serde_json::from_str(&context_str) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        return EvaluationResult::error(
-                            ErrorCode::ParseError,
-                            format!("Failed to parse context JSON: {}", e),
-                        )
-                    }
+        for flag_key in ["boolFlag", "targetedFlag", "disabledFlag", "odd\"key"] {
+            let individual = evaluator.evaluate_flag(flag_key, context.clone());
+            let expected: Value = serde_json::from_str(&individual.to_json_string()).unwrap();
+            assert_eq!(batched[flag_key], expected, "mismatch for {}", flag_key);
+        }
+        assert_eq!(batched["targetedFlag"]["variant"], "admin");
+
+        let keys: Vec<String> = evaluator
+            .evaluate_all(context)
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(
+            keys,
+            ["boolFlag", "disabledFlag", "odd\"key", "targetedFlag"]
+        );
+
+        assert_eq!(
+            FlagEvaluator::new(ValidationMode::Strict).evaluate_all_json(json!({})),
+            "{}"
+        );
+    }
+
+    #[test]
+    fn test_evaluate_distribution_fractional_split() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        let config = r#"{
+            "flags": {
+                "rollout": {
+                    "state": "ENABLED",
+                    "variants": {"new": true, "old": false},
+                    "defaultVariant": "old",
+                    "targeting": {"fractional": [["new", 70], ["old", 30]]}
+                },
+                "noDefault": {
+                    "state": "ENABLED",
+                    "variants": {"on": true},
+                    "targeting": {"if": [{"==": [{"var": "tier"}, "gold"]}, "on", null]}
                 }
-            };
+            }
+        }"#;
+        evaluator.update_state(config).unwrap();
 
-            eval.evaluate_flag_by_index(flag_index, context)
-        })
-    });
+        let contexts: Vec<Value> = (0..2000)
+            .map(|i| json!({"targetingKey": format!("user-{}", i)}))
+            .collect();
+        let counts = evaluator.evaluate_distribution("rollout", &contexts);
+        assert_eq!(counts.values().sum::<usize>(), contexts.len());
+        let new = counts["new"] as f64 / contexts.len() as f64;
+        assert!((0.65..=0.75).contains(&new), "new share was {}", new);
+        assert!(counts["old"] > 0);
+
+        let counts =
+            evaluator.evaluate_distribution("noDefault", &[json!({"tier": "gold"}), json!({})]);
+        assert_eq!(counts["on"], 1);
+        assert_eq!(counts["FALLBACK"], 1);
+
+        assert!(evaluator.evaluate_distribution("rollout", &[]).is_empty());
+    }
 
-    result.unwrap_or_else(|panic_err| {
-        let msg = if let Some(s) = panic_err.downcast_ref::<&str>() {
-            format!("Evaluation panic: {}", s)
-        } else if let Some(s) = panic_err.downcast_ref::<String>() {
-            format!("Evaluation panic: {}", s)
-        } else {
-            "Evaluation panic: unknown error".to_string()
-        };
-        EvaluationResult::error(ErrorCode::General, msg)
-    })
-}
+    #[test]
+    fn test_update_state_error_codes() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
 
-/// Internal implementation of evaluate.
-fn evaluate_internal(
-    flag_key_ptr: *const u8,
-    flag_key_len: u32,
-    context_ptr: *const u8,
-    context_len: u32,
-) -> EvaluationResult {
-    // Initialize panic hook for better error messages
-    init_panic_hook();
+        let response = evaluator.update_state("{not json").unwrap();
+        assert_eq!(response.error_code, Some(UpdateErrorCode::InvalidJson));
 
-    // Catch any panics and convert them to error responses
-    let result = std::panic::catch_unwind(|| {
-        wasm_evaluator::with_evaluator(|eval| {
-            // Check if state is initialized
-            if eval.get_state().is_none() {
-                return EvaluationResult::error(
-                    ErrorCode::FlagNotFound,
-                    "Flag state not initialized. Call update_state first.",
-                );
+        let response = evaluator
+            .update_state(r#"{"flags": {"f": {"state": "BOGUS", "variants": {"on": true}}}}"#)
+            .unwrap();
+        assert_eq!(response.error_code, Some(UpdateErrorCode::SchemaInvalid));
+
+        // Schema-valid but references an unknown shared evaluator
+        let semantic = r#"{
+            "flags": {
+                "f": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on",
+                    "targeting": {"if": [{"$ref": "missing"}, "on", "off"]}
+                }
             }
+        }"#;
+        let response = evaluator.update_state(semantic).unwrap();
+        assert_eq!(response.error_code, Some(UpdateErrorCode::SemanticInvalid));
+
+        evaluator.set_validation_mode(ValidationMode::Permissive);
+        let response = evaluator.update_state("{not json").unwrap();
+        assert_eq!(response.error_code, Some(UpdateErrorCode::InvalidJson));
+
+        evaluator.set_max_config_bytes(Some(4));
+        let response = evaluator.update_state(semantic).unwrap();
+        assert_eq!(response.error_code, Some(UpdateErrorCode::ConfigTooLarge));
+
+        // Success responses carry no code, and it is omitted from the JSON
+        evaluator.set_max_config_bytes(None);
+        let response = evaluator
+            .update_state(r#"{"flags": {"f": {"state": "ENABLED", "variants": {"on": true}, "defaultVariant": "on"}}}"#)
+            .unwrap();
+        assert!(response.success);
+        assert_eq!(response.error_code, None);
+        let json = serde_json::to_value(&response).unwrap();
+        assert!(json.get("errorCode").is_none());
+
+        let failure = serde_json::to_value(UpdateStateResponse::failure(
+            UpdateErrorCode::SchemaInvalid,
+            "bad",
+        ))
+        .unwrap();
+        assert_eq!(failure["errorCode"], "SCHEMA_INVALID");
+    }
 
-            // SAFETY: The caller guarantees valid memory regions
-            let flag_key = match unsafe { string_from_memory(flag_key_ptr, flag_key_len) } {
-                Ok(s) => s,
-                Err(e) => {
-                    return EvaluationResult::error(
-                        ErrorCode::ParseError,
-                        format!("Failed to read flag key: {}", e),
-                    )
+    #[test]
+    fn test_operator_aliases() {
+        let config = r#"{
+            "$evaluators": {
+                "isAdmin": {"startsWith": [{"var": "email"}, "admin@"]}
+            },
+            "flags": {
+                "aliased": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [
+                        {"or": [{"$ref": "isAdmin"}, {"semver": [{"var": "version"}, "^", "2.0.0"]}]},
+                        "on",
+                        "off"
+                    ]}
+                },
+                "canonical": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [
+                        {"or": [
+                            {"starts_with": [{"var": "email"}, "admin@"]},
+                            {"sem_ver": [{"var": "version"}, "^", "2.0.0"]}
+                        ]},
+                        "on",
+                        "off"
+                    ]}
                 }
-            };
+            }
+        }"#;
 
-            let flag = eval.get_state().unwrap().flags.get(&flag_key);
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+        assert_eq!(
+            evaluator
+                .operator_aliases()
+                .get("semver")
+                .map(String::as_str),
+            Some("sem_ver")
+        );
+        let response = evaluator.update_state(config).unwrap();
+        assert!(response.success, "{:?}", response.error);
+
+        for context in [
+            json!({"email": "admin@example.com", "version": "1.0.0"}),
+            json!({"email": "user@example.com", "version": "2.3.1"}),
+            json!({"email": "user@example.com", "version": "3.0.0"}),
+        ] {
+            let aliased = evaluator.evaluate_flag("aliased", context.clone());
+            let canonical = evaluator.evaluate_flag("canonical", context.clone());
+            assert_eq!(aliased.value, canonical.value, "{}", context);
+            assert_eq!(aliased.reason, canonical.reason, "{}", context);
+        }
+        assert_eq!(
+            evaluator
+                .evaluate_flag("aliased", json!({"email": "admin@example.com"}))
+                .value,
+            json!(true)
+        );
 
-            // Parse protobuf context
-            let context: Value = if context_ptr.is_null()
-                || context_len == 0
-                || flag.is_some_and(|f| f.targeting.is_none())
-            {
-                Value::Null
-            } else {
-                let context_str = match unsafe { string_from_memory(context_ptr, context_len) } {
-                    Ok(s) => s,
-                    Err(e) => {
-                        return EvaluationResult::error(
-                            ErrorCode::ParseError,
-                            format!("Failed to read context: {}", e),
-                        )
-                    }
-                };
+        // Hosts can register their own aliases for any targeting operator
+        let rule = json!({
+            "if": [{"oneOf": [{"var": "tier"}, ["gold", "platinum"]]}, "on", "off"]
+        });
+        evaluator.add_operator_alias("oneOf", "in").unwrap();
+        let result = evaluator.evaluate_rule_for_flag("canonical", &rule, json!({"tier": "gold"}));
+        assert_eq!(result.value, json!(true));
 
-                match serde_json::from_str(&context_str) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        return EvaluationResult::error(
-                            ErrorCode::ParseError,
-                            format!("Failed to parse context JSON: {}", e),
-                        )
+        assert_eq!(
+            evaluator.add_operator_alias("oneOf", "no_such_operator"),
+            Err("Unknown operator 'no_such_operator'".to_string())
+        );
+        assert!(evaluator.add_operator_alias("in", "sem_ver").is_err());
+
+        // Without the alias the schema rejects the rule in strict mode
+        assert!(evaluator.remove_operator_alias("semver"));
+        assert!(!evaluator.remove_operator_alias("semver"));
+        assert!(!evaluator.update_state(config).unwrap().success);
+
+        // The engine accepts the default aliases directly too
+        let logic = operators::create_evaluator();
+        assert_eq!(
+            logic
+                .evaluate_json(r#"{"semver": ["1.2.3", ">=", "1.0.0"]}"#, "{}")
+                .unwrap(),
+            json!(true)
+        );
+    }
+
+    #[test]
+    fn test_evaluator_strict_variables() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "flags": {
+                "tierFlag": {
+                    "state": "ENABLED",
+                    "variants": {"premium": "premium-tier", "basic": "basic-tier"},
+                    "defaultVariant": "basic",
+                    "targeting": {
+                        "if": [{"==": [{"var": "user.tier"}, "premium"]}, "premium", "basic"]
                     }
                 }
-            };
+            }
+        }"#;
 
-            // Evaluate using the evaluator instance
-            eval.evaluate_flag(&flag_key, context)
-        })
-    });
+        evaluator.update_state(config).unwrap();
+        assert!(!evaluator.strict_variables());
 
-    result.unwrap_or_else(|panic_err| {
-        let msg = if let Some(s) = panic_err.downcast_ref::<&str>() {
-            format!("Evaluation panic: {}", s)
-        } else if let Some(s) = panic_err.downcast_ref::<String>() {
-            format!("Evaluation panic: {}", s)
-        } else {
-            "Evaluation panic: unknown error".to_string()
-        };
-        EvaluationResult::error(ErrorCode::General, msg)
-    })
+        // Default mode: the missing variable silently resolves to null
+        let result = evaluator.evaluate_flag("tierFlag", json!({"email": "a@b.c"}));
+        assert_eq!(result.value, json!("basic-tier"));
+        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+
+        // Strict mode: the missing variable is an error
+        evaluator.set_strict_variables(true);
+        let result = evaluator.evaluate_flag("tierFlag", json!({"email": "a@b.c"}));
+        assert_eq!(result.reason, ResolutionReason::Error);
+        assert_eq!(result.error_code, Some(ErrorCode::General));
+        assert!(result
+            .error_message
+            .unwrap()
+            .contains("variable 'user.tier' not found"));
+
+        // Strict mode: a present variable (even null) evaluates normally
+        let result = evaluator.evaluate_flag("tierFlag", json!({"user": {"tier": "premium"}}));
+        assert_eq!(result.value, json!("premium-tier"));
+        let result = evaluator.evaluate_flag("tierFlag", json!({"user": {"tier": null}}));
+        assert_eq!(result.value, json!("basic-tier"));
+    }
+
+    #[test]
+    fn test_evaluator_validation_modes() {
+        // Strict mode
+        let mut strict_eval = FlagEvaluator::new(ValidationMode::Strict);
+        assert_eq!(strict_eval.validation_mode(), ValidationMode::Strict);
+
+        // Permissive mode
+        let permissive_eval = FlagEvaluator::new(ValidationMode::Permissive);
+        assert_eq!(
+            permissive_eval.validation_mode(),
+            ValidationMode::Permissive
+        );
+
+        // Change mode
+        strict_eval.set_validation_mode(ValidationMode::Permissive);
+        assert_eq!(strict_eval.validation_mode(), ValidationMode::Permissive);
+    }
+
+    #[test]
+    fn test_evaluator_clear_state() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config = r#"{
+            "flags": {
+                "testFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on"
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config).unwrap();
+        assert!(evaluator.get_state().is_some());
+
+        evaluator.clear_state();
+        assert!(evaluator.get_state().is_none());
+    }
+
+    #[test]
+    fn test_evaluator_changed_flags_detection() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+        let config1 = r#"{
+            "flags": {
+                "flag1": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on"
+                }
+            }
+        }"#;
+
+        let response1 = evaluator.update_state(config1).unwrap();
+        assert!(response1.changed_flags.is_some());
+        assert_eq!(response1.changed_flags.unwrap(), vec!["flag1"]);
+
+        let config2 = r#"{
+            "flags": {
+                "flag1": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on"
+                },
+                "flag2": {
+                    "state": "ENABLED",
+                    "variants": {"off": false, "on": true},
+                    "defaultVariant": "off"
+                }
+            }
+        }"#;
+
+        let response2 = evaluator.update_state(config2).unwrap();
+        assert!(response2.changed_flags.is_some());
+        assert_eq!(response2.changed_flags.unwrap(), vec!["flag2"]);
+    }
 }
 
+// ============================================================================
+// WASM Integration Tests
+// ============================================================================
+
 #[cfg(test)]
-mod tests {
+mod wasm_tests {
     use super::*;
     use serde_json::json;
 
-    // ============================================================================
-    // Library tests using FlagEvaluator directly
-    // ============================================================================
+    /// Helper function to reset the WASM singleton evaluator between tests
+    fn reset_wasm_evaluator() {
+        wasm_evaluator::with_evaluator(|eval| {
+            eval.clear_state();
+            eval.reset_options();
+        });
+    }
 
-    #[test]
-    fn test_evaluator_update_state_and_evaluate() {
-        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+    /// Helper to call update_state WASM export
+    fn update_state_wasm(config: &str) -> String {
+        let config_bytes = config.as_bytes();
+        update_state_internal(config_bytes.as_ptr(), config_bytes.len() as u32)
+    }
 
-        let config = r#"{
-            "flags": {
-                "boolFlag": {
-                    "state": "ENABLED",
-                    "variants": {
-                        "on": true,
-                        "off": false
-                    },
-                    "defaultVariant": "off"
-                }
-            }
-        }"#;
+    /// Helper to call evaluate WASM export
+    fn evaluate_wasm(flag_key: &str, context: &str) -> EvaluationResult {
+        let flag_key_bytes = flag_key.as_bytes();
+        let context_bytes = context.as_bytes();
 
-        let response = evaluator.update_state(config).unwrap();
-        assert!(response.success);
+        let packed = evaluate_internal(
+            flag_key_bytes.as_ptr(),
+            flag_key_bytes.len() as u32,
+            context_bytes.as_ptr(),
+            context_bytes.len() as u32,
+        );
 
-        let result = evaluator.evaluate_bool("boolFlag", json!({}));
-        assert_eq!(result.value, json!(false));
-        assert_eq!(result.variant, Some("off".to_string()));
-        assert_eq!(result.reason, ResolutionReason::Static);
+        // evaluate_internal already returns EvaluationResult directly
+        packed
     }
 
     #[test]
-    fn test_evaluator_int_flag() {
-        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
-
-        let config = r#"{
-            "flags": {
-                "intFlag": {
-                    "state": "ENABLED",
-                    "variants": {
-                        "small": 10,
-                        "large": 100
-                    },
-                    "defaultVariant": "small"
-                }
-            }
-        }"#;
+    fn test_wasm_memory_allocation() {
+        // Test alloc and dealloc
+        let size = 100;
+        let ptr = wasm_alloc(size);
+        assert!(!ptr.is_null());
 
-        evaluator.update_state(config).unwrap();
+        // Write some data
+        unsafe {
+            std::ptr::write_bytes(ptr, 0x42, size as usize);
+        }
 
-        let result = evaluator.evaluate_int("intFlag", json!({}));
-        assert_eq!(result.value, json!(10));
-        assert_eq!(result.variant, Some("small".to_string()));
+        // Free the memory
+        wasm_dealloc(ptr, size);
     }
 
     #[test]
-    fn test_evaluator_float_flag() {
-        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+    fn test_wasm_update_state_export() {
+        reset_wasm_evaluator();
 
         let config = r#"{
             "flags": {
-                "floatFlag": {
+                "testFlag": {
                     "state": "ENABLED",
-                    "variants": {
-                        "pi": 3.14,
-                        "e": 2.71
-                    },
-                    "defaultVariant": "pi"
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on"
                 }
             }
         }"#;
 
-        evaluator.update_state(config).unwrap();
-
-        let result = evaluator.evaluate_float("floatFlag", json!({}));
-        assert_eq!(result.value, json!(3.14));
-        assert_eq!(result.variant, Some("pi".to_string()));
+        let response_json = update_state_wasm(config);
+        let response: Value = serde_json::from_str(&response_json).unwrap();
+        assert_eq!(response["success"], true);
     }
 
     #[test]
-    fn test_evaluator_string_flag() {
-        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+    fn test_wasm_lint_config_export_leaves_state_untouched() {
+        reset_wasm_evaluator();
 
         let config = r#"{
             "flags": {
-                "stringFlag": {
+                "dupFlag": {
                     "state": "ENABLED",
-                    "variants": {
-                        "hello": "Hello, World!",
-                        "goodbye": "Goodbye!"
-                    },
-                    "defaultVariant": "hello"
+                    "variants": {"on": true, "off": true},
+                    "defaultVariant": "on"
                 }
             }
         }"#;
 
-        evaluator.update_state(config).unwrap();
+        let config_bytes = config.as_bytes();
+        let response = lint_config_internal(config_bytes.as_ptr(), config_bytes.len() as u32);
+        let lints: Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(
+            lints,
+            json!([{
+                "severity": "warning",
+                "path": "/flags/dupFlag/variants",
+                "message": "Variants 'off' and 'on' have identical values"
+            }])
+        );
 
-        let result = evaluator.evaluate_string("stringFlag", json!({}));
-        assert_eq!(result.value, json!("Hello, World!"));
-        assert_eq!(result.variant, Some("hello".to_string()));
+        // Linting never stores the configuration
+        let result = evaluate_wasm("dupFlag", "{}");
+        assert_eq!(result.reason, ResolutionReason::Error);
     }
 
     #[test]
-    fn test_evaluator_object_flag() {
-        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+    fn test_wasm_max_config_bytes_rejects_before_parsing() {
+        reset_wasm_evaluator();
+
+        let config = r#"{"flags": {"testFlag": {"state": "ENABLED", "variants": {"on": true}, "defaultVariant": "on"}}}"#;
+        let response: Value = serde_json::from_str(&update_state_wasm(config)).unwrap();
+        assert_eq!(response["success"], true);
+
+        // Keep the limit well above other tests' configs, which share this evaluator
+        set_max_config_bytes_wasm(64 * 1024);
+
+        // Invalid JSON over the limit: the size error proves it was never parsed
+        let oversized = format!("{{not json {}", "x".repeat(64 * 1024));
+        let response: Value = serde_json::from_str(&update_state_wasm(&oversized)).unwrap();
+        assert_eq!(response["success"], false);
+        assert_eq!(
+            response["error"],
+            "Configuration size of 65546 bytes exceeds the maximum of 65536 bytes"
+        );
+
+        // The previous state is untouched
+        let result = evaluate_wasm("testFlag", "{}");
+        assert_eq!(result.value, json!(true));
+
+        // 0 restores the unlimited default
+        set_max_config_bytes_wasm(0);
+        let response: Value = serde_json::from_str(&update_state_wasm(config)).unwrap();
+        assert_eq!(response["success"], true);
+    }
 
+    #[test]
+    fn test_wasm_evaluate_all_export() {
+        reset_wasm_evaluator();
         let config = r#"{
             "flags": {
-                "objectFlag": {
+                "batchA": {
                     "state": "ENABLED",
-                    "variants": {
-                        "config1": {"key": "value1"},
-                        "config2": {"key": "value2"}
-                    },
-                    "defaultVariant": "config1"
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"==": [{"var": "tier"}, "gold"]}, "on", "off"]}
+                },
+                "batchB": {
+                    "state": "ENABLED",
+                    "variants": {"x": 1},
+                    "defaultVariant": "x"
+                },
+                "batchDisabled": {
+                    "state": "DISABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off"
+                },
+                "batchBroken": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"==": [{"var": "tier"}, "gold"]}, "gone", "off"]}
                 }
             }
         }"#;
+        update_state_wasm(config);
 
-        evaluator.update_state(config).unwrap();
+        let context = r#"{"tier": "gold"}"#;
+        let response: Value = serde_json::from_str(&evaluate_all_internal(
+            context.as_ptr(),
+            context.len() as u32,
+        ))
+        .unwrap();
+        assert_eq!(response["batchA"]["value"], true);
+        assert_eq!(response["batchA"]["reason"], "TARGETING_MATCH");
+        assert_eq!(response["batchB"]["value"], 1);
+        // Disabled and failing flags are included with their usual reason and error code
+        assert_eq!(response["batchDisabled"]["reason"], "DISABLED");
+        assert_eq!(response["batchBroken"]["reason"], "ERROR");
+        assert!(response["batchBroken"]["errorCode"].is_string());
+        for key in ["batchA", "batchB", "batchDisabled", "batchBroken"] {
+            assert_eq!(
+                response[key],
+                serde_json::to_value(evaluate_wasm(key, context)).unwrap(),
+                "{}",
+                key
+            );
+        }
 
-        let result = evaluator.evaluate_flag("objectFlag", json!({}));
-        assert_eq!(result.value, json!({"key": "value1"}));
-        assert_eq!(result.variant, Some("config1".to_string()));
+        let bad = "{not json";
+        let response: Value =
+            serde_json::from_str(&evaluate_all_internal(bad.as_ptr(), bad.len() as u32)).unwrap();
+        assert_eq!(response["batchA"]["errorCode"], "PARSE_ERROR");
+        assert_eq!(response["batchB"]["errorCode"], "PARSE_ERROR");
     }
 
     #[test]
-    fn test_evaluator_with_targeting() {
-        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+    fn test_wasm_compile_report_export() {
+        reset_wasm_evaluator();
+        let config = r#"{"flags": {
+            "goodFlag": {"state": "ENABLED", "variants": {"on": true, "off": false}, "defaultVariant": "off", "targeting": {"if": [{"==": [{"var": "tier"}, "gold"]}, "on", "off"]}},
+            "badFlag": {"state": "ENABLED", "variants": {"on": true, "off": false}, "defaultVariant": "off", "targeting": {"if": [{"$ref": "missing"}, "on", "off"]}}
+        }}"#;
+        let response: Value = serde_json::from_str(&compile_report_internal(
+            config.as_ptr(),
+            config.len() as u32,
+        ))
+        .unwrap();
+        assert_eq!(
+            response,
+            json!({"flags": {
+                "badFlag": "Evaluator 'missing' not found in $evaluators",
+                "goodFlag": null
+            }})
+        );
+
+        let bad = "{not json";
+        let response: Value =
+            serde_json::from_str(&compile_report_internal(bad.as_ptr(), bad.len() as u32)).unwrap();
+        assert!(response["error"]
+            .as_str()
+            .unwrap()
+            .starts_with("Failed to parse JSON"));
+    }
+
+    #[test]
+    fn test_wasm_evaluate_changed_since_export() {
+        reset_wasm_evaluator();
+        update_state_wasm(
+            r#"{"flags": {"sinceA": {"state": "ENABLED", "variants": {"on": true, "off": false}, "defaultVariant": "on"}, "sinceB": {"state": "ENABLED", "variants": {"on": true, "off": false}, "defaultVariant": "on"}}}"#,
+        );
+        let health: Value = serde_json::from_str(&health_check_internal()).unwrap();
+        let version = health["stateVersion"].as_u64().unwrap().to_string();
+
+        update_state_wasm(
+            r#"{"flags": {"sinceA": {"state": "ENABLED", "variants": {"on": true, "off": false}, "defaultVariant": "on"}, "sinceB": {"state": "ENABLED", "variants": {"on": true, "off": false}, "defaultVariant": "off"}}}"#,
+        );
+        let evaluate_since = |version: &str, context: &str| -> Value {
+            serde_json::from_str(&evaluate_changed_since_internal(
+                version.as_ptr(),
+                version.len() as u32,
+                context.as_ptr(),
+                context.len() as u32,
+            ))
+            .unwrap()
+        };
+
+        let response = evaluate_since(&version, "{}");
+        assert_eq!(response.as_object().unwrap().len(), 1);
+        assert_eq!(response["sinceB"]["value"], false);
+        assert_eq!(response["sinceB"]["reason"], "STATIC");
+
+        // An unparsable version evaluates every flag
+        let response = evaluate_since("latest", "{}");
+        assert_eq!(response["sinceA"]["value"], true);
+        assert_eq!(response["sinceB"]["value"], false);
+
+        let response = evaluate_since(&version, "{not json");
+        assert_eq!(response.as_object().unwrap().len(), 1);
+        assert_eq!(response["sinceB"]["errorCode"], "PARSE_ERROR");
+    }
 
+    #[test]
+    fn test_wasm_evaluate_all_typed_export() {
+        reset_wasm_evaluator();
         let config = r#"{
             "flags": {
-                "targetedFlag": {
+                "typedBool": {
                     "state": "ENABLED",
-                    "variants": {
-                        "admin": "admin-value",
-                        "user": "user-value"
-                    },
-                    "defaultVariant": "user",
-                    "targeting": {
-                        "if": [
-                            {"==": [{"var": "role"}, "admin"]},
-                            "admin",
-                            "user"
-                        ]
-                    }
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off"
+                },
+                "typedString": {
+                    "state": "ENABLED",
+                    "variants": {"red": "red", "blue": "blue"},
+                    "defaultVariant": "red",
+                    "targeting": {"if": [{"==": [{"var": "tier"}, "gold"]}, "blue", null]}
+                },
+                "typedObject": {
+                    "state": "ENABLED",
+                    "variants": {"dark": {"theme": "dark"}},
+                    "defaultVariant": "dark"
+                },
+                "typedMixed": {
+                    "state": "ENABLED",
+                    "variants": {"flag": true, "text": "yes"},
+                    "defaultVariant": "text"
                 }
             }
         }"#;
+        update_state_wasm(config);
 
-        evaluator.update_state(config).unwrap();
-
-        // Test admin role
-        let result_admin = evaluator.evaluate_flag("targetedFlag", json!({"role": "admin"}));
-        assert_eq!(result_admin.value, json!("admin-value"));
-        assert_eq!(result_admin.variant, Some("admin".to_string()));
-        assert_eq!(result_admin.reason, ResolutionReason::TargetingMatch);
-
-        // Test user role
-        let result_user = evaluator.evaluate_flag("targetedFlag", json!({"role": "user"}));
-        assert_eq!(result_user.value, json!("user-value"));
-        assert_eq!(result_user.variant, Some("user".to_string()));
-        assert_eq!(result_user.reason, ResolutionReason::TargetingMatch);
+        let context = r#"{"tier": "gold"}"#;
+        let response: Value = serde_json::from_str(&evaluate_all_typed_internal(
+            context.as_ptr(),
+            context.len() as u32,
+        ))
+        .unwrap();
+        assert_eq!(
+            response["typedBool"],
+            json!({"value": false, "variant": "off", "reason": "STATIC", "type": "BOOLEAN"})
+        );
+        assert_eq!(response["typedString"]["value"], "blue");
+        assert_eq!(response["typedString"]["reason"], "TARGETING_MATCH");
+        assert_eq!(response["typedString"]["type"], "STRING");
+        assert_eq!(response["typedObject"]["value"], json!({"theme": "dark"}));
+        assert_eq!(response["typedObject"]["type"], "OBJECT");
+        assert_eq!(response["typedMixed"]["value"], "yes");
+        assert_eq!(response["typedMixed"]["type"], "MIXED");
+
+        let bad = "{not json";
+        let response: Value =
+            serde_json::from_str(&evaluate_all_typed_internal(bad.as_ptr(), bad.len() as u32))
+                .unwrap();
+        assert_eq!(response["typedBool"]["errorCode"], "PARSE_ERROR");
+        assert_eq!(response["typedBool"]["type"], "BOOLEAN");
     }
 
     #[test]
-    fn test_evaluator_disabled_flag() {
-        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
-
+    fn test_wasm_evaluate_distribution_export() {
+        reset_wasm_evaluator();
         let config = r#"{
             "flags": {
-                "disabledFlag": {
-                    "state": "DISABLED",
-                    "variants": {"on": true, "off": false},
-                    "defaultVariant": "on"
+                "tiered": {
+                    "state": "ENABLED",
+                    "variants": {"gold": "gold", "basic": "basic"},
+                    "defaultVariant": "basic",
+                    "targeting": {"if": [{"==": [{"var": "tier"}, "gold"]}, "gold", null]}
                 }
             }
         }"#;
+        update_state_wasm(config);
 
-        evaluator.update_state(config).unwrap();
-
-        let result = evaluator.evaluate_bool("disabledFlag", json!({}));
-        assert_eq!(result.value, Value::Null);
-        assert_eq!(result.reason, ResolutionReason::Disabled);
-        assert_eq!(result.error_code, Some(ErrorCode::FlagNotFound));
+        let key = "tiered";
+        let contexts = r#"[{"tier": "gold"}, {"tier": "silver"}, {}]"#;
+        let response: Value = serde_json::from_str(&evaluate_distribution_internal(
+            key.as_ptr(),
+            key.len() as u32,
+            contexts.as_ptr(),
+            contexts.len() as u32,
+        ))
+        .unwrap();
+        assert_eq!(response, json!({"counts": {"gold": 1, "basic": 2}}));
+
+        let missing = "missing";
+        let response: Value = serde_json::from_str(&evaluate_distribution_internal(
+            missing.as_ptr(),
+            missing.len() as u32,
+            contexts.as_ptr(),
+            contexts.len() as u32,
+        ))
+        .unwrap();
+        assert_eq!(response["counts"]["FLAG_NOT_FOUND"], 3);
+
+        let bad = r#"{"tier": "gold"}"#;
+        let response: Value = serde_json::from_str(&evaluate_distribution_internal(
+            key.as_ptr(),
+            key.len() as u32,
+            bad.as_ptr(),
+            bad.len() as u32,
+        ))
+        .unwrap();
+        assert!(response["error"].as_str().unwrap().contains("array"));
     }
 
     #[test]
-    fn test_evaluator_flag_not_found() {
-        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
-
+    fn test_wasm_evaluate_rule_for_flag_export() {
+        reset_wasm_evaluator();
         let config = r#"{
             "flags": {
-                "existingFlag": {
+                "ruleFlag": {
                     "state": "ENABLED",
                     "variants": {"on": true, "off": false},
-                    "defaultVariant": "on"
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"==": [{"var": "email"}, "a@example.com"]}, "on", "off"]}
                 }
             }
         }"#;
+        update_state_wasm(config);
+
+        let key = "ruleFlag";
+        let rule = r#"{"if": [{"ends_with": [{"var": "email"}, "@example.com"]}, "on", "off"]}"#;
+        let context = r#"{"email": "b@example.com"}"#;
+        let result = evaluate_rule_for_flag_internal(
+            key.as_ptr(),
+            key.len() as u32,
+            rule.as_ptr(),
+            rule.len() as u32,
+            context.as_ptr(),
+            context.len() as u32,
+        );
+        assert_eq!(result.value, json!(true));
+        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+
+        // The configured rule is unchanged
+        let result = evaluate_wasm("ruleFlag", context);
+        assert_eq!(result.value, json!(false));
+
+        let bad_rule = "{not json";
+        let result = evaluate_rule_for_flag_internal(
+            key.as_ptr(),
+            key.len() as u32,
+            bad_rule.as_ptr(),
+            bad_rule.len() as u32,
+            std::ptr::null(),
+            0,
+        );
+        assert_eq!(result.error_code, Some(ErrorCode::ParseError));
+    }
+
+    #[test]
+    fn test_wasm_update_state_with_events_reflects_diff() {
+        reset_wasm_evaluator();
+        let config = |flags: &[&str]| {
+            let flags: Vec<String> = flags
+                .iter()
+                .map(|key| {
+                    format!(
+                        r#""{}": {{"state": "ENABLED", "variants": {{"on": true}}, "defaultVariant": "on"}}"#,
+                        key
+                    )
+                })
+                .collect();
+            format!(r#"{{"flags": {{{}}}}}"#, flags.join(","))
+        };
+        let update = |config: &str| -> Value {
+            serde_json::from_str(&update_state_with_events_internal(
+                config.as_ptr(),
+                config.len() as u32,
+            ))
+            .unwrap()
+        };
 
-        evaluator.update_state(config).unwrap();
+        let response = update(&config(&["eventsA", "eventsB"]));
+        assert_eq!(response["success"], true);
+        assert!(response["flagIndices"].is_object());
+        let events = response["events"].as_array().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["type"], "PROVIDER_CONFIGURATION_CHANGED");
+        assert_eq!(events[0]["flags"], response["changedFlags"]);
+
+        let response = update(&config(&["eventsB", "eventsC"]));
+        assert_eq!(response["changedFlags"], json!(["eventsA", "eventsC"]));
+        assert_eq!(
+            response["events"][0]["flags"],
+            json!(["eventsA", "eventsC"])
+        );
 
-        let result = evaluator.evaluate_flag("nonexistentFlag", json!({}));
-        assert_eq!(result.reason, ResolutionReason::FlagNotFound);
-        assert_eq!(result.error_code, Some(ErrorCode::FlagNotFound));
+        // No diff, no event
+        let response = update(&config(&["eventsB", "eventsC"]));
+        assert_eq!(response["changedFlags"], json!([]));
+        assert_eq!(response["events"], json!([]));
+
+        // Failed updates emit no events
+        let response = update("{not json");
+        assert_eq!(response["success"], false);
+        assert_eq!(response["errorCode"], "INVALID_JSON");
+        assert_eq!(response["events"], json!([]));
     }
 
     #[test]
-    fn test_evaluator_invalid_json_config() {
-        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+    fn test_wasm_error_codes() {
+        reset_wasm_evaluator();
 
-        let invalid_config = r#"{"flags": invalid}"#;
-        let result = evaluator.update_state(invalid_config);
-        assert!(result.is_ok()); // Returns Ok with error in response
-        let response = result.unwrap();
-        assert!(!response.success);
-        assert!(response.error.is_some());
+        let response: Value = serde_json::from_str(&update_state_wasm("{not json")).unwrap();
+        assert_eq!(response["success"], false);
+        assert_eq!(response["errorCode"], "INVALID_JSON");
+
+        let response: Value = serde_json::from_str(&update_state_wasm(
+            r#"{"flags": {"f": {"state": "BOGUS"}}}"#,
+        ))
+        .unwrap();
+        assert_eq!(response["errorCode"], "SCHEMA_INVALID");
+
+        // Invalid UTF-8 cannot be read
+        let bytes = [0xff, 0xfe, 0xfd];
+        let response: Value =
+            serde_json::from_str(&update_state_internal(bytes.as_ptr(), bytes.len() as u32))
+                .unwrap();
+        assert_eq!(response["errorCode"], "INVALID_INPUT");
+
+        let response: Value = serde_json::from_str(&set_validation_mode_internal(7)).unwrap();
+        assert_eq!(response["success"], false);
+        assert_eq!(response["errorCode"], "INVALID_ARGUMENT");
     }
 
     #[test]
-    fn test_evaluator_fractional_targeting() {
-        let mut evaluator = FlagEvaluator::new(ValidationMode::Permissive);
+    fn test_wasm_evaluate_export() {
+        reset_wasm_evaluator();
 
         let config = r#"{
             "flags": {
-                "abTestFlag": {
+                "boolFlag": {
                     "state": "ENABLED",
-                    "variants": {
-                        "control": "control-experience",
-                        "treatment": "treatment-experience"
-                    },
-                    "defaultVariant": "control",
-                    "targeting": {
-                        "fractional": [
-                            ["control", 50],
-                            ["treatment", 50]
-                        ]
-                    }
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on"
                 }
             }
         }"#;
 
-        evaluator.update_state(config).unwrap();
+        update_state_wasm(config);
 
-        let result = evaluator.evaluate_flag("abTestFlag", json!({"targetingKey": "user-123"}));
-        assert!(
-            result.value == json!("control-experience")
-                || result.value == json!("treatment-experience")
-        );
-        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+        let result = evaluate_wasm("boolFlag", "{}");
+        assert_eq!(result.value, json!(true));
+        assert_eq!(result.reason, ResolutionReason::Static);
     }
 
     #[test]
-    fn test_evaluator_missing_targeting_key() {
-        let mut evaluator = FlagEvaluator::new(ValidationMode::Permissive);
-
-        let config = r#"{
-            "flags": {
-                "testFlag": {
-                    "state": "ENABLED",
-                    "variants": {
-                        "a": "variant-a",
-                        "b": "variant-b"
-                    },
-                    "defaultVariant": "a",
-                    "targeting": {
-                        "fractional": [
-                            ["a", 50],
-                            ["b", 50]
-                        ]
-                    }
-                }
-            }
-        }"#;
+    fn test_wasm_packed_pointer_format() {
+        // Test pack and unpack utilities
+        let ptr = 0x12345678 as *mut u8;
+        let len = 42;
 
-        evaluator.update_state(config).unwrap();
+        let packed = pack_ptr_len(ptr, len);
+        let (unpacked_ptr, unpacked_len) = unpack_ptr_len(packed);
 
-        // Missing targetingKey - should use empty string
-        let result = evaluator.evaluate_flag("testFlag", json!({}));
-        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
-        assert!(result.value == json!("variant-a") || result.value == json!("variant-b"));
+        assert_eq!(unpacked_ptr, ptr);
+        assert_eq!(unpacked_len, len);
     }
 
     #[test]
-    fn test_evaluator_unknown_variant_from_targeting() {
-        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+    fn test_wasm_utf8_handling() {
+        reset_wasm_evaluator();
 
         let config = r#"{
             "flags": {
-                "brokenFlag": {
+                "unicodeFlag": {
                     "state": "ENABLED",
-                    "variants": {
-                        "valid": "valid-value"
-                    },
-                    "defaultVariant": "valid",
-                    "targeting": {
-                        "if": [
-                            true,
-                            "unknown-variant",
-                            "valid"
-                        ]
-                    }
+                    "variants": {"emoji": "Hello 👋 World 🌍"},
+                    "defaultVariant": "emoji"
                 }
             }
         }"#;
 
-        evaluator.update_state(config).unwrap();
+        update_state_wasm(config);
 
-        let result = evaluator.evaluate_flag("brokenFlag", json!({}));
-        assert_eq!(result.reason, ResolutionReason::Error);
-        assert_eq!(result.error_code, Some(ErrorCode::General));
+        let result = evaluate_wasm("unicodeFlag", "{}");
+        assert_eq!(result.value, json!("Hello 👋 World 🌍"));
     }
 
     #[test]
-    fn test_evaluator_complex_targeting() {
-        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+    fn test_wasm_evaluate_by_index() {
+        reset_wasm_evaluator();
 
         let config = r#"{
             "flags": {
-                "complexFlag": {
+                "boolFlag": {
                     "state": "ENABLED",
-                    "variants": {
-                        "premium": "premium-tier",
-                        "standard": "standard-tier",
-                        "basic": "basic-tier"
-                    },
-                    "defaultVariant": "basic",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
                     "targeting": {
                         "if": [
-                            {"starts_with": [{"var": "email"}, "admin@"]},
-                            "premium",
-                            {
-                                "if": [
-                                    {"sem_ver": [{"var": "appVersion"}, ">=", "2.0.0"]},
-                                    "standard",
-                                    "basic"
-                                ]
-                            }
+                            {"==": [{"var": "role"}, "admin"]},
+                            "on",
+                            "off"
                         ]
                     }
                 }
             }
         }"#;
 
-        evaluator.update_state(config).unwrap();
+        let response_json = update_state_wasm(config);
+        let response: Value = serde_json::from_str(&response_json).unwrap();
+        assert!(response["success"].as_bool().unwrap());
 
-        // Admin email
-        let result_admin = evaluator.evaluate_flag(
-            "complexFlag",
-            json!({"email": "admin@example.com", "appVersion": "1.0.0"}),
-        );
-        assert_eq!(result_admin.value, json!("premium-tier"));
+        // Get the flag index from the response
+        let flag_indices = response["flagIndices"].as_object().unwrap();
+        let bool_flag_index = flag_indices["boolFlag"].as_u64().unwrap() as u32;
 
-        // Non-admin with new version
-        let result_standard = evaluator.evaluate_flag(
-            "complexFlag",
-            json!({"email": "user@example.com", "appVersion": "2.1.0"}),
+        // Test with pre-enriched context (matching)
+        let context = json!({
+            "role": "admin",
+            "targetingKey": "user-1",
+            "$flagd": {
+                "flagKey": "boolFlag",
+                "timestamp": 1234567890
+            }
+        });
+        let context_str = context.to_string();
+        let context_bytes = context_str.as_bytes();
+
+        let result = evaluate_by_index_internal(
+            bool_flag_index,
+            context_bytes.as_ptr(),
+            context_bytes.len() as u32,
         );
-        assert_eq!(result_standard.value, json!("standard-tier"));
+        assert_eq!(result.value, json!(true));
+        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
 
-        // Non-admin with old version
-        let result_basic = evaluator.evaluate_flag(
-            "complexFlag",
-            json!({"email": "user@example.com", "appVersion": "1.5.0"}),
+        // Test with non-matching context
+        let context2 = json!({
+            "role": "user",
+            "targetingKey": "user-2",
+            "$flagd": {
+                "flagKey": "boolFlag",
+                "timestamp": 1234567890
+            }
+        });
+        let context2_str = context2.to_string();
+        let context2_bytes = context2_str.as_bytes();
+
+        let result2 = evaluate_by_index_internal(
+            bool_flag_index,
+            context2_bytes.as_ptr(),
+            context2_bytes.len() as u32,
         );
-        assert_eq!(result_basic.value, json!("basic-tier"));
+        assert_eq!(result2.value, json!(false));
     }
 
     #[test]
-    fn test_evaluator_flagd_properties() {
-        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+    fn test_wasm_evaluate_by_index_invalid_index() {
+        reset_wasm_evaluator();
 
         let config = r#"{
             "flags": {
-                "enrichedFlag": {
+                "flag1": {
                     "state": "ENABLED",
-                    "variants": {
-                        "verified": "properties-present",
-                        "failed": "properties-missing"
-                    },
-                    "defaultVariant": "failed",
-                    "targeting": {
-                        "if": [
-                            {
-                                "and": [
-                                    {"==": [{"var": "$flagd.flagKey"}, "enrichedFlag"]},
-                                    {">": [{"var": "$flagd.timestamp"}, 0]}
-                                ]
-                            },
-                            "verified",
-                            "failed"
-                        ]
-                    }
+                    "variants": {"on": true},
+                    "defaultVariant": "on"
                 }
             }
         }"#;
 
-        evaluator.update_state(config).unwrap();
+        update_state_wasm(config);
 
-        let result = evaluator.evaluate_flag("enrichedFlag", json!({}));
-        assert_eq!(result.value, json!("properties-present"));
-        assert_eq!(result.variant, Some("verified".to_string()));
+        let result = evaluate_by_index_internal(999, std::ptr::null(), 0);
+        assert_eq!(result.reason, ResolutionReason::Error);
+        assert_eq!(result.error_code, Some(ErrorCode::FlagNotFound));
+    }
+
+    struct PanickingOperator;
+
+    impl datalogic_rs::Operator for PanickingOperator {
+        fn evaluate(
+            &self,
+            _args: &[Value],
+            _context: &mut datalogic_rs::ContextStack,
+            _evaluator: &dyn datalogic_rs::Evaluator,
+        ) -> Result<Value, datalogic_rs::Error> {
+            panic!("operator exploded");
+        }
     }
 
     #[test]
-    fn test_evaluator_type_checking_bool() {
-        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+    fn test_panic_location_in_error() {
+        init_panic_hook();
 
-        let config = r#"{
-            "flags": {
-                "stringFlag": {
-                    "state": "ENABLED",
-                    "variants": {"val": "string-value", "alt": "alternative-value"},
-                    "defaultVariant": "val"
-                }
-            }
-        }"#;
+        let result = std::panic::catch_unwind(|| {
+            let mut logic = operators::create_evaluator();
+            logic.add_operator("explode".to_string(), Box::new(PanickingOperator));
+            logic.evaluate_json(r#"{"explode": []}"#, "{}")
+        });
 
-        evaluator.update_state(config).unwrap();
+        let message = evaluation_panic_message(&*result.unwrap_err());
+        assert!(message.starts_with("Evaluation panic: operator exploded at "));
+        assert!(message.contains(&format!("{}:", file!())));
 
-        let result = evaluator.evaluate_bool("stringFlag", json!({}));
-        assert_eq!(result.reason, ResolutionReason::Error);
-        assert_eq!(result.error_code, Some(ErrorCode::TypeMismatch));
+        // The location is consumed by the handler that reports it
+        assert!(LAST_PANIC_LOCATION.with(|last| last.borrow().is_none()));
     }
 
     #[test]
-    fn test_evaluator_type_checking_string() {
-        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+    fn test_wasm_evaluate_preview() {
+        reset_wasm_evaluator();
+        update_state_wasm(
+            r#"{
+                "flags": {
+                    "previewWasmFlag": {
+                        "state": "DISABLED",
+                        "variants": {"on": true, "off": false},
+                        "defaultVariant": "off",
+                        "targeting": {"if": [{"==": [{"var": "tier"}, "gold"]}, "on"]}
+                    }
+                }
+            }"#,
+        );
+
+        let key = "previewWasmFlag";
+        let context = r#"{"tier": "gold"}"#;
+        let result = evaluate_preview_internal(
+            key.as_ptr(),
+            key.len() as u32,
+            context.as_ptr(),
+            context.len() as u32,
+        );
+        assert_eq!(result.value, json!(true));
+        assert_eq!(result.reason, ResolutionReason::Preview);
+
+        let result = evaluate_wasm(key, context);
+        assert_eq!(result.reason, ResolutionReason::Disabled);
+    }
 
-        let config = r#"{
-            "flags": {
-                "boolFlag": {
-                    "state": "ENABLED",
-                    "variants": {"val": true, "alt": false},
-                    "defaultVariant": "val"
+    #[test]
+    fn test_wasm_resolve_default() {
+        reset_wasm_evaluator();
+        update_state_wasm(
+            r#"{
+                "flags": {
+                    "resolveDefaultFlag": {
+                        "state": "ENABLED",
+                        "variants": {"on": true, "off": false},
+                        "defaultVariant": "off",
+                        "targeting": {"if": [true, "on", "off"]}
+                    }
                 }
-            }
-        }"#;
+            }"#,
+        );
 
-        evaluator.update_state(config).unwrap();
+        let key = "resolveDefaultFlag";
+        let result = resolve_default_internal(key.as_ptr(), key.len() as u32);
+        assert_eq!(result.value, json!(false));
+        assert_eq!(result.variant, Some("off".to_string()));
+        assert_eq!(result.reason, ResolutionReason::Default);
 
-        let result = evaluator.evaluate_string("stringFlag", json!({}));
+        let missing = "resolveDefaultMissing";
+        let result = resolve_default_internal(missing.as_ptr(), missing.len() as u32);
         assert_eq!(result.reason, ResolutionReason::FlagNotFound);
     }
 
     #[test]
-    fn test_evaluator_type_checking_int() {
-        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
-
-        let config = r#"{
-            "flags": {
-                "floatFlag": {
-                    "state": "ENABLED",
-                    "variants": {"val": 3.14, "alt": 2.71},
-                    "defaultVariant": "val"
+    fn test_wasm_variant_code_exports() {
+        reset_wasm_evaluator();
+        update_state_wasm(
+            r#"{
+                "flags": {
+                    "variantCodeFlag": {
+                        "state": "ENABLED",
+                        "variants": {"red": "r", "blue": "b", "green": "g"},
+                        "defaultVariant": "red",
+                        "targeting": {"if": [{"==": [{"var": "tier"}, "gold"]}, "green"]}
+                    }
                 }
-            }
-        }"#;
+            }"#,
+        );
 
-        evaluator.update_state(config).unwrap();
+        let key = "variantCodeFlag";
+        let names: Vec<String> =
+            serde_json::from_str(&get_variant_names_internal(key.as_ptr(), key.len() as u32))
+                .unwrap();
+        assert_eq!(names, vec!["blue", "green", "red"]);
+
+        let decode = |context: &str| {
+            let code = evaluate_variant_code_internal(
+                key.as_ptr(),
+                key.len() as u32,
+                context.as_ptr(),
+                context.len() as u32,
+            )
+            .pack();
+            let code = VariantCode::unpack(code).unwrap();
+            (
+                code.variant_index.map(|i| names[i as usize].as_str()),
+                code.reason,
+            )
+        };
+        assert_eq!(
+            decode(r#"{"tier": "gold"}"#),
+            (Some("green"), ResolutionReason::TargetingMatch)
+        );
+        assert_eq!(
+            decode(r#"{"tier": "silver"}"#),
+            (Some("red"), ResolutionReason::Default)
+        );
 
-        // Integer evaluation should accept floats via coercion
-        let result = evaluator.evaluate_int("floatFlag", json!({}));
-        assert_eq!(result.value, json!(3));
+        let missing = "variantCodeMissing";
+        assert_eq!(
+            get_variant_names_internal(missing.as_ptr(), missing.len() as u32),
+            "null"
+        );
+        let code = VariantCode::unpack(
+            evaluate_variant_code_internal(
+                missing.as_ptr(),
+                missing.len() as u32,
+                std::ptr::null(),
+                0,
+            )
+            .pack(),
+        )
+        .unwrap();
+        assert_eq!(code.variant_index, None);
+        assert_eq!(code.reason, ResolutionReason::FlagNotFound);
+        assert_eq!(code.error_code, Some(ErrorCode::FlagNotFound));
     }
 
     #[test]
-    fn test_evaluator_type_checking_float() {
-        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+    fn test_wasm_set_targeting_key_field_export() {
+        reset_wasm_evaluator();
+
+        let response: Value =
+            serde_json::from_str(&set_targeting_key_field_internal("".as_ptr(), 0)).unwrap();
+        assert_eq!(response["success"], json!(false));
+        assert_eq!(response["errorCode"], json!("INVALID_ARGUMENT"));
 
         let config = r#"{
             "flags": {
-                "intFlag": {
+                "targetingKeyFieldFlag": {
                     "state": "ENABLED",
-                    "variants": {"val": 42, "alt": 100},
-                    "defaultVariant": "val"
+                    "variants": {"a": "a", "b": "b", "c": "c", "d": "d"},
+                    "defaultVariant": "a",
+                    "targeting": {"fractional": [["a", 25], ["b", 25], ["c", 25], ["d", 25]]}
                 }
             }
         }"#;
+        let mut expected = FlagEvaluator::new(ValidationMode::Strict);
+        expected.update_state(config).unwrap();
+
+        let field = "userId";
+        let response: Value = serde_json::from_str(&set_targeting_key_field_internal(
+            field.as_ptr(),
+            field.len() as u32,
+        ))
+        .unwrap();
+        assert_eq!(response["success"], json!(true));
+        update_state_wasm(config);
 
-        evaluator.update_state(config).unwrap();
+        for i in 0..20 {
+            let user = format!("user-{}", i);
+            let result = evaluate_wasm(
+                "targetingKeyFieldFlag",
+                &json!({"userId": user, "targetingKey": "ignored"}).to_string(),
+            );
+            let reference =
+                expected.evaluate_flag("targetingKeyFieldFlag", json!({"targetingKey": user}));
+            assert_eq!(result.variant, reference.variant);
+        }
 
-        // Float evaluation should accept integers via coercion
-        let result = evaluator.evaluate_float("intFlag", json!({}));
-        assert_eq!(result.value, json!(42.0));
+        wasm_evaluator::with_evaluator(|eval| eval.set_targeting_key_field(None));
     }
 
     #[test]
-    fn test_evaluator_type_checking_object() {
-        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
-
-        let config = r#"{
-            "flags": {
-                "stringFlag": {
-                    "state": "ENABLED",
-                    "variants": {"val": "string-value", "alt": "alternative-value"},
-                    "defaultVariant": "val"
-                }
-            }
-        }"#;
-
-        evaluator.update_state(config).unwrap();
+    fn test_wasm_get_change_history_export() {
+        reset_wasm_evaluator();
+        update_state_wasm(
+            r#"{"flags": {"historyFlag": {"state": "ENABLED", "variants": {"on": true}, "defaultVariant": "on"}}}"#,
+        );
 
-        let result = evaluator.evaluate_object("stringFlag", json!({}));
-        assert_eq!(result.reason, ResolutionReason::Error);
-        assert_eq!(result.error_code, Some(ErrorCode::TypeMismatch));
+        // Other tests share the singleton, so only the shape of the entries is checked
+        let history: Value = serde_json::from_str(&get_change_history_internal(2)).unwrap();
+        let entries = history.as_array().unwrap();
+        assert!(!entries.is_empty() && entries.len() <= 2);
+        for entry in entries {
+            assert!(entry["version"].is_u64());
+            assert!(entry["added"].is_array());
+            assert!(entry["removed"].is_array());
+            assert!(entry["mutated"].is_array());
+        }
     }
 
     #[test]
-    fn test_evaluator_all_types_flag_not_found() {
-        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+    fn test_wasm_health_check_export() {
+        reset_wasm_evaluator();
 
-        let config = r#"{
-            "flags": {
-                "existingFlag": {
-                    "state": "ENABLED",
-                    "variants": {"on": true, "off": false},
-                    "defaultVariant": "on"
-                }
-            }
-        }"#;
+        let health: Value = serde_json::from_str(&health_check_internal()).unwrap();
+        assert!(health["stateVersion"].is_u64());
+        assert_eq!(health["timeAvailable"], json!(true));
+        assert_eq!(health["warnings"], json!([]));
+    }
 
-        evaluator.update_state(config).unwrap();
+    #[test]
+    fn test_wasm_inventory_export() {
+        reset_wasm_evaluator();
+        assert_eq!(inventory_internal(), "[]");
 
-        let bool_result = evaluator.evaluate_bool("missingFlag", json!({}));
-        assert_eq!(bool_result.reason, ResolutionReason::FlagNotFound);
+        update_state_wasm(
+            r#"{"flags": {"inventoryFlag": {"state": "ENABLED", "variants": {"a": 1, "b": 2.5}, "defaultVariant": "a"}}}"#,
+        );
+        let response: Value = serde_json::from_str(&inventory_internal()).unwrap();
+        assert_eq!(
+            response,
+            json!([{
+                "key": "inventoryFlag",
+                "state": "ENABLED",
+                "type": "FLOAT",
+                "variants": ["a", "b"],
+                "hasTargeting": false,
+                "defaultVariant": "a"
+            }])
+        );
+    }
 
-        let string_result = evaluator.evaluate_string("missingFlag", json!({}));
-        assert_eq!(string_result.reason, ResolutionReason::FlagNotFound);
+    #[test]
+    fn test_wasm_list_flags_export() {
+        reset_wasm_evaluator();
+        assert_eq!(list_flags_internal(), "[]");
+
+        update_state_wasm(
+            r#"{"flags": {
+                "boolFlag": {"state": "ENABLED", "variants": {"on": true, "off": false}, "defaultVariant": "on"},
+                "intFlag": {"state": "DISABLED", "variants": {"a": 1, "b": 2.5}, "defaultVariant": "a"},
+                "objectFlag": {"state": "ENABLED", "variants": {"x": {"color": "red"}}, "defaultVariant": "x"},
+                "noDefaultFlag": {"state": "ENABLED", "variants": {"s": "text"}, "defaultVariant": null}
+            }}"#,
+        );
+        let response: Value = serde_json::from_str(&list_flags_internal()).unwrap();
+        assert_eq!(
+            response,
+            json!([
+                {"key": "boolFlag", "state": "ENABLED", "defaultVariant": "on", "type": "boolean"},
+                {"key": "intFlag", "state": "DISABLED", "defaultVariant": "a", "type": "integer"},
+                {"key": "noDefaultFlag", "state": "ENABLED"},
+                {"key": "objectFlag", "state": "ENABLED", "defaultVariant": "x", "type": "object"}
+            ])
+        );
+    }
 
-        let int_result = evaluator.evaluate_int("missingFlag", json!({}));
-        assert_eq!(int_result.reason, ResolutionReason::FlagNotFound);
+    #[test]
+    fn test_wasm_effective_config_export() {
+        reset_wasm_evaluator();
+        assert_eq!(effective_config_internal(), "null");
 
-        let float_result = evaluator.evaluate_float("missingFlag", json!({}));
-        assert_eq!(float_result.reason, ResolutionReason::FlagNotFound);
+        update_state_wasm(
+            r#"{"$evaluators": {"beta": {"var": ["beta"]}}, "flags": {"effectiveFlag": {"state": "ENABLED", "variants": {"on": true, "off": false}, "defaultVariant": "off", "targeting": {"if": [{"$ref": "beta"}, "on"]}}}}"#,
+        );
+        let response: Value = serde_json::from_str(&effective_config_internal()).unwrap();
+        assert_eq!(
+            response["flags"]["effectiveFlag"]["targeting"],
+            json!({"if": [{"var": "beta"}, "on"]})
+        );
+    }
 
-        let object_result = evaluator.evaluate_object("missingFlag", json!({}));
-        assert_eq!(object_result.reason, ResolutionReason::FlagNotFound);
+    /// Helper to call context_set WASM export
+    fn context_set_wasm(key: &str, value: &str) -> Value {
+        let response = context_set_internal(
+            key.as_ptr(),
+            key.len() as u32,
+            value.as_ptr(),
+            value.len() as u32,
+        );
+        serde_json::from_str(&response).unwrap()
     }
 
     #[test]
-    fn test_evaluator_disabled_flags_all_types() {
-        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
-
-        let config = r#"{
+    fn test_wasm_context_builder_matches_json_context() {
+        reset_wasm_evaluator();
+        update_state_wasm(
+            r#"{
             "flags": {
-                "disabledBool": {
-                    "state": "DISABLED",
-                    "variants": {"on": true, "off": false},
-                    "defaultVariant": "on"
+                "checkout": {
+                    "state": "ENABLED",
+                    "variants": {"new": "new", "old": "old"},
+                    "defaultVariant": "old",
+                    "targeting": {
+                        "if": [
+                            {"and": [
+                                {"==": [{"var": "tier"}, "gold"]},
+                                {">=": [{"var": "age"}, 21]},
+                                {"<": [{"var": "score"}, 0.5]},
+                                {"var": "beta"}
+                            ]},
+                            "new"
+                        ]
+                    }
                 },
-                "disabledString": {
-                    "state": "DISABLED",
-                    "variants": {"val": "text", "alt": "alternative"},
-                    "defaultVariant": "val"
+                "rollout": {
+                    "state": "ENABLED",
+                    "variants": {"a": "a", "b": "b"},
+                    "defaultVariant": "a",
+                    "targeting": {"fractional": [["a", 50], ["b", 50]]}
                 }
             }
-        }"#;
-
-        evaluator.update_state(config).unwrap();
+        }"#,
+        );
 
-        let bool_result = evaluator.evaluate_bool("disabledBool", json!({}));
-        assert_eq!(bool_result.value, Value::Null);
-        assert_eq!(bool_result.reason, ResolutionReason::Disabled);
+        let response: Value = serde_json::from_str(&context_begin_internal()).unwrap();
+        assert_eq!(response["success"], true);
+        for (key, value) in [
+            ("targetingKey", "suser-42"),
+            ("tier", "sgold"),
+            ("age", "n30"),
+            ("score", "n0.25"),
+            ("beta", "btrue"),
+        ] {
+            assert_eq!(context_set_wasm(key, value)["success"], true);
+        }
 
-        let string_result = evaluator.evaluate_string("disabledString", json!({}));
-        assert_eq!(string_result.value, Value::Null);
-        assert_eq!(string_result.reason, ResolutionReason::Disabled);
+        let json_context = r#"{"targetingKey": "user-42", "tier": "gold", "age": 30, "score": 0.25, "beta": true}"#;
+        for flag_key in ["checkout", "rollout"] {
+            let built = evaluate_with_builder_internal(flag_key.as_ptr(), flag_key.len() as u32);
+            let parsed = evaluate_wasm(flag_key, json_context);
+            assert_eq!(built.value, parsed.value, "{}", flag_key);
+            assert_eq!(built.variant, parsed.variant, "{}", flag_key);
+            assert_eq!(built.reason, parsed.reason, "{}", flag_key);
+        }
+        let checkout = "checkout";
+        let result = evaluate_with_builder_internal(checkout.as_ptr(), checkout.len() as u32);
+        assert_eq!(result.value, json!("new"));
+
+        // Setting an attribute again replaces it
+        assert_eq!(context_set_wasm("beta", "bfalse")["success"], true);
+        let result = evaluate_with_builder_internal(checkout.as_ptr(), checkout.len() as u32);
+        assert_eq!(result.value, json!("old"));
+
+        // A new context starts empty
+        context_begin_internal();
+        let result = evaluate_with_builder_internal(checkout.as_ptr(), checkout.len() as u32);
+        assert_eq!(result.value, json!("old"));
+        assert_eq!(result.reason, ResolutionReason::Default);
     }
 
     #[test]
-    fn test_evaluator_validation_modes() {
-        // Strict mode
-        let mut strict_eval = FlagEvaluator::new(ValidationMode::Strict);
-        assert_eq!(strict_eval.validation_mode(), ValidationMode::Strict);
+    fn test_wasm_context_set_rejects_bad_values() {
+        context_begin_internal();
 
-        // Permissive mode
-        let permissive_eval = FlagEvaluator::new(ValidationMode::Permissive);
+        for value in ["", "x1", "nabc", "byes"] {
+            let response = context_set_wasm("attr", value);
+            assert_eq!(response["success"], false, "{:?}", value);
+            assert_eq!(response["errorCode"], "INVALID_ARGUMENT");
+        }
+        assert_eq!(context_set_wasm("name", "s")["success"], true);
         assert_eq!(
-            permissive_eval.validation_mode(),
-            ValidationMode::Permissive
+            CONTEXT_BUILDER.lock().unwrap().as_ref().unwrap(),
+            &json!({"name": ""}).as_object().cloned().unwrap()
         );
+    }
 
-        // Change mode
-        strict_eval.set_validation_mode(ValidationMode::Permissive);
-        assert_eq!(strict_eval.validation_mode(), ValidationMode::Permissive);
+    /// Helper to call validate_and_evaluate WASM export
+    fn validate_and_evaluate_wasm(config: &str, flag_key: &str, context: &str) -> Value {
+        let response = validate_and_evaluate_internal(
+            config.as_ptr(),
+            config.len() as u32,
+            flag_key.as_ptr(),
+            flag_key.len() as u32,
+            context.as_ptr(),
+            context.len() as u32,
+        );
+        serde_json::from_str(&response).unwrap()
     }
 
     #[test]
-    fn test_evaluator_clear_state() {
-        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+    fn test_wasm_validate_and_evaluate_export() {
+        reset_wasm_evaluator();
 
         let config = r#"{
             "flags": {
-                "testFlag": {
+                "checkout": {
                     "state": "ENABLED",
-                    "variants": {"on": true, "off": false},
-                    "defaultVariant": "on"
+                    "variants": {"new": "new", "old": "old"},
+                    "defaultVariant": "old",
+                    "targeting": {"if": [{"==": [{"var": "tier"}, "gold"]}, "new"]}
                 }
             }
         }"#;
+        let response = validate_and_evaluate_wasm(config, "checkout", r#"{"tier": "gold"}"#);
+        assert_eq!(response["validation"]["valid"], true);
+        assert_eq!(response["result"]["value"], "new");
+        assert_eq!(response["result"]["reason"], "TARGETING_MATCH");
 
-        evaluator.update_state(config).unwrap();
-        assert!(evaluator.get_state().is_some());
+        let response = validate_and_evaluate_wasm(config, "missing", "{}");
+        assert_eq!(response["validation"]["valid"], true);
+        assert_eq!(response["result"]["errorCode"], "FLAG_NOT_FOUND");
 
-        evaluator.clear_state();
-        assert!(evaluator.get_state().is_none());
+        // The configuration is not stored
+        wasm_evaluator::with_evaluator(|eval| assert!(eval.get_state().is_none()));
     }
 
     #[test]
-    fn test_evaluator_changed_flags_detection() {
-        let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
-
-        let config1 = r#"{
-            "flags": {
-                "flag1": {
-                    "state": "ENABLED",
-                    "variants": {"on": true, "off": false},
-                    "defaultVariant": "on"
-                }
-            }
-        }"#;
-
-        let response1 = evaluator.update_state(config1).unwrap();
-        assert!(response1.changed_flags.is_some());
-        assert_eq!(response1.changed_flags.unwrap(), vec!["flag1"]);
+    fn test_wasm_validate_and_evaluate_skips_invalid_config() {
+        reset_wasm_evaluator();
 
-        let config2 = r#"{
+        let config = r#"{
             "flags": {
-                "flag1": {
-                    "state": "ENABLED",
-                    "variants": {"on": true, "off": false},
-                    "defaultVariant": "on"
-                },
-                "flag2": {
+                "checkout": {
                     "state": "ENABLED",
-                    "variants": {"off": false, "on": true},
-                    "defaultVariant": "off"
+                    "variants": {"new": "new", "old": 1},
+                    "defaultVariant": "old"
                 }
             }
         }"#;
-
-        let response2 = evaluator.update_state(config2).unwrap();
-        assert!(response2.changed_flags.is_some());
-        assert_eq!(response2.changed_flags.unwrap(), vec!["flag2"]);
-    }
-}
-
-// ============================================================================
-// WASM Integration Tests
-// ============================================================================
-
-#[cfg(test)]
-mod wasm_tests {
-    use super::*;
-    use serde_json::json;
-
-    /// Helper function to reset the WASM singleton evaluator between tests
-    fn reset_wasm_evaluator() {
-        wasm_evaluator::with_evaluator(|eval| {
-            eval.clear_state();
-            eval.set_validation_mode(ValidationMode::Strict);
-        });
-    }
-
-    /// Helper to call update_state WASM export
-    fn update_state_wasm(config: &str) -> String {
-        let config_bytes = config.as_bytes();
-        update_state_internal(config_bytes.as_ptr(), config_bytes.len() as u32)
+        let response = validate_and_evaluate_wasm(config, "checkout", "{}");
+        assert_eq!(response["validation"]["valid"], false);
+        assert!(!response["validation"]["errors"]
+            .as_array()
+            .unwrap()
+            .is_empty());
+        assert!(response["result"].is_null());
+
+        // Permissive mode evaluates despite the validation errors
+        wasm_evaluator::with_evaluator(|eval| eval.set_validation_mode(ValidationMode::Permissive));
+        let response = validate_and_evaluate_wasm(config, "checkout", "{}");
+        assert_eq!(response["validation"]["valid"], false);
+        assert_eq!(response["result"]["value"], 1);
+
+        let response = validate_and_evaluate_wasm("not json", "checkout", "{}");
+        assert_eq!(response["validation"]["valid"], false);
+        assert_eq!(response["result"]["errorCode"], "PARSE_ERROR");
     }
 
-    /// Helper to call evaluate WASM export
-    fn evaluate_wasm(flag_key: &str, context: &str) -> EvaluationResult {
-        let flag_key_bytes = flag_key.as_bytes();
-        let context_bytes = context.as_bytes();
+    /// Deterministic xorshift generator driving the fuzz tests.
+    struct Fuzzer(u64);
 
-        let packed = evaluate_internal(
-            flag_key_bytes.as_ptr(),
-            flag_key_bytes.len() as u32,
-            context_bytes.as_ptr(),
-            context_bytes.len() as u32,
-        );
+    impl Fuzzer {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
 
-        // evaluate_internal already returns EvaluationResult directly
-        packed
-    }
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
 
-    #[test]
-    fn test_wasm_memory_allocation() {
-        // Test alloc and dealloc
-        let size = 100;
-        let ptr = wasm_alloc(size);
-        assert!(!ptr.is_null());
+        /// Random bytes, biased towards JSON punctuation so inputs get past the lexer.
+        fn bytes(&mut self, max_len: usize) -> Vec<u8> {
+            const ALPHABET: &[u8] = b"{}[]\":,0123456789.-eE truefalsnl\\u$";
+            let len = self.below(max_len + 1);
+            (0..len)
+                .map(|_| match self.below(4) {
+                    0 => self.next() as u8,
+                    _ => ALPHABET[self.below(ALPHABET.len())],
+                })
+                .collect()
+        }
 
-        // Write some data
-        unsafe {
-            std::ptr::write_bytes(ptr, 0x42, size as usize);
+        /// A copy of `input` with a few bytes replaced, inserted, or removed.
+        fn mutate(&mut self, input: &[u8]) -> Vec<u8> {
+            let mut output = input.to_vec();
+            for _ in 0..=self.below(4) {
+                let at = self.below(output.len() + 1);
+                match self.below(3) {
+                    0 if at < output.len() => output[at] = self.next() as u8,
+                    1 => {
+                        let insert = self.bytes(8);
+                        output.splice(at..at, insert);
+                    }
+                    _ if at < output.len() => {
+                        output.remove(at);
+                    }
+                    _ => {}
+                }
+            }
+            output
         }
-
-        // Free the memory
-        wasm_dealloc(ptr, size);
     }
 
     #[test]
-    fn test_wasm_update_state_export() {
+    fn test_wasm_evaluate_safe_never_panics() {
         reset_wasm_evaluator();
 
-        let config = r#"{
+        let config = br#"{
+            "$evaluators": {"isBeta": {"in": [{"var": "tier"}, ["beta", "alpha"]]}},
+            "patterns": {"corp": "@corp\\.example$"},
             "flags": {
-                "testFlag": {
+                "fractionalFlag": {
+                    "state": "ENABLED",
+                    "variants": {"a": "a", "b": "b"},
+                    "defaultVariant": "a",
+                    "targeting": {"fractional": [{"var": "id"}, ["a", 50], ["b", 50]]}
+                },
+                "semVerFlag": {
                     "state": "ENABLED",
                     "variants": {"on": true, "off": false},
-                    "defaultVariant": "on"
-                }
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"sem_ver": [{"var": "version"}, "^", "1.2.0"]}, "on", "off"]}
+                },
+                "mixedFlag": {
+                    "state": "ENABLED",
+                    "variants": {"n": 1, "s": "x", "o": {"k": [1, 2]}},
+                    "defaultVariant": "n",
+                    "targeting": {"if": [
+                        {"and": [{"$ref": "isBeta"}, {"has": [{"var": "user.id"}]}]}, "o",
+                        {"matches_named": [{"var": "email"}, "corp"]}, "s",
+                        {"/": [{"var": "a"}, {"var": "b"}]}
+                    ]}
+                },
+                "disabledFlag": {"state": "DISABLED", "variants": {"on": true}, "defaultVariant": "on"}
             }
         }"#;
+        let context = br#"{"id": "user-1", "version": "1.4.2", "tier": "beta", "user": {"id": 7}, "email": "x@corp.example", "a": 1, "b": 2}"#;
+        let flag_keys = [
+            "fractionalFlag",
+            "semVerFlag",
+            "mixedFlag",
+            "disabledFlag",
+            "missing",
+        ];
+
+        let mut fuzzer = Fuzzer(0x9E37_79B9_7F4A_7C15);
+        for _ in 0..1000 {
+            // Load a mangled config now and then; failed updates keep the last good state
+            if fuzzer.below(4) == 0 {
+                let config = match fuzzer.below(3) {
+                    0 => fuzzer.bytes(256),
+                    _ => fuzzer.mutate(config),
+                };
+                let response = update_state_internal(config.as_ptr(), config.len() as u32);
+                serde_json::from_str::<Value>(&response)
+                    .expect("update_state returned invalid JSON");
+            }
 
-        let response_json = update_state_wasm(config);
-        let response: Value = serde_json::from_str(&response_json).unwrap();
-        assert_eq!(response["success"], true);
+            let context = match fuzzer.below(3) {
+                0 => fuzzer.bytes(128),
+                _ => fuzzer.mutate(context),
+            };
+            let flag_key = match fuzzer.below(5) {
+                0 => fuzzer.bytes(16),
+                _ => flag_keys[fuzzer.below(flag_keys.len())].as_bytes().to_vec(),
+            };
+
+            let result = evaluate_safe_internal(
+                flag_key.as_ptr(),
+                flag_key.len() as u32,
+                context.as_ptr(),
+                context.len() as u32,
+            );
+            let message = result.error_message.clone().unwrap_or_default();
+            assert!(!message.contains("panic"), "{}", message);
+            let json: Value = serde_json::from_str(&result.to_json_string()).unwrap();
+            assert!(json["reason"].is_string());
+        }
     }
 
     #[test]
-    fn test_wasm_evaluate_export() {
+    fn test_wasm_evaluate_safe_rejects_non_object_context() {
         reset_wasm_evaluator();
+        update_state_wasm(
+            r#"{"flags": {"f": {"state": "ENABLED", "variants": {"on": true, "off": false}, "defaultVariant": "off", "targeting": {"if": [{"var": "beta"}, "on"]}}}}"#,
+        );
 
-        let config = r#"{
-            "flags": {
-                "boolFlag": {
-                    "state": "ENABLED",
-                    "variants": {"on": true, "off": false},
-                    "defaultVariant": "on"
-                }
-            }
-        }"#;
-
-        update_state_wasm(config);
-
-        let result = evaluate_wasm("boolFlag", "{}");
+        let key = "f";
+        for context in ["[1, 2]", "\"beta\"", "42", "{\"beta\": ", "\u{FFFD}"] {
+            let result = evaluate_safe_internal(
+                key.as_ptr(),
+                key.len() as u32,
+                context.as_ptr(),
+                context.len() as u32,
+            );
+            assert_eq!(
+                result.error_code,
+                Some(ErrorCode::ParseError),
+                "{}",
+                context
+            );
+        }
+        let invalid_utf8 = [b'{', 0xFF, b'}'];
+        let result = evaluate_safe_internal(
+            key.as_ptr(),
+            key.len() as u32,
+            invalid_utf8.as_ptr(),
+            invalid_utf8.len() as u32,
+        );
+        assert_eq!(result.error_code, Some(ErrorCode::ParseError));
+
+        let context = r#"{"beta": true}"#;
+        let result = evaluate_safe_internal(
+            key.as_ptr(),
+            key.len() as u32,
+            context.as_ptr(),
+            context.len() as u32,
+        );
         assert_eq!(result.value, json!(true));
-        assert_eq!(result.reason, ResolutionReason::Static);
+        let result = evaluate_safe_internal(key.as_ptr(), key.len() as u32, std::ptr::null(), 0);
+        assert_eq!(result.value, json!(false));
     }
 
     #[test]
-    fn test_wasm_packed_pointer_format() {
-        // Test pack and unpack utilities
-        let ptr = 0x12345678 as *mut u8;
-        let len = 42;
+    fn test_wasm_possible_reasons_export() {
+        reset_wasm_evaluator();
+        update_state_wasm(
+            r#"{"flags": {"targetedFlag": {"state": "ENABLED", "variants": {"on": true, "off": false}, "defaultVariant": "off", "targeting": {"if": [{"var": "beta"}, "on"]}}}}"#,
+        );
 
-        let packed = pack_ptr_len(ptr, len);
-        let (unpacked_ptr, unpacked_len) = unpack_ptr_len(packed);
+        let key = "targetedFlag";
+        assert_eq!(
+            possible_reasons_internal(key.as_ptr(), key.len() as u32),
+            r#"["DEFAULT","TARGETING_MATCH","ERROR"]"#
+        );
+        let missing = "missingFlag";
+        assert_eq!(
+            possible_reasons_internal(missing.as_ptr(), missing.len() as u32),
+            "null"
+        );
+    }
 
-        assert_eq!(unpacked_ptr, ptr);
-        assert_eq!(unpacked_len, len);
+    #[test]
+    fn test_wasm_update_state_multi_export() {
+        reset_wasm_evaluator();
+
+        let configs = r#"[
+            {"flags": {"a": {"state": "ENABLED", "variants": {"on": true}, "defaultVariant": "on"}}},
+            {"flags": {"b": {"state": "ENABLED", "variants": {"on": true}, "defaultVariant": "on"}}}
+        ]"#;
+        let response: Value = serde_json::from_str(&update_state_multi_internal(
+            configs.as_ptr(),
+            configs.len() as u32,
+        ))
+        .unwrap();
+        assert_eq!(response["success"], json!(true));
+        assert_eq!(response["changedFlags"], json!(["a", "b"]));
+
+        let configs = r#"{"flags": {}}"#;
+        let response: Value = serde_json::from_str(&update_state_multi_internal(
+            configs.as_ptr(),
+            configs.len() as u32,
+        ))
+        .unwrap();
+        assert_eq!(response["errorCode"], "INVALID_JSON");
     }
 
     #[test]
-    fn test_wasm_utf8_handling() {
+    fn test_wasm_flags_referencing_export() {
         reset_wasm_evaluator();
+        let attribute = "country";
+        assert_eq!(
+            flags_referencing_internal(attribute.as_ptr(), attribute.len() as u32),
+            "[]"
+        );
 
-        let config = r#"{
-            "flags": {
-                "unicodeFlag": {
-                    "state": "ENABLED",
-                    "variants": {"emoji": "Hello 👋 World 🌍"},
-                    "defaultVariant": "emoji"
-                }
-            }
-        }"#;
+        update_state_wasm(
+            r#"{"flags": {"regionFlag": {"state": "ENABLED", "variants": {"on": true, "off": false}, "defaultVariant": "off", "targeting": {"if": [{"==": [{"var": "country"}, "DE"]}, "on"]}}}}"#,
+        );
+        assert_eq!(
+            flags_referencing_internal(attribute.as_ptr(), attribute.len() as u32),
+            r#"["regionFlag"]"#
+        );
+    }
 
-        update_state_wasm(config);
+    #[test]
+    fn test_wasm_evaluable_flags_export() {
+        reset_wasm_evaluator();
+        update_state_wasm(
+            r#"{"flags": {"regionFlag": {"state": "ENABLED", "variants": {"on": true, "off": false}, "defaultVariant": "off", "targeting": {"if": [{"==": [{"var": "country"}, "DE"]}, "on"]}}}}"#,
+        );
 
-        let result = evaluate_wasm("unicodeFlag", "{}");
-        assert_eq!(result.value, json!("Hello 👋 World 🌍"));
+        let context = r#"{"country": "DE"}"#;
+        assert_eq!(
+            evaluable_flags_internal(context.as_ptr(), context.len() as u32),
+            r#"["regionFlag"]"#
+        );
+        let context = r#"{"tier": "gold"}"#;
+        assert_eq!(
+            evaluable_flags_internal(context.as_ptr(), context.len() as u32),
+            "[]"
+        );
+        let context = "{not json";
+        assert_eq!(
+            evaluable_flags_internal(context.as_ptr(), context.len() as u32),
+            "[]"
+        );
     }
 
     #[test]
-    fn test_wasm_evaluate_by_index() {
+    fn test_wasm_status_prefixed_results() {
         reset_wasm_evaluator();
+        update_state_wasm(
+            r#"{"flags": {"framedFlag": {"state": "ENABLED", "variants": {"on": true, "off": false}, "defaultVariant": "on"}}}"#,
+        );
 
-        let config = r#"{
-            "flags": {
-                "boolFlag": {
-                    "state": "ENABLED",
-                    "variants": {"on": true, "off": false},
-                    "defaultVariant": "off",
-                    "targeting": {
-                        "if": [
-                            {"==": [{"var": "role"}, "admin"]},
-                            "on",
-                            "off"
-                        ]
-                    }
-                }
-            }
-        }"#;
+        let response: Value = serde_json::from_str(&set_result_framing_internal(2)).unwrap();
+        assert_eq!(response["errorCode"], "INVALID_ARGUMENT");
+        let response: Value = serde_json::from_str(&set_result_framing_internal(1)).unwrap();
+        assert_eq!(response["success"], true);
 
-        let response_json = update_state_wasm(config);
-        let response: Value = serde_json::from_str(&response_json).unwrap();
-        assert!(response["success"].as_bool().unwrap());
+        // The bytes result_to_memory writes; its packed pointer only round-trips on wasm32
+        let framed = |result: EvaluationResult| result.to_framed_bytes(result_framing());
 
-        // Get the flag index from the response
-        let flag_indices = response["flagIndices"].as_object().unwrap();
-        let bool_flag_index = flag_indices["boolFlag"].as_u64().unwrap() as u32;
+        let success = framed(evaluate_wasm("framedFlag", "{}"));
+        assert_eq!(EvaluationResult::peek_error_code(&success), Some(None));
+        let body: Value = serde_json::from_slice(&success[1..]).unwrap();
+        assert_eq!(body["value"], json!(true));
 
-        // Test with pre-enriched context (matching)
-        let context = json!({
-            "role": "admin",
-            "targetingKey": "user-1",
-            "$flagd": {
-                "flagKey": "boolFlag",
-                "timestamp": 1234567890
-            }
-        });
-        let context_str = context.to_string();
-        let context_bytes = context_str.as_bytes();
+        let not_found = framed(evaluate_wasm("missingFramedFlag", "{}"));
+        assert_eq!(
+            EvaluationResult::peek_error_code(&not_found),
+            Some(Some(ErrorCode::FlagNotFound))
+        );
 
-        let result = evaluate_by_index_internal(
-            bool_flag_index,
-            context_bytes.as_ptr(),
-            context_bytes.len() as u32,
+        let mismatch = framed(wasm_evaluator::with_evaluator(|eval| {
+            eval.evaluate_string("framedFlag", json!({}))
+        }));
+        assert_eq!(
+            EvaluationResult::peek_error_code(&mismatch),
+            Some(Some(ErrorCode::TypeMismatch))
         );
-        assert_eq!(result.value, json!(true));
-        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+        let body: Value = serde_json::from_slice(&mismatch[1..]).unwrap();
+        assert_eq!(body["errorCode"], "TYPE_MISMATCH");
 
-        // Test with non-matching context
-        let context2 = json!({
-            "role": "user",
-            "targetingKey": "user-2",
-            "$flagd": {
-                "flagKey": "boolFlag",
-                "timestamp": 1234567890
-            }
+        set_result_framing_internal(0);
+        let plain = framed(evaluate_wasm("framedFlag", "{}"));
+        assert_eq!(plain[0], b'{');
+    }
+
+    #[test]
+    fn test_wasm_reset_options_export() {
+        reset_wasm_evaluator();
+        update_state_wasm(
+            r#"{"flags": {"resetFlag": {"state": "ENABLED", "variants": {"on": true, "off": false}, "defaultVariant": "on"}}}"#,
+        );
+        set_result_framing_internal(1);
+        wasm_evaluator::with_evaluator(|eval| {
+            eval.set_validation_mode(ValidationMode::Permissive);
+            eval.set_default_timestamp(42);
         });
-        let context2_str = context2.to_string();
-        let context2_bytes = context2_str.as_bytes();
 
-        let result2 = evaluate_by_index_internal(
-            bool_flag_index,
-            context2_bytes.as_ptr(),
-            context2_bytes.len() as u32,
+        let response: Value = serde_json::from_str(&reset_options_internal()).unwrap();
+        assert_eq!(response, json!({"success": true, "error": null}));
+        assert_eq!(result_framing(), ResultFraming::Json);
+        wasm_evaluator::with_evaluator(|eval| {
+            assert_eq!(eval.validation_mode(), ValidationMode::Strict);
+            assert_eq!(eval.default_timestamp(), 0);
+        });
+        assert_eq!(evaluate_wasm("resetFlag", "{}").value, json!(true));
+    }
+
+    #[test]
+    fn test_wasm_validate_targeting_export() {
+        let rule = r#"{"if": [{"$ref": "isStaff"}, "on", "off"]}"#;
+        let evaluators = r#"{"isStaff": {"ends_with": [{"var": "email"}, "@example.com"]}}"#;
+        let result = validate_targeting_internal(
+            rule.as_ptr(),
+            rule.len() as u32,
+            evaluators.as_ptr(),
+            evaluators.len() as u32,
         );
-        assert_eq!(result2.value, json!(false));
+        assert!(result.valid);
+
+        let result =
+            validate_targeting_internal(rule.as_ptr(), rule.len() as u32, std::ptr::null(), 0);
+        assert_eq!(result.errors[0].path, "/if/0/$ref");
+
+        let rule = "{not json";
+        let result =
+            validate_targeting_internal(rule.as_ptr(), rule.len() as u32, std::ptr::null(), 0);
+        assert!(result.errors[0]
+            .message
+            .starts_with("Failed to parse rule JSON"));
     }
 
     #[test]
-    fn test_wasm_evaluate_by_index_invalid_index() {
+    fn test_wasm_result_schema_export() {
+        let schema: Value = serde_json::from_str(&result_schema_internal()).unwrap();
+        assert_eq!(schema["title"], json!("EvaluationResult"));
+        assert_eq!(schema["required"], json!(["value", "reason"]));
+        assert!(schema["properties"]["reason"]["enum"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("TARGETING_MATCH")));
+    }
+
+    #[test]
+    fn test_wasm_evaluate_inline_export() {
         reset_wasm_evaluator();
 
-        let config = r#"{
-            "flags": {
-                "flag1": {
-                    "state": "ENABLED",
-                    "variants": {"on": true},
-                    "defaultVariant": "on"
-                }
-            }
+        let flag = r#"{
+            "key": "inlineFlag",
+            "state": "ENABLED",
+            "variants": {"on": true, "off": false},
+            "defaultVariant": "off",
+            "targeting": {"if": [{"==": [{"var": "$flagd.flagKey"}, "inlineFlag"]}, "on", "off"]}
         }"#;
+        let result =
+            evaluate_inline_internal(flag.as_ptr(), flag.len() as u32, std::ptr::null(), 0);
+        assert_eq!(result.value, json!(true));
+        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
 
-        update_state_wasm(config);
-
-        let result = evaluate_by_index_internal(999, std::ptr::null(), 0);
+        let bad = r#"{"state": "ENABLED"}"#;
+        let context = "{}";
+        let result = evaluate_inline_internal(
+            bad.as_ptr(),
+            bad.len() as u32,
+            context.as_ptr(),
+            context.len() as u32,
+        );
         assert_eq!(result.reason, ResolutionReason::Error);
-        assert_eq!(result.error_code, Some(ErrorCode::FlagNotFound));
+        assert_eq!(result.error_code, Some(ErrorCode::ParseError));
+    }
+
+    #[test]
+    fn test_wasm_evaluate_before_update_state() {
+        reset_wasm_evaluator();
+
+        let uninitialized = evaluate_wasm("uninitFlag", "{}");
+        assert_eq!(uninitialized.reason, ResolutionReason::Error);
+        assert_eq!(uninitialized.error_code, Some(ErrorCode::General));
+        assert_eq!(
+            uninitialized.error_message.as_deref(),
+            Some("state uninitialized")
+        );
+        let by_index = evaluate_by_index_internal(0, std::ptr::null(), 0);
+        assert_eq!(
+            by_index.error_message.as_deref(),
+            Some("state uninitialized")
+        );
+
+        update_state_wasm(r#"{"flags": {}}"#);
+        let missing = evaluate_wasm("uninitFlag", "{}");
+        assert_eq!(missing.reason, ResolutionReason::FlagNotFound);
+        assert_eq!(missing.error_code, Some(ErrorCode::FlagNotFound));
     }
 }
 
@@ -1790,4 +8594,105 @@ mod optimization_tests {
         // targetingKey is always included
         assert!(flag_keys.contains(&"targetingKey".to_string()));
     }
+
+    #[test]
+    fn test_result_cache_hit_returns_identical_result() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Permissive);
+        evaluator.set_result_cache_capacity(16);
+
+        let config = r#"{
+            "flags": {
+                "targetedFlag": {
+                    "state": "ENABLED",
+                    "variants": {"admin": "admin-value", "user": "user-value"},
+                    "defaultVariant": "user",
+                    "metadata": {"owner": "team-a"},
+                    "targeting": {
+                        "if": [{"==": [{"var": "role"}, "admin"]}, "admin", "user"]
+                    }
+                }
+            }
+        }"#;
+        evaluator.update_state(config).unwrap();
+
+        let first = evaluator.evaluate_flag("targetedFlag", json!({"role": "admin"}));
+        let second = evaluator.evaluate_flag("targetedFlag", json!({"role": "admin"}));
+        assert_eq!(first.to_json_string(), second.to_json_string());
+
+        let stats = evaluator.cache_stats();
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.size, 1);
+    }
+
+    #[test]
+    fn test_result_cache_invalidated_by_update_state() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Permissive);
+        evaluator.set_result_cache_capacity(16);
+
+        let config_v1 = r#"{
+            "flags": {
+                "targetedFlag": {
+                    "state": "ENABLED",
+                    "variants": {"admin": "admin-value", "user": "user-value"},
+                    "defaultVariant": "user",
+                    "targeting": {
+                        "if": [{"==": [{"var": "role"}, "admin"]}, "admin", "user"]
+                    }
+                }
+            }
+        }"#;
+        let config_v2 = r#"{
+            "flags": {
+                "targetedFlag": {
+                    "state": "ENABLED",
+                    "variants": {"admin": "admin-value-v2", "user": "user-value"},
+                    "defaultVariant": "user",
+                    "targeting": {
+                        "if": [{"==": [{"var": "role"}, "admin"]}, "admin", "user"]
+                    }
+                }
+            }
+        }"#;
+
+        evaluator.update_state(config_v1).unwrap();
+        let version = evaluator.state_version();
+        let before = evaluator.evaluate_flag("targetedFlag", json!({"role": "admin"}));
+        assert_eq!(before.value, json!("admin-value"));
+
+        evaluator.update_state(config_v2).unwrap();
+        assert!(evaluator.state_version() > version);
+        assert_eq!(evaluator.cache_stats().size, 0);
+
+        let after = evaluator.evaluate_flag("targetedFlag", json!({"role": "admin"}));
+        assert_eq!(after.value, json!("admin-value-v2"));
+        assert_eq!(evaluator.cache_stats().hits, 0);
+    }
+
+    #[test]
+    fn test_result_cache_skips_time_based_targeting() {
+        let mut evaluator = FlagEvaluator::new(ValidationMode::Permissive);
+        evaluator.set_result_cache_capacity(16);
+
+        let config = r#"{
+            "flags": {
+                "timeFlag": {
+                    "state": "ENABLED",
+                    "variants": {"new": "new-value", "old": "old-value"},
+                    "defaultVariant": "old",
+                    "targeting": {
+                        "if": [{">": [{"var": "$flagd.timestamp"}, 0]}, "new", "old"]
+                    }
+                }
+            }
+        }"#;
+        evaluator.update_state(config).unwrap();
+
+        evaluator.evaluate_flag("timeFlag", json!({}));
+        evaluator.evaluate_flag("timeFlag", json!({}));
+
+        let stats = evaluator.cache_stats();
+        assert_eq!(stats.hits, 0);
+        assert_eq!(stats.size, 0);
+    }
 }
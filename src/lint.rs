@@ -0,0 +1,539 @@
+//! On-demand linting of flag configurations.
+//!
+//! Linting goes beyond schema validation: it reports configurations that are valid
+//! but likely to be mistakes, such as unused shared evaluators, variants with
+//! duplicate values, suspicious fractional weights, overly complex targeting rules,
+//! a missing default variant, or keys the schema does not define (typically typos).
+//! Linting never modifies evaluator state and does not depend on the validation mode.
+
+use crate::model::remove_annotations;
+use crate::validation::{escape_pointer, find_unknown_keys, validate_flags_config};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+
+/// How serious a lint finding is.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LintSeverity {
+    /// The configuration is invalid or will fail to evaluate
+    Error,
+    /// The configuration is valid but very likely a mistake
+    Warning,
+    /// The configuration relies on behavior that may be surprising
+    Info,
+}
+
+/// A single lint finding.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LintWarning {
+    /// How serious the finding is
+    pub severity: LintSeverity,
+    /// The JSON pointer of the offending location (e.g., "/flags/myFlag/defaultVariant")
+    pub path: String,
+    /// A human-readable description of the finding
+    pub message: String,
+}
+
+impl LintWarning {
+    fn new(severity: LintSeverity, path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Default maximum number of nodes in a targeting rule before it is reported.
+pub const DEFAULT_MAX_RULE_NODES: usize = 200;
+
+/// Default maximum nesting depth of a targeting rule before it is reported.
+pub const DEFAULT_MAX_RULE_DEPTH: usize = 12;
+
+/// Thresholds used by `lint_config_with_options`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LintOptions {
+    /// Targeting rules with more nodes than this are reported
+    pub max_rule_nodes: usize,
+    /// Targeting rules nested deeper than this are reported
+    pub max_rule_depth: usize,
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        Self {
+            max_rule_nodes: DEFAULT_MAX_RULE_NODES,
+            max_rule_depth: DEFAULT_MAX_RULE_DEPTH,
+        }
+    }
+}
+
+/// Lints a JSON flag configuration string with the default `LintOptions`.
+///
+/// Schema validation errors are included with `error` severity, followed by the
+/// semantic lints. An empty list means no problems were found.
+///
+/// # Example
+///
+/// ```
+/// use flagd_evaluator::lint::{lint_config, LintSeverity};
+///
+/// let config = r#"{
+///     "flags": {
+///         "myFlag": {
+///             "state": "ENABLED",
+///             "variants": {"on": true, "off": true},
+///             "defaultVariant": "on"
+///         }
+///     }
+/// }"#;
+///
+/// let lints = lint_config(config);
+/// assert_eq!(lints.len(), 1);
+/// assert_eq!(lints[0].severity, LintSeverity::Warning);
+/// ```
+pub fn lint_config(json_str: &str) -> Vec<LintWarning> {
+    lint_config_with_options(json_str, &LintOptions::default())
+}
+
+/// Lints a JSON flag configuration string with custom thresholds.
+pub fn lint_config_with_options(json_str: &str, options: &LintOptions) -> Vec<LintWarning> {
+    let mut config: Value = match serde_json::from_str(json_str) {
+        Ok(config) => config,
+        Err(e) => {
+            return vec![LintWarning::new(
+                LintSeverity::Error,
+                "",
+                format!("Failed to parse JSON: {}", e),
+            )]
+        }
+    };
+
+    let mut lints: Vec<LintWarning> = match validate_flags_config(json_str) {
+        Ok(()) => Vec::new(),
+        Err(result) => result
+            .errors
+            .into_iter()
+            .map(|e| LintWarning::new(LintSeverity::Error, e.path, e.message))
+            .collect(),
+    };
+    lints.extend(
+        find_unknown_keys(json_str)
+            .into_iter()
+            .map(|e| LintWarning::new(LintSeverity::Warning, e.path, e.message)),
+    );
+
+    remove_annotations(&mut config);
+
+    let empty = Map::new();
+    let evaluators = config
+        .get("$evaluators")
+        .and_then(Value::as_object)
+        .unwrap_or(&empty);
+    let mut referenced = HashSet::new();
+
+    for (name, rule) in evaluators {
+        let path = format!("/$evaluators/{}", escape_pointer(name));
+        lint_targeting(rule, &path, evaluators, &mut referenced, &mut lints);
+    }
+
+    if let Some(flags) = config.get("flags").and_then(Value::as_object) {
+        for (flag_key, flag) in flags {
+            lint_flag(
+                flag_key,
+                flag,
+                evaluators,
+                options,
+                &mut referenced,
+                &mut lints,
+            );
+        }
+    }
+
+    for name in evaluators.keys() {
+        if !referenced.contains(name.as_str()) {
+            lints.push(LintWarning::new(
+                LintSeverity::Warning,
+                format!("/$evaluators/{}", escape_pointer(name)),
+                format!("Evaluator '{}' is never referenced", name),
+            ));
+        }
+    }
+
+    lints
+}
+
+/// Lints a single flag definition.
+fn lint_flag<'a>(
+    flag_key: &str,
+    flag: &Value,
+    evaluators: &'a Map<String, Value>,
+    options: &LintOptions,
+    referenced: &mut HashSet<&'a str>,
+    lints: &mut Vec<LintWarning>,
+) {
+    let flag_path = format!("/flags/{}", escape_pointer(flag_key));
+    let variants = flag.get("variants").and_then(Value::as_object);
+
+    match flag.get("defaultVariant") {
+        None | Some(Value::Null) => lints.push(LintWarning::new(
+            LintSeverity::Warning,
+            format!("{}/defaultVariant", flag_path),
+            format!(
+                "Flag '{}' has no default variant; evaluation falls back to the code default",
+                flag_key
+            ),
+        )),
+        Some(Value::String(default)) => {
+            if variants.is_some_and(|v| !v.contains_key(default)) {
+                lints.push(LintWarning::new(
+                    LintSeverity::Error,
+                    format!("{}/defaultVariant", flag_path),
+                    format!("Default variant '{}' is not defined in variants", default),
+                ));
+            }
+        }
+        Some(_) => {}
+    }
+
+    if let Some(variants) = variants {
+        let names: Vec<&String> = variants.keys().collect();
+        for (i, first) in names.iter().enumerate() {
+            if let Some(second) = names[i + 1..]
+                .iter()
+                .find(|second| variants[first.as_str()] == variants[second.as_str()])
+            {
+                lints.push(LintWarning::new(
+                    LintSeverity::Warning,
+                    format!("{}/variants", flag_path),
+                    format!(
+                        "Variants '{}' and '{}' have identical values",
+                        first, second
+                    ),
+                ));
+            }
+        }
+    }
+
+    if let Some(targeting) = flag.get("targeting") {
+        let path = format!("{}/targeting", flag_path);
+        lint_targeting(targeting, &path, evaluators, referenced, lints);
+
+        let (nodes, depth) = rule_complexity(targeting);
+        if nodes > options.max_rule_nodes || depth > options.max_rule_depth {
+            lints.push(LintWarning::new(
+                LintSeverity::Warning,
+                path,
+                format!(
+                    "Targeting for flag '{}' is overly complex ({} nodes, depth {}); consider simplifying it or moving parts into $evaluators",
+                    flag_key, nodes, depth
+                ),
+            ));
+        }
+    }
+}
+
+/// Computes the complexity of a targeting rule as written, as `(nodes, depth)`.
+///
+/// Every operation and literal counts as a node. Arrays are argument lists and
+/// add neither nodes nor depth; the depth is the deepest nesting of operations.
+/// `$ref` references are not expanded.
+fn rule_complexity(rule: &Value) -> (usize, usize) {
+    match rule {
+        Value::Object(obj) => obj.values().fold((1, 1), |(nodes, depth), value| {
+            let (child_nodes, child_depth) = rule_complexity(value);
+            (nodes + child_nodes, depth.max(child_depth + 1))
+        }),
+        Value::Array(items) => items.iter().fold((0, 0), |(nodes, depth), item| {
+            let (child_nodes, child_depth) = rule_complexity(item);
+            (nodes + child_nodes, depth.max(child_depth))
+        }),
+        _ => (1, 0),
+    }
+}
+
+/// Walks a targeting rule, collecting `$ref` usages and linting fractional weights.
+fn lint_targeting<'a>(
+    rule: &Value,
+    path: &str,
+    evaluators: &'a Map<String, Value>,
+    referenced: &mut HashSet<&'a str>,
+    lints: &mut Vec<LintWarning>,
+) {
+    match rule {
+        Value::Object(obj) => {
+            if let Some(Value::String(name)) = obj.get("$ref") {
+                match evaluators.get_key_value(name) {
+                    Some((key, _)) => {
+                        referenced.insert(key.as_str());
+                    }
+                    None => lints.push(LintWarning::new(
+                        LintSeverity::Error,
+                        format!("{}/$ref", path),
+                        format!("Evaluator '{}' not found in $evaluators", name),
+                    )),
+                }
+            }
+            if let Some(Value::Array(args)) = obj.get("fractional") {
+                lint_fractional(args, &format!("{}/fractional", path), lints);
+            }
+            for (key, value) in obj {
+                let child = format!("{}/{}", path, escape_pointer(key));
+                lint_targeting(value, &child, evaluators, referenced, lints);
+            }
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                lint_targeting(
+                    item,
+                    &format!("{}/{}", path, i),
+                    evaluators,
+                    referenced,
+                    lints,
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Checks the literal bucket weights of a fractional operation.
+///
+/// Weights are compared in thousandths, the precision the fractional operator uses,
+/// so a weight such as `0.0001` is reported as zero.
+fn lint_fractional(args: &[Value], path: &str, lints: &mut Vec<LintWarning>) {
+    let mut total: u64 = 0;
+    for bucket_list in args.iter().filter_map(Value::as_array) {
+        // Handles both [name, weight] buckets and the flat [name, weight, ...] form
+        for pair in bucket_list.chunks(2) {
+            let Some(name) = pair[0].as_str() else {
+                return;
+            };
+            let weight = match pair.get(1) {
+                None => 1000,
+                Some(weight) => match weight.as_f64().filter(|w| *w >= 0.0) {
+                    Some(weight) => (weight * 1000.0).round() as u64,
+                    // Computed (or negative) weights cannot be checked statically
+                    None => return,
+                },
+            };
+            if weight == 0 {
+                lints.push(LintWarning::new(
+                    LintSeverity::Warning,
+                    path,
+                    format!(
+                        "Bucket '{}' has zero weight and can never be selected",
+                        name
+                    ),
+                ));
+            }
+            total += weight;
+        }
+    }
+
+    if total > 0 && total != 100_000 {
+        lints.push(LintWarning::new(
+            LintSeverity::Info,
+            path,
+            format!(
+                "Bucket weights sum to {}; they are normalized relative to the total",
+                total as f64 / 1000.0
+            ),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(lints: &[LintWarning]) -> Vec<&str> {
+        lints.iter().map(|l| l.message.as_str()).collect()
+    }
+
+    #[test]
+    fn test_clean_config_has_no_lints() {
+        let config = r#"{
+            "flags": {
+                "myFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on",
+                    "targeting": {"fractional": [["on", 50], ["off", 50]]}
+                }
+            }
+        }"#;
+
+        assert!(lint_config(config).is_empty());
+    }
+
+    #[test]
+    fn test_crafted_config_lints() {
+        let config = r#"{
+            "$evaluators": {
+                "usedRule": {"==": [{"var": "tier"}, "gold"]},
+                "unusedRule": {"==": [{"var": "tier"}, "silver"]}
+            },
+            "flags": {
+                "dupFlag": {
+                    "state": "ENABLED",
+                    "variants": {"a": "same", "b": "same"},
+                    "defaultVariant": "a",
+                    "targeting": {"if": [{"$ref": "usedRule"}, "b", "a"]}
+                },
+                "weightFlag": {
+                    "state": "ENABLED",
+                    "variants": {"x": 1, "y": 2, "z": 3},
+                    "defaultVariant": "x",
+                    "targeting": {"fractional": [{"var": "id"}, ["x", 30], ["y", 30], ["z", 0]]}
+                },
+                "noDefault": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false}
+                }
+            }
+        }"#;
+
+        let lints = lint_config(config);
+        let messages = messages(&lints);
+
+        assert!(messages.contains(&"Evaluator 'unusedRule' is never referenced"));
+        assert!(!messages.iter().any(|m| m.contains("'usedRule'")));
+        assert!(messages.contains(&"Variants 'a' and 'b' have identical values"));
+        assert!(messages.contains(&"Bucket 'z' has zero weight and can never be selected"));
+        assert!(messages
+            .contains(&"Bucket weights sum to 60; they are normalized relative to the total"));
+        assert!(messages
+            .iter()
+            .any(|m| m.contains("'noDefault' has no default variant")));
+
+        let unused = lints
+            .iter()
+            .find(|l| l.message.contains("unusedRule"))
+            .unwrap();
+        assert_eq!(unused.severity, LintSeverity::Warning);
+        assert_eq!(unused.path, "/$evaluators/unusedRule");
+
+        let weights = lints
+            .iter()
+            .find(|l| l.message.contains("sum to 60"))
+            .unwrap();
+        assert_eq!(weights.severity, LintSeverity::Info);
+        assert_eq!(weights.path, "/flags/weightFlag/targeting/fractional");
+    }
+
+    #[test]
+    fn test_zero_weight_buckets_are_reported() {
+        let config = r#"{
+            "flags": {
+                "splitFlag": {
+                    "state": "ENABLED",
+                    "variants": {"a": 1, "b": 2, "c": 3, "d": 4},
+                    "defaultVariant": "a",
+                    "targeting": {"fractional": [
+                        ["a", 0], ["b", 33.33], ["c", 0.0001], ["d", 66.67]
+                    ]}
+                }
+            }
+        }"#;
+
+        let lints = lint_config(config);
+        let messages = messages(&lints);
+        assert_eq!(
+            messages,
+            vec![
+                "Bucket 'a' has zero weight and can never be selected",
+                "Bucket 'c' has zero weight and can never be selected",
+            ]
+        );
+        assert!(lints.iter().all(|l| l.severity == LintSeverity::Warning));
+    }
+
+    #[test]
+    fn test_complex_targeting_is_reported() {
+        let mut rule = serde_json::json!("on");
+        for _ in 0..DEFAULT_MAX_RULE_DEPTH {
+            rule = serde_json::json!({"if": [{"var": "beta"}, rule, "off"]});
+        }
+        let config = serde_json::json!({
+            "flags": {
+                "deepFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": rule
+                }
+            }
+        })
+        .to_string();
+
+        let lints = lint_config(&config);
+        let complex = lints
+            .iter()
+            .find(|l| l.message.contains("overly complex"))
+            .unwrap();
+        assert_eq!(complex.severity, LintSeverity::Warning);
+        assert_eq!(complex.path, "/flags/deepFlag/targeting");
+        assert!(complex.message.contains("'deepFlag'"));
+        assert!(complex
+            .message
+            .contains(&format!("depth {}", DEFAULT_MAX_RULE_DEPTH + 1)));
+
+        // Raising the threshold silences the warning
+        let options = LintOptions {
+            max_rule_depth: DEFAULT_MAX_RULE_DEPTH + 1,
+            ..LintOptions::default()
+        };
+        assert!(!lint_config_with_options(&config, &options)
+            .iter()
+            .any(|l| l.message.contains("overly complex")));
+    }
+
+    #[test]
+    fn test_unknown_keys_are_reported() {
+        let config = r#"{
+            "flags": {
+                "typoFlag": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "on",
+                    "targetting": {"if": [{"var": "beta"}, "on", "off"]}
+                }
+            }
+        }"#;
+
+        let lints = lint_config(config);
+        assert_eq!(
+            lints,
+            vec![LintWarning::new(
+                LintSeverity::Warning,
+                "/flags/typoFlag/targetting",
+                "Unknown property 'targetting' in flag 'typoFlag'"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_unknown_default_variant_and_invalid_json() {
+        let config = r#"{
+            "flags": {
+                "badDefault": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "missing"
+                }
+            }
+        }"#;
+
+        let lints = lint_config(config);
+        assert!(lints
+            .iter()
+            .any(|l| l.severity == LintSeverity::Error
+                && l.path == "/flags/badDefault/defaultVariant"));
+
+        let lints = lint_config("{not json");
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].severity, LintSeverity::Error);
+    }
+}
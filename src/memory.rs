@@ -209,10 +209,11 @@ pub fn string_to_memory_checked(s: &str) -> Result<u64, MemoryAllocationError> {
 /// * `len` - Length of the string in bytes
 ///
 /// # Returns
-/// The string, or an error if the memory is invalid
-pub unsafe fn string_from_memory(ptr: *const u8, len: u32) -> Result<String, &'static str> {
+/// The string, or an error if the memory is invalid. For invalid UTF-8, the error
+/// includes the byte offset of the first invalid sequence.
+pub unsafe fn string_from_memory(ptr: *const u8, len: u32) -> Result<String, String> {
     if ptr.is_null() {
-        return Err("Null pointer provided");
+        return Err("Null pointer provided".to_string());
     }
 
     if len == 0 {
@@ -222,7 +223,7 @@ pub unsafe fn string_from_memory(ptr: *const u8, len: u32) -> Result<String, &'s
     let slice = std::slice::from_raw_parts(ptr, len as usize);
     std::str::from_utf8(slice)
         .map(|s| s.to_string())
-        .map_err(|_| "Invalid UTF-8 in memory")
+        .map_err(|e| format!("Invalid UTF-8 in memory at byte offset {}", e.valid_up_to()))
 }
 
 #[cfg(test)]
@@ -286,6 +287,14 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_string_from_memory_reports_invalid_utf8_offset() {
+        let bytes = [b'{', b'"', b'a', 0xFF, b'"', b'}'];
+
+        let error = unsafe { string_from_memory(bytes.as_ptr(), bytes.len() as u32) }.unwrap_err();
+        assert_eq!(error, "Invalid UTF-8 in memory at byte offset 3");
+    }
+
     #[test]
     fn test_string_to_memory_empty_string() {
         // Empty strings should return a non-null pointer with length 0
@@ -3,10 +3,12 @@
 //! This module provides data structures for parsing and working with flagd feature flag
 //! configurations as defined in the [flagd specification](https://flagd.dev/reference/flag-definitions/).
 
-use crate::operators::create_evaluator;
+use crate::operators::{compile_patterns, create_evaluator, named_pattern_refs};
+use crate::types::{serialize_sorted, serialize_sorted_nested, FlagType};
 use datalogic_rs::CompiledLogic;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
 /// Represents a feature flag according to the flagd specification.
@@ -49,6 +51,7 @@ pub struct FeatureFlag {
     pub default_variant: Option<String>,
 
     /// Map of variant names to their values (can be any JSON value)
+    #[serde(serialize_with = "serialize_sorted")]
     pub variants: HashMap<String, serde_json::Value>,
 
     /// Optional targeting rules (JSON Logic expression)
@@ -60,8 +63,54 @@ pub struct FeatureFlag {
     pub compiled_targeting: Option<Arc<CompiledLogic>>,
 
     /// Optional metadata associated with the flag
-    #[serde(default)]
+    #[serde(default, serialize_with = "serialize_sorted")]
     pub metadata: HashMap<String, serde_json::Value>,
+
+    /// Optional metadata for individual variants, keyed by variant name.
+    ///
+    /// The resolved variant's metadata is merged over flag-level metadata in
+    /// evaluation results.
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_sorted_nested"
+    )]
+    pub variant_metadata: HashMap<String, HashMap<String, serde_json::Value>>,
+
+    /// Flags that must resolve to a given variant before this flag's targeting applies.
+    ///
+    /// If any prerequisite resolves to another variant, the flag resolves to its
+    /// default variant with reason PREREQUISITE_FAILED.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prerequisites: Vec<Prerequisite>,
+
+    /// Former variant names, mapped to the variant they now refer to.
+    ///
+    /// A targeting rule (including fractional buckets) or a prerequisite naming an
+    /// alias resolves to the aliased variant, so variants can be renamed without
+    /// rewriting every rule that refers to them.
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_sorted"
+    )]
+    pub variant_aliases: HashMap<String, String>,
+}
+
+/// A flag that must resolve to a given variant, see `FeatureFlag::prerequisites`.
+///
+/// # Example
+///
+/// ```json
+/// "prerequisites": [{"flag": "new-checkout", "variant": "on"}]
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Prerequisite {
+    /// Key of the prerequisite flag
+    pub flag: String,
+    /// Variant the prerequisite flag must resolve to
+    pub variant: String,
 }
 
 impl PartialEq for FeatureFlag {
@@ -73,6 +122,9 @@ impl PartialEq for FeatureFlag {
             && self.variants == other.variants
             && self.targeting == other.targeting
             && self.metadata == other.metadata
+            && self.variant_metadata == other.variant_metadata
+            && self.prerequisites == other.prerequisites
+            && self.variant_aliases == other.variant_aliases
     }
 }
 
@@ -96,6 +148,9 @@ impl FeatureFlag {
     ///     targeting: Some(json!({"==": [1, 1]})),
     ///     compiled_targeting: None,
     ///     metadata: HashMap::new(),
+    ///     variant_metadata: HashMap::new(),
+    ///     prerequisites: Vec::new(),
+    ///     variant_aliases: HashMap::new(),
     /// };
     ///
     /// let targeting_str = flag.get_targeting();
@@ -132,6 +187,9 @@ impl FeatureFlag {
     ///     targeting: Some(json!({"==": [1, 1]})),
     ///     compiled_targeting: None,
     ///     metadata: HashMap::new(),
+    ///     variant_metadata: HashMap::new(),
+    ///     prerequisites: Vec::new(),
+    ///     variant_aliases: HashMap::new(),
     /// };
     ///
     /// let mut flag2 = flag1.clone();
@@ -142,6 +200,130 @@ impl FeatureFlag {
     pub fn is_different_from(&self, other: &FeatureFlag) -> bool {
         self != other
     }
+
+    /// Returns the name of the variant a name refers to, following `variant_aliases`.
+    pub fn resolve_variant_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.variant_aliases.get(name).map_or(name, String::as_str)
+    }
+
+    /// Infers the flag's value type from its variants.
+    ///
+    /// Numbers are `Integer` if every variant is an integer and `Float` otherwise.
+    /// Variants of different types, arrays, nulls, or an empty variant set give `Mixed`.
+    pub fn flag_type(&self) -> FlagType {
+        use serde_json::Value;
+
+        let mut flag_type = None;
+        for value in self.variants.values() {
+            let value_type = match value {
+                Value::Bool(_) => FlagType::Boolean,
+                Value::String(_) => FlagType::String,
+                Value::Number(n) if n.is_i64() || n.is_u64() => FlagType::Integer,
+                Value::Number(_) => FlagType::Float,
+                Value::Object(_) => FlagType::Object,
+                Value::Array(_) | Value::Null => return FlagType::Mixed,
+            };
+            flag_type = match (flag_type, value_type) {
+                (None, t) => Some(t),
+                (Some(a), b) if a == b => Some(a),
+                (Some(FlagType::Integer), FlagType::Float)
+                | (Some(FlagType::Float), FlagType::Integer) => Some(FlagType::Float),
+                _ => return FlagType::Mixed,
+            };
+        }
+        flag_type.unwrap_or(FlagType::Mixed)
+    }
+}
+
+/// Flag-set-level values used for flags that have no default variant.
+///
+/// Parsed from the top-level `defaults` object of a configuration. The value is
+/// chosen by the type of the flag's variants; when no value is configured for that
+/// type, evaluation keeps returning FALLBACK so the caller's code default applies.
+///
+/// # Example
+///
+/// ```json
+/// {
+///   "defaults": {"boolean": false, "string": ""},
+///   "flags": { ... }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FlagSetDefaults {
+    /// Value for flags whose variants are all booleans
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub boolean: Option<bool>,
+    /// Value for flags whose variants are all strings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub string: Option<String>,
+    /// Value for flags whose variants are all numbers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub number: Option<serde_json::Number>,
+    /// Value for flags whose variants are all objects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+impl FlagSetDefaults {
+    /// Returns the configured default for a flag, based on the type of its variants.
+    ///
+    /// Returns `None` if the flag has no variants, its variants have mixed types,
+    /// or no default is configured for that type.
+    pub fn value_for(&self, flag: &FeatureFlag) -> Option<serde_json::Value> {
+        use serde_json::Value;
+
+        let mut values = flag.variants.values();
+        let first = values.next()?;
+        let same_type =
+            |other: &Value| std::mem::discriminant(first) == std::mem::discriminant(other);
+        if !values.all(same_type) {
+            return None;
+        }
+
+        match first {
+            Value::Bool(_) => self.boolean.map(Value::Bool),
+            Value::String(_) => self.string.clone().map(Value::String),
+            Value::Number(_) => self.number.clone().map(Value::Number),
+            Value::Object(_) => self.object.clone().map(Value::Object),
+            _ => None,
+        }
+    }
+}
+
+/// Root keys starting with `$` that carry meaning and are kept by `remove_annotations`.
+const PRESERVED_ROOT_KEYS: [&str; 2] = ["$schema", "$evaluators"];
+
+/// Removes `$`-prefixed annotation keys (such as `$comment`) from a configuration.
+///
+/// Authors annotate configurations with keys that are not part of the schema. They
+/// are dropped from the root (except `$schema` and `$evaluators`), from the `flags`,
+/// `$evaluators` and `defaults` objects, and from each flag definition, so neither
+/// parsing nor strict validation rejects them. Metadata is left untouched, and
+/// targeting rules are never modified.
+pub(crate) fn remove_annotations(config: &mut serde_json::Value) {
+    use serde_json::Value;
+
+    fn remove_from(value: Option<&mut Value>) {
+        if let Some(Value::Object(map)) = value {
+            map.retain(|key, _| !key.starts_with('$'));
+        }
+    }
+
+    let Value::Object(root) = config else {
+        return;
+    };
+    root.retain(|key, _| !key.starts_with('$') || PRESERVED_ROOT_KEYS.contains(&key.as_str()));
+    remove_from(root.get_mut("$evaluators"));
+    remove_from(root.get_mut("defaults"));
+    remove_from(root.get_mut("patterns"));
+    remove_from(root.get_mut("flags"));
+    if let Some(Value::Object(flags)) = root.get_mut("flags") {
+        for flag in flags.values_mut() {
+            remove_from(Some(flag));
+        }
+    }
 }
 
 /// Result of parsing a flagd configuration file.
@@ -179,6 +361,16 @@ pub struct ParsingResult {
 
     /// Optional metadata about the flag set
     pub flag_set_metadata: HashMap<String, serde_json::Value>,
+
+    /// Flag-set-level defaults for flags without a default variant
+    pub flag_set_defaults: FlagSetDefaults,
+
+    /// Named patterns from the top-level `patterns` object, compiled for `matches_named`
+    pub patterns: HashMap<String, Regex>,
+
+    /// Whether the top-level `state` is `DISABLED`, a kill switch that makes every
+    /// flag evaluate as disabled regardless of its own state
+    pub disabled: bool,
 }
 
 impl ParsingResult {
@@ -215,20 +407,24 @@ impl ParsingResult {
     /// ```
     pub fn parse(json_str: &str) -> Result<Self, String> {
         // Parse the JSON string
-        let config: serde_json::Value =
+        let mut config: serde_json::Value =
             serde_json::from_str(json_str).map_err(|e| format!("Failed to parse JSON: {}", e))?;
+        remove_annotations(&mut config);
 
-        // Extract $evaluators if present
+        // Extract $evaluators if present. Ordered maps keep the expansion independent
+        // of hash seeds, so the same config always expands to the same rules.
         let evaluators =
             if let Some(eval_obj) = config.get("$evaluators").and_then(|v| v.as_object()) {
-                let mut map = HashMap::new();
+                let mut map = BTreeMap::new();
                 for (name, rule) in eval_obj {
                     map.insert(name.clone(), rule.clone());
                 }
                 map
             } else {
-                HashMap::new()
+                BTreeMap::new()
             };
+        // Each evaluator is expanded once and reused by every reference to it
+        let mut expanded = BTreeMap::new();
 
         // Extract the flags object
         let flags_obj = config
@@ -237,6 +433,12 @@ impl ParsingResult {
             .as_object()
             .ok_or_else(|| "'flags' must be an object".to_string())?;
 
+        // Compile the named patterns once; targeting may only refer to defined ones
+        let patterns = match config.get("patterns") {
+            Some(patterns) => compile_patterns(patterns)?,
+            None => HashMap::new(),
+        };
+
         // Create a shared DataLogic engine for compiling targeting rules
         let engine = create_evaluator();
 
@@ -247,12 +449,12 @@ impl ParsingResult {
                 .map_err(|e| format!("Failed to parse flag '{}': {}", flag_name, e))?;
             // Set the flag key
             flag.key = Some(flag_name.clone());
+            Self::check_variant_aliases(flag_name, &flag)?;
 
             // Resolve $ref references in targeting rules if evaluators exist
-            if !evaluators.is_empty() && flag.targeting.is_some() {
-                let targeting = flag.targeting.take().unwrap();
+            if let Some(targeting) = flag.targeting.as_ref().filter(|_| !evaluators.is_empty()) {
                 let mut visited = std::collections::HashSet::new();
-                match Self::resolve_refs(&targeting, &evaluators, &mut visited) {
+                match Self::resolve_refs(targeting, &evaluators, &mut expanded, &mut visited) {
                     Ok(resolved) => flag.targeting = Some(resolved),
                     Err(e) => {
                         return Err(format!(
@@ -263,28 +465,38 @@ impl ParsingResult {
                 }
             }
 
-            // Pre-compile targeting rules for fast evaluation
+            if let Some(name) = flag.targeting.as_ref().and_then(|targeting| {
+                named_pattern_refs(targeting)
+                    .into_iter()
+                    .find(|name| !patterns.contains_key(*name))
+            }) {
+                return Err(format!(
+                    "Unknown pattern '{}' in flag '{}'",
+                    name, flag_name
+                ));
+            }
+
+            // Pre-compile targeting rules once, so evaluation never re-parses them.
+            // A rule that does not compile rejects the configuration here instead of
+            // failing every evaluation of the flag.
             if let Some(ref targeting) = flag.targeting {
                 // Only compile non-empty targeting rules
                 if !targeting.as_object().map(|o| o.is_empty()).unwrap_or(false) {
-                    match engine.compile(targeting) {
-                        Ok(compiled) => {
-                            flag.compiled_targeting = Some(compiled);
-                        }
-                        Err(e) => {
-                            // Log warning but don't fail - fall back to runtime compilation
-                            eprintln!(
-                                "Warning: Failed to pre-compile targeting for flag '{}': {}",
-                                flag_name, e
-                            );
-                        }
-                    }
+                    let compiled = engine.compile(targeting).map_err(|e| {
+                        format!(
+                            "Failed to compile targeting for flag '{}': {}",
+                            flag_name, e
+                        )
+                    })?;
+                    flag.compiled_targeting = Some(compiled);
                 }
             }
 
             flags.insert(flag_name.clone(), flag);
         }
 
+        Self::check_prerequisites(&flags)?;
+
         // Extract flag-set metadata from top-level "metadata" object
         let mut flag_set_metadata = HashMap::new();
 
@@ -297,9 +509,34 @@ impl ParsingResult {
             }
         }
 
+        // Extract flag-set-level defaults from top-level "defaults" object
+        let flag_set_defaults = match config.get("defaults") {
+            Some(defaults) => serde_json::from_value(defaults.clone())
+                .map_err(|e| format!("Invalid 'defaults' in configuration: {}", e))?,
+            None => FlagSetDefaults::default(),
+        };
+
+        // Extract the flag-set kill switch from the top-level "state"
+        let disabled = match config.get("state") {
+            None => false,
+            Some(state) => match state.as_str() {
+                Some("ENABLED") => false,
+                Some("DISABLED") => true,
+                _ => {
+                    return Err(format!(
+                        "Invalid flag set 'state' {}: expected \"ENABLED\" or \"DISABLED\"",
+                        state
+                    ))
+                }
+            },
+        };
+
         Ok(ParsingResult {
             flags,
             flag_set_metadata,
+            flag_set_defaults,
+            patterns,
+            disabled,
         })
     }
 
@@ -308,9 +545,93 @@ impl ParsingResult {
         ParsingResult {
             flags: HashMap::new(),
             flag_set_metadata: HashMap::new(),
+            flag_set_defaults: FlagSetDefaults::default(),
+            patterns: HashMap::new(),
+            disabled: false,
         }
     }
 
+    /// Checks that variant aliases refer to existing variants and do not shadow one.
+    fn check_variant_aliases(flag_name: &str, flag: &FeatureFlag) -> Result<(), String> {
+        let mut aliases: Vec<(&String, &String)> = flag.variant_aliases.iter().collect();
+        aliases.sort();
+        for (alias, target) in aliases {
+            if flag.variants.contains_key(alias) {
+                return Err(format!(
+                    "Variant alias '{}' in flag '{}' shadows an existing variant",
+                    alias, flag_name
+                ));
+            }
+            if !flag.variants.contains_key(target) {
+                return Err(format!(
+                    "Variant alias '{}' in flag '{}' refers to unknown variant '{}'",
+                    alias, flag_name, target
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that prerequisites name existing flags and variants and form no cycle.
+    fn check_prerequisites(flags: &HashMap<String, FeatureFlag>) -> Result<(), String> {
+        // Sorted so that the reported error does not depend on hash order
+        let mut keys: Vec<&String> = flags.keys().collect();
+        keys.sort();
+
+        for key in &keys {
+            for prerequisite in &flags[*key].prerequisites {
+                let required = flags.get(&prerequisite.flag).ok_or_else(|| {
+                    format!(
+                        "Unknown prerequisite flag '{}' in flag '{}'",
+                        prerequisite.flag, key
+                    )
+                })?;
+                if !required
+                    .variants
+                    .contains_key(required.resolve_variant_name(&prerequisite.variant))
+                {
+                    return Err(format!(
+                        "Unknown variant '{}' of prerequisite flag '{}' in flag '{}'",
+                        prerequisite.variant, prerequisite.flag, key
+                    ));
+                }
+            }
+        }
+
+        // Depth-first search; a flag reached again while still on the path closes a cycle
+        fn visit<'a>(
+            key: &'a str,
+            flags: &'a HashMap<String, FeatureFlag>,
+            path: &mut Vec<&'a str>,
+            done: &mut std::collections::HashSet<&'a str>,
+        ) -> Result<(), String> {
+            if done.contains(key) {
+                return Ok(());
+            }
+            if let Some(start) = path.iter().position(|k| *k == key) {
+                let mut cycle = path[start..].to_vec();
+                cycle.push(key);
+                return Err(format!(
+                    "Circular prerequisite detected: {}",
+                    cycle.join(" -> ")
+                ));
+            }
+            path.push(key);
+            for prerequisite in &flags[key].prerequisites {
+                visit(&prerequisite.flag, flags, path, done)?;
+            }
+            path.pop();
+            done.insert(key);
+            Ok(())
+        }
+
+        let mut done = std::collections::HashSet::new();
+        for key in keys {
+            visit(key, flags, &mut Vec::new(), &mut done)?;
+        }
+        Ok(())
+    }
+
     /// Resolves $ref references in a JSON value by replacing them with evaluators.
     ///
     /// This function recursively traverses the JSON structure and replaces any
@@ -320,14 +641,16 @@ impl ParsingResult {
     /// # Arguments
     /// * `value` - The JSON value to process (typically a targeting rule)
     /// * `evaluators` - Map of evaluator names to their definitions
+    /// * `expanded` - Evaluators already expanded, reused instead of expanding them again
     /// * `visited` - Set of evaluator names already being resolved (for circular reference detection)
     ///
     /// # Returns
     /// * `Ok(Value)` - The JSON value with all $refs resolved
     /// * `Err(String)` - Error if a $ref points to a non-existent evaluator or circular reference detected
-    fn resolve_refs(
+    pub(crate) fn resolve_refs(
         value: &serde_json::Value,
-        evaluators: &HashMap<String, serde_json::Value>,
+        evaluators: &BTreeMap<String, serde_json::Value>,
+        expanded: &mut BTreeMap<String, serde_json::Value>,
         visited: &mut std::collections::HashSet<String>,
     ) -> Result<serde_json::Value, String> {
         use serde_json::{Map, Value};
@@ -337,6 +660,11 @@ impl ParsingResult {
                 // Check if this is a $ref object
                 if obj.len() == 1 && obj.contains_key("$ref") {
                     if let Some(Value::String(ref_name)) = obj.get("$ref") {
+                        // An evaluator expanded before has no cycles, so reuse it as is
+                        if let Some(resolved) = expanded.get(ref_name) {
+                            return Ok(resolved.clone());
+                        }
+
                         // Check for circular references
                         if visited.contains(ref_name) {
                             return Err(format!(
@@ -352,8 +680,10 @@ impl ParsingResult {
 
                         // Add to visited set and recurse
                         visited.insert(ref_name.clone());
-                        let resolved = Self::resolve_refs(evaluator, evaluators, visited)?;
+                        let resolved =
+                            Self::resolve_refs(evaluator, evaluators, expanded, visited)?;
                         visited.remove(ref_name);
+                        expanded.insert(ref_name.clone(), resolved.clone());
 
                         return Ok(resolved);
                     }
@@ -362,7 +692,10 @@ impl ParsingResult {
                 // Not a $ref, recursively resolve any nested $refs
                 let mut resolved_obj = Map::new();
                 for (key, val) in obj {
-                    resolved_obj.insert(key.clone(), Self::resolve_refs(val, evaluators, visited)?);
+                    resolved_obj.insert(
+                        key.clone(),
+                        Self::resolve_refs(val, evaluators, expanded, visited)?,
+                    );
                 }
                 Ok(Value::Object(resolved_obj))
             }
@@ -370,7 +703,7 @@ impl ParsingResult {
                 // Recursively resolve $refs in array elements
                 let mut resolved_arr = Vec::new();
                 for item in arr {
-                    resolved_arr.push(Self::resolve_refs(item, evaluators, visited)?);
+                    resolved_arr.push(Self::resolve_refs(item, evaluators, expanded, visited)?);
                 }
                 Ok(Value::Array(resolved_arr))
             }
@@ -448,6 +781,36 @@ mod tests {
         assert!(targeting_str.contains("yellow"));
     }
 
+    #[test]
+    fn test_targeting_is_precompiled() {
+        let config = r#"{
+            "flags": {
+                "targeted": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"==": [{"var": "color"}, "yellow"]}, "on", "off"]}
+                },
+                "emptyTargeting": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {}
+                },
+                "static": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off"
+                }
+            }
+        }"#;
+
+        let result = ParsingResult::parse(config).unwrap();
+        assert!(result.flags["targeted"].compiled_targeting.is_some());
+        assert!(result.flags["emptyTargeting"].compiled_targeting.is_none());
+        assert!(result.flags["static"].compiled_targeting.is_none());
+    }
+
     #[test]
     fn test_flag_with_metadata() {
         let config = r#"{
@@ -500,6 +863,83 @@ mod tests {
         assert!(result.flags.contains_key("flag2"));
     }
 
+    #[test]
+    fn test_flag_with_variant_metadata() {
+        let flag_json = json!({
+            "state": "ENABLED",
+            "defaultVariant": "on",
+            "variants": {"on": true, "off": false},
+            "variantMetadata": {"on": {"rollout": "full"}}
+        });
+
+        let flag: FeatureFlag = serde_json::from_value(flag_json).unwrap();
+        assert_eq!(
+            flag.variant_metadata["on"].get("rollout"),
+            Some(&json!("full"))
+        );
+        assert!(!flag.variant_metadata.contains_key("off"));
+
+        let mut changed = flag.clone();
+        changed
+            .variant_metadata
+            .insert("off".to_string(), HashMap::new());
+        assert!(flag.is_different_from(&changed));
+    }
+
+    #[test]
+    fn test_flag_set_defaults_by_variant_type() {
+        let config = r#"{
+            "defaults": {"boolean": true, "object": {"theme": "light"}},
+            "flags": {
+                "boolFlag": {"state": "ENABLED", "variants": {"on": true, "off": false}},
+                "objectFlag": {"state": "ENABLED", "variants": {"a": {"theme": "dark"}}},
+                "mixedFlag": {"state": "ENABLED", "variants": {"a": true, "b": "yes"}},
+                "emptyFlag": {"state": "ENABLED", "variants": {}}
+            }
+        }"#;
+
+        let result = ParsingResult::parse(config).unwrap();
+        let defaults = &result.flag_set_defaults;
+        assert_eq!(
+            defaults.value_for(&result.flags["boolFlag"]),
+            Some(json!(true))
+        );
+        assert_eq!(
+            defaults.value_for(&result.flags["objectFlag"]),
+            Some(json!({"theme": "light"}))
+        );
+        assert_eq!(defaults.value_for(&result.flags["mixedFlag"]), None);
+        assert_eq!(defaults.value_for(&result.flags["emptyFlag"]), None);
+
+        let unknown = config.replace(r#""boolean": true"#, r#""integer": 1"#);
+        assert!(ParsingResult::parse(&unknown).is_err());
+    }
+
+    #[test]
+    fn test_flag_type_inference() {
+        let config = r#"{
+            "flags": {
+                "boolFlag": {"state": "ENABLED", "variants": {"on": true, "off": false}},
+                "intFlag": {"state": "ENABLED", "variants": {"a": 1, "b": 2}},
+                "floatFlag": {"state": "ENABLED", "variants": {"a": 1, "b": 2.5}},
+                "objectFlag": {"state": "ENABLED", "variants": {"a": {"theme": "dark"}}},
+                "mixedFlag": {"state": "ENABLED", "variants": {"a": true, "b": "yes"}},
+                "arrayFlag": {"state": "ENABLED", "variants": {"a": [1, 2]}},
+                "emptyFlag": {"state": "ENABLED", "variants": {}}
+            }
+        }"#;
+
+        let result = ParsingResult::parse(config).unwrap();
+        let flag_type = |key: &str| result.flags[key].flag_type();
+        assert_eq!(flag_type("boolFlag"), FlagType::Boolean);
+        assert_eq!(flag_type("intFlag"), FlagType::Integer);
+        assert_eq!(flag_type("floatFlag"), FlagType::Float);
+        assert_eq!(flag_type("objectFlag"), FlagType::Object);
+        assert_eq!(flag_type("mixedFlag"), FlagType::Mixed);
+        assert_eq!(flag_type("arrayFlag"), FlagType::Mixed);
+        assert_eq!(flag_type("emptyFlag"), FlagType::Mixed);
+    }
+
     #[test]
     fn test_flag_set_metadata() {
         let config = r#"{
@@ -595,6 +1035,9 @@ mod tests {
             targeting: Some(json!({"==": [1, 1]})),
             compiled_targeting: None,
             metadata: HashMap::new(),
+            variant_metadata: HashMap::new(),
+            prerequisites: Vec::new(),
+            variant_aliases: HashMap::new(),
         };
 
         let targeting = flag.get_targeting();
@@ -612,6 +1055,9 @@ mod tests {
             targeting: None,
             compiled_targeting: None,
             metadata: HashMap::new(),
+            variant_metadata: HashMap::new(),
+            prerequisites: Vec::new(),
+            variant_aliases: HashMap::new(),
         };
 
         let targeting = flag.get_targeting();
@@ -663,6 +1109,9 @@ mod tests {
             targeting: None,
             compiled_targeting: None,
             metadata: HashMap::new(),
+            variant_metadata: HashMap::new(),
+            prerequisites: Vec::new(),
+            variant_aliases: HashMap::new(),
         };
 
         let flag2 = FeatureFlag {
@@ -673,6 +1122,9 @@ mod tests {
             targeting: None,
             compiled_targeting: None,
             metadata: HashMap::new(),
+            variant_metadata: HashMap::new(),
+            prerequisites: Vec::new(),
+            variant_aliases: HashMap::new(),
         };
 
         assert_eq!(flag1, flag2);
@@ -692,6 +1144,9 @@ mod tests {
             targeting: Some(json!({"==": [1, 1]})),
             compiled_targeting: None,
             metadata: HashMap::new(),
+            variant_metadata: HashMap::new(),
+            prerequisites: Vec::new(),
+            variant_aliases: HashMap::new(),
         };
 
         let serialized = serde_json::to_string(&flag).unwrap();
@@ -898,6 +1353,79 @@ mod tests {
         assert!(err.contains("Circular reference"));
     }
 
+    #[test]
+    fn test_prerequisites_must_exist() {
+        let config = |prerequisite: &str| {
+            format!(
+                r#"{{
+                    "flags": {{
+                        "base": {{
+                            "state": "ENABLED",
+                            "variants": {{"on": true, "off": false}},
+                            "defaultVariant": "on"
+                        }},
+                        "dependent": {{
+                            "state": "ENABLED",
+                            "variants": {{"on": true, "off": false}},
+                            "defaultVariant": "on",
+                            "prerequisites": [{}]
+                        }}
+                    }}
+                }}"#,
+                prerequisite
+            )
+        };
+
+        let result = ParsingResult::parse(&config(r#"{"flag": "base", "variant": "on"}"#)).unwrap();
+        assert_eq!(
+            result.flags["dependent"].prerequisites,
+            vec![Prerequisite {
+                flag: "base".to_string(),
+                variant: "on".to_string()
+            }]
+        );
+
+        let err =
+            ParsingResult::parse(&config(r#"{"flag": "missing", "variant": "on"}"#)).unwrap_err();
+        assert_eq!(
+            err,
+            "Unknown prerequisite flag 'missing' in flag 'dependent'"
+        );
+
+        let err =
+            ParsingResult::parse(&config(r#"{"flag": "base", "variant": "maybe"}"#)).unwrap_err();
+        assert_eq!(
+            err,
+            "Unknown variant 'maybe' of prerequisite flag 'base' in flag 'dependent'"
+        );
+    }
+
+    #[test]
+    fn test_flag_set_state() {
+        let config = |state: &str| {
+            format!(
+                r#"{{{}"flags": {{"myFlag": {{"state": "ENABLED", "variants": {{"on": true}}, "defaultVariant": "on"}}}}}}"#,
+                state
+            )
+        };
+
+        assert!(!ParsingResult::parse(&config("")).unwrap().disabled);
+        assert!(
+            !ParsingResult::parse(&config(r#""state": "ENABLED","#))
+                .unwrap()
+                .disabled
+        );
+        assert!(
+            ParsingResult::parse(&config(r#""state": "DISABLED","#))
+                .unwrap()
+                .disabled
+        );
+        assert_eq!(
+            ParsingResult::parse(&config(r#""state": true,"#)).unwrap_err(),
+            r#"Invalid flag set 'state' true: expected "ENABLED" or "DISABLED""#
+        );
+    }
+
     #[test]
     fn test_evaluators_with_multiple_flags() {
         let config = r#"{
@@ -944,6 +1472,57 @@ mod tests {
         assert!(premium_targeting.contains("=="));
     }
 
+    #[test]
+    fn test_evaluators_expand_reproducibly() {
+        let config = r#"{
+            "$evaluators": {
+                "isStaff": {"ends_with": [{"var": "email"}, "@example.com"]},
+                "isBeta": {"and": [{"$ref": "isStaff"}, {"==": [{"var": "beta"}, true]}]},
+                "isEligible": {"or": [{"$ref": "isBeta"}, {"$ref": "isStaff"}]}
+            },
+            "flags": {
+                "first": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"$ref": "isEligible"}, "on", "off"]}
+                },
+                "second": {
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVariant": "off",
+                    "targeting": {"if": [{"$ref": "isStaff"}, "on", {"$ref": "isBeta"}]}
+                }
+            }
+        }"#;
+
+        let expanded_targeting = |result: &ParsingResult| {
+            let mut keys: Vec<&String> = result.flags.keys().collect();
+            keys.sort();
+            keys.iter()
+                .map(|key| {
+                    serde_json::to_vec(result.flags[*key].targeting.as_ref().unwrap()).unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let first = ParsingResult::parse(config).unwrap();
+        let second = ParsingResult::parse(config).unwrap();
+        assert_eq!(expanded_targeting(&first), expanded_targeting(&second));
+
+        // Evaluators reused by other evaluators and by several flags expand identically
+        let is_staff = json!({"ends_with": [{"var": "email"}, "@example.com"]});
+        let is_beta = json!({"and": [is_staff, {"==": [{"var": "beta"}, true]}]});
+        assert_eq!(
+            first.flags["first"].targeting,
+            Some(json!({"if": [{"or": [is_beta, is_staff]}, "on", "off"]}))
+        );
+        assert_eq!(
+            first.flags["second"].targeting,
+            Some(json!({"if": [is_staff, "on", is_beta]}))
+        );
+    }
+
     #[test]
     fn test_flags_without_evaluators() {
         // Flags should work fine without $evaluators
@@ -5,11 +5,33 @@
 
 mod feature_flag;
 
-pub use feature_flag::{FeatureFlag, ParsingResult};
+pub(crate) use feature_flag::remove_annotations;
+pub use feature_flag::{FeatureFlag, FlagSetDefaults, ParsingResult, Prerequisite};
 
-use crate::types::EvaluationResult;
+use crate::operators::named_pattern_refs;
+use crate::types::{serialize_sorted_option, EvaluationResult, FlagType};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+/// Machine-readable reason for a failed state update.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum UpdateErrorCode {
+    /// The configuration is not valid JSON
+    InvalidJson,
+    /// The configuration does not conform to the flagd schema (strict mode only)
+    SchemaInvalid,
+    /// The configuration is well-formed but cannot be used (e.g. a `$ref` to an
+    /// unknown evaluator or a missing `flags` object)
+    SemanticInvalid,
+    /// The configuration exceeds the configured maximum size
+    ConfigTooLarge,
+    /// The input could not be read (e.g. invalid UTF-8)
+    InvalidInput,
+    /// An argument to the call was out of range (e.g. an unknown validation mode)
+    InvalidArgument,
+}
 
 /// Response from updating flag state indicating which flags have changed.
 ///
@@ -24,16 +46,39 @@ pub struct UpdateStateResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
 
+    /// Machine-readable error code if the update failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<UpdateErrorCode>,
+
     /// List of flag keys that were changed (added, removed, or mutated)
+    ///
+    /// Replaced by `changed_flags_bitmap` and `removed_flags` when the evaluator
+    /// reports changes as a bitmap (see `FlagEvaluator::set_changed_flags_bitmap`).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub changed_flags: Option<Vec<String>>,
 
+    /// The added and mutated flags as a bitmap over `flag_indices`, in hex.
+    ///
+    /// Bit `i` is set if the flag with index `i` changed. Bits are numbered from the
+    /// least significant bit of the first byte, and each byte is written as two
+    /// lowercase hex digits, so flags 0 and 9 of ten give `"0102"`. Indices follow
+    /// the flag keys sorted ascending, which is how `flag_indices` assigns them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changed_flags_bitmap: Option<String>,
+
+    /// Keys of removed flags, which have no index; only reported with the bitmap
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub removed_flags: Option<Vec<String>>,
+
     /// Pre-evaluated results for static and disabled flags.
     ///
     /// These flags don't require targeting evaluation, so their results are
     /// computed during `update_state()` to allow host-side caching and avoid
     /// WASM boundary overhead on every evaluation call.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_sorted_option"
+    )]
     pub pre_evaluated: Option<HashMap<String, EvaluationResult>>,
 
     /// Per-flag required context keys for host-side filtering.
@@ -41,13 +86,478 @@ pub struct UpdateStateResponse {
     /// When present, the host should only serialize the listed context keys
     /// (plus `$flagd.*` enrichment and `targetingKey`) before calling evaluate.
     /// `None` for a flag means "send all context" (e.g., the rule uses `{"var": ""}`).
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_sorted_option"
+    )]
     pub required_context_keys: Option<HashMap<String, Vec<String>>>,
 
     /// Flag key to numeric index mapping for `evaluate_by_index`.
     ///
     /// Allows the host to call `evaluate_by_index(index, ...)` instead of
     /// passing flag key strings, avoiding string serialization overhead.
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_sorted_option"
+    )]
     pub flag_indices: Option<HashMap<String, u32>>,
+
+    /// `false` if the configuration was identical to the stored one and every other
+    /// field was omitted (see `FlagEvaluator::set_compact_unchanged_updates`).
+    ///
+    /// Absent from full responses.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changed: Option<bool>,
+}
+
+impl UpdateStateResponse {
+    /// Creates a failed update response with the given error code and message.
+    pub fn failure(error_code: UpdateErrorCode, error: impl Into<String>) -> Self {
+        Self {
+            success: false,
+            error: Some(error.into()),
+            error_code: Some(error_code),
+            changed_flags: None,
+            changed_flags_bitmap: None,
+            removed_flags: None,
+            pre_evaluated: None,
+            required_context_keys: None,
+            flag_indices: None,
+            changed: None,
+        }
+    }
+
+    /// Creates the compact response for an update with an unchanged configuration.
+    ///
+    /// Serializes as `{"success":true,"changed":false}`; the host keeps the
+    /// pre-evaluated results, required context keys and flag indices it has.
+    pub fn unchanged() -> Self {
+        Self {
+            success: true,
+            error: None,
+            error_code: None,
+            changed_flags: None,
+            changed_flags_bitmap: None,
+            removed_flags: None,
+            pre_evaluated: None,
+            required_context_keys: None,
+            flag_indices: None,
+            changed: Some(false),
+        }
+    }
+
+    /// Returns the changed flag keys, sorted, whichever way the response reports them.
+    ///
+    /// Decodes `changed_flags_bitmap` against `flag_indices` and adds
+    /// `removed_flags` when the response carries a bitmap; otherwise returns
+    /// `changed_flags`. Empty for failed and compact unchanged responses.
+    pub fn changed_flag_keys(&self) -> Vec<String> {
+        let Some(bitmap) = &self.changed_flags_bitmap else {
+            return self.changed_flags.clone().unwrap_or_default();
+        };
+
+        let changed = decode_bitmap(bitmap);
+        let mut keys: Vec<String> = self
+            .flag_indices
+            .iter()
+            .flatten()
+            .filter(|(_, index)| changed.contains(*index))
+            .map(|(key, _)| key.clone())
+            .chain(self.removed_flags.iter().flatten().cloned())
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    /// Encodes the response in the compact binary format returned by `update_state_bin`.
+    ///
+    /// All integers are little-endian `u32`, and a string is its UTF-8 byte length
+    /// followed by the bytes:
+    ///
+    /// - 1 byte: `1` on success, `0` on failure
+    /// - On success: the number of changed flags, then each changed flag key as a string
+    /// - On failure: the error code (e.g. `SCHEMA_INVALID`) as a string, then the error
+    ///   message as a string
+    ///
+    /// Only the change set is encoded; `preEvaluated`, `requiredContextKeys` and
+    /// `flagIndices` are only available in the JSON response.
+    pub fn to_binary(&self) -> Vec<u8> {
+        fn put_str(buf: &mut Vec<u8>, s: &str) {
+            buf.extend_from_slice(&(s.len() as u32).to_le_bytes());
+            buf.extend_from_slice(s.as_bytes());
+        }
+
+        let mut buf = Vec::new();
+        if self.success {
+            let changed = self.changed_flag_keys();
+            buf.push(1);
+            buf.extend_from_slice(&(changed.len() as u32).to_le_bytes());
+            for key in &changed {
+                put_str(&mut buf, key);
+            }
+        } else {
+            let code = self
+                .error_code
+                .and_then(|code| serde_json::to_value(code).ok())
+                .and_then(|code| code.as_str().map(str::to_string))
+                .unwrap_or_default();
+            buf.push(0);
+            put_str(&mut buf, &code);
+            put_str(&mut buf, self.error.as_deref().unwrap_or_default());
+        }
+        buf
+    }
+}
+
+/// Encodes flag indices as the hex bitmap of `UpdateStateResponse::changed_flags_bitmap`.
+///
+/// `len` is the number of indexed flags; the bitmap has one bit per flag, rounded
+/// up to whole bytes.
+pub(crate) fn encode_bitmap(indices: impl IntoIterator<Item = u32>, len: usize) -> String {
+    let mut bytes = vec![0u8; len.div_ceil(8)];
+    for index in indices {
+        if let Some(byte) = bytes.get_mut(index as usize / 8) {
+            *byte |= 1 << (index % 8);
+        }
+    }
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Returns the indices set in a hex bitmap; malformed bytes count as zero.
+fn decode_bitmap(bitmap: &str) -> Vec<u32> {
+    bitmap
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .unwrap_or(0)
+        })
+        .enumerate()
+        .flat_map(|(byte_index, byte)| {
+            (0..8)
+                .filter(move |bit| byte & (1 << bit) != 0)
+                .map(move |bit| (byte_index * 8 + bit) as u32)
+        })
+        .collect()
+}
+
+/// Type of a provider event derived from a state update.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ProviderEventType {
+    /// The flag configuration changed
+    ProviderConfigurationChanged,
+}
+
+/// A provider event ready to be emitted by the host.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ProviderEvent {
+    /// The event type
+    #[serde(rename = "type")]
+    pub event_type: ProviderEventType,
+
+    /// Keys of the flags the event applies to
+    pub flags: Vec<String>,
+}
+
+/// Response from updating flag state, together with the provider events to emit.
+///
+/// Contains every field of `UpdateStateResponse` plus an `events` array, so hosts
+/// don't need to derive events from `changed_flags` themselves.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateStateWithEventsResponse {
+    /// The plain update response
+    #[serde(flatten)]
+    pub response: UpdateStateResponse,
+
+    /// Events to emit, empty if the update failed or no flags changed
+    pub events: Vec<ProviderEvent>,
+}
+
+impl From<UpdateStateResponse> for UpdateStateWithEventsResponse {
+    fn from(response: UpdateStateResponse) -> Self {
+        let flags = response.changed_flag_keys();
+        let events = if response.success && !flags.is_empty() {
+            vec![ProviderEvent {
+                event_type: ProviderEventType::ProviderConfigurationChanged,
+                flags,
+            }]
+        } else {
+            Vec::new()
+        };
+        Self { response, events }
+    }
+}
+
+/// A summary of a stored flag's configuration, without evaluating it.
+///
+/// Returned by `FlagEvaluator::inventory` for admin UIs that list all flags.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FlagSummary {
+    /// The flag key
+    pub key: String,
+
+    /// The flag state ("ENABLED" or "DISABLED")
+    pub state: String,
+
+    /// The value type inferred from the flag's variants
+    #[serde(rename = "type")]
+    pub flag_type: FlagType,
+
+    /// The variant names, sorted
+    pub variants: Vec<String>,
+
+    /// Whether the flag has a non-empty targeting rule
+    pub has_targeting: bool,
+
+    /// The default variant, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_variant: Option<String>,
+}
+
+/// A stored flag's key, state, and default variant type.
+///
+/// Returned by `FlagEvaluator::list_flags` for hosts that enumerate the loaded
+/// flags, for example to prefetch them.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FlagListing {
+    /// The flag key
+    pub key: String,
+
+    /// The flag state ("ENABLED" or "DISABLED")
+    pub state: String,
+
+    /// The default variant, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_variant: Option<String>,
+
+    /// The JSON type of the default variant's value ("boolean", "string",
+    /// "integer", "float", "object", ...), if the flag has a default variant
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub flag_type: Option<String>,
+}
+
+/// The flags that differ between two parsed flag configurations.
+///
+/// Computed by `diff_parsing_results`. Each list is sorted by flag key.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct FlagSetDiff {
+    /// Flags only present in the new configuration
+    pub added: Vec<String>,
+
+    /// Flags only present in the old configuration
+    pub removed: Vec<String>,
+
+    /// Flags present in both whose definition changed
+    pub mutated: Vec<String>,
+}
+
+impl FlagSetDiff {
+    /// Returns all added, removed, and mutated flag keys, sorted.
+    ///
+    /// This is the `changedFlags` list reported by `update_state`.
+    pub fn changed_flags(&self) -> Vec<String> {
+        let mut changed: Vec<String> = self
+            .added
+            .iter()
+            .chain(&self.removed)
+            .chain(&self.mutated)
+            .cloned()
+            .collect();
+        changed.sort();
+        changed
+    }
+
+    /// Returns whether no flag changed.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.mutated.is_empty()
+    }
+}
+
+/// Computes which flags were added, removed, or mutated between two configurations.
+///
+/// With no old configuration, every flag in the new one is added. A flag is mutated
+/// when `FeatureFlag::is_different_from` reports a change.
+///
+/// # Example
+///
+/// ```
+/// use flagd_evaluator::model::{diff_parsing_results, ParsingResult};
+///
+/// let old = ParsingResult::parse(r#"{"flags": {"a": {"state": "ENABLED", "variants": {"on": true}, "defaultVariant": "on"}}}"#).unwrap();
+/// let new = ParsingResult::parse(r#"{"flags": {"b": {"state": "ENABLED", "variants": {"on": true}, "defaultVariant": "on"}}}"#).unwrap();
+///
+/// let diff = diff_parsing_results(Some(&old), &new);
+/// assert_eq!(diff.added, vec!["b"]);
+/// assert_eq!(diff.removed, vec!["a"]);
+/// assert!(diff.mutated.is_empty());
+/// ```
+pub fn diff_parsing_results(old: Option<&ParsingResult>, new: &ParsingResult) -> FlagSetDiff {
+    let mut diff = FlagSetDiff::default();
+
+    match old {
+        None => {
+            // No previous state, all flags are new
+            diff.added.extend(new.flags.keys().cloned());
+        }
+        Some(old) => {
+            // Check for added and mutated flags
+            for key in new.flags.keys() {
+                match old.flags.get(key) {
+                    None => diff.added.push(key.clone()),
+                    Some(_) => {
+                        if resolves_differently(key, old, new) {
+                            diff.mutated.push(key.clone());
+                        }
+                    }
+                }
+            }
+
+            // Check for removed flags
+            for key in old.flags.keys() {
+                if !new.flags.contains_key(key) {
+                    diff.removed.push(key.clone());
+                }
+            }
+        }
+    }
+
+    diff.added.sort();
+    diff.removed.sort();
+    diff.mutated.sort();
+    diff
+}
+
+/// Whether a flag present in the new state may evaluate differently than in the old one.
+///
+/// Besides its own definition, a flag depends on the flag-set kill switch, on the
+/// named patterns it refers to and on its prerequisite flags, recursively. Prerequisites of the new state are free of
+/// cycles, so the recursion ends.
+fn resolves_differently(key: &str, old: &ParsingResult, new: &ParsingResult) -> bool {
+    let (Some(old_flag), Some(new_flag)) = (old.flags.get(key), new.flags.get(key)) else {
+        return true;
+    };
+    old.disabled != new.disabled
+        || new_flag.is_different_from(old_flag)
+        || uses_changed_pattern(new_flag, old, new)
+        || new_flag
+            .prerequisites
+            .iter()
+            .any(|prerequisite| resolves_differently(&prerequisite.flag, old, new))
+}
+
+/// Whether a flag refers to a named pattern that differs between two states.
+///
+/// Patterns are referenced by name, so a flag whose pattern changed has identical
+/// targeting but matches differently.
+fn uses_changed_pattern(flag: &FeatureFlag, old: &ParsingResult, new: &ParsingResult) -> bool {
+    flag.targeting.as_ref().is_some_and(|targeting| {
+        named_pattern_refs(targeting).into_iter().any(|name| {
+            old.patterns.get(name).map(Regex::as_str) != new.patterns.get(name).map(Regex::as_str)
+        })
+    })
+}
+
+/// The flags changed by one state update, recorded for auditing config churn.
+///
+/// Returned by `FlagEvaluator::change_history`. Each list is sorted by flag key.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeRecord {
+    /// The state version produced by the update
+    pub version: u64,
+
+    /// Unix timestamp in seconds of the update
+    pub timestamp: u64,
+
+    /// Flags that did not exist before the update
+    pub added: Vec<String>,
+
+    /// Flags that no longer exist after the update
+    pub removed: Vec<String>,
+
+    /// Flags whose definition changed
+    pub mutated: Vec<String>,
+}
+
+/// The health of an evaluator, for host liveness and readiness checks.
+///
+/// Returned by `FlagEvaluator::health_check`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthStatus {
+    /// Whether a flag configuration is loaded
+    pub state_loaded: bool,
+
+    /// The current state version
+    pub state_version: u64,
+
+    /// The number of stored flags
+    pub flag_count: usize,
+
+    /// Whether the flag-set kill switch (top-level `"state": "DISABLED"`) is on
+    pub flag_set_disabled: bool,
+
+    /// Whether the current time is available for `$flagd.timestamp`
+    pub time_available: bool,
+
+    /// Conditions that degrade evaluation without failing it
+    pub warnings: Vec<String>,
+}
+
+/// How evaluation decisions would change under a candidate configuration.
+///
+/// Returned by `FlagEvaluator::shadow_eval`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ShadowReport {
+    /// The number of contexts evaluated against both configurations
+    pub contexts_evaluated: usize,
+
+    /// Flags with at least one changed decision, keyed by flag key
+    pub flags: BTreeMap<String, ShadowFlagDiff>,
+}
+
+impl ShadowReport {
+    /// Returns whether no decision would change.
+    pub fn is_empty(&self) -> bool {
+        self.flags.is_empty()
+    }
+}
+
+/// The changed decisions of one flag in a `ShadowReport`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ShadowFlagDiff {
+    /// The number of contexts whose decision changed
+    pub changed: usize,
+
+    /// The indices of those contexts in the evaluated list, ascending
+    pub changed_contexts: Vec<usize>,
+}
+
+impl FlagSummary {
+    /// Summarizes a flag stored under `key`.
+    pub fn new(key: &str, flag: &FeatureFlag) -> Self {
+        let mut variants: Vec<String> = flag.variants.keys().cloned().collect();
+        variants.sort();
+        let has_targeting = match &flag.targeting {
+            None => false,
+            Some(serde_json::Value::Object(map)) => !map.is_empty(),
+            Some(_) => true,
+        };
+
+        Self {
+            key: key.to_string(),
+            state: flag.state.clone(),
+            flag_type: flag.flag_type(),
+            variants,
+            has_targeting,
+            default_variant: flag.default_variant.clone(),
+        }
+    }
 }
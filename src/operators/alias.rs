@@ -0,0 +1,106 @@
+//! Alternative spellings of operator names.
+//!
+//! flagd-compatible configurations do not always agree on operator names: some
+//! write `semver` or `startsWith` where flagd uses `sem_ver` and `starts_with`.
+//! An alias forwards to its canonical operator, so both spellings evaluate alike.
+//! `FlagEvaluator` goes further and rewrites aliases in stored configurations to
+//! their canonical names before validation, so aliased rules pass the schema.
+
+use datalogic_rs::{ContextStack, Evaluator, Operator};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+use super::common::OperatorResult;
+
+/// Aliases registered by default, as `(alias, canonical operator)` pairs.
+pub const DEFAULT_OPERATOR_ALIASES: [(&str, &str); 3] = [
+    ("semver", "sem_ver"),
+    ("startsWith", "starts_with"),
+    ("endsWith", "ends_with"),
+];
+
+/// Custom operator evaluating its arguments with another operator.
+pub struct AliasOperator {
+    canonical: String,
+}
+
+impl AliasOperator {
+    /// Creates an alias of the operator registered as `canonical`.
+    pub fn new(canonical: impl Into<String>) -> Self {
+        Self {
+            canonical: canonical.into(),
+        }
+    }
+}
+
+impl Operator for AliasOperator {
+    fn evaluate(
+        &self,
+        args: &[Value],
+        context: &mut ContextStack,
+        evaluator: &dyn Evaluator,
+    ) -> OperatorResult<Value> {
+        let mut rule = Map::new();
+        rule.insert(self.canonical.clone(), Value::Array(args.to_vec()));
+        evaluator.evaluate(&Value::Object(rule), context)
+    }
+}
+
+/// Rewrites a targeting rule so that aliased operators use their canonical names.
+pub(crate) fn resolve_operator_aliases(rule: &Value, aliases: &BTreeMap<String, String>) -> Value {
+    match rule {
+        Value::Object(obj) => obj
+            .iter()
+            .map(|(key, value)| {
+                let key = match aliases.get(key) {
+                    Some(canonical) if obj.len() == 1 => canonical.clone(),
+                    _ => key.clone(),
+                };
+                (key, resolve_operator_aliases(value, aliases))
+            })
+            .collect::<Map<String, Value>>()
+            .into(),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| resolve_operator_aliases(item, aliases))
+            .collect(),
+        _ => rule.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_resolve_operator_aliases() {
+        let aliases: BTreeMap<String, String> = DEFAULT_OPERATOR_ALIASES
+            .iter()
+            .map(|(alias, canonical)| (alias.to_string(), canonical.to_string()))
+            .collect();
+        let rule = json!({
+            "if": [
+                {"and": [
+                    {"semver": [{"var": "version"}, ">=", "2.0.0"]},
+                    {"startsWith": [{"var": "email"}, "admin"]}
+                ]},
+                {"semver": "literal", "startsWith": "not an operator"},
+                "off"
+            ]
+        });
+        assert_eq!(
+            resolve_operator_aliases(&rule, &aliases),
+            json!({
+                "if": [
+                    {"and": [
+                        {"sem_ver": [{"var": "version"}, ">=", "2.0.0"]},
+                        {"starts_with": [{"var": "email"}, "admin"]}
+                    ]},
+                    {"semver": "literal", "startsWith": "not an operator"},
+                    "off"
+                ]
+            })
+        );
+    }
+}
@@ -0,0 +1,133 @@
+//! Comparisons without numeric string coercion.
+//!
+//! JSON Logic's comparison operators convert a numeric string to a number when it
+//! is compared with one, so `{"==": ["18", 18]}` and `{"<": ["9", 10]}` are true.
+//! flagd behaves the same way. When coercion is disabled, targeting rules are
+//! rewritten to use the operators in this module, under which a string never
+//! equals or orders against a number.
+
+use datalogic_rs::{ContextStack, Evaluator, Operator};
+use serde_json::{Map, Value};
+
+use super::common::OperatorResult;
+
+/// Comparison operators that coerce numeric strings.
+pub(crate) const COERCING_COMPARISONS: [&str; 6] = ["==", "!=", "<", "<=", ">", ">="];
+
+/// Name under which the non-coercing variant of a comparison is registered.
+pub(crate) fn uncoerced_name(operator: &str) -> String {
+    format!("uncoerced:{}", operator)
+}
+
+/// Custom operator performing a comparison without numeric string coercion.
+///
+/// If the operands include both a string and a number, `!=` is true and every
+/// other comparison is false. Otherwise the built-in comparison decides, so
+/// numbers compare numerically and strings lexicographically as usual.
+pub struct UncoercedComparisonOperator {
+    operator: &'static str,
+}
+
+impl UncoercedComparisonOperator {
+    /// Creates the non-coercing variant of a built-in comparison such as `"<"`.
+    pub fn new(operator: &'static str) -> Self {
+        Self { operator }
+    }
+}
+
+impl Operator for UncoercedComparisonOperator {
+    fn evaluate(
+        &self,
+        args: &[Value],
+        context: &mut ContextStack,
+        evaluator: &dyn Evaluator,
+    ) -> OperatorResult<Value> {
+        let values = args
+            .iter()
+            .map(|arg| evaluator.evaluate(arg, context))
+            .collect::<OperatorResult<Vec<Value>>>()?;
+
+        if values.iter().any(Value::is_string) && values.iter().any(Value::is_number) {
+            return Ok(Value::Bool(self.operator == "!="));
+        }
+
+        // Primitive operands evaluate to themselves, so they can be passed to the
+        // built-in comparison as is; anything else is evaluated again from the rule
+        let operands = if values.iter().all(|v| !v.is_object() && !v.is_array()) {
+            Value::Array(values)
+        } else {
+            Value::Array(args.to_vec())
+        };
+        let mut rule = Map::new();
+        rule.insert(self.operator.to_string(), operands);
+        evaluator.evaluate(&Value::Object(rule), context)
+    }
+}
+
+/// Rewrites a targeting rule so that its comparisons do not coerce numeric strings.
+pub(crate) fn without_numeric_coercion(rule: &Value) -> Value {
+    match rule {
+        Value::Object(obj) => obj
+            .iter()
+            .map(|(key, value)| {
+                let key = if obj.len() == 1 && COERCING_COMPARISONS.contains(&key.as_str()) {
+                    uncoerced_name(key)
+                } else {
+                    key.clone()
+                };
+                (key, without_numeric_coercion(value))
+            })
+            .collect::<Map<String, Value>>()
+            .into(),
+        Value::Array(items) => items.iter().map(without_numeric_coercion).collect(),
+        _ => rule.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_without_numeric_coercion_rewrites_comparisons() {
+        let rule = json!({
+            "if": [
+                {"and": [{">=": [{"var": "age"}, 18]}, {"!=": [{"var": "tier"}, "free"]}]},
+                "on",
+                "off"
+            ]
+        });
+        assert_eq!(
+            without_numeric_coercion(&rule),
+            json!({
+                "if": [
+                    {"and": [
+                        {"uncoerced:>=": [{"var": "age"}, 18]},
+                        {"uncoerced:!=": [{"var": "tier"}, "free"]}
+                    ]},
+                    "on",
+                    "off"
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_uncoerced_comparisons_are_registered_on_every_engine() {
+        let rule = r#"{"uncoerced:==": [{"var": "n"}, 1]}"#;
+        for logic in [
+            crate::operators::get_evaluator(),
+            &crate::operators::create_evaluator(),
+        ] {
+            assert_eq!(
+                logic.evaluate_json(rule, r#"{"n": 1}"#).unwrap(),
+                json!(true)
+            );
+            assert_eq!(
+                logic.evaluate_json(rule, r#"{"n": "1"}"#).unwrap(),
+                json!(false)
+            );
+        }
+    }
+}
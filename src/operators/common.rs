@@ -3,7 +3,7 @@
 //! This module provides shared functionality used by all custom operators,
 //! including variable resolution from the context stack.
 
-use datalogic_rs::{ContextStack, Error as DataLogicError};
+use datalogic_rs::{ContextStack, Error as DataLogicError, Evaluator};
 use serde_json::Value;
 
 /// Type alias for operator results using datalogic_rs Error type.
@@ -11,11 +11,14 @@ pub type OperatorResult<T> = std::result::Result<T, DataLogicError>;
 
 /// Resolves a variable path from the context data, or returns the string value directly.
 ///
-/// This helper function handles both direct string values and variable references
-/// (like `{"var": "path.to.value"}`) for the custom operators.
+/// This helper function handles direct string values, variable references (like
+/// `{"var": "path.to.value"}`), and nested JSON Logic expressions (like
+/// `{"cat": [{"var": "org"}, {"var": "user"}]}`), which are evaluated through
+/// `evaluator` and must produce a string, number, or null.
 pub fn resolve_string_from_context(
     value: &Value,
-    context: &ContextStack,
+    context: &mut ContextStack,
+    evaluator: &dyn Evaluator,
 ) -> OperatorResult<String> {
     match value {
         Value::String(s) => Ok(s.clone()),
@@ -47,10 +50,18 @@ pub fn resolve_string_from_context(
                 ))),
             }
         }
+        Value::Object(_) => match evaluator.evaluate(value, context)? {
+            Value::String(s) => Ok(s),
+            Value::Number(n) => Ok(n.to_string()),
+            Value::Null => Ok(String::new()),
+            _ => Err(DataLogicError::TypeError(
+                "Expression must evaluate to a string or number".into(),
+            )),
+        },
         Value::Number(n) => Ok(n.to_string()),
         Value::Null => Ok(String::new()),
         _ => Err(DataLogicError::InvalidArguments(
-            "Value must be a string, number, null, or var reference".into(),
+            "Value must be a string, number, null, var reference, or expression".into(),
         )),
     }
 }
@@ -0,0 +1,343 @@
+//! Chronological date comparison operators.
+//!
+//! The `date_before`, `date_after` and `date_eq` operators parse ISO-8601 dates
+//! (`2024-03-01`) and date-times (`2024-03-01T09:30:00+02:00`) and compare the
+//! instants they denote, so that operands written in different timezones compare
+//! correctly where a plain string comparison would not.
+
+use datalogic_rs::{ContextStack, Error as DataLogicError, Evaluator, Operator};
+use serde_json::Value;
+use std::cmp::Ordering;
+
+use super::common::OperatorResult;
+
+/// Names of the date comparison operators, with the ordering each one tests for.
+pub(crate) const DATE_COMPARISONS: [(&str, Ordering); 3] = [
+    ("date_before", Ordering::Less),
+    ("date_after", Ordering::Greater),
+    ("date_eq", Ordering::Equal),
+];
+
+/// Custom operator comparing two dates chronologically.
+///
+/// Takes exactly two operands, e.g. `{"date_before": [{"var": "signupDate"}, "2024-03-01"]}`,
+/// each an ISO-8601 string (see `IsoDate::parse`) or a number of seconds since the Unix
+/// epoch, such as `$flagd.timestamp`. When either operand is a date without a time, both
+/// are compared by their calendar date in UTC; otherwise the instants are compared.
+/// An operand that is not a valid date is an evaluation error.
+pub struct DateComparisonOperator {
+    name: &'static str,
+    expected: Ordering,
+}
+
+impl DateComparisonOperator {
+    /// Creates the operator registered as `name`, which holds when the first operand
+    /// compares to the second as `expected`.
+    pub fn new(name: &'static str, expected: Ordering) -> Self {
+        Self { name, expected }
+    }
+}
+
+impl Operator for DateComparisonOperator {
+    fn evaluate(
+        &self,
+        args: &[Value],
+        context: &mut ContextStack,
+        evaluator: &dyn Evaluator,
+    ) -> OperatorResult<Value> {
+        let [left, right] = args else {
+            return Err(DataLogicError::InvalidArguments(format!(
+                "{} operator requires exactly 2 arguments",
+                self.name
+            )));
+        };
+
+        let left = self.resolve_date(left, context, evaluator)?;
+        let right = self.resolve_date(right, context, evaluator)?;
+        Ok(Value::Bool(left.compare(&right) == self.expected))
+    }
+}
+
+impl DateComparisonOperator {
+    /// Evaluates an operand and parses it as a date.
+    fn resolve_date(
+        &self,
+        arg: &Value,
+        context: &mut ContextStack,
+        evaluator: &dyn Evaluator,
+    ) -> OperatorResult<IsoDate> {
+        let value = evaluator.evaluate(arg, context)?;
+        let parsed = match &value {
+            Value::String(s) => IsoDate::parse(s),
+            Value::Number(n) => n
+                .as_i64()
+                .map(IsoDate::from_unix_seconds)
+                .ok_or_else(|| format!("Invalid Unix timestamp: {}", n)),
+            other => Err(format!(
+                "Expected a date string or timestamp, got {}",
+                other
+            )),
+        };
+        parsed.map_err(|e| DataLogicError::InvalidArguments(format!("{}: {}", self.name, e)))
+    }
+}
+
+/// A parsed ISO-8601 date or date-time, normalized to UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IsoDate {
+    /// Seconds since the Unix epoch, in UTC
+    pub seconds: i64,
+    /// Fraction of the second, in nanoseconds
+    pub nanos: u32,
+    /// Whether the source had no time of day
+    pub date_only: bool,
+}
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+impl IsoDate {
+    /// Parses an ISO-8601 date or date-time.
+    ///
+    /// Handles values like:
+    /// - "2024-03-01" (a date, midnight UTC)
+    /// - "2024-03-01T09:30" or "2024-03-01 09:30:15" (UTC when no offset is given)
+    /// - "2024-03-01T09:30:15.250Z" (fractions of up to 9 digits)
+    /// - "2024-03-01T09:30:15+02:00", "2024-03-01T09:30:15-0500"
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid ISO-8601 date: '{}'", value);
+        let text = value.trim();
+        if text.len() < 10 || !text.is_char_boundary(10) {
+            return Err(invalid());
+        }
+        let (date, rest) = text.split_at(10);
+        let days = parse_date(date).ok_or_else(invalid)?;
+        if rest.is_empty() {
+            return Ok(Self {
+                seconds: days * SECONDS_PER_DAY,
+                nanos: 0,
+                date_only: true,
+            });
+        }
+
+        let rest = rest.strip_prefix(['T', 't', ' ']).ok_or_else(invalid)?;
+        let offset_start = rest.find(['Z', 'z', '+', '-']).unwrap_or(rest.len());
+        let (time, offset) = rest.split_at(offset_start);
+        let (seconds_of_day, nanos) = parse_time(time).ok_or_else(invalid)?;
+        let offset_seconds = parse_offset(offset).ok_or_else(invalid)?;
+
+        Ok(Self {
+            seconds: days * SECONDS_PER_DAY + seconds_of_day - offset_seconds,
+            nanos,
+            date_only: false,
+        })
+    }
+
+    /// Creates a date-time from seconds since the Unix epoch.
+    pub fn from_unix_seconds(seconds: i64) -> Self {
+        Self {
+            seconds,
+            nanos: 0,
+            date_only: false,
+        }
+    }
+
+    /// Compares chronologically, by UTC calendar date if either side has no time of day.
+    pub fn compare(&self, other: &Self) -> Ordering {
+        if self.date_only || other.date_only {
+            self.seconds
+                .div_euclid(SECONDS_PER_DAY)
+                .cmp(&other.seconds.div_euclid(SECONDS_PER_DAY))
+        } else {
+            (self.seconds, self.nanos).cmp(&(other.seconds, other.nanos))
+        }
+    }
+}
+
+/// Parses a fixed-width run of ASCII digits.
+fn parse_digits(digits: &str) -> Option<i64> {
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Parses `YYYY-MM-DD` into days since the Unix epoch.
+fn parse_date(date: &str) -> Option<i64> {
+    let bytes = date.as_bytes();
+    if bytes.len() != 10 || bytes[4] != b'-' || bytes[7] != b'-' {
+        return None;
+    }
+    let year = parse_digits(&date[0..4])?;
+    let month = parse_digits(&date[5..7])?;
+    let day = parse_digits(&date[8..10])?;
+    if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+    Some(days_from_civil(year, month, day))
+}
+
+/// Parses `HH:MM[:SS[.fraction]]` into seconds of the day and nanoseconds.
+fn parse_time(time: &str) -> Option<(i64, u32)> {
+    let (time, fraction) = match time.split_once(['.', ',']) {
+        Some((time, fraction)) => (time, Some(fraction)),
+        None => (time, None),
+    };
+    let mut parts = time.split(':');
+    let hour = parts
+        .next()
+        .filter(|p| p.len() == 2)
+        .and_then(parse_digits)?;
+    let minute = parts
+        .next()
+        .filter(|p| p.len() == 2)
+        .and_then(parse_digits)?;
+    let second = match parts.next() {
+        Some(part) if part.len() == 2 => parse_digits(part)?,
+        Some(_) => return None,
+        None if fraction.is_some() => return None,
+        None => 0,
+    };
+    if parts.next().is_some() || hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+
+    let nanos = match fraction {
+        Some(fraction) if (1..=9).contains(&fraction.len()) => {
+            let padded = format!("{:0<9}", fraction);
+            u32::try_from(parse_digits(&padded)?).ok()?
+        }
+        Some(_) => return None,
+        None => 0,
+    };
+    Some((hour * 3600 + minute * 60 + second, nanos))
+}
+
+/// Parses `Z`, `±HH:MM`, `±HHMM` or `±HH` (or nothing, meaning UTC) into seconds east of UTC.
+fn parse_offset(offset: &str) -> Option<i64> {
+    let sign = match offset.chars().next() {
+        None => return Some(0),
+        Some('Z' | 'z') if offset.len() == 1 => return Some(0),
+        Some('+') => 1,
+        Some('-') => -1,
+        Some(_) => return None,
+    };
+    let offset = &offset[1..];
+    if !offset.is_ascii() {
+        return None;
+    }
+    let (hours, minutes) = match offset.len() {
+        2 => (offset, "00"),
+        4 => offset.split_at(2),
+        5 if offset.as_bytes()[2] == b':' => (&offset[..2], &offset[3..]),
+        _ => return None,
+    };
+    let hours = parse_digits(hours)?;
+    let minutes = parse_digits(minutes)?;
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian calendar date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_date_only() {
+        let date = IsoDate::parse("1970-01-02").unwrap();
+        assert_eq!(date.seconds, SECONDS_PER_DAY);
+        assert!(date.date_only);
+        assert_eq!(IsoDate::parse("2024-02-29").unwrap().seconds, 1_709_164_800);
+        assert_eq!(
+            IsoDate::parse("1969-12-31").unwrap().seconds,
+            -SECONDS_PER_DAY
+        );
+    }
+
+    #[test]
+    fn test_parse_date_time_with_offsets() {
+        let utc = IsoDate::parse("2024-03-01T09:30:00Z").unwrap();
+        assert_eq!(utc.seconds, 1_709_285_400);
+        assert!(!utc.date_only);
+
+        for same in [
+            "2024-03-01T09:30:00",
+            "2024-03-01 09:30",
+            "2024-03-01T11:30:00+02:00",
+            "2024-03-01T04:30:00-0500",
+            "2024-03-01T10:30+01",
+        ] {
+            assert_eq!(IsoDate::parse(same).unwrap(), utc, "{}", same);
+        }
+
+        let fraction = IsoDate::parse("2024-03-01T09:30:00.25Z").unwrap();
+        assert_eq!(fraction.seconds, utc.seconds);
+        assert_eq!(fraction.nanos, 250_000_000);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        for invalid in [
+            "",
+            "2024-3-01",
+            "2024-02-30",
+            "2023-02-29",
+            "2024-13-01",
+            "2024-03-01T",
+            "2024-03-01T24:00",
+            "2024-03-01T09:60",
+            "2024-03-01T09:30:00+2",
+            "2024-03-01T09:30:00.",
+            "2024-03-01X09:30",
+            "not a date",
+        ] {
+            assert!(IsoDate::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_compare_across_timezones() {
+        let tokyo = IsoDate::parse("2024-03-02T08:00:00+09:00").unwrap();
+        let new_york = IsoDate::parse("2024-03-01T18:30:00-05:00").unwrap();
+        // 23:00Z on March 1st is before 23:30Z, though its local date is later
+        assert_eq!(tokyo.compare(&new_york), Ordering::Less);
+        assert_eq!(new_york.compare(&tokyo), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_compare_date_only_by_utc_calendar_date() {
+        let day = IsoDate::parse("2024-03-01").unwrap();
+        let evening = IsoDate::parse("2024-03-01T23:59:59Z").unwrap();
+        let next_day_locally = IsoDate::parse("2024-03-02T01:00:00+02:00").unwrap();
+        assert_eq!(day.compare(&evening), Ordering::Equal);
+        assert_eq!(evening.compare(&day), Ordering::Equal);
+        assert_eq!(next_day_locally.compare(&day), Ordering::Equal);
+        let next_day = IsoDate::parse("2024-03-02T00:00:00Z").unwrap();
+        assert_eq!(next_day.compare(&day), Ordering::Greater);
+    }
+}
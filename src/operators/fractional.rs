@@ -5,14 +5,215 @@
 
 use super::common::OperatorResult;
 use datalogic_rs::{ContextStack, Error as DataLogicError, Evaluator, Operator};
-use murmurhash3::murmurhash3_x86_32;
+use murmurhash3::{murmurhash3_x64_128, murmurhash3_x86_32};
 use serde_json::Value;
+use std::cell::Cell;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Fixed-point scale applied to bucket weights, allowing up to three decimal places.
+///
+/// Weights are accumulated as integers so decimal weights such as `33.33` sum exactly.
+/// Scaling every weight by the same factor leaves integer weights' ranges unchanged.
+const WEIGHT_SCALE: f64 = 1000.0;
+
+/// Maximum sum of the bucket weights in one `fractional` rule.
+///
+/// Weights are relative, so larger totals add no precision; meanwhile bucket ranges
+/// are computed in `f64`, which stops representing the scaled weights exactly beyond
+/// 2^53. The cap keeps every scaled total (at most 10^12) well within that range.
+/// Rules whose weights add up to more fail to evaluate with an error.
+pub const MAX_TOTAL_WEIGHT: u64 = 1_000_000_000;
+
+thread_local! {
+    /// Whether fractional evaluations on this thread should record their percentile
+    static RECORDING: Cell<bool> = const { Cell::new(false) };
+    /// Percentile of the most recent fractional evaluation while recording
+    static LAST_PERCENTILE: Cell<Option<f64>> = const { Cell::new(None) };
+}
+
+/// How fractional bucket weights are interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FractionalWeights {
+    /// Weights are relative and normalized by their total, so `[1, 1, 2]` splits
+    /// 25/25/50 and `[30, 30, 30]` splits evenly (default)
+    #[default]
+    Relative,
+    /// Weights are percentages and must sum to exactly 100; any other total is an
+    /// evaluation error
+    Percent,
+}
+
+/// How the fractional operator maps a bucketing key to a bucket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FractionalMode {
+    /// The key's hash percentile is looked up in the cumulative bucket weights,
+    /// matching the other flagd implementations (default). Adding or removing a
+    /// bucket shifts the ranges of the buckets after it, reassigning many keys.
+    #[default]
+    Linear,
+    /// Weighted rendezvous (highest random weight) hashing: every bucket scores the
+    /// key independently and the highest score wins. Adding a bucket only moves keys
+    /// to the new bucket, and removing one only moves the keys it held.
+    Rendezvous,
+}
+
+/// The hash function the fractional operator applies to bucketing keys.
+///
+/// Only `Murmur3_32` buckets like the other flagd implementations. The alternatives
+/// exist for parity with other systems that assign the same users, and produce
+/// different assignments than flagd.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FractionalHash {
+    /// 32-bit MurmurHash3 (x86 variant, seed 0), as used by every flagd
+    /// implementation and Apache Commons `MurmurHash3.hash32x86` (default)
+    #[default]
+    Murmur3_32,
+    /// 128-bit MurmurHash3 (x64 variant, seed 0), truncated to its low 32 bits.
+    /// Matches Guava's `Hashing.murmur3_128().hashString(key, UTF_8).asInt()` and
+    /// the low 32 bits of Python's `mmh3.hash128(key)`.
+    Murmur3_128,
+    /// SipHash-2-4 with Guava's default key, truncated to its low 32 bits. Matches
+    /// Guava's `Hashing.sipHash24().hashString(key, UTF_8).asInt()`.
+    Sip,
+}
+
+/// How the fractional operator handles an empty or whitespace-only bucketing key.
+///
+/// The key checked is the `targetingKey` when no bucketing expression is given,
+/// otherwise the evaluated expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmptyKeyPolicy {
+    /// The empty key is hashed like any other, so every such user lands in the
+    /// same bucket (default)
+    #[default]
+    Deterministic,
+    /// A fresh random key is used on every evaluation, spreading such users across
+    /// buckets without stickiness
+    Random,
+    /// The evaluation fails with an error
+    Error,
+}
+
+/// The options of a `FractionalOperator`, i.e. everything its builder methods set.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FractionalOptions {
+    /// How bucket weights are interpreted
+    pub weights: FractionalWeights,
+    /// How bucketing keys are mapped to buckets
+    pub mode: FractionalMode,
+    /// How an empty bucketing key is handled
+    pub empty_key: EmptyKeyPolicy,
+    /// Hash function applied to bucketing keys
+    pub hash: FractionalHash,
+    /// Seed of the random keys used by `EmptyKeyPolicy::Random` (time-based when `None`)
+    pub random_seed: Option<u64>,
+    /// Flag metadata key whose value seeds implicit bucketing keys
+    pub seed_metadata: Option<String>,
+}
+
+/// Counter mixed into random bucketing keys so that consecutive keys differ.
+static RANDOM_KEY_COUNTER: AtomicU64 = AtomicU64::new(0);
 
 /// Custom operator for fractional/percentage-based bucket assignment.
 ///
 /// The fractional operator uses consistent hashing to assign users to buckets
 /// for A/B testing scenarios.
-pub struct FractionalOperator;
+#[derive(Debug, Default)]
+pub struct FractionalOperator {
+    weights: FractionalWeights,
+    mode: FractionalMode,
+    empty_key: EmptyKeyPolicy,
+    hash: FractionalHash,
+    random_seed: Option<u64>,
+    /// Number of random keys generated from `random_seed`
+    random_counter: AtomicU64,
+    /// Flag metadata key whose value replaces the flag key in implicit bucketing keys
+    seed_metadata: Option<String>,
+}
+
+impl FractionalOperator {
+    /// Creates a fractional operator interpreting bucket weights as given.
+    pub fn new(weights: FractionalWeights) -> Self {
+        Self {
+            weights,
+            ..Self::default()
+        }
+    }
+
+    /// Creates a fractional operator with all of the given options.
+    pub fn with_options(options: FractionalOptions) -> Self {
+        Self::new(options.weights)
+            .with_mode(options.mode)
+            .with_empty_key(options.empty_key)
+            .with_hash(options.hash)
+            .with_random_seed(options.random_seed)
+            .with_seed_metadata(options.seed_metadata)
+    }
+
+    /// Sets how the operator maps bucketing keys to buckets.
+    pub fn with_mode(mut self, mode: FractionalMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets how the operator handles an empty bucketing key.
+    pub fn with_empty_key(mut self, policy: EmptyKeyPolicy) -> Self {
+        self.empty_key = policy;
+        self
+    }
+
+    /// Sets the hash function applied to bucketing keys.
+    pub fn with_hash(mut self, hash: FractionalHash) -> Self {
+        self.hash = hash;
+        self
+    }
+
+    /// Seeds the random keys used by `EmptyKeyPolicy::Random`.
+    ///
+    /// With a seed, the operator generates the same sequence of keys every time it
+    /// is created, so random assignments are reproducible. Without one (the
+    /// default), keys are derived from the current time.
+    pub fn with_random_seed(mut self, seed: Option<u64>) -> Self {
+        self.random_seed = seed;
+        self
+    }
+
+    /// Seeds implicit bucketing keys from a flag metadata value.
+    ///
+    /// When the rule gives no explicit bucketing expression, the bucketing key is
+    /// normally the flag key followed by the targeting key. With a metadata key such
+    /// as `experimentId`, the value of `$flagd.flagMetadata.experimentId` (a string
+    /// or number) is used instead of the flag key, so flags sharing an experiment id
+    /// bucket users identically and a new id reshuffles them. Flags without that
+    /// metadata keep using their flag key. An explicit bucketing expression always
+    /// takes precedence.
+    pub fn with_seed_metadata(mut self, key: Option<String>) -> Self {
+        self.seed_metadata = key;
+        self
+    }
+
+    /// Generates a bucketing key that differs on every call.
+    ///
+    /// This only needs to spread keys across buckets, not to be unpredictable, so the
+    /// key is derived from a counter and the seed (or the current time) instead of
+    /// an entropy source (which some WASM hosts do not provide).
+    fn random_bucket_key(&self) -> String {
+        let (base, counter) = match self.random_seed {
+            Some(seed) => (seed, self.random_counter.fetch_add(1, Ordering::Relaxed)),
+            None => (
+                crate::current_time().unwrap_or(0),
+                RANDOM_KEY_COUNTER.fetch_add(1, Ordering::Relaxed),
+            ),
+        };
+        let seed = base ^ counter.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+
+        // SplitMix64 finalizer, so consecutive counters give unrelated keys
+        let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        format!("{:016x}", z ^ (z >> 31))
+    }
+}
 
 impl Operator for FractionalOperator {
     fn evaluate(
@@ -29,9 +230,10 @@ impl Operator for FractionalOperator {
 
         // Evaluate the first argument to determine bucketing key logic
         let evaluated_first = evaluator.evaluate(&args[0], context)?;
-        let (bucket_key, start_index) = if let Value::String(s) = &evaluated_first {
+        let (mut bucket_key, key_is_empty, start_index) = if let Value::String(s) = &evaluated_first
+        {
             // Explicit bucketing key provided
-            (s.clone(), 1)
+            (s.clone(), s.trim().is_empty(), 1)
         } else {
             // Fallback: use flagKey + targetingKey from context data
             let data = context.root().data().clone();
@@ -39,14 +241,42 @@ impl Operator for FractionalOperator {
                 .get("targetingKey")
                 .and_then(|v| v.as_str())
                 .unwrap_or("");
-            let flag_key = data
-                .get("$flagd")
-                .and_then(|v| v.get("flagKey"))
-                .and_then(|v| v.as_str())
-                .unwrap_or("");
-            (format!("{}{}", flag_key, targeting_key), 0)
+            let flagd = data.get("$flagd");
+            let seed = self
+                .seed_metadata
+                .as_ref()
+                .and_then(|key| flagd?.get("flagMetadata")?.get(key))
+                .and_then(|seed| match seed {
+                    Value::String(s) => Some(s.clone()),
+                    Value::Number(n) => Some(n.to_string()),
+                    _ => None,
+                });
+            let seed = seed.unwrap_or_else(|| {
+                flagd
+                    .and_then(|v| v.get("flagKey"))
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string()
+            });
+            (
+                format!("{}{}", seed, targeting_key),
+                targeting_key.trim().is_empty(),
+                0,
+            )
         };
 
+        if key_is_empty {
+            match self.empty_key {
+                EmptyKeyPolicy::Deterministic => {}
+                EmptyKeyPolicy::Random => bucket_key = self.random_bucket_key(),
+                EmptyKeyPolicy::Error => {
+                    return Err(DataLogicError::Custom(
+                        "Fractional bucketing key is empty".into(),
+                    ))
+                }
+            }
+        }
+
         // Parse bucket definitions from remaining arguments
         let mut bucket_values: Vec<Value> = Vec::new();
 
@@ -66,15 +296,7 @@ impl Operator for FractionalOperator {
             for arg in &args[start_index..] {
                 let evaluated = evaluator.evaluate(arg, context)?;
                 if let Some(bucket_def) = evaluated.as_array() {
-                    // Each bucket is [name, weight] or [name] (weight=1)
-                    if bucket_def.len() >= 2 {
-                        bucket_values.push(bucket_def[0].clone());
-                        bucket_values.push(bucket_def[1].clone());
-                    } else if bucket_def.len() == 1 {
-                        // Shorthand: [name] implies weight of 1
-                        bucket_values.push(bucket_def[0].clone());
-                        bucket_values.push(Value::Number(1.into()));
-                    }
+                    push_bucket_def(bucket_def, &mut bucket_values);
                 } else {
                     return Err(DataLogicError::InvalidArguments(format!(
                         "Bucket definition must be an array, got: {:?}",
@@ -84,40 +306,210 @@ impl Operator for FractionalOperator {
             }
         }
 
-        match fractional(&bucket_key, &bucket_values) {
-            Ok(bucket_name) => Ok(Value::String(bucket_name)),
+        match fractional_with_percentile(
+            &bucket_key,
+            &bucket_values,
+            self.weights,
+            self.mode,
+            self.hash,
+        ) {
+            Ok((bucket_name, percentile)) => {
+                if RECORDING.with(Cell::get) {
+                    LAST_PERCENTILE.with(|last| last.set(Some(reported_percentile(percentile))));
+                }
+                Ok(Value::String(bucket_name))
+            }
             Err(e) => Err(DataLogicError::Custom(e)),
         }
     }
 }
 
-/// Evaluates the fractional operator for consistent bucket assignment.
+/// Computes the 32-bit MurmurHash3 (x86 variant, seed 0) of a bucketing key.
+///
+/// This is the exact hash the `fractional` operator uses, matching Apache Commons
+/// `MurmurHash3.hash32x86` so that all flagd implementations bucket identically.
+pub fn murmurhash3_32(key: &str) -> u32 {
+    murmurhash3_x86_32(key.as_bytes(), 0)
+}
+
+/// Hashes a bucketing key to 32 bits with the given hash function.
+pub fn hash_bucket_key(key: &str, hash: FractionalHash) -> u32 {
+    match hash {
+        FractionalHash::Murmur3_32 => murmurhash3_32(key),
+        FractionalHash::Murmur3_128 => murmurhash3_x64_128(key.as_bytes(), 0).0 as u32,
+        FractionalHash::Sip => siphash24(key.as_bytes()) as u32,
+    }
+}
+
+/// Guava's default SipHash key, the bytes 0x00 to 0x0f read as little-endian words.
+const SIPHASH_KEY: (u64, u64) = (0x0706_0504_0302_0100, 0x0f0e_0d0c_0b0a_0908);
+
+/// Computes SipHash-2-4 of `data` under `SIPHASH_KEY`.
+///
+/// Implemented here rather than with `std::hash::SipHasher`, which is deprecated and
+/// whose `Hasher` impls add length prefixes that other implementations do not.
+fn siphash24(data: &[u8]) -> u64 {
+    let (k0, k1) = SIPHASH_KEY;
+    let mut v = [
+        k0 ^ 0x736f_6d65_7073_6575,
+        k1 ^ 0x646f_7261_6e64_6f6d,
+        k0 ^ 0x6c79_6765_6e65_7261,
+        k1 ^ 0x7465_6462_7974_6573,
+    ];
+
+    fn round(v: &mut [u64; 4]) {
+        v[0] = v[0].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(13) ^ v[0];
+        v[0] = v[0].rotate_left(32);
+        v[2] = v[2].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(16) ^ v[2];
+        v[0] = v[0].wrapping_add(v[3]);
+        v[3] = v[3].rotate_left(21) ^ v[0];
+        v[2] = v[2].wrapping_add(v[1]);
+        v[1] = v[1].rotate_left(17) ^ v[2];
+        v[2] = v[2].rotate_left(32);
+    }
+
+    fn compress(v: &mut [u64; 4], m: u64) {
+        v[3] ^= m;
+        round(v);
+        round(v);
+        v[0] ^= m;
+    }
+
+    let mut chunks = data.chunks_exact(8);
+    for chunk in &mut chunks {
+        compress(&mut v, u64::from_le_bytes(chunk.try_into().unwrap()));
+    }
+    let mut last = [0u8; 8];
+    last[..chunks.remainder().len()].copy_from_slice(chunks.remainder());
+    last[7] = data.len() as u8;
+    compress(&mut v, u64::from_le_bytes(last));
+
+    v[2] ^= 0xff;
+    for _ in 0..4 {
+        round(&mut v);
+    }
+    v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+/// Appends a `[name, weight]` or `[name]` bucket definition to a flat bucket list.
+///
+/// The shorthand `[name]` implies a weight of 1; an empty definition adds nothing.
+fn push_bucket_def(bucket_def: &[Value], bucket_values: &mut Vec<Value>) {
+    match bucket_def {
+        [] => {}
+        [name] => {
+            bucket_values.push(name.clone());
+            bucket_values.push(Value::Number(1.into()));
+        }
+        [name, weight, ..] => {
+            bucket_values.push(name.clone());
+            bucket_values.push(weight.clone());
+        }
+    }
+}
+
+/// Predicts the bucket a user lands in for a fractional rule using the default key.
 ///
-/// The fractional operator takes a bucket key (typically a user ID) and
-/// a list of bucket definitions with percentages. It uses consistent hashing
-/// to always assign the same bucket key to the same bucket.
+/// This mirrors the `fractional` operator exactly when no explicit bucketing
+/// expression is given, in which case the bucketing key is the flag key followed by
+/// the targeting key. To predict a rule with an explicit bucketing expression, pass
+/// the evaluated expression as `key` and an empty `flag_key`.
 ///
 /// # Arguments
-/// * `bucket_key` - The key to use for bucket assignment (e.g., user ID)
-/// * `buckets` - Array of [name, percentage, name, percentage, ...] values
+/// * `key` - The targeting key (or the evaluated explicit bucketing key)
+/// * `flag_key` - The key of the flag the rule belongs to
+/// * `buckets` - Array of [name, weight, name, weight, ...] values, or of
+///   `[name, weight]` / `[name]` bucket arrays as written in the rule
 ///
 /// # Returns
-/// The name of the selected bucket, or an error if the input is invalid
+/// The selected bucket name and the key's percentile in `[0, 100]`
+pub fn bucket_of(key: &str, flag_key: &str, buckets: &[Value]) -> Result<(String, f64), String> {
+    let flattened: Vec<Value>;
+    let buckets = if !buckets.is_empty() && buckets.iter().all(Value::is_array) {
+        flattened =
+            buckets
+                .iter()
+                .filter_map(Value::as_array)
+                .fold(Vec::new(), |mut flat, bucket_def| {
+                    push_bucket_def(bucket_def, &mut flat);
+                    flat
+                });
+        &flattened[..]
+    } else {
+        buckets
+    };
+    fractional_with_percentile(
+        &format!("{}{}", flag_key, key),
+        buckets,
+        FractionalWeights::Relative,
+        FractionalMode::Linear,
+        FractionalHash::Murmur3_32,
+    )
+}
+
+/// Runs `f` and returns the percentile of the last fractional operation it evaluated.
+///
+/// Used by `FlagEvaluator::explain_flag`. Recording is per thread and only active
+/// while `f` runs, so regular evaluations only pay for a flag check. The percentile
+/// is reported in `[0, 100)`, see `reported_percentile`.
+pub(crate) fn record_bucket_percentile<R>(f: impl FnOnce() -> R) -> (R, Option<f64>) {
+    LAST_PERCENTILE.with(|last| last.set(None));
+    RECORDING.with(|recording| recording.set(true));
+    let result = f();
+    RECORDING.with(|recording| recording.set(false));
+    (result, LAST_PERCENTILE.with(Cell::take))
+}
+
+/// Maps a selection percentile into `[0, 100)` for reporting.
 ///
-/// # Example
-/// ```json
-/// {"fractional": ["user123", ["control", 50, "treatment", 50]]}
-/// ```
-/// This will consistently assign "user123" to either "control" or "treatment"
-/// based on its hash value.
-pub fn fractional(bucket_key: &str, buckets: &[Value]) -> Result<String, String> {
+/// The percentile used for selection is exactly 100 for the maximum hash (which
+/// selects the last bucket) and negative for `i32::MIN` (which selects the first
+/// bucket, matching Java's `Math.abs`). Both are clamped to the matching end of
+/// the range so reported values always fall within it.
+fn reported_percentile(percentile: f64) -> f64 {
+    if percentile < 0.0 {
+        0.0
+    } else if percentile >= 100.0 {
+        // Largest f64 below 100
+        f64::from_bits(100.0_f64.to_bits() - 1)
+    } else {
+        percentile
+    }
+}
+
+/// Selects a bucket and returns it together with the key's hash percentile.
+///
+/// In rendezvous mode the percentile does not determine the bucket on its own,
+/// but is still reported so traces look the same in both modes.
+fn fractional_with_percentile(
+    bucket_key: &str,
+    buckets: &[Value],
+    weights: FractionalWeights,
+    mode: FractionalMode,
+    hash: FractionalHash,
+) -> Result<(String, f64), String> {
     if buckets.is_empty() {
         return Err("Fractional operator requires at least one bucket".to_string());
     }
 
+    // Bare names without weights split evenly: ["a", "b", "c"] is ["a", 1, "b", 1, "c", 1]
+    let even_split = buckets.len() > 1 && buckets.iter().all(Value::is_string);
+    let weighted: Vec<Value>;
+    let buckets = if even_split {
+        weighted = buckets
+            .iter()
+            .flat_map(|name| [name.clone(), Value::from(1)])
+            .collect();
+        &weighted[..]
+    } else {
+        buckets
+    };
+
     // Parse bucket definitions: [name1, weight1, name2, weight2, ...]
-    let mut bucket_defs: Vec<(String, u32)> = Vec::new();
-    let mut total_weight: u32 = 0;
+    let mut bucket_defs: Vec<(String, u64)> = Vec::new();
+    let mut total_weight: u64 = 0;
 
     let mut i = 0;
     while i < buckets.len() {
@@ -131,20 +523,25 @@ pub fn fractional(bucket_key: &str, buckets: &[Value]) -> Result<String, String>
 
         // Get bucket weight
         if i >= buckets.len() {
-            return Err(format!("Missing weight for bucket '{}'", name));
+            return Err(missing_weight(&name));
         }
 
         let weight = match &buckets[i] {
-            Value::Number(n) => n
-                .as_u64()
-                .ok_or_else(|| format!("Weight for bucket '{}' must be a positive integer", name))?
-                as u32,
+            Value::Number(n) => n.as_f64().unwrap_or(f64::NAN),
+            Value::String(_) => return Err(missing_weight(&name)),
             _ => return Err(format!("Weight for bucket '{}' must be a number", name)),
         };
+        if weight > MAX_TOTAL_WEIGHT as f64 {
+            return Err(total_weight_exceeded(weight));
+        }
+        let weight = scale_weight(weight)
+            .ok_or_else(|| format!("Weight for bucket '{}' must be a non-negative number", name))?;
 
-        total_weight = total_weight
-            .checked_add(weight)
-            .ok_or_else(|| "Total weight overflow".to_string())?;
+        // Both terms are capped, so the sum cannot overflow
+        total_weight += weight;
+        if total_weight > MAX_TOTAL_WEIGHT * WEIGHT_SCALE as u64 {
+            return Err(total_weight_exceeded(total_weight as f64 / WEIGHT_SCALE));
+        }
 
         bucket_defs.push((name, weight));
         i += 1;
@@ -158,28 +555,112 @@ pub fn fractional(bucket_key: &str, buckets: &[Value]) -> Result<String, String>
         return Err("Total weight must be greater than zero".to_string());
     }
 
+    // Implied weights always split the whole range
+    if weights == FractionalWeights::Percent
+        && !even_split
+        && total_weight != 100 * WEIGHT_SCALE as u64
+    {
+        return Err(format!(
+            "Bucket weights must sum to 100 in percent mode, got {}",
+            total_weight as f64 / WEIGHT_SCALE
+        ));
+    }
+
     // Hash the bucket key to get a consistent value
-    // Using murmurhash3_x86_32 to match Apache Commons MurmurHash3.hash32x86
-    // Java code: Math.abs(mmrHash) * 1.0f / Integer.MAX_VALUE * 100
-    let hash: u32 = murmurhash3_x86_32(bucket_key.as_bytes(), 0);
-    let hash_i32 = hash as i32; // Cast to signed integer (may be negative)
-    let abs_hash = hash_i32.abs(); // Take absolute value like Java does
-    let bucket_value = (abs_hash as f64 / i32::MAX as f64) * 100.0;
-
-    // Find which bucket this value falls into by accumulating weights
+    let key_hash = hash_bucket_key(bucket_key, hash);
+    let bucket_value = hash_to_percentile(key_hash);
+    let name = match mode {
+        FractionalMode::Linear => select_bucket(bucket_value, &bucket_defs, total_weight),
+        FractionalMode::Rendezvous => select_rendezvous_bucket(key_hash, &bucket_defs),
+    };
+    Ok((name, bucket_value))
+}
+
+/// Builds the error for a bucket without a weight among weighted buckets.
+fn missing_weight(name: &str) -> String {
+    format!(
+        "Missing weight for bucket '{}': give every bucket a weight, or none to split evenly",
+        name
+    )
+}
+
+/// Builds the error for bucket weights adding up to more than `MAX_TOTAL_WEIGHT`.
+fn total_weight_exceeded(total: f64) -> String {
+    format!(
+        "Total bucket weight {} exceeds the maximum of {}",
+        total, MAX_TOTAL_WEIGHT
+    )
+}
+
+/// Converts a bucket weight to fixed point, rounding to three decimal places.
+///
+/// Returns `None` for negative, NaN, or out-of-range weights.
+fn scale_weight(weight: f64) -> Option<u64> {
+    let scaled = (weight * WEIGHT_SCALE).round();
+    (scaled >= 0.0 && scaled <= u64::MAX as f64).then_some(scaled as u64)
+}
+
+/// Maps a 32-bit hash to a percentile in `[0, 100]`.
+///
+/// The single hash `0x80000000` maps slightly below zero, exactly like the Java
+/// reference implementation, and therefore lands in the first bucket.
+///
+/// Using murmurhash3_x86_32 to match Apache Commons MurmurHash3.hash32x86
+/// Java code: Math.abs(mmrHash) * 1.0f / Integer.MAX_VALUE * 100
+fn hash_to_percentile(hash: u32) -> f64 {
+    // Cast to signed integer (may be negative)
+    let hash_i32 = hash as i32;
+    // Take absolute value like Java does (Math.abs(Integer.MIN_VALUE) stays negative)
+    let abs_hash = hash_i32.wrapping_abs();
+    (abs_hash as f64 / i32::MAX as f64) * 100.0
+}
+
+/// Finds the bucket a percentile falls into by accumulating weights.
+///
+/// Zero-weight buckets cover no range and are never selected, wherever they appear;
+/// a percentile at a range boundary belongs to the next weighted bucket.
+/// The last weighted bucket's range is closed at the top, so the maximum hash
+/// (percentile exactly 100) or floating point rounding in the cumulative weights
+/// always selects it instead of falling off the end.
+fn select_bucket(bucket_value: f64, bucket_defs: &[(String, u64)], total_weight: u64) -> String {
+    let last_weighted = bucket_defs
+        .iter()
+        .rposition(|(_, weight)| *weight > 0)
+        .unwrap_or(bucket_defs.len() - 1);
+
     let mut cumulative_weight: f64 = 0.;
-    for (name, weight) in &bucket_defs {
-        cumulative_weight += (weight * 100) as f64 / total_weight as f64;
-        if bucket_value < cumulative_weight {
-            return Ok(name.clone());
+    for (name, weight) in &bucket_defs[..last_weighted] {
+        cumulative_weight += (*weight as f64 * 100.0) / total_weight as f64;
+        if *weight > 0 && bucket_value < cumulative_weight {
+            return name.clone();
         }
     }
 
-    // If we didn't find a bucket (e.g., total_weight < 100), return the last one
-    Ok(bucket_defs
-        .last()
-        .map(|(name, _)| name.clone())
-        .unwrap_or_default())
+    bucket_defs[last_weighted].0.clone()
+}
+
+/// Picks the bucket with the highest weighted rendezvous score for a key.
+///
+/// Each bucket hashes its name seeded with the key hash into `(0, 1)` and scores
+/// `weight / -ln(hash)`, which selects buckets in proportion to their weights. A
+/// bucket's score does not depend on the other buckets, so changing the bucket set
+/// only moves keys to or from the buckets that changed. Ties go to the earlier bucket.
+fn select_rendezvous_bucket(key_hash: u32, bucket_defs: &[(String, u64)]) -> String {
+    let mut best: Option<(&str, f64)> = None;
+    for (name, weight) in bucket_defs {
+        if *weight == 0 {
+            continue;
+        }
+        let hash = murmurhash3_x86_32(name.as_bytes(), key_hash);
+        let unit = (hash as f64 + 1.0) / (u32::MAX as f64 + 2.0);
+        let score = *weight as f64 / -unit.ln();
+        if best.is_none_or(|(_, best_score)| score > best_score) {
+            best = Some((name, score));
+        }
+    }
+
+    // The total weight is non-zero, so at least one bucket was scored
+    best.map(|(name, _)| name.to_string()).unwrap_or_default()
 }
 
 #[cfg(test)]
@@ -187,6 +668,18 @@ mod tests {
     use super::*;
     use serde_json::json;
 
+    /// Selects a bucket with relative weights and the default mode and hash.
+    fn fractional(bucket_key: &str, buckets: &[Value]) -> Result<String, String> {
+        fractional_with_percentile(
+            bucket_key,
+            buckets,
+            FractionalWeights::Relative,
+            FractionalMode::Linear,
+            FractionalHash::Murmur3_32,
+        )
+        .map(|(name, _)| name)
+    }
+
     #[test]
     fn test_fractional_50_50() {
         let buckets = vec![json!("control"), json!(50), json!("treatment"), json!(50)];
@@ -239,6 +732,283 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_max_hash_selects_last_bucket() {
+        let defs = vec![
+            ("a".to_string(), 33),
+            ("b".to_string(), 33),
+            ("c".to_string(), 34),
+        ];
+
+        // The maximum hash maps to exactly 100 and must not fall off the end
+        let max_value = hash_to_percentile(i32::MAX as u32);
+        assert_eq!(max_value, 100.0);
+        assert_eq!(select_bucket(max_value, &defs, 100), "c");
+
+        // Weights whose percentages don't add up exactly in floating point
+        let thirds = vec![
+            ("a".to_string(), 1),
+            ("b".to_string(), 1),
+            ("c".to_string(), 1),
+        ];
+        assert_eq!(select_bucket(max_value, &thirds, 3), "c");
+
+        // A trailing zero-weight bucket is never selected
+        let trailing_zero = vec![
+            ("a".to_string(), 50),
+            ("b".to_string(), 50),
+            ("z".to_string(), 0),
+        ];
+        assert_eq!(select_bucket(max_value, &trailing_zero, 100), "b");
+    }
+
+    #[test]
+    fn test_zero_weight_buckets_are_never_selected() {
+        let buckets = vec![
+            json!("leading"),
+            json!(0),
+            json!("a"),
+            json!(50),
+            json!("middle"),
+            json!(0),
+            json!("b"),
+            json!(50),
+            json!("trailing"),
+            json!(0),
+        ];
+
+        for mode in [FractionalMode::Linear, FractionalMode::Rendezvous] {
+            for i in 0..1000 {
+                let key = format!("user-{}", i);
+                let (name, _) = fractional_with_percentile(
+                    &key,
+                    &buckets,
+                    FractionalWeights::default(),
+                    mode,
+                    FractionalHash::default(),
+                )
+                .unwrap();
+                assert!(name == "a" || name == "b", "{:?} selected {}", mode, name);
+            }
+        }
+
+        // Boundary percentiles skip the zero-weight buckets around them
+        let defs = vec![
+            ("leading".to_string(), 0),
+            ("a".to_string(), 50),
+            ("middle".to_string(), 0),
+            ("b".to_string(), 50),
+            ("trailing".to_string(), 0),
+        ];
+        let min_value = hash_to_percentile(i32::MIN as u32);
+        assert_eq!(select_bucket(min_value, &defs, 100), "a");
+        assert_eq!(select_bucket(0.0, &defs, 100), "a");
+        assert_eq!(select_bucket(50.0, &defs, 100), "b");
+        assert_eq!(select_bucket(100.0, &defs, 100), "b");
+    }
+
+    #[test]
+    fn test_min_hash_matches_java_abs() {
+        // Math.abs(Integer.MIN_VALUE) is negative in Java, landing in the first bucket
+        let min_value = hash_to_percentile(i32::MIN as u32);
+        assert!(min_value < 0.0);
+
+        let defs = vec![("a".to_string(), 50), ("b".to_string(), 50)];
+        assert_eq!(select_bucket(min_value, &defs, 100), "a");
+        assert_eq!(select_bucket(0.0, &defs, 100), "a");
+    }
+
+    #[test]
+    fn test_reported_percentile_is_within_range() {
+        let max = reported_percentile(hash_to_percentile(i32::MAX as u32));
+        assert!(max < 100.0);
+        assert!(max > 99.999);
+        assert_eq!(
+            reported_percentile(hash_to_percentile(i32::MIN as u32)),
+            0.0
+        );
+        assert_eq!(reported_percentile(42.5), 42.5);
+    }
+
+    #[test]
+    fn test_fractional_percent_mode_requires_100() {
+        let buckets = vec![
+            json!("a"),
+            json!(30),
+            json!("b"),
+            json!(30),
+            json!("c"),
+            json!(30),
+        ];
+
+        let relative = fractional_with_percentile(
+            "user-1",
+            &buckets,
+            FractionalWeights::Relative,
+            FractionalMode::Linear,
+            FractionalHash::Murmur3_32,
+        );
+        assert!(relative.is_ok());
+
+        let percent = fractional_with_percentile(
+            "user-1",
+            &buckets,
+            FractionalWeights::Percent,
+            FractionalMode::Linear,
+            FractionalHash::Murmur3_32,
+        );
+        assert_eq!(
+            percent.unwrap_err(),
+            "Bucket weights must sum to 100 in percent mode, got 90"
+        );
+
+        let hundred = vec![json!("a"), json!(40), json!("b"), json!(60)];
+        assert!(fractional_with_percentile(
+            "user-1",
+            &hundred,
+            FractionalWeights::Percent,
+            FractionalMode::Linear,
+            FractionalHash::Murmur3_32
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_decimal_weights() {
+        let decimal = vec![
+            json!("a"),
+            json!(33.33),
+            json!("b"),
+            json!(33.33),
+            json!("c"),
+            json!(33.34),
+        ];
+
+        // The weights sum to exactly 100 despite binary floating point
+        let mut seen = std::collections::HashSet::new();
+        for i in 0..300 {
+            let key = format!("user-{}", i);
+            let first = fractional_with_percentile(
+                &key,
+                &decimal,
+                FractionalWeights::Percent,
+                FractionalMode::Linear,
+                FractionalHash::Murmur3_32,
+            )
+            .unwrap();
+            let again = fractional_with_percentile(
+                &key,
+                &decimal,
+                FractionalWeights::Percent,
+                FractionalMode::Linear,
+                FractionalHash::Murmur3_32,
+            )
+            .unwrap();
+            assert_eq!(first, again);
+            seen.insert(first.0);
+        }
+        assert_eq!(seen.len(), 3);
+
+        // Integral floats bucket exactly like integers
+        let integers = vec![json!("x"), json!(50), json!("y"), json!(50)];
+        let floats = vec![json!("x"), json!(50.0), json!("y"), json!(50.0)];
+        for i in 0..100 {
+            let key = format!("user-{}", i);
+            assert_eq!(
+                fractional(&key, &integers).unwrap(),
+                fractional(&key, &floats).unwrap()
+            );
+        }
+
+        let negative = vec![json!("x"), json!(-1.5), json!("y"), json!(50)];
+        assert_eq!(
+            fractional("user-1", &negative).unwrap_err(),
+            "Weight for bucket 'x' must be a non-negative number"
+        );
+    }
+
+    #[test]
+    fn test_rendezvous_mode_minimizes_reassignment() {
+        let before = vec![
+            json!("a"),
+            json!(1),
+            json!("b"),
+            json!(1),
+            json!("c"),
+            json!(1),
+        ];
+        let mut after = before.clone();
+        after.extend([json!("d"), json!(1)]);
+
+        let assign = |buckets: &[Value], key: &str, mode| {
+            fractional_with_percentile(
+                key,
+                buckets,
+                FractionalWeights::Relative,
+                mode,
+                FractionalHash::Murmur3_32,
+            )
+            .unwrap()
+            .0
+        };
+
+        let users = 1000;
+        let mut kept_linear = 0;
+        let mut kept_rendezvous = 0;
+        for i in 0..users {
+            let key = format!("user-{}", i);
+            if assign(&before, &key, FractionalMode::Linear)
+                == assign(&after, &key, FractionalMode::Linear)
+            {
+                kept_linear += 1;
+            }
+
+            let old = assign(&before, &key, FractionalMode::Rendezvous);
+            let new = assign(&after, &key, FractionalMode::Rendezvous);
+            if old == new {
+                kept_rendezvous += 1;
+            } else {
+                // Keys only ever move to the new bucket
+                assert_eq!(new, "d");
+            }
+        }
+
+        // Ideally 3/4 of the keys stay put; the linear scheme only keeps about half
+        assert!(kept_rendezvous * 10 > users * 7, "kept {}", kept_rendezvous);
+        assert!(kept_rendezvous > kept_linear);
+    }
+
+    #[test]
+    fn test_rendezvous_mode_respects_weights() {
+        let buckets = vec![
+            json!("small"),
+            json!(10),
+            json!("large"),
+            json!(90),
+            json!("none"),
+            json!(0),
+        ];
+
+        let mut small_count = 0;
+        for i in 0..1000 {
+            let key = format!("user-{}", i);
+            let (name, _) = fractional_with_percentile(
+                &key,
+                &buckets,
+                FractionalWeights::Relative,
+                FractionalMode::Rendezvous,
+                FractionalHash::Murmur3_32,
+            )
+            .unwrap();
+            assert_ne!(name, "none");
+            if name == "small" {
+                small_count += 1;
+            }
+        }
+
+        assert!((50..150).contains(&small_count), "small: {}", small_count);
+    }
+
     #[test]
     fn test_fractional_empty_buckets() {
         let buckets: Vec<Value> = vec![];
@@ -253,6 +1023,54 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_fractional_bare_names_split_evenly() {
+        let buckets = vec![json!("a"), json!("b"), json!("c")];
+        let weighted = vec![
+            json!("a"),
+            json!(1),
+            json!("b"),
+            json!(1),
+            json!("c"),
+            json!(1),
+        ];
+
+        let mut counts = std::collections::HashMap::new();
+        for i in 0..3000 {
+            let key = format!("user-{}", i);
+            let bucket = fractional(&key, &buckets).unwrap();
+            assert_eq!(bucket, fractional(&key, &weighted).unwrap());
+            *counts.entry(bucket).or_insert(0) += 1;
+        }
+        assert_eq!(counts.len(), 3);
+        for (bucket, count) in &counts {
+            assert!(
+                (800..1200).contains(count),
+                "bucket {} got {} of 3000 keys",
+                bucket,
+                count
+            );
+        }
+
+        // Implied weights satisfy percent mode
+        assert!(fractional_with_percentile(
+            "user-1",
+            &buckets,
+            FractionalWeights::Percent,
+            FractionalMode::Linear,
+            FractionalHash::Murmur3_32,
+        )
+        .is_ok());
+
+        // Mixing weighted and bare buckets is an error
+        let expected =
+            "Missing weight for bucket 'b': give every bucket a weight, or none to split evenly";
+        let trailing = vec![json!("a"), json!(50), json!("b")];
+        assert_eq!(fractional("user-1", &trailing).unwrap_err(), expected);
+        let leading = vec![json!("a"), json!(50), json!("b"), json!("c"), json!(50)];
+        assert_eq!(fractional("user-1", &leading).unwrap_err(), expected);
+    }
+
     #[test]
     fn test_fractional_invalid_name_type() {
         let buckets = vec![json!(123), json!(50)];
@@ -262,10 +1080,100 @@ mod tests {
 
     #[test]
     fn test_fractional_invalid_weight_type() {
-        let buckets = vec![json!("bucket"), json!("not-a-number")];
+        let buckets = vec![json!("bucket"), json!(true)];
         let result = fractional("user-123", &buckets);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_total_weight_cap() {
+        let half = MAX_TOTAL_WEIGHT / 2;
+
+        // Exactly at the cap, buckets still split by their weights
+        let buckets = vec![json!("a"), json!(half), json!("b"), json!(half)];
+        let mut seen = std::collections::HashSet::new();
+        for i in 0..100 {
+            seen.insert(fractional(&format!("user-{}", i), &buckets).unwrap());
+        }
+        assert_eq!(seen.len(), 2);
+
+        // A tiny bucket at the cap is still distinguished from its neighbour
+        let buckets = vec![
+            json!("a"),
+            json!(1),
+            json!("b"),
+            json!(MAX_TOTAL_WEIGHT - 1),
+        ];
+        assert_eq!(fractional("user-1", &buckets).unwrap(), "b");
+
+        // Just past the cap, in one weight or across several, is an error
+        let buckets = vec![
+            json!("a"),
+            json!(half),
+            json!("b"),
+            json!(half as f64 + 0.001),
+        ];
+        assert_eq!(
+            fractional("user-1", &buckets),
+            Err("Total bucket weight 1000000000.001 exceeds the maximum of 1000000000".into())
+        );
+        let buckets = vec![json!("a"), json!(MAX_TOTAL_WEIGHT + 1)];
+        assert_eq!(
+            fractional("user-1", &buckets),
+            Err("Total bucket weight 1000000001 exceeds the maximum of 1000000000".into())
+        );
+        let buckets = vec![json!("a"), json!(1e300), json!("b"), json!(1)];
+        assert!(fractional("user-1", &buckets)
+            .unwrap_err()
+            .starts_with("Total bucket weight"));
+    }
+
+    #[test]
+    fn test_fractional_hashes_are_deterministic() {
+        // Reference values: the empty input hashes to 0 under both MurmurHash3
+        // variants, and the SipHash-2-4 paper's test vector for the empty input under
+        // the key 00..0f (Guava's default) is 0x726fdb47dd0e0e31
+        assert_eq!(hash_bucket_key("", FractionalHash::Murmur3_32), 0);
+        assert_eq!(hash_bucket_key("", FractionalHash::Murmur3_128), 0);
+        assert_eq!(siphash24(b""), 0x726f_db47_dd0e_0e31);
+        assert_eq!(hash_bucket_key("", FractionalHash::Sip), 0xdd0e_0e31);
+
+        let buckets = vec![json!("a"), json!(50), json!("b"), json!(50)];
+        for hash in [
+            FractionalHash::Murmur3_32,
+            FractionalHash::Murmur3_128,
+            FractionalHash::Sip,
+        ] {
+            let mut seen = std::collections::HashSet::new();
+            for i in 0..200 {
+                let key = format!("flaguser-{}", i);
+                assert_eq!(hash_bucket_key(&key, hash), hash_bucket_key(&key, hash));
+                let assign = || {
+                    fractional_with_percentile(
+                        &key,
+                        &buckets,
+                        FractionalWeights::Relative,
+                        FractionalMode::Linear,
+                        hash,
+                    )
+                    .unwrap()
+                };
+                assert_eq!(assign(), assign());
+                seen.insert(assign().0);
+            }
+            assert_eq!(seen.len(), 2, "{:?} should use both buckets", hash);
+        }
+
+        // The default hash is the one flagd uses
+        assert_eq!(
+            hash_bucket_key("flaguser-1", FractionalHash::default()),
+            murmurhash3_32("flaguser-1")
+        );
+        assert_ne!(
+            hash_bucket_key("flaguser-1", FractionalHash::Murmur3_128),
+            hash_bucket_key("flaguser-1", FractionalHash::Sip)
+        );
+    }
 }
 
 #[cfg(test)]
@@ -333,4 +1241,17 @@ fn debug_shorthand_keys() {
         println!("  Result: {}", result);
         println!("  Match: {}", result == expected);
     }
+
+    #[test]
+    fn test_bucket_of_accepts_rule_bucket_shapes() {
+        let flat = [json!("a"), json!(50), json!("b"), json!(50)];
+        let nested = [json!(["a", 50]), json!(["b", 50])];
+        let shorthand = [json!(["a"]), json!(["b"])];
+        for i in 0..50 {
+            let user = format!("user-{}", i);
+            let expected = bucket_of(&user, "flag", &flat).unwrap();
+            assert_eq!(bucket_of(&user, "flag", &nested).unwrap(), expected);
+            assert_eq!(bucket_of(&user, "flag", &shorthand).unwrap(), expected);
+        }
+    }
 }
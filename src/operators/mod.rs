@@ -12,19 +12,46 @@
 //!
 //! - `FractionalOperator`: Percentage-based bucket assignment for A/B testing
 //! - `SemVerOperator`: Semantic version comparison
+//! - `HasOperator` / `IsTypeOperator`: Context attribute presence and type checks
+//! - `UncoercedComparisonOperator`: Comparisons without numeric string coercion
+//! - `MatchesNamedOperator`: Matching against named regular expression patterns
+//! - `DateComparisonOperator`: Chronological comparison of ISO-8601 dates
+//! - `AliasOperator`: Alternative spellings of operator names (e.g. `semver`)
 //!
 //! ## Module Organization
 //!
 //! Each operator is implemented in its own file for easier maintenance:
+//! - `alias.rs`: Operator name aliases
+//! - `coercion.rs`: Comparisons without numeric string coercion
 //! - `common.rs`: Shared utilities and helper functions
+//! - `date.rs`: ISO-8601 date comparison
 //! - `fractional.rs`: Fractional/percentage-based bucket assignment
+//! - `pattern.rs`: Named regular expression patterns
 //! - `sem_ver.rs`: Semantic version comparison
+//! - `presence.rs`: Attribute presence and type checks
 
+mod alias;
+mod coercion;
 mod common;
+mod date;
 mod fractional;
+mod pattern;
+mod presence;
 mod sem_ver;
 
-pub use fractional::FractionalOperator;
+pub(crate) use alias::resolve_operator_aliases;
+pub use alias::{AliasOperator, DEFAULT_OPERATOR_ALIASES};
+pub(crate) use coercion::without_numeric_coercion;
+pub use coercion::UncoercedComparisonOperator;
+pub use date::{DateComparisonOperator, IsoDate};
+pub(crate) use fractional::record_bucket_percentile;
+pub use fractional::{
+    bucket_of, hash_bucket_key, murmurhash3_32, EmptyKeyPolicy, FractionalHash, FractionalMode,
+    FractionalOperator, FractionalOptions, FractionalWeights, MAX_TOTAL_WEIGHT,
+};
+pub use pattern::MatchesNamedOperator;
+pub(crate) use pattern::{compile_patterns, named_pattern_refs, MATCHES_NAMED};
+pub use presence::{HasOperator, IsTypeOperator};
 pub use sem_ver::{SemVer, SemVerOperator};
 
 use datalogic_rs::DataLogic;
@@ -37,12 +64,7 @@ static EVALUATOR: OnceLock<DataLogic> = OnceLock::new();
 /// Gets a reference to the global singleton DataLogic engine.
 /// The engine is lazily initialized on first access.
 pub fn get_evaluator() -> &'static DataLogic {
-    EVALUATOR.get_or_init(|| {
-        let mut logic = DataLogic::new();
-        logic.add_operator("fractional".to_string(), Box::new(FractionalOperator));
-        logic.add_operator("sem_ver".to_string(), Box::new(SemVerOperator));
-        logic
-    })
+    EVALUATOR.get_or_init(create_evaluator)
 }
 
 /// Creates a new DataLogic instance with all custom operators registered.
@@ -56,6 +78,15 @@ pub fn get_evaluator() -> &'static DataLogic {
 /// A configured DataLogic instance with the following operators registered:
 /// - `fractional`: For A/B testing bucket assignment
 /// - `sem_ver`: For semantic version comparison
+/// - `has`: For checking that a context attribute is defined
+/// - `is_type`: For checking the JSON type of a value
+/// - `matches_named`: For matching against a named pattern (none are defined here;
+///   `FlagEvaluator` registers the stored configuration's patterns)
+/// - `uncoerced:==`, `uncoerced:<`, etc.: The comparisons without numeric string
+///   coercion, which `FlagEvaluator` substitutes when coercion is disabled
+/// - `date_before`, `date_after`, `date_eq`: For comparing ISO-8601 dates chronologically
+/// - The `DEFAULT_OPERATOR_ALIASES`: `semver`, `startsWith` and `endsWith`, for
+///   `sem_ver`, `starts_with` and `ends_with`
 ///
 /// Note: The `starts_with` and `ends_with` operators are provided by datalogic-rs
 /// and are available by default without custom registration.
@@ -69,9 +100,64 @@ pub fn get_evaluator() -> &'static DataLogic {
 /// // Now you can use custom operators in your rules
 /// ```
 pub fn create_evaluator() -> DataLogic {
-    let mut logic = DataLogic::new();
-    logic.add_operator("fractional".to_string(), Box::new(FractionalOperator));
-    logic.add_operator("sem_ver".to_string(), Box::new(SemVerOperator));
+    create_evaluator_with_weights(FractionalWeights::default())
+}
 
+/// Creates a new DataLogic instance whose `fractional` operator interprets bucket
+/// weights according to `weights`.
+///
+/// `create_evaluator()` is equivalent to passing `FractionalWeights::Relative`.
+pub fn create_evaluator_with_weights(weights: FractionalWeights) -> DataLogic {
+    create_evaluator_with_fractional(weights, FractionalMode::default())
+}
+
+/// Creates a new DataLogic instance whose `fractional` operator interprets bucket
+/// weights according to `weights` and selects buckets according to `mode`.
+pub fn create_evaluator_with_fractional(
+    weights: FractionalWeights,
+    mode: FractionalMode,
+) -> DataLogic {
+    create_evaluator_with_fractional_options(FractionalOptions {
+        weights,
+        mode,
+        ..FractionalOptions::default()
+    })
+}
+
+/// Creates a new DataLogic instance whose `fractional` operator is configured with
+/// `options` (see `FractionalOptions`).
+pub fn create_evaluator_with_fractional_options(options: FractionalOptions) -> DataLogic {
+    let mut logic = DataLogic::new();
+    register_operators(&mut logic, FractionalOperator::with_options(options));
     logic
 }
+
+/// Registers every flagd-specific operator, using `fractional` for `fractional`.
+///
+/// The single list of custom operators, shared by all the constructors above so
+/// that an operator added here is available to every engine.
+fn register_operators(logic: &mut DataLogic, fractional: FractionalOperator) {
+    logic.add_operator("fractional".to_string(), Box::new(fractional));
+    logic.add_operator("sem_ver".to_string(), Box::new(SemVerOperator));
+    logic.add_operator("has".to_string(), Box::new(HasOperator));
+    logic.add_operator("is_type".to_string(), Box::new(IsTypeOperator));
+    logic.add_operator(
+        MATCHES_NAMED.to_string(),
+        Box::new(MatchesNamedOperator::default()),
+    );
+    for operator in coercion::COERCING_COMPARISONS {
+        logic.add_operator(
+            coercion::uncoerced_name(operator),
+            Box::new(UncoercedComparisonOperator::new(operator)),
+        );
+    }
+    for (name, ordering) in date::DATE_COMPARISONS {
+        logic.add_operator(
+            name.to_string(),
+            Box::new(DateComparisonOperator::new(name, ordering)),
+        );
+    }
+    for (alias, canonical) in DEFAULT_OPERATOR_ALIASES {
+        logic.add_operator(alias.to_string(), Box::new(AliasOperator::new(canonical)));
+    }
+}
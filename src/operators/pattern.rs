@@ -0,0 +1,132 @@
+//! Named regular expression patterns.
+//!
+//! A configuration can define reusable regular expressions in a top-level
+//! `patterns` object, keyed by name. Targeting refers to them by name, as in
+//! `{"matches_named": [{"var": "email"}, "corpEmailPattern"]}`, so an allowlist such
+//! as the corporate email domains is written once and compiled once per configuration.
+
+use std::collections::HashMap;
+
+use datalogic_rs::{ContextStack, Error as DataLogicError, Evaluator, Operator};
+use regex::Regex;
+use serde_json::Value;
+
+use super::common::OperatorResult;
+
+/// Name under which `MatchesNamedOperator` is registered.
+pub(crate) const MATCHES_NAMED: &str = "matches_named";
+
+/// Custom operator matching a string against a named pattern.
+///
+/// Takes a value (usually a `var` reference) and the name of a pattern from the
+/// configuration's `patterns`, e.g.
+/// `{"matches_named": [{"var": "email"}, "corpEmailPattern"]}`. The pattern matches
+/// anywhere in the string unless it is anchored with `^` and `$`. Values that are not
+/// strings never match.
+#[derive(Default)]
+pub struct MatchesNamedOperator {
+    patterns: HashMap<String, Regex>,
+}
+
+impl MatchesNamedOperator {
+    /// Creates the operator for a configuration's compiled patterns.
+    pub fn new(patterns: HashMap<String, Regex>) -> Self {
+        Self { patterns }
+    }
+}
+
+impl Operator for MatchesNamedOperator {
+    fn evaluate(
+        &self,
+        args: &[Value],
+        context: &mut ContextStack,
+        evaluator: &dyn Evaluator,
+    ) -> OperatorResult<Value> {
+        let [value, name] = args else {
+            return Err(DataLogicError::InvalidArguments(
+                "matches_named operator requires exactly 2 arguments".into(),
+            ));
+        };
+
+        let name = name.as_str().ok_or_else(|| {
+            DataLogicError::InvalidArguments("matches_named pattern name must be a string".into())
+        })?;
+        let pattern = self.patterns.get(name).ok_or_else(|| {
+            DataLogicError::InvalidArguments(format!("Unknown pattern '{}'", name))
+        })?;
+        let value = evaluator.evaluate(value, context)?;
+
+        Ok(Value::Bool(
+            value.as_str().is_some_and(|text| pattern.is_match(text)),
+        ))
+    }
+}
+
+/// Compiles the `patterns` object of a configuration.
+pub(crate) fn compile_patterns(patterns: &Value) -> Result<HashMap<String, Regex>, String> {
+    let patterns = patterns
+        .as_object()
+        .ok_or_else(|| "'patterns' must be an object".to_string())?;
+
+    patterns
+        .iter()
+        .map(|(name, source)| {
+            let source = source
+                .as_str()
+                .ok_or_else(|| format!("Pattern '{}' must be a string", name))?;
+            let regex =
+                Regex::new(source).map_err(|e| format!("Invalid pattern '{}': {}", name, e))?;
+            Ok((name.clone(), regex))
+        })
+        .collect()
+}
+
+/// Returns the pattern names a rule refers to with `matches_named`.
+pub(crate) fn named_pattern_refs(rule: &Value) -> Vec<&str> {
+    let mut names = Vec::new();
+    collect_named_pattern_refs(rule, &mut names);
+    names
+}
+
+fn collect_named_pattern_refs<'a>(rule: &'a Value, names: &mut Vec<&'a str>) {
+    match rule {
+        Value::Object(obj) => {
+            if let Some(Value::Array(args)) = obj.get(MATCHES_NAMED) {
+                if let Some(name) = args.get(1).and_then(Value::as_str) {
+                    names.push(name);
+                }
+            }
+            obj.values()
+                .for_each(|value| collect_named_pattern_refs(value, names));
+        }
+        Value::Array(items) => items
+            .iter()
+            .for_each(|item| collect_named_pattern_refs(item, names)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_named_pattern_refs() {
+        let rule = json!({
+            "or": [
+                {"matches_named": [{"var": "email"}, "corp"]},
+                {"and": [{"matches_named": [{"var": "host"}, "internal"]}, true]}
+            ]
+        });
+        assert_eq!(named_pattern_refs(&rule), vec!["corp", "internal"]);
+    }
+
+    #[test]
+    fn test_compile_patterns_rejects_invalid_regex() {
+        let error = compile_patterns(&json!({"broken": "(unclosed"})).unwrap_err();
+        assert!(error.starts_with("Invalid pattern 'broken'"), "{}", error);
+        assert!(compile_patterns(&json!({"number": 1})).is_err());
+        assert!(compile_patterns(&json!(["corp"])).is_err());
+    }
+}
@@ -0,0 +1,92 @@
+//! Context attribute presence and type operators.
+//!
+//! The `has` operator checks whether a context attribute is defined, which `var`
+//! cannot express because a missing attribute and a `null` default look the same.
+//! The `is_type` operator checks the JSON type of any value.
+
+use datalogic_rs::{ContextStack, Error as DataLogicError, Evaluator, Operator};
+use serde_json::Value;
+
+use super::common::OperatorResult;
+
+/// Custom operator checking that a context attribute is present and not null.
+///
+/// Takes a single `var` reference, e.g. `{"has": [{"var": "plan"}]}`. Dotted paths
+/// check nested attributes; a `var` default value is ignored.
+pub struct HasOperator;
+
+impl Operator for HasOperator {
+    fn evaluate(
+        &self,
+        args: &[Value],
+        context: &mut ContextStack,
+        _evaluator: &dyn Evaluator,
+    ) -> OperatorResult<Value> {
+        let path = match args {
+            [Value::Object(obj)] => match obj.get("var") {
+                Some(Value::String(path)) => Some(path.as_str()),
+                Some(Value::Array(items)) => items.first().and_then(Value::as_str),
+                _ => None,
+            },
+            _ => None,
+        }
+        .ok_or_else(|| {
+            DataLogicError::InvalidArguments("has operator requires a single var reference".into())
+        })?;
+
+        let root_ref = context.root();
+        let data = root_ref.data();
+        let found = if path.is_empty() {
+            Some(data)
+        } else {
+            path.split('.')
+                .try_fold(data, |current, part| current.get(part))
+        };
+
+        Ok(Value::Bool(found.is_some_and(|value| !value.is_null())))
+    }
+}
+
+/// Custom operator checking the JSON type of a value.
+///
+/// Takes a value (usually a `var` reference) and a type name, one of `null`,
+/// `boolean`, `number`, `string`, `array` or `object`, e.g.
+/// `{"is_type": [{"var": "age"}, "number"]}`. A missing attribute has type `null`.
+pub struct IsTypeOperator;
+
+impl Operator for IsTypeOperator {
+    fn evaluate(
+        &self,
+        args: &[Value],
+        context: &mut ContextStack,
+        evaluator: &dyn Evaluator,
+    ) -> OperatorResult<Value> {
+        let [value, type_name] = args else {
+            return Err(DataLogicError::InvalidArguments(
+                "is_type operator requires exactly 2 arguments".into(),
+            ));
+        };
+
+        let type_name = type_name.as_str().ok_or_else(|| {
+            DataLogicError::InvalidArguments("is_type type name must be a string".into())
+        })?;
+        let value = evaluator.evaluate(value, context)?;
+
+        let matches = match type_name {
+            "null" => value.is_null(),
+            "boolean" => value.is_boolean(),
+            "number" => value.is_number(),
+            "string" => value.is_string(),
+            "array" => value.is_array(),
+            "object" => value.is_object(),
+            _ => {
+                return Err(DataLogicError::InvalidArguments(format!(
+                    "Unknown is_type type name '{}'",
+                    type_name
+                )))
+            }
+        };
+
+        Ok(Value::Bool(matches))
+    }
+}
@@ -18,7 +18,7 @@ impl Operator for SemVerOperator {
         &self,
         args: &[Value],
         context: &mut ContextStack,
-        _evaluator: &dyn Evaluator,
+        evaluator: &dyn Evaluator,
     ) -> OperatorResult<Value> {
         if args.len() < 3 {
             return Err(DataLogicError::InvalidArguments(
@@ -26,13 +26,32 @@ impl Operator for SemVerOperator {
             ));
         }
 
-        let version = resolve_string_from_context(&args[0], context)?;
         let operator = args[1].as_str().ok_or_else(|| {
             DataLogicError::InvalidArguments("sem_ver operator must be a string".into())
         })?;
-        let target = resolve_string_from_context(&args[2], context)?;
 
-        match sem_ver(&version, operator, &target) {
+        // Range operators take two bounds, all others a single target
+        let expected_args = if is_range_operator(operator) { 4 } else { 3 };
+        if args.len() != expected_args {
+            return Err(DataLogicError::InvalidArguments(format!(
+                "sem_ver operator '{}' requires {} arguments, got {}",
+                operator,
+                expected_args,
+                args.len()
+            )));
+        }
+
+        let version = resolve_string_from_context(&args[0], context, evaluator)?;
+        let target = resolve_string_from_context(&args[2], context, evaluator)?;
+
+        let result = if expected_args == 4 {
+            let upper = resolve_string_from_context(&args[3], context, evaluator)?;
+            sem_ver_range(&version, operator, &target, &upper)
+        } else {
+            sem_ver(&version, operator, &target)
+        };
+
+        match result {
             Ok(result) => Ok(Value::Bool(result)),
             // For invalid versions, return false instead of error (matching Java behavior).
             // This allows if statements to gracefully fall through to the next branch
@@ -209,6 +228,9 @@ impl PartialOrd for SemVer {
 /// - `"^"` - Caret range (compatible with - allows patch and minor updates)
 /// - `"~"` - Tilde range (allows patch updates only)
 ///
+/// A target with `x`, `X` or `*` components, such as `"1.2.x"`, stands for the range
+/// of versions starting with the other components; see `WildcardRange`.
+///
 /// # Example
 /// ```json
 /// {"sem_ver": [{"var": "version"}, ">=", "2.0.0"]}
@@ -216,6 +238,9 @@ impl PartialOrd for SemVer {
 /// Returns `true` if version is "2.0.0" or higher
 pub fn sem_ver(version: &str, operator: &str, target: &str) -> Result<bool, String> {
     let version = SemVer::parse(version)?;
+    if let Some(range) = WildcardRange::parse(target)? {
+        return range.compare(&version, operator);
+    }
     let target = SemVer::parse(target)?;
 
     let result = match operator {
@@ -260,6 +285,134 @@ pub fn sem_ver(version: &str, operator: &str, target: &str) -> Result<bool, Stri
     Ok(result)
 }
 
+/// A sem_ver target with wildcard components, such as `1.2.x`, `1.*` or `x`.
+///
+/// The target matches every version starting with its fixed components: `1.2.x` is
+/// `>=1.2.0 <1.3.0`, `1.x` is `>=1.0.0 <2.0.0` and `x` matches any version. Like
+/// the `^` and `~` ranges, the range excludes prereleases of its upper bound, so
+/// `1.3.0-alpha` is not in `1.2.x`. Components after a wildcard must be wildcards.
+struct WildcardRange {
+    /// The components before the first wildcard
+    fixed: Vec<u64>,
+}
+
+impl WildcardRange {
+    /// Parses a target, returning `None` if none of its components is a wildcard.
+    fn parse(target: &str) -> Result<Option<Self>, String> {
+        let target = target.trim();
+        let target = target
+            .strip_prefix('v')
+            .or_else(|| target.strip_prefix('V'))
+            .unwrap_or(target);
+
+        let parts: Vec<&str> = target.split('.').collect();
+        let is_wildcard = |part: &&str| matches!(*part, "x" | "X" | "*");
+        let Some(first_wildcard) = parts.iter().position(is_wildcard) else {
+            return Ok(None);
+        };
+        let invalid = || format!("Invalid wildcard version: {}", target);
+        if parts.len() > 3 || !parts[first_wildcard..].iter().all(is_wildcard) {
+            return Err(invalid());
+        }
+        let fixed = parts[..first_wildcard]
+            .iter()
+            .map(|part| part.parse::<u64>().map_err(|_| invalid()))
+            .collect::<Result<_, _>>()?;
+        Ok(Some(Self { fixed }))
+    }
+
+    /// Evaluates a sem_ver comparison of `version` against the range.
+    ///
+    /// `=` and `~` test membership and `!=` its absence; `<` and `>=` compare with
+    /// the lowest version in the range, `<=` and `>` with the versions above it.
+    /// `^` keeps the lower bound but allows the same updates as for a full version:
+    /// `^1.2.x` is `>=1.2.0 <2.0.0` and `^0.0.x` is `>=0.0.0 <0.1.0`.
+    fn compare(&self, version: &SemVer, operator: &str) -> Result<bool, String> {
+        let lower = self.bound(None);
+        let below = |upper: Option<SemVer>| upper.is_none_or(|upper| *version < upper);
+        // The component bumped for the exclusive upper bound, if the range has one
+        let last_fixed = self.fixed.len().checked_sub(1);
+        let in_range = *version >= lower && below(last_fixed.map(|i| self.bound(Some(i))));
+
+        let result = match operator {
+            "=" | "~" => in_range,
+            "!=" => !in_range,
+            "<" => *version < lower,
+            "<=" => below(last_fixed.map(|i| self.bound(Some(i)))),
+            ">" => !below(last_fixed.map(|i| self.bound(Some(i)))),
+            ">=" => *version >= lower,
+            "^" => {
+                // Bump the first non-zero component, or the last fixed one if all are 0
+                let caret = self.fixed.iter().position(|c| *c != 0).or(last_fixed);
+                *version >= lower && below(caret.map(|i| self.bound(Some(i))))
+            }
+            _ => return Err(format!("Unknown operator: {}", operator)),
+        };
+        Ok(result)
+    }
+
+    /// Returns the lowest version in the range, or with `bump` the exclusive upper
+    /// bound found by incrementing that component.
+    ///
+    /// Upper bounds carry the lowest possible prerelease, `0`, so prereleases of the
+    /// bumped version compare above them.
+    fn bound(&self, bump: Option<usize>) -> SemVer {
+        let mut components = [0u64; 3];
+        components[..self.fixed.len()].copy_from_slice(&self.fixed);
+        if let Some(i) = bump {
+            components[i] = components[i].saturating_add(1);
+            components[i + 1..].fill(0);
+        }
+        SemVer {
+            major: components[0],
+            minor: components[1],
+            patch: components[2],
+            prerelease: bump.map(|_| "0".to_string()),
+            build_metadata: None,
+        }
+    }
+}
+
+/// Returns whether a sem_ver operator takes a lower and an upper bound.
+fn is_range_operator(operator: &str) -> bool {
+    matches!(operator, "in" | "in_inclusive")
+}
+
+/// Evaluates a two-bound sem_ver range.
+///
+/// This is an explicit alternative to the `^`/`~` range semantics.
+///
+/// # Arguments
+/// * `version` - The version string to check
+/// * `operator` - `"in"` (`>=lower <upper`) or `"in_inclusive"` (`>=lower <=upper`)
+/// * `lower` - The inclusive lower bound
+/// * `upper` - The upper bound
+///
+/// # Example
+/// ```json
+/// {"sem_ver": [{"var": "version"}, "in", "1.2.0", "2.0.0"]}
+/// ```
+/// Returns `true` if version is at least "1.2.0" and below "2.0.0"
+pub fn sem_ver_range(
+    version: &str,
+    operator: &str,
+    lower: &str,
+    upper: &str,
+) -> Result<bool, String> {
+    let version = SemVer::parse(version)?;
+    let lower = SemVer::parse(lower)?;
+    let upper = SemVer::parse(upper)?;
+
+    let above_lower = version >= lower;
+    let result = match operator {
+        "in" => above_lower && version < upper,
+        "in_inclusive" => above_lower && version <= upper,
+        _ => return Err(format!("Unknown range operator: {}", operator)),
+    };
+
+    Ok(result)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -462,6 +615,84 @@ mod tests {
         assert!(!sem_ver("2.0.0", "~", "1.2.3").unwrap());
     }
 
+    #[test]
+    fn test_sem_ver_range_matrix() {
+        // (version, operator, target, expected), just inside and just outside each range
+        let cases = [
+            // ^0.2.3 is >=0.2.3 <0.3.0
+            ("0.2.3", "^", "0.2.3", true),
+            ("0.2.99", "^", "0.2.3", true),
+            ("0.2.2", "^", "0.2.3", false),
+            ("0.2.3-rc.1", "^", "0.2.3", false),
+            ("0.3.0-alpha", "^", "0.2.3", false),
+            ("0.3.0", "^", "0.2.3", false),
+            // ^0.0.3 is >=0.0.3 <0.0.4
+            ("0.0.3", "^", "0.0.3", true),
+            ("0.0.3+build.7", "^", "0.0.3", true),
+            ("0.0.2", "^", "0.0.3", false),
+            ("0.0.4-alpha", "^", "0.0.3", false),
+            ("0.0.4", "^", "0.0.3", false),
+            // ~1.2.3 is >=1.2.3 <1.3.0
+            ("1.2.3", "~", "1.2.3", true),
+            ("1.2.99", "~", "1.2.3", true),
+            ("1.2.3-beta", "~", "1.2.3", false),
+            ("1.3.0-alpha", "~", "1.2.3", false),
+            ("1.3.0", "~", "1.2.3", false),
+            // 1.x is >=1.0.0 <2.0.0
+            ("1.0.0", "=", "1.x", true),
+            ("1.99.99", "=", "1.x", true),
+            ("0.99.99", "=", "1.x", false),
+            ("1.0.0-rc.1", "=", "1.x", false),
+            ("2.0.0-alpha", "=", "1.x", false),
+            ("2.0.0", "=", "1.x", false),
+            ("2.0.0", "!=", "1.x", true),
+            ("1.5.0", "!=", "1.X", false),
+            // 1.2.x and 1.2.* are >=1.2.0 <1.3.0
+            ("1.2.0", "=", "1.2.x", true),
+            ("1.2.99", "=", "1.2.*", true),
+            ("1.1.99", "=", "1.2.x", false),
+            ("1.3.0", "=", "v1.2.x", false),
+            ("1.2.5", "~", "1.2.x", true),
+            ("1.3.0", "~", "1.2.x", false),
+            // Comparisons against a wildcard compare with the whole range
+            ("1.1.99", "<", "1.2.x", true),
+            ("1.2.0", "<", "1.2.x", false),
+            ("1.2.99", "<=", "1.2.x", true),
+            ("1.3.0", "<=", "1.2.x", false),
+            ("1.3.0", ">", "1.2.x", true),
+            ("1.2.99", ">", "1.2.x", false),
+            ("1.2.0", ">=", "1.2.x", true),
+            ("1.1.99", ">=", "1.2.x", false),
+            // Caret ranges over wildcards bump the first non-zero component
+            ("1.9.0", "^", "1.2.x", true),
+            ("2.0.0", "^", "1.2.x", false),
+            ("1.1.0", "^", "1.2.x", false),
+            ("0.0.9", "^", "0.0.x", true),
+            ("0.1.0", "^", "0.0.x", false),
+            ("0.9.9", "^", "0.x", true),
+            ("1.0.0", "^", "0.x", false),
+            // A bare wildcard matches any version
+            ("0.0.0", "=", "x", true),
+            ("99.0.0", "=", "*", true),
+            ("99.0.0", ">", "*", false),
+        ];
+        for (version, operator, target, expected) in cases {
+            assert_eq!(
+                sem_ver(version, operator, target).unwrap(),
+                expected,
+                "{} {} {}",
+                version,
+                operator,
+                target
+            );
+        }
+
+        // Fixed components may not follow a wildcard
+        assert!(sem_ver("1.2.3", "=", "1.x.3").is_err());
+        assert!(sem_ver("1.2.3", "=", "1.2.x.x").is_err());
+        assert!(sem_ver("1.2.3", "??", "1.x").is_err());
+    }
+
     #[test]
     fn test_sem_ver_with_prerelease() {
         // Prerelease versions
@@ -482,6 +713,27 @@ mod tests {
         assert!(sem_ver("1.2.3", "??", "1.2.3").is_err());
     }
 
+    #[test]
+    fn test_sem_ver_range_in() {
+        // In range
+        assert!(sem_ver_range("1.5.0", "in", "1.2.0", "2.0.0").unwrap());
+        // Lower bound is inclusive, upper bound is exclusive
+        assert!(sem_ver_range("1.2.0", "in", "1.2.0", "2.0.0").unwrap());
+        assert!(!sem_ver_range("2.0.0", "in", "1.2.0", "2.0.0").unwrap());
+        assert!(sem_ver_range("2.0.0-rc.1", "in", "1.2.0", "2.0.0").unwrap());
+        // Out of range
+        assert!(!sem_ver_range("1.1.9", "in", "1.2.0", "2.0.0").unwrap());
+        assert!(!sem_ver_range("2.1.0", "in", "1.2.0", "2.0.0").unwrap());
+    }
+
+    #[test]
+    fn test_sem_ver_range_in_inclusive() {
+        assert!(sem_ver_range("1.2.0", "in_inclusive", "1.2.0", "2.0.0").unwrap());
+        assert!(sem_ver_range("2.0.0", "in_inclusive", "1.2.0", "2.0.0").unwrap());
+        assert!(!sem_ver_range("2.0.1", "in_inclusive", "1.2.0", "2.0.0").unwrap());
+        assert!(sem_ver_range("1.2.0", ">=", "1.0.0", "2.0.0").is_err());
+    }
+
     #[test]
     fn test_sem_ver_invalid_version() {
         assert!(sem_ver("not.a.version", "=", "1.2.3").is_err());
@@ -0,0 +1,159 @@
+//! Injectable platform services for the core evaluation logic.
+//!
+//! The evaluation core does not call OS-dependent facilities such as
+//! `std::time::SystemTime` or `eprintln!` directly. With the `os` feature (the
+//! default), the system clock and stderr are used unless something else is
+//! installed. Without it, embedders supply a clock and a logger through
+//! `set_clock` and `set_logger`; until they do, the time is 0 and warnings are
+//! dropped.
+//!
+//! Note that the crate as a whole still links the standard library: the schema
+//! validator and JSON Logic engine require it. The feature only removes the
+//! dependency on an operating system.
+
+use std::panic::UnwindSafe;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+
+static CLOCK: OnceLock<fn() -> u64> = OnceLock::new();
+static LOGGER: OnceLock<fn(&str)> = OnceLock::new();
+
+/// Installs the clock used for `$flagd.timestamp`, returning Unix time in seconds.
+///
+/// The clock can only be installed once; returns `false` if one already was.
+/// An installed clock takes precedence over the host and system clocks.
+pub fn set_clock(clock: fn() -> u64) -> bool {
+    CLOCK.set(clock).is_ok()
+}
+
+/// Installs the logger used for warnings emitted during evaluation and parsing.
+///
+/// The logger can only be installed once; returns `false` if one already was.
+pub fn set_logger(logger: fn(&str)) -> bool {
+    LOGGER.set(logger).is_ok()
+}
+
+/// Returns the time from the installed clock, if any.
+pub(crate) fn injected_time() -> Option<u64> {
+    CLOCK.get().map(|clock| clock())
+}
+
+/// Emits a warning through the installed logger, or stderr with the `os` feature.
+pub(crate) fn warn(message: &str) {
+    match LOGGER.get() {
+        Some(logger) => logger(message),
+        None => default_warn(message),
+    }
+}
+
+const HOST_CLOCK_UNKNOWN: u8 = 0;
+const HOST_CLOCK_AVAILABLE: u8 = 1;
+const HOST_CLOCK_UNAVAILABLE: u8 = 2;
+
+/// Remembers whether a host time source works, so a broken one is only called once.
+///
+/// The first read probes the source. If it panics or returns 0 (what hosts without a
+/// clock return from a stub import), the source is marked unavailable and every later
+/// read returns 0 without calling it again.
+#[cfg_attr(not(target_family = "wasm"), allow(dead_code))]
+pub(crate) struct HostClock {
+    state: AtomicU8,
+}
+
+#[cfg_attr(not(target_family = "wasm"), allow(dead_code))]
+impl HostClock {
+    pub(crate) const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(HOST_CLOCK_UNKNOWN),
+        }
+    }
+
+    /// Reads the time from `source`, or returns 0 if it was found to be unavailable.
+    pub(crate) fn now(&self, source: impl FnOnce() -> u64 + UnwindSafe) -> u64 {
+        match self.state.load(Ordering::Acquire) {
+            HOST_CLOCK_UNAVAILABLE => 0,
+            HOST_CLOCK_AVAILABLE => std::panic::catch_unwind(source).unwrap_or(0),
+            _ => {
+                let now = std::panic::catch_unwind(source).unwrap_or(0);
+                let state = if now == 0 {
+                    HOST_CLOCK_UNAVAILABLE
+                } else {
+                    HOST_CLOCK_AVAILABLE
+                };
+                self.state.store(state, Ordering::Release);
+                now
+            }
+        }
+    }
+
+    /// Returns whether the source works, or `None` if it has not been probed yet.
+    pub(crate) fn is_available(&self) -> Option<bool> {
+        match self.state.load(Ordering::Acquire) {
+            HOST_CLOCK_AVAILABLE => Some(true),
+            HOST_CLOCK_UNAVAILABLE => Some(false),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "os")]
+fn default_warn(message: &str) {
+    eprintln!("{}", message);
+}
+
+#[cfg(not(feature = "os"))]
+fn default_warn(_message: &str) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    static LOGGED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    fn record(message: &str) {
+        LOGGED.lock().unwrap().push(message.to_string());
+    }
+
+    #[test]
+    fn test_injected_logger_receives_warnings() {
+        // The logger is process-wide, so this is the only test that installs one
+        assert!(set_logger(record));
+        assert!(!set_logger(record));
+
+        warn("first warning");
+        assert!(LOGGED
+            .lock()
+            .unwrap()
+            .contains(&"first warning".to_string()));
+    }
+
+    #[test]
+    fn test_unavailable_host_clock_is_probed_once() {
+        use std::sync::atomic::AtomicUsize;
+
+        let clock = HostClock::new();
+        let calls = AtomicUsize::new(0);
+        let unavailable = || {
+            calls.fetch_add(1, Ordering::SeqCst);
+            0
+        };
+
+        assert_eq!(clock.is_available(), None);
+        for _ in 0..3 {
+            assert_eq!(clock.now(unavailable), 0);
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(clock.is_available(), Some(false));
+
+        let panicking = HostClock::new();
+        assert_eq!(panicking.now(|| panic!("host function missing")), 0);
+        assert_eq!(panicking.now(|| 1_700_000_000), 0);
+        assert_eq!(panicking.is_available(), Some(false));
+
+        let available = HostClock::new();
+        assert_eq!(available.now(|| 1_700_000_000), 1_700_000_000);
+        assert_eq!(available.now(|| 1_700_000_001), 1_700_000_001);
+        assert_eq!(available.is_available(), Some(true));
+    }
+}
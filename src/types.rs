@@ -3,9 +3,53 @@
 //! This module provides the data structures for representing evaluation results
 //! according to the flagd provider specification.
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+
+/// Serializes a map with its keys in ascending order.
+///
+/// `HashMap` iteration order differs between runs, so maps in JSON output are
+/// serialized through this to make equal values serialize to identical bytes.
+pub(crate) fn serialize_sorted<S, V>(
+    map: &HashMap<String, V>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    V: Serialize,
+{
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+/// Like `serialize_sorted`, for an optional map.
+pub(crate) fn serialize_sorted_option<S, V>(
+    map: &Option<HashMap<String, V>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    V: Serialize,
+{
+    map.as_ref()
+        .map(|map| map.iter().collect::<BTreeMap<_, _>>())
+        .serialize(serializer)
+}
+
+/// Like `serialize_sorted`, sorting the nested maps as well.
+pub(crate) fn serialize_sorted_nested<S, V>(
+    map: &HashMap<String, HashMap<String, V>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    V: Serialize,
+{
+    map.iter()
+        .map(|(key, inner)| (key, inner.iter().collect::<BTreeMap<_, _>>()))
+        .collect::<BTreeMap<_, _>>()
+        .serialize(serializer)
+}
 
 /// The reason for the evaluation result.
 ///
@@ -26,6 +70,11 @@ pub enum ResolutionReason {
     /// The flag was not found in the configuration.
     FlagNotFound,
     Fallback,
+    /// A disabled flag was evaluated as though it were enabled (see `evaluate_preview`).
+    Preview,
+    /// A prerequisite flag did not resolve to the required variant, so the default
+    /// variant is used.
+    PrerequisiteFailed,
 }
 
 /// Error codes matching the flagd provider specification.
@@ -42,6 +91,213 @@ pub enum ErrorCode {
     General,
 }
 
+impl ErrorCode {
+    /// Numeric code used by the compact encodings: 1 = `FLAG_NOT_FOUND`,
+    /// 2 = `PARSE_ERROR`, 3 = `TYPE_MISMATCH`, 4 = `GENERAL` (0 means no error).
+    pub fn code(&self) -> u8 {
+        match self {
+            ErrorCode::FlagNotFound => 1,
+            ErrorCode::ParseError => 2,
+            ErrorCode::TypeMismatch => 3,
+            ErrorCode::General => 4,
+        }
+    }
+
+    /// Decodes a numeric code, returning `None` for 0 and unknown codes.
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            1 => Some(ErrorCode::FlagNotFound),
+            2 => Some(ErrorCode::ParseError),
+            3 => Some(ErrorCode::TypeMismatch),
+            4 => Some(ErrorCode::General),
+            _ => None,
+        }
+    }
+}
+
+/// How single-result evaluation exports frame their JSON response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResultFraming {
+    /// The response is the result JSON (default)
+    #[default]
+    Json,
+    /// The response is one status byte followed by the result JSON. The status is
+    /// 0 on success, otherwise the error code as numbered by `ErrorCode::code`, so
+    /// hosts can branch on errors without parsing the JSON.
+    StatusPrefixed,
+}
+
+/// How a flag's targeting rule was configured.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetingPresence {
+    /// The flag has no `targeting` property.
+    Absent,
+    /// The flag has an empty `targeting: {}` rule (e.g. "intentionally no rule yet").
+    Empty,
+    /// The flag has a targeting rule that was evaluated.
+    Rule,
+}
+
+/// Why an evaluation did not resolve to a variant selected by targeting.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DefaultReason {
+    /// The targeting rule evaluated to `null`, so the default variant was used.
+    TargetingReturnedNull,
+    /// The flag has no (or an empty) targeting rule.
+    NoTargeting,
+    /// The selected variant (or the default variant) is not defined in the flag's variants.
+    VariantNotFound,
+    /// The flag is disabled.
+    Disabled,
+    /// The targeting rule returned a value that is not a variant name, such as an object.
+    InvalidTargetingResult,
+    /// A prerequisite flag did not resolve to the required variant.
+    PrerequisiteFailed,
+}
+
+/// A compact, JSON-free encoding of which variant an evaluation selected and why.
+///
+/// Produced by `FlagEvaluator::evaluate_variant_code` for analytics pipelines that
+/// only record the variant and reason. `pack` encodes it into a `u64`:
+///
+/// | Bits | Field |
+/// |------|-------|
+/// | 32..64 | Index of the variant in the flag's variant names sorted ascending (see `FlagEvaluator::variant_names`), or `u32::MAX` if no variant was selected |
+/// | 16..32 | Reserved (0) |
+/// | 8..16 | Error code: 0 = none, 1 = `FLAG_NOT_FOUND`, 2 = `PARSE_ERROR`, 3 = `TYPE_MISMATCH`, 4 = `GENERAL` |
+/// | 0..8 | Reason: 0 = `STATIC`, 1 = `DEFAULT`, 2 = `TARGETING_MATCH`, 3 = `DISABLED`, 4 = `ERROR`, 5 = `FLAG_NOT_FOUND`, 6 = `FALLBACK`, 7 = `PREVIEW`, 8 = `PREREQUISITE_FAILED` |
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariantCode {
+    /// Index of the selected variant in the sorted variant names, if any.
+    pub variant_index: Option<u32>,
+    /// The reason for the resolution.
+    pub reason: ResolutionReason,
+    /// Error code if an error occurred.
+    pub error_code: Option<ErrorCode>,
+}
+
+impl VariantCode {
+    /// Packed value of `variant_index` when no variant was selected.
+    pub const NO_VARIANT: u32 = u32::MAX;
+
+    /// Encodes the code into a `u64` (see the type documentation for the layout).
+    pub fn pack(&self) -> u64 {
+        let variant = self.variant_index.unwrap_or(Self::NO_VARIANT) as u64;
+        let error_code = self.error_code.as_ref().map_or(0, ErrorCode::code) as u64;
+        let reason: u64 = match self.reason {
+            ResolutionReason::Static => 0,
+            ResolutionReason::Default => 1,
+            ResolutionReason::TargetingMatch => 2,
+            ResolutionReason::Disabled => 3,
+            ResolutionReason::Error => 4,
+            ResolutionReason::FlagNotFound => 5,
+            ResolutionReason::Fallback => 6,
+            ResolutionReason::Preview => 7,
+            ResolutionReason::PrerequisiteFailed => 8,
+        };
+        (variant << 32) | (error_code << 8) | reason
+    }
+
+    /// Decodes a packed code, returning `None` if the reason or error code is unknown.
+    pub fn unpack(code: u64) -> Option<Self> {
+        let variant = (code >> 32) as u32;
+        let error_code = match ((code >> 8) & 0xFF) as u8 {
+            0 => None,
+            n => Some(ErrorCode::from_code(n)?),
+        };
+        let reason = match code & 0xFF {
+            0 => ResolutionReason::Static,
+            1 => ResolutionReason::Default,
+            2 => ResolutionReason::TargetingMatch,
+            3 => ResolutionReason::Disabled,
+            4 => ResolutionReason::Error,
+            5 => ResolutionReason::FlagNotFound,
+            6 => ResolutionReason::Fallback,
+            7 => ResolutionReason::Preview,
+            8 => ResolutionReason::PrerequisiteFailed,
+            _ => return None,
+        };
+        Some(Self {
+            variant_index: (variant != Self::NO_VARIANT).then_some(variant),
+            reason,
+            error_code,
+        })
+    }
+}
+
+/// The value type of a flag, inferred from its variants.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum FlagType {
+    /// All variants are booleans.
+    Boolean,
+    /// All variants are strings.
+    String,
+    /// All variants are integers.
+    Integer,
+    /// All variants are numbers, at least one of them fractional.
+    Float,
+    /// All variants are objects.
+    Object,
+    /// The variants have different types (or there are none), so no single type applies.
+    Mixed,
+}
+
+/// An evaluation result resolved as the flag's inferred type.
+///
+/// Produced by `FlagEvaluator::evaluate_all_typed`. Serializes as the result's
+/// fields plus a `type` field.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TypedEvaluationResult {
+    /// The evaluation result, type checked against `flag_type` unless it is `Mixed`.
+    #[serde(flatten)]
+    pub result: EvaluationResult,
+
+    /// The type the flag was resolved as.
+    #[serde(rename = "type")]
+    pub flag_type: FlagType,
+}
+
+impl TypedEvaluationResult {
+    /// Appends the JSON serialization of the result to an existing buffer.
+    ///
+    /// See `EvaluationResult::write_json`.
+    pub fn write_json(&self, out: &mut Vec<u8>) {
+        let start = out.len();
+        if let Err(e) = serde_json::to_writer(&mut *out, self) {
+            out.truncate(start);
+            out.extend_from_slice(EvaluationResult::serialization_failure(&e).as_bytes());
+        }
+    }
+}
+
+/// An evaluation result together with diagnostic details explaining how it was reached.
+///
+/// Produced by `FlagEvaluator::explain_flag`. The `result` is identical to what
+/// `FlagEvaluator::evaluate_flag` returns for the same flag and context.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvaluationTrace {
+    /// The evaluation result.
+    pub result: EvaluationResult,
+
+    /// How the flag's targeting was configured (absent when the flag was not found).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub targeting: Option<TargetingPresence>,
+
+    /// Why the default was returned instead of a targeted variant (absent on a match).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_reason: Option<DefaultReason>,
+
+    /// The bucketing key's percentile in `[0, 100)`, derived from its normalized hash.
+    ///
+    /// Present only when a fractional rule ran; if several did, this is the last one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bucket_percentile: Option<f64>,
+}
+
 /// The result of a feature flag evaluation.
 ///
 /// This structure matches the flagd provider specification for evaluation results.
@@ -78,8 +334,21 @@ pub struct EvaluationResult {
     /// - For disabled flags: both flag-set and flag metadata are merged
     /// - For missing flags (FLAG_NOT_FOUND): only flag-set metadata is returned
     /// - For error cases: metadata is omitted
-    #[serde(skip_serializing_if = "Option::is_none")]
+    ///
+    /// Keys are serialized in ascending order.
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_sorted_option"
+    )]
     pub flag_metadata: Option<HashMap<String, Value>>,
+
+    /// The variant value as configured, when a typed evaluation coerced it.
+    ///
+    /// Only set when raw value reporting is enabled on the evaluator and `value` differs
+    /// from the stored variant, e.g. an integer evaluation of the variant `3.7` returns
+    /// `value` 3 and `raw_value` 3.7.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_value: Option<Value>,
 }
 
 impl EvaluationResult {
@@ -94,6 +363,7 @@ impl EvaluationResult {
             error_code: None,
             error_message: None,
             flag_metadata: None,
+            raw_value: None,
         }
     }
 
@@ -108,6 +378,36 @@ impl EvaluationResult {
             error_code: None,
             error_message: None,
             flag_metadata: None,
+            raw_value: None,
+        }
+    }
+
+    /// Creates a default variant result for a flag whose prerequisites are not met.
+    pub fn prerequisite_failed(value: Value, variant: String) -> Self {
+        Self {
+            value,
+            variant: Some(variant),
+            reason: ResolutionReason::PrerequisiteFailed,
+            error_code: None,
+            error_message: None,
+            flag_metadata: None,
+            raw_value: None,
+        }
+    }
+
+    /// Creates a result using a flag-set-level default value.
+    ///
+    /// Used when a flag has no default variant but the flag set configures a
+    /// default for the flag's type. No variant is reported.
+    pub fn flag_set_default(value: Value) -> Self {
+        Self {
+            value,
+            variant: None,
+            reason: ResolutionReason::Default,
+            error_code: None,
+            error_message: None,
+            flag_metadata: None,
+            raw_value: None,
         }
     }
 
@@ -122,6 +422,7 @@ impl EvaluationResult {
             error_code: None,
             error_message: None,
             flag_metadata: None,
+            raw_value: None,
         }
     }
 
@@ -134,6 +435,7 @@ impl EvaluationResult {
             error_code: None,
             error_message: None,
             flag_metadata: None,
+            raw_value: None,
         }
     }
 
@@ -146,9 +448,19 @@ impl EvaluationResult {
             error_code: Some(error_code),
             error_message: Some(error_message.into()),
             flag_metadata: None,
+            raw_value: None,
         }
     }
 
+    /// Creates the result returned when no flag configuration has been loaded yet.
+    ///
+    /// This is distinct from a flag missing from a loaded configuration
+    /// (`FLAG_NOT_FOUND`): hosts that evaluate while the initial `update_state` is
+    /// still in flight can recognize it and treat the provider as not ready.
+    pub fn uninitialized() -> Self {
+        Self::error(ErrorCode::General, "state uninitialized")
+    }
+
     /// Creates a flag not found result.
     pub fn flag_not_found(flag_key: &str) -> Self {
         Self {
@@ -158,6 +470,7 @@ impl EvaluationResult {
             error_code: Some(ErrorCode::FlagNotFound),
             error_message: Some(format!("Flag '{}' not found in configuration", flag_key)),
             flag_metadata: None,
+            raw_value: None,
         }
     }
 
@@ -178,6 +491,7 @@ impl EvaluationResult {
                 flag_key
             )),
             flag_metadata: None,
+            raw_value: None,
         }
     }
 
@@ -189,13 +503,114 @@ impl EvaluationResult {
 
     /// Serializes the result to a JSON string.
     pub fn to_json_string(&self) -> String {
-        serde_json::to_string(self).unwrap_or_else(|e| {
-            format!(
-                r#"{{"value":null,"reason":"ERROR","errorCode":"GENERAL","errorMessage":"Serialization failed: {}"}}"#,
-                e
-            )
+        serde_json::to_string(self).unwrap_or_else(|e| Self::serialization_failure(&e))
+    }
+
+    /// Serializes the result with the given framing (see `ResultFraming`).
+    pub fn to_framed_bytes(&self, framing: ResultFraming) -> Vec<u8> {
+        let mut out = Vec::new();
+        if framing == ResultFraming::StatusPrefixed {
+            out.push(self.error_code.as_ref().map_or(0, ErrorCode::code));
+        }
+        self.write_json(&mut out);
+        out
+    }
+
+    /// Reads the error code from the status byte of a `StatusPrefixed` response
+    /// without parsing the JSON body.
+    ///
+    /// Returns `Some(None)` on success, `Some(Some(code))` on error, and `None` if the
+    /// response is empty or the status byte is unknown.
+    pub fn peek_error_code(framed: &[u8]) -> Option<Option<ErrorCode>> {
+        match *framed.first()? {
+            0 => Some(None),
+            status => ErrorCode::from_code(status).map(Some),
+        }
+    }
+
+    /// Appends the JSON serialization of the result to an existing buffer.
+    ///
+    /// Used by batch paths to serialize many results without allocating a
+    /// separate `String` per result. On failure, any partially written output is
+    /// discarded and the same error object as `to_json_string` is written instead.
+    pub fn write_json(&self, out: &mut Vec<u8>) {
+        let start = out.len();
+        if let Err(e) = serde_json::to_writer(&mut *out, self) {
+            out.truncate(start);
+            out.extend_from_slice(Self::serialization_failure(&e).as_bytes());
+        }
+    }
+
+    /// Returns a JSON Schema (draft-07) describing the serialized result.
+    ///
+    /// The `reason` and `errorCode` enums are generated from the Rust enums, so their
+    /// spelling always matches what is serialized; the properties mirror the struct's
+    /// fields, and additional properties are rejected so that a new field has to be
+    /// added here as well.
+    pub fn json_schema() -> Value {
+        let reasons = [
+            ResolutionReason::Static,
+            ResolutionReason::Default,
+            ResolutionReason::TargetingMatch,
+            ResolutionReason::Disabled,
+            ResolutionReason::Error,
+            ResolutionReason::FlagNotFound,
+            ResolutionReason::Fallback,
+            ResolutionReason::Preview,
+            ResolutionReason::PrerequisiteFailed,
+        ];
+        let error_codes = [
+            ErrorCode::FlagNotFound,
+            ErrorCode::ParseError,
+            ErrorCode::TypeMismatch,
+            ErrorCode::General,
+        ];
+
+        serde_json::json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": "EvaluationResult",
+            "description": "The result of a feature flag evaluation.",
+            "type": "object",
+            "required": ["value", "reason"],
+            "properties": {
+                "value": {
+                    "description": "The resolved value of the flag."
+                },
+                "variant": {
+                    "description": "The variant name that was selected.",
+                    "type": "string"
+                },
+                "reason": {
+                    "description": "The reason for the resolution.",
+                    "enum": reasons
+                },
+                "errorCode": {
+                    "description": "Error code if an error occurred.",
+                    "enum": error_codes
+                },
+                "errorMessage": {
+                    "description": "Error message if an error occurred.",
+                    "type": "string"
+                },
+                "flagMetadata": {
+                    "description": "Merged flag-set and flag metadata.",
+                    "type": "object"
+                },
+                "rawValue": {
+                    "description": "The variant value as configured, when the typed evaluation coerced it."
+                }
+            },
+            "additionalProperties": false
         })
     }
+
+    /// Builds the JSON error object reported when a result cannot be serialized.
+    fn serialization_failure(e: &serde_json::Error) -> String {
+        format!(
+            r#"{{"value":null,"reason":"ERROR","errorCode":"GENERAL","errorMessage":"Serialization failed: {}"}}"#,
+            e
+        )
+    }
 }
 
 #[cfg(test)]
@@ -274,6 +689,32 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_metadata_serialization_is_deterministic() {
+        let keys: Vec<String> = (0..32).map(|i| format!("key{}", i)).collect();
+        let serialize = |keys: &mut dyn Iterator<Item = &String>| {
+            let metadata = keys.map(|key| (key.clone(), json!(key))).collect();
+            EvaluationResult::static_result(json!(true), "on".to_string())
+                .with_metadata(metadata)
+                .to_json_string()
+        };
+
+        // Maps built separately (with different hash seeds and insertion orders)
+        // serialize identically, with sorted keys
+        let first = serialize(&mut keys.iter());
+        for _ in 0..8 {
+            assert_eq!(serialize(&mut keys.iter()), first);
+            assert_eq!(serialize(&mut keys.iter().rev()), first);
+        }
+        let mut sorted = keys.clone();
+        sorted.sort();
+        let positions: Vec<usize> = sorted
+            .iter()
+            .map(|key| first.find(&format!("\"{}\":", key)).unwrap())
+            .collect();
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
     #[test]
     fn test_to_json_string() {
         let result = EvaluationResult::static_result(json!(42), "variant".to_string());
@@ -283,6 +724,39 @@ mod tests {
         assert!(json_str.contains("\"reason\":\"STATIC\""));
     }
 
+    #[test]
+    fn test_write_json_appends_same_output() {
+        let result = EvaluationResult::targeting_match(json!({"a": 1}), "obj".to_string());
+        let mut buf = b"prefix:".to_vec();
+        result.write_json(&mut buf);
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            format!("prefix:{}", result.to_json_string())
+        );
+    }
+
+    #[test]
+    fn test_variant_code_round_trip() {
+        let code = VariantCode {
+            variant_index: Some(3),
+            reason: ResolutionReason::TargetingMatch,
+            error_code: None,
+        };
+        assert_eq!(code.pack(), (3 << 32) | 2);
+        assert_eq!(VariantCode::unpack(code.pack()), Some(code));
+
+        let code = VariantCode {
+            variant_index: None,
+            reason: ResolutionReason::Error,
+            error_code: Some(ErrorCode::TypeMismatch),
+        };
+        assert_eq!(code.pack(), (0xFFFF_FFFF << 32) | (3 << 8) | 4);
+        assert_eq!(VariantCode::unpack(code.pack()), Some(code));
+
+        assert_eq!(VariantCode::unpack(0xFF), None);
+        assert_eq!(VariantCode::unpack(0x0900), None);
+    }
+
     #[test]
     fn test_all_error_codes_serialize() {
         let error_codes = vec![
@@ -335,4 +809,40 @@ mod tests {
             assert_eq!(parsed["reason"], expected_reason);
         }
     }
+
+    #[cfg(feature = "validation")]
+    #[test]
+    fn test_json_schema_accepts_real_results() {
+        let mut schemas = boon::Schemas::new();
+        let mut compiler = boon::Compiler::new();
+        compiler
+            .add_resource("http://example.com/result", EvaluationResult::json_schema())
+            .unwrap();
+        let index = compiler
+            .compile("http://example.com/result", &mut schemas)
+            .unwrap();
+
+        let mut metadata = HashMap::new();
+        metadata.insert("team".to_string(), json!("growth"));
+        let results = [
+            EvaluationResult::targeting_match(json!("red"), "red".to_string())
+                .with_metadata(metadata),
+            EvaluationResult::error(ErrorCode::ParseError, "bad rule"),
+            EvaluationResult::flag_not_found("missing"),
+            EvaluationResult::fallback("noDefault"),
+        ];
+        for result in results {
+            let instance: Value = serde_json::from_str(&result.to_json_string()).unwrap();
+            assert!(
+                schemas.validate(&instance, index).is_ok(),
+                "{} does not match the schema",
+                instance
+            );
+        }
+
+        let unknown_field = json!({"value": true, "reason": "STATIC", "extra": 1});
+        assert!(schemas.validate(&unknown_field, index).is_err());
+        let unknown_reason = json!({"value": true, "reason": "SOMETHING_ELSE"});
+        assert!(schemas.validate(&unknown_reason, index).is_err());
+    }
 }
@@ -1,21 +1,35 @@
 //! JSON Schema validation for feature flag configurations.
 //!
 //! This module provides validation of flag configurations against the official
-//! flagd JSON schema from https://github.com/open-feature/flagd-schemas.
+//! flagd JSON schema from https://github.com/open-feature/flagd-schemas, followed
+//! by semantic checks the schema cannot express (e.g. fractional bucket names that
+//! are not defined variants).
+//!
+//! The schema check requires the `validation` feature (on by default). Without it,
+//! the schema and the boon validator are left out of the build, which noticeably
+//! shrinks the WASM module, and only the semantic checks run. Hosts that disable
+//! it should validate configurations upstream: a config that is well-formed JSON
+//! but violates the schema may then be accepted or fail later during parsing.
 
+use crate::model::remove_annotations;
+use crate::operators::MAX_TOTAL_WEIGHT;
+#[cfg(feature = "validation")]
 use boon::{Compiler, SchemaIndex, Schemas};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+#[cfg(feature = "validation")]
 use std::cell::RefCell;
 
 /// The embedded JSON Schema for flag definitions.
 ///
 /// This schema is loaded from the official flagd-schemas repository at build time.
+#[cfg(feature = "validation")]
 const FLAGS_SCHEMA: &str = include_str!("../schemas/flags.json");
 
 /// The embedded JSON Schema for targeting rules.
 ///
 /// This schema is referenced by the flags schema.
+#[cfg(feature = "validation")]
 const TARGETING_SCHEMA: &str = include_str!("../schemas/targeting.json");
 
 /// Fallback error JSON when serialization fails.
@@ -23,11 +37,13 @@ const VALIDATION_RESULT_FALLBACK: &str =
     r#"{"valid":false,"errors":[{"path":"","message":"Failed to serialize validation result"}]}"#;
 
 /// Cached compiled schema data for boon
+#[cfg(feature = "validation")]
 struct CompiledSchema {
     schemas: Schemas,
     schema_index: SchemaIndex,
 }
 
+#[cfg(feature = "validation")]
 thread_local! {
     /// Thread-local cached compiled schema.
     ///
@@ -56,7 +72,7 @@ impl ValidationError {
 }
 
 /// Represents the result of schema validation.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ValidationResult {
     /// Whether the validation succeeded
     pub valid: bool,
@@ -85,12 +101,20 @@ impl ValidationResult {
     pub fn to_json_string(&self) -> String {
         serde_json::to_string(self).unwrap_or_else(|_| VALIDATION_RESULT_FALLBACK.to_string())
     }
+
+    /// Parses a validation result from the JSON produced by `to_json_string`.
+    ///
+    /// Lets tooling that consumes the error envelope recover the structured errors.
+    pub fn from_json_string(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("Invalid validation result JSON: {}", e))
+    }
 }
 
 /// Gets or compiles the JSON schema validator.
 ///
 /// The validator is compiled once per thread and cached for subsequent use.
 /// In WASM environments (single-threaded), this effectively caches it globally.
+#[cfg(feature = "validation")]
 fn get_compiled_schema() -> Result<(), String> {
     COMPILED_SCHEMA.with(|schema| {
         let mut schema_ref = schema.borrow_mut();
@@ -136,6 +160,7 @@ fn get_compiled_schema() -> Result<(), String> {
 }
 
 /// Validates using the cached schema.
+#[cfg(feature = "validation")]
 fn validate_with_schema(config: &Value) -> Result<(), Vec<ValidationError>> {
     COMPILED_SCHEMA.with(|schema| {
         let schema_ref = schema.borrow();
@@ -205,24 +230,33 @@ pub fn validate_flags_config(json_str: &str) -> Result<(), ValidationResult> {
     // Catch any panics in validation and convert to errors
     let result = std::panic::catch_unwind(|| {
         // First, try to parse the JSON
-        let config: Value = match serde_json::from_str(json_str) {
+        let mut config: Value = match serde_json::from_str(json_str) {
             Ok(v) => v,
             Err(e) => {
                 let error = ValidationError::new("", format!("Invalid JSON: {}", e));
                 return Err(ValidationResult::failure(vec![error]));
             }
         };
+        // Annotations such as `$comment` are not part of the schema
+        remove_annotations(&mut config);
 
-        // Ensure the schema is compiled (cached after first use)
-        if let Err(e) = get_compiled_schema() {
-            let error = ValidationError::new("", e);
-            return Err(ValidationResult::failure(vec![error]));
-        }
+        let mut errors = Vec::new();
+        #[cfg(feature = "validation")]
+        {
+            // Ensure the schema is compiled (cached after first use)
+            if let Err(e) = get_compiled_schema() {
+                let error = ValidationError::new("", e);
+                return Err(ValidationResult::failure(vec![error]));
+            }
 
-        // Validate the configuration using the cached schema
-        match validate_with_schema(&config) {
-            Ok(()) => Ok(()),
-            Err(errors) => Err(ValidationResult::failure(errors)),
+            // Validate the configuration using the cached schema
+            errors.extend(validate_with_schema(&config).err().unwrap_or_default());
+        }
+        errors.extend(validate_fractional_buckets(&config));
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationResult::failure(errors))
         }
     });
 
@@ -243,6 +277,348 @@ pub fn validate_flags_config(json_str: &str) -> Result<(), ValidationResult> {
     }
 }
 
+/// Top-level configuration keys the flagd schema defines (`$`-prefixed keys aside).
+const CONFIG_KEYS: &[&str] = &["flags", "metadata", "state", "patterns", "defaults"];
+
+/// Flag-level keys the flagd schema defines, plus the `key` of flags given as an array.
+const FLAG_KEYS: &[&str] = &[
+    "key",
+    "state",
+    "defaultVariant",
+    "variants",
+    "targeting",
+    "metadata",
+    "variantMetadata",
+    "variantAliases",
+    "prerequisites",
+];
+
+/// Lists the top-level and flag-level keys of a configuration the schema does not define.
+///
+/// The schema allows additional properties at both levels, so a typo such as
+/// `defaultVarient` is otherwise accepted and silently ignored. Keys starting with `$`
+/// are extensions (such as `$evaluators` or `$comment`) and are never reported.
+/// Invalid JSON yields no errors here; `validate_flags_config` reports it.
+///
+/// # Example
+///
+/// ```
+/// use flagd_evaluator::validation::find_unknown_keys;
+///
+/// let config = r#"{
+///     "flags": {
+///         "myFlag": {
+///             "state": "ENABLED",
+///             "variants": {"on": true, "off": false},
+///             "defaultVarient": "on"
+///         }
+///     }
+/// }"#;
+///
+/// let errors = find_unknown_keys(config);
+/// assert_eq!(errors.len(), 1);
+/// assert_eq!(errors[0].path, "/flags/myFlag/defaultVarient");
+/// ```
+pub fn find_unknown_keys(json_str: &str) -> Vec<ValidationError> {
+    let Ok(Value::Object(config)) = serde_json::from_str::<Value>(json_str) else {
+        return Vec::new();
+    };
+    let is_unknown = |key: &str, known: &[&str]| !key.starts_with('$') && !known.contains(&key);
+
+    let mut errors: Vec<ValidationError> = config
+        .keys()
+        .filter(|key| is_unknown(key, CONFIG_KEYS))
+        .map(|key| {
+            ValidationError::new(
+                format!("/{}", escape_pointer(key)),
+                format!("Unknown top-level property '{}'", key),
+            )
+        })
+        .collect();
+
+    if let Some(flags) = config.get("flags").and_then(Value::as_object) {
+        for (flag_key, flag) in flags {
+            let Some(flag) = flag.as_object() else {
+                continue;
+            };
+            errors.extend(
+                flag.keys()
+                    .filter(|key| is_unknown(key, FLAG_KEYS))
+                    .map(|key| {
+                        ValidationError::new(
+                            format!(
+                                "/flags/{}/{}",
+                                escape_pointer(flag_key),
+                                escape_pointer(key)
+                            ),
+                            format!("Unknown property '{}' in flag '{}'", key, flag_key),
+                        )
+                    }),
+            );
+        }
+    }
+    errors
+}
+
+/// Checks that literal fractional bucket names in each flag's targeting are variants of that flag,
+/// and that literal weights do not add up to more than `MAX_TOTAL_WEIGHT`.
+///
+/// A name listed in the flag's `variantAliases` counts as a variant.
+/// Otherwise the mistake only surfaces when a user happens to land in the bucket.
+/// Both the `[name, weight]` bucket format and the flat `[name, weight, ...]` format
+/// are checked. Computed bucket names and rules in `$evaluators` (which may be shared
+/// by flags with different variants) are skipped.
+fn validate_fractional_buckets(config: &Value) -> Vec<ValidationError> {
+    let mut errors = Vec::new();
+    let Some(flags) = config.get("flags").and_then(Value::as_object) else {
+        return errors;
+    };
+
+    for (flag_key, flag) in flags {
+        let (Some(variants), Some(targeting)) = (
+            flag.get("variants").and_then(Value::as_object),
+            flag.get("targeting"),
+        ) else {
+            continue;
+        };
+        let aliases = flag.get("variantAliases").and_then(Value::as_object);
+        let path = format!("/flags/{}/targeting", escape_pointer(flag_key));
+        check_fractional_buckets(targeting, &path, flag_key, variants, aliases, &mut errors);
+    }
+
+    errors
+}
+
+/// Walks a targeting rule, reporting fractional bucket names missing from `variants`.
+fn check_fractional_buckets(
+    rule: &Value,
+    path: &str,
+    flag_key: &str,
+    variants: &serde_json::Map<String, Value>,
+    aliases: Option<&serde_json::Map<String, Value>>,
+    errors: &mut Vec<ValidationError>,
+) {
+    match rule {
+        Value::Object(obj) => {
+            if let Some(Value::Array(args)) = obj.get("fractional") {
+                let mut total_weight = 0.0;
+                for (i, bucket_list) in args.iter().enumerate() {
+                    let Value::Array(bucket_list) = bucket_list else {
+                        continue;
+                    };
+                    // [name], [name, weight], [name, weight, name, weight, ...] and
+                    // bare names splitting evenly, [name, name, ...]
+                    let step = if bucket_list.len() > 1 && bucket_list.iter().all(Value::is_string)
+                    {
+                        1
+                    } else {
+                        2
+                    };
+                    for (j, pair) in bucket_list.chunks(step).enumerate() {
+                        total_weight += pair.get(1).and_then(Value::as_f64).unwrap_or(0.0);
+                        if let Value::String(name) = &pair[0] {
+                            if !variants.contains_key(name)
+                                && !aliases.is_some_and(|aliases| aliases.contains_key(name))
+                            {
+                                errors.push(ValidationError::new(
+                                    format!("{}/fractional/{}/{}", path, i, j * step),
+                                    format!(
+                                        "Fractional bucket '{}' is not a variant of flag '{}'",
+                                        name, flag_key
+                                    ),
+                                ));
+                            }
+                        }
+                    }
+                }
+                if total_weight > MAX_TOTAL_WEIGHT as f64 {
+                    errors.push(ValidationError::new(
+                        format!("{}/fractional", path),
+                        format!(
+                            "Fractional bucket weights add up to {}, more than the maximum of {}",
+                            total_weight, MAX_TOTAL_WEIGHT
+                        ),
+                    ));
+                }
+            }
+            for (key, value) in obj {
+                let child = format!("{}/{}", path, escape_pointer(key));
+                check_fractional_buckets(value, &child, flag_key, variants, aliases, errors);
+            }
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                let child = format!("{}/{}", path, i);
+                check_fractional_buckets(item, &child, flag_key, variants, aliases, errors);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Operators accepted in targeting rules with their minimum and maximum argument
+/// counts, mirroring `schemas/targeting.json`. A non-array argument counts as one.
+const TARGETING_OPERATORS: &[(&str, usize, Option<usize>)] = &[
+    ("var", 0, Some(2)),
+    ("missing", 0, None),
+    ("missing_some", 2, Some(2)),
+    ("if", 1, None),
+    ("==", 2, Some(2)),
+    ("===", 2, Some(2)),
+    ("!=", 2, Some(2)),
+    ("!==", 2, Some(2)),
+    (">", 2, Some(2)),
+    (">=", 2, Some(2)),
+    ("<", 2, Some(3)),
+    ("<=", 2, Some(3)),
+    ("!", 1, Some(1)),
+    ("!!", 1, Some(1)),
+    ("or", 1, None),
+    ("and", 1, None),
+    ("+", 1, None),
+    ("-", 1, None),
+    ("*", 2, None),
+    ("/", 2, Some(2)),
+    ("%", 2, Some(2)),
+    ("max", 1, None),
+    ("min", 1, None),
+    ("map", 2, Some(2)),
+    ("filter", 2, Some(2)),
+    ("reduce", 3, Some(3)),
+    ("all", 2, Some(2)),
+    ("none", 2, Some(2)),
+    ("some", 2, Some(2)),
+    ("merge", 1, None),
+    ("in", 2, Some(2)),
+    ("cat", 1, None),
+    ("substr", 2, Some(3)),
+    ("starts_with", 2, Some(2)),
+    ("ends_with", 2, Some(2)),
+    ("sem_ver", 3, Some(4)),
+    ("fractional", 1, None),
+    ("has", 1, Some(1)),
+    ("is_type", 2, Some(2)),
+    ("matches_named", 2, Some(2)),
+    ("date_before", 2, Some(2)),
+    ("date_after", 2, Some(2)),
+    ("date_eq", 2, Some(2)),
+];
+
+/// Returns whether `name` is an operator accepted in targeting rules.
+pub(crate) fn is_targeting_operator(name: &str) -> bool {
+    TARGETING_OPERATORS
+        .iter()
+        .any(|(operator, _, _)| *operator == name)
+}
+
+/// Validates a single targeting rule, independent of any flag.
+///
+/// Intended for config editors checking a snippet as it is typed. Reports unknown
+/// operators, operators called with the wrong number of arguments, and `$ref`s
+/// that do not name an evaluator in `evaluators` (the `$evaluators` object, if
+/// any). Error paths are JSON pointers relative to the rule. An empty object is a
+/// valid rule, meaning no targeting.
+///
+/// # Example
+///
+/// ```
+/// use flagd_evaluator::validate_targeting;
+/// use serde_json::json;
+///
+/// let rule = json!({"if": [{"==": [{"var": "tier"}, "gold"]}, "on", "off"]});
+/// assert!(validate_targeting(&rule, None).valid);
+///
+/// let rule = json!({"if": [{"equals": [{"var": "tier"}, "gold"]}, "on", "off"]});
+/// assert!(!validate_targeting(&rule, None).valid);
+/// ```
+pub fn validate_targeting(rule: &Value, evaluators: Option<&Value>) -> ValidationResult {
+    if rule.as_object().is_some_and(|obj| obj.is_empty()) {
+        return ValidationResult::success();
+    }
+
+    let evaluators = evaluators.and_then(Value::as_object);
+    let mut errors = Vec::new();
+    check_targeting_rule(rule, "", evaluators, &mut errors);
+    if errors.is_empty() {
+        ValidationResult::success()
+    } else {
+        ValidationResult::failure(errors)
+    }
+}
+
+/// Walks a targeting rule, reporting unknown operators, bad arity and unresolved `$ref`s.
+fn check_targeting_rule(
+    rule: &Value,
+    path: &str,
+    evaluators: Option<&serde_json::Map<String, Value>>,
+    errors: &mut Vec<ValidationError>,
+) {
+    match rule {
+        Value::Object(obj) => {
+            let mut entries = obj.iter();
+            let (Some((operator, args)), None) = (entries.next(), entries.next()) else {
+                errors.push(ValidationError::new(
+                    path,
+                    format!("A rule must have exactly one operator, found {}", obj.len()),
+                ));
+                return;
+            };
+
+            let child = format!("{}/{}", path, escape_pointer(operator));
+            if operator == "$ref" {
+                match args.as_str() {
+                    Some(name) if evaluators.is_some_and(|e| e.contains_key(name)) => {}
+                    Some(name) => errors.push(ValidationError::new(
+                        child,
+                        format!("Evaluator '{}' not found in $evaluators", name),
+                    )),
+                    None => errors.push(ValidationError::new(child, "$ref must name an evaluator")),
+                }
+                return;
+            }
+
+            let Some(&(_, min, max)) = TARGETING_OPERATORS
+                .iter()
+                .find(|(name, _, _)| *name == operator.as_str())
+            else {
+                errors.push(ValidationError::new(
+                    path,
+                    format!("Unknown operator '{}'", operator),
+                ));
+                return;
+            };
+
+            let count = args.as_array().map_or(1, Vec::len);
+            if count < min || max.is_some_and(|max| count > max) {
+                let expected = match max {
+                    Some(max) if max == min => format!("{}", min),
+                    Some(max) => format!("{} to {}", min, max),
+                    None => format!("at least {}", min),
+                };
+                errors.push(ValidationError::new(
+                    child.clone(),
+                    format!(
+                        "Operator '{}' expects {} arguments, got {}",
+                        operator, expected, count
+                    ),
+                ));
+            }
+            check_targeting_rule(args, &child, evaluators, errors);
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                check_targeting_rule(item, &format!("{}/{}", path, i), evaluators, errors);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Escapes a key for use as a JSON pointer segment (RFC 6901).
+pub(crate) fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -477,6 +853,128 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_fractional_bucket_must_be_variant() {
+        let config = r#"{
+            "flags": {
+                "splitFlag": {
+                    "state": "ENABLED",
+                    "variants": {"control": "c", "treatment": "t"},
+                    "defaultVariant": "control",
+                    "targeting": {
+                        "if": [
+                            {"==": [{"var": "beta"}, true]},
+                            {"fractional": [{"var": "id"}, ["control", 50], ["treatmnet", 50]]},
+                            "control"
+                        ]
+                    }
+                }
+            }
+        }"#;
+
+        let result = validate_flags_config(config).unwrap_err();
+        assert_eq!(
+            result.errors,
+            vec![ValidationError::new(
+                "/flags/splitFlag/targeting/if/1/fractional/2/0",
+                "Fractional bucket 'treatmnet' is not a variant of flag 'splitFlag'"
+            )]
+        );
+        assert!(validate_flags_config(&config.replace("treatmnet", "treatment")).is_ok());
+
+        // The flat bucket format is accepted by the evaluator, though not by the schema
+        let flat = serde_json::json!({
+            "flags": {
+                "flatFlag": {
+                    "variants": {"a": 1, "b": 2},
+                    "targeting": {"fractional": ["key", ["a", 50, "bogus", 50]]}
+                }
+            }
+        });
+        assert_eq!(
+            validate_fractional_buckets(&flat),
+            vec![ValidationError::new(
+                "/flags/flatFlag/targeting/fractional/1/2",
+                "Fractional bucket 'bogus' is not a variant of flag 'flatFlag'"
+            )]
+        );
+
+        // Every bare name of an even split is a bucket
+        let bare = serde_json::json!({
+            "flags": {
+                "bareFlag": {
+                    "variants": {"a": 1, "b": 2},
+                    "targeting": {"fractional": ["key", ["a", "bogus", "b"]]}
+                }
+            }
+        });
+        assert_eq!(
+            validate_fractional_buckets(&bare),
+            vec![ValidationError::new(
+                "/flags/bareFlag/targeting/fractional/1/1",
+                "Fractional bucket 'bogus' is not a variant of flag 'bareFlag'"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_fractional_weights_are_capped() {
+        let config = |weight: f64| {
+            serde_json::json!({
+                "flags": {
+                    "splitFlag": {
+                        "state": "ENABLED",
+                        "variants": {"a": 1, "b": 2},
+                        "defaultVariant": "a",
+                        "targeting": {"fractional": [["a", 1], ["b", weight]]}
+                    }
+                }
+            })
+            .to_string()
+        };
+
+        let at_cap = MAX_TOTAL_WEIGHT as f64 - 1.0;
+        assert!(validate_flags_config(&config(at_cap)).is_ok());
+        let result = validate_flags_config(&config(at_cap + 1.0)).unwrap_err();
+        assert_eq!(
+            result.errors,
+            vec![ValidationError::new(
+                "/flags/splitFlag/targeting/fractional",
+                "Fractional bucket weights add up to 1000000001, more than the maximum of 1000000000"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_find_unknown_keys() {
+        let config = r#"{
+            "$schema": "https://flagd.dev/schema/v0/flags.json",
+            "flag": {},
+            "flags": {
+                "myFlag": {
+                    "$comment": "extensions are allowed",
+                    "state": "ENABLED",
+                    "variants": {"on": true, "off": false},
+                    "defaultVarient": "on"
+                }
+            }
+        }"#;
+
+        // The schema itself lets both typos through
+        assert!(validate_flags_config(config).is_ok());
+        assert_eq!(
+            find_unknown_keys(config),
+            vec![
+                ValidationError::new("/flag", "Unknown top-level property 'flag'"),
+                ValidationError::new(
+                    "/flags/myFlag/defaultVarient",
+                    "Unknown property 'defaultVarient' in flag 'myFlag'"
+                ),
+            ]
+        );
+        assert!(find_unknown_keys("not json").is_empty());
+    }
+
     #[test]
     fn test_validation_result_serialization() {
         let result = ValidationResult::success();
@@ -492,4 +990,66 @@ mod tests {
         assert!(json.contains("\"valid\":false"));
         assert!(json.contains("Missing required field"));
     }
+
+    #[test]
+    fn test_validation_result_round_trip() {
+        let result = ValidationResult::failure(vec![
+            ValidationError::new(
+                "/flags/myFlag/state",
+                "\"ON\" is not one of the allowed values",
+            ),
+            ValidationError::new("/flags/myFlag", "missing property 'variants'"),
+        ]);
+
+        let parsed = ValidationResult::from_json_string(&result.to_json_string()).unwrap();
+        assert_eq!(parsed, result);
+
+        assert!(ValidationResult::from_json_string("{not json").is_err());
+    }
+
+    #[test]
+    fn test_validate_targeting() {
+        use serde_json::json;
+
+        let evaluators = json!({"isStaff": {"ends_with": [{"var": "email"}, "@example.com"]}});
+
+        let valid = json!({
+            "if": [
+                {"and": [{"$ref": "isStaff"}, {"sem_ver": [{"var": "version"}, ">=", "1.2.0"]}]},
+                {"fractional": [["on", 50], ["off", 50]]},
+                "off"
+            ]
+        });
+        assert_eq!(
+            validate_targeting(&valid, Some(&evaluators)),
+            ValidationResult::success()
+        );
+        assert!(validate_targeting(&json!({}), None).valid);
+        assert!(validate_targeting(&json!({"!": {"var": "beta"}}), None).valid);
+
+        let unknown = json!({"if": [{"equals": [{"var": "tier"}, "gold"]}, "on", "off"]});
+        assert_eq!(
+            validate_targeting(&unknown, None).errors,
+            vec![ValidationError::new("/if/0", "Unknown operator 'equals'")]
+        );
+
+        let unresolved = json!({"if": [{"$ref": "isAdmin"}, "on", "off"]});
+        assert_eq!(
+            validate_targeting(&unresolved, Some(&evaluators)).errors,
+            vec![ValidationError::new(
+                "/if/0/$ref",
+                "Evaluator 'isAdmin' not found in $evaluators"
+            )]
+        );
+        assert!(!validate_targeting(&json!({"$ref": "isStaff"}), None).valid);
+
+        let arity = json!({"==": [{"var": "tier"}]});
+        assert_eq!(
+            validate_targeting(&arity, None).errors,
+            vec![ValidationError::new(
+                "/==",
+                "Operator '==' expects 2 arguments, got 1"
+            )]
+        );
+    }
 }
@@ -0,0 +1,252 @@
+//! File watching adapter for native embedders.
+//!
+//! This module provides a `FileWatcher` that watches a flag configuration file
+//! on disk and calls `FlagEvaluator::update_state` whenever it changes. It is
+//! only available on native targets with the `notify` feature enabled and is
+//! never part of the WASM build.
+
+use crate::evaluator::FlagEvaluator;
+use crate::model::{UpdateErrorCode, UpdateStateResponse};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Default quiet period used to coalesce bursts of writes into a single reload.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Watches a flag configuration file and reloads it into a shared evaluator on change.
+///
+/// The parent directory is watched (rather than the file itself) so that editors and
+/// deployment tools that replace the file atomically via rename are still picked up.
+/// Rapid successive writes are debounced: a reload only happens once no further
+/// events have arrived for the configured debounce period.
+///
+/// The callback receives the `UpdateStateResponse` of every reload, including failed
+/// ones (e.g. unreadable file or invalid configuration), so the embedder can emit
+/// PROVIDER_CONFIGURATION_CHANGED or error events as appropriate.
+///
+/// Dropping the `FileWatcher` stops watching and joins the background thread.
+///
+/// # Example
+///
+/// ```no_run
+/// use flagd_evaluator::watcher::{FileWatcher, DEFAULT_DEBOUNCE};
+/// use flagd_evaluator::{FlagEvaluator, ValidationMode};
+/// use std::sync::{Arc, Mutex};
+///
+/// let evaluator = Arc::new(Mutex::new(FlagEvaluator::new(ValidationMode::Strict)));
+/// let _watcher = FileWatcher::watch("flags.json", evaluator.clone(), DEFAULT_DEBOUNCE, |response| {
+///     println!("changed flags: {:?}", response.changed_flags);
+/// })
+/// .unwrap();
+/// ```
+pub struct FileWatcher {
+    watcher: Option<RecommendedWatcher>,
+    handle: Option<JoinHandle<()>>,
+    path: PathBuf,
+}
+
+impl FileWatcher {
+    /// Starts watching `path` and reloading it into `evaluator` on change.
+    ///
+    /// The file is not loaded initially; call `update_state` on the evaluator first
+    /// if the current contents should be applied before the first change.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the flag configuration file
+    /// * `evaluator` - The evaluator to update
+    /// * `debounce` - Quiet period to wait for before reloading after a change
+    /// * `callback` - Invoked with the response of every reload
+    ///
+    /// # Returns
+    /// * `Ok(FileWatcher)` - If watching started successfully
+    /// * `Err(String)` - If the path is invalid or the watcher could not be created
+    pub fn watch<P, F>(
+        path: P,
+        evaluator: Arc<Mutex<FlagEvaluator>>,
+        debounce: Duration,
+        mut callback: F,
+    ) -> Result<Self, String>
+    where
+        P: AsRef<Path>,
+        F: FnMut(UpdateStateResponse) + Send + 'static,
+    {
+        let path = path.as_ref().to_path_buf();
+        let file_name: OsString = path
+            .file_name()
+            .ok_or_else(|| format!("Invalid config path: {}", path.display()))?
+            .to_os_string();
+        let parent = match path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+
+        let (tx, rx) = mpsc::channel::<()>();
+
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            let Ok(event) = res else {
+                return;
+            };
+            let relevant_kind = matches!(
+                event.kind,
+                EventKind::Create(_) | EventKind::Modify(_) | EventKind::Any
+            );
+            let relevant_path = event
+                .paths
+                .iter()
+                .any(|p| p.file_name() == Some(file_name.as_os_str()));
+            if relevant_kind && relevant_path {
+                // The receiver only goes away when the watcher is shutting down
+                let _ = tx.send(());
+            }
+        })
+        .map_err(|e| format!("Failed to create file watcher: {}", e))?;
+
+        watcher
+            .watch(&parent, RecursiveMode::NonRecursive)
+            .map_err(|e| format!("Failed to watch '{}': {}", parent.display(), e))?;
+
+        let reload_path = path.clone();
+        let handle = std::thread::spawn(move || {
+            while rx.recv().is_ok() {
+                // Coalesce bursts of writes into a single reload
+                loop {
+                    match rx.recv_timeout(debounce) {
+                        Ok(()) => continue,
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+
+                callback(Self::reload(&reload_path, &evaluator));
+            }
+        });
+
+        Ok(Self {
+            watcher: Some(watcher),
+            handle: Some(handle),
+            path,
+        })
+    }
+
+    /// Returns the path of the watched configuration file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reads the configuration file and applies it to the evaluator.
+    fn reload(path: &Path, evaluator: &Mutex<FlagEvaluator>) -> UpdateStateResponse {
+        let config = match std::fs::read_to_string(path) {
+            Ok(config) => config,
+            Err(e) => {
+                return UpdateStateResponse::failure(
+                    UpdateErrorCode::InvalidInput,
+                    format!("Failed to read configuration '{}': {}", path.display(), e),
+                )
+            }
+        };
+
+        let mut guard = match evaluator.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        guard
+            .update_state(&config)
+            .unwrap_or_else(|e| UpdateStateResponse::failure(UpdateErrorCode::SemanticInvalid, e))
+    }
+}
+
+impl Drop for FileWatcher {
+    fn drop(&mut self) {
+        // Dropping the watcher drops the event sender, which ends the reload loop
+        self.watcher.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl std::fmt::Debug for FileWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileWatcher")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::ValidationMode;
+
+    fn config_with_flags(flags: &[&str]) -> String {
+        let flags: Vec<String> = flags
+            .iter()
+            .map(|key| {
+                format!(
+                    r#""{}": {{"state": "ENABLED", "variants": {{"on": true, "off": false}}, "defaultVariant": "on"}}"#,
+                    key
+                )
+            })
+            .collect();
+        format!(r#"{{"flags": {{{}}}}}"#, flags.join(","))
+    }
+
+    /// Waits for a reload that reports exactly the expected changed flags.
+    fn wait_for_changes(rx: &mpsc::Receiver<UpdateStateResponse>, expected: &[&str]) {
+        let expected: Vec<String> = expected.iter().map(|s| s.to_string()).collect();
+        let deadline = std::time::Instant::now() + Duration::from_secs(10);
+        while let Some(remaining) = deadline.checked_duration_since(std::time::Instant::now()) {
+            let response = rx
+                .recv_timeout(remaining)
+                .expect("watcher callback did not fire");
+            if response.changed_flags.as_ref() == Some(&expected) {
+                return;
+            }
+        }
+        panic!("did not observe changed flags {:?}", expected);
+    }
+
+    #[test]
+    fn test_file_watcher_reloads_on_change() {
+        let dir =
+            std::env::temp_dir().join(format!("flagd-evaluator-watcher-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("flags.json");
+        std::fs::write(&path, config_with_flags(&["flagA"])).unwrap();
+
+        let evaluator = Arc::new(Mutex::new(FlagEvaluator::new(ValidationMode::Strict)));
+        evaluator
+            .lock()
+            .unwrap()
+            .update_state(&config_with_flags(&["flagA"]))
+            .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let watcher = FileWatcher::watch(&path, evaluator.clone(), DEFAULT_DEBOUNCE, move |r| {
+            let _ = tx.send(r);
+        })
+        .unwrap();
+
+        std::fs::write(&path, config_with_flags(&["flagA", "flagB"])).unwrap();
+        wait_for_changes(&rx, &["flagB"]);
+
+        std::fs::write(&path, config_with_flags(&["flagB"])).unwrap();
+        wait_for_changes(&rx, &["flagA"]);
+
+        let state_has_b = evaluator
+            .lock()
+            .unwrap()
+            .get_state()
+            .map(|s| s.flags.contains_key("flagB") && !s.flags.contains_key("flagA"))
+            .unwrap_or(false);
+        assert!(state_has_b);
+
+        drop(watcher);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
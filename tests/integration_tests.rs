@@ -966,6 +966,218 @@ fn test_fractional_unequal_weights() {
     assert!(b_count > 0, "Expected some 'b' variants, got {}", b_count);
 }
 
+#[test]
+fn test_fractional_decimal_weights() {
+    use flagd_evaluator::ResolutionReason;
+    use serde_json::json;
+
+    // Percentages that sum to 100 without being whole numbers
+    let config = r#"{
+        "flags": {
+            "decimalSplit": {
+                "state": "ENABLED",
+                "defaultVariant": "a",
+                "variants": {"a": "variant-a", "b": "variant-b"},
+                "targeting": {
+                    "fractional": [["a", 33.3], ["b", 66.7]]
+                }
+            }
+        }
+    }"#;
+
+    let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+    let response = evaluator
+        .update_state(config)
+        .expect("state should be updated");
+    assert!(response.success, "{:?}", response.error);
+    let mut other = FlagEvaluator::new(ValidationMode::Strict);
+    let response = other.update_state(config).expect("state should be updated");
+    assert!(response.success, "{:?}", response.error);
+
+    let mut b_count = 0;
+    for i in 0..300 {
+        let context = json!({"targetingKey": format!("test-user-{}", i)});
+        let result = evaluator.evaluate_flag("decimalSplit", context.clone());
+        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+        // Assignment is deterministic across evaluator instances
+        assert_eq!(
+            result.variant,
+            other.evaluate_flag("decimalSplit", context).variant
+        );
+        if result.variant.as_deref() == Some("b") {
+            b_count += 1;
+        }
+    }
+
+    // Roughly two thirds of the users land in "b"
+    assert!(
+        (150..250).contains(&b_count),
+        "Expected about 200 'b' variants, got {}",
+        b_count
+    );
+}
+
+#[test]
+fn test_fractional_even_split_without_weights() {
+    use flagd_evaluator::ResolutionReason;
+    use serde_json::json;
+
+    // Bare bucket names pass strict schema validation and split evenly
+    let config = r#"{
+        "flags": {
+            "evenSplit": {
+                "state": "ENABLED",
+                "defaultVariant": "a",
+                "variants": {"a": "variant-a", "b": "variant-b", "c": "variant-c"},
+                "targeting": {
+                    "fractional": [{"var": "targetingKey"}, ["a", "b", "c"]]
+                }
+            }
+        }
+    }"#;
+
+    let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+    let response = evaluator
+        .update_state(config)
+        .expect("state should be updated");
+    assert!(response.success, "{:?}", response.error);
+
+    let mut counts = std::collections::HashMap::new();
+    for i in 0..300 {
+        let context = json!({"targetingKey": format!("test-user-{}", i)});
+        let result = evaluator.evaluate_flag("evenSplit", context);
+        assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+        *counts.entry(result.variant.unwrap()).or_insert(0) += 1;
+    }
+
+    // Roughly a third of the users land in each bucket
+    for variant in ["a", "b", "c"] {
+        let count = counts.get(variant).copied().unwrap_or(0);
+        assert!(
+            (60..140).contains(&count),
+            "Expected about 100 '{}' variants, got {}",
+            variant,
+            count
+        );
+    }
+
+    // A bare-name list that names an unknown variant is still rejected
+    let invalid = config.replace(r#"["a", "b", "c"]"#, r#"["a", "b", "z"]"#);
+    let response = FlagEvaluator::new(ValidationMode::Strict)
+        .update_state(&invalid)
+        .expect("update should return a response");
+    assert!(!response.success);
+}
+
+#[test]
+fn test_fractional_flags_bucket_independently() {
+    use serde_json::json;
+
+    // Two experiments with the same split; only the second pair shares an explicit key
+    let config = r#"{
+        "flags": {
+            "experimentA": {
+                "state": "ENABLED",
+                "defaultVariant": "control",
+                "variants": {"control": "control", "treatment": "treatment"},
+                "targeting": {"fractional": [["control", 50], ["treatment", 50]]}
+            },
+            "experimentB": {
+                "state": "ENABLED",
+                "defaultVariant": "control",
+                "variants": {"control": "control", "treatment": "treatment"},
+                "targeting": {"fractional": [["control", 50], ["treatment", 50]]}
+            },
+            "sharedA": {
+                "state": "ENABLED",
+                "defaultVariant": "control",
+                "variants": {"control": "control", "treatment": "treatment"},
+                "targeting": {"fractional": [{"var": "targetingKey"}, ["control", 50], ["treatment", 50]]}
+            },
+            "sharedB": {
+                "state": "ENABLED",
+                "defaultVariant": "control",
+                "variants": {"control": "control", "treatment": "treatment"},
+                "targeting": {"fractional": [{"var": "targetingKey"}, ["control", 50], ["treatment", 50]]}
+            }
+        }
+    }"#;
+
+    let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+    evaluator
+        .update_state(config)
+        .expect("state should be updated");
+
+    let mut same = 0;
+    for i in 0..1000 {
+        let context = json!({"targetingKey": format!("user-{}", i)});
+        let variant = |flag: &str| evaluator.evaluate_flag(flag, context.clone()).variant;
+        if variant("experimentA") == variant("experimentB") {
+            same += 1;
+        }
+        // An explicit bucketing key replaces the flag key salt
+        assert_eq!(variant("sharedA"), variant("sharedB"));
+    }
+
+    // By default the flag key salts the hash, so the assignments agree only by chance
+    assert!(
+        (400..600).contains(&same),
+        "Expected about 500 matching assignments, got {}",
+        same
+    );
+}
+
+#[test]
+fn test_bucket_of_matches_fractional_evaluation() {
+    use flagd_evaluator::operators::bucket_of;
+    use serde_json::json;
+
+    let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+    let config = r#"{
+        "flags": {
+            "rollout": {
+                "state": "ENABLED",
+                "defaultVariant": "off",
+                "variants": {"a": "variant-a", "b": "variant-b", "c": "variant-c"},
+                "targeting": {
+                    "fractional": [
+                        ["a", 20],
+                        ["b", 30],
+                        ["c", 50]
+                    ]
+                }
+            }
+        }
+    }"#;
+
+    evaluator
+        .update_state(config)
+        .expect("state should be updated");
+
+    let buckets = vec![
+        json!("a"),
+        json!(20),
+        json!("b"),
+        json!(30),
+        json!("c"),
+        json!(50),
+    ];
+    for i in 0..50 {
+        let targeting_key = format!("user-{}", i);
+        let (variant, percentile) = bucket_of(&targeting_key, "rollout", &buckets).unwrap();
+        assert!((0.0..=100.0).contains(&percentile));
+
+        let result = evaluator.evaluate_flag("rollout", json!({"targetingKey": targeting_key}));
+        assert_eq!(
+            result.variant.as_deref(),
+            Some(variant.as_str()),
+            "bucket_of disagrees with evaluation for {}",
+            targeting_key
+        );
+    }
+}
+
 #[test]
 fn test_unicode_flag_key() {
     use serde_json::json;
@@ -1195,6 +1407,402 @@ fn test_flag_removal_and_readd() {
     );
 }
 
+#[test]
+fn test_sem_ver_range_operator() {
+    use flagd_evaluator::ResolutionReason;
+    use serde_json::json;
+
+    let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+    let config = r#"{
+        "flags": {
+            "rangeFlag": {
+                "state": "ENABLED",
+                "defaultVariant": "outside",
+                "variants": {"inside": true, "outside": false},
+                "targeting": {
+                    "if": [
+                        {"sem_ver": [{"var": "appVersion"}, "in", "1.2.0", "2.0.0"]},
+                        "inside",
+                        "outside"
+                    ]
+                }
+            },
+            "badArity": {
+                "state": "ENABLED",
+                "defaultVariant": "outside",
+                "variants": {"inside": true, "outside": false},
+                "targeting": {
+                    "if": [
+                        {"sem_ver": [{"var": "appVersion"}, ">=", "1.2.0", "2.0.0"]},
+                        "inside",
+                        "outside"
+                    ]
+                }
+            }
+        }
+    }"#;
+
+    // The mismatched argument count is rejected by the schema in strict mode
+    let response = evaluator.update_state(config).unwrap();
+    assert!(!response.success);
+
+    evaluator.set_validation_mode(ValidationMode::Permissive);
+    evaluator
+        .update_state(config)
+        .expect("state should be updated");
+
+    let test_cases = vec![
+        ("1.1.9", "outside"),
+        ("1.2.0", "inside"), // Lower bound is inclusive
+        ("1.9.9", "inside"),
+        ("2.0.0", "outside"), // Upper bound is exclusive
+        ("3.0.0", "outside"),
+    ];
+
+    for (version, expected) in test_cases {
+        let result = evaluator.evaluate_flag("rangeFlag", json!({"appVersion": version}));
+        assert_eq!(
+            result.variant,
+            Some(expected.to_string()),
+            "Version {} should map to variant {}",
+            version,
+            expected
+        );
+    }
+
+    // A comparison operator with an extra bound is an evaluation error
+    let result = evaluator.evaluate_flag("badArity", json!({"appVersion": "1.5.0"}));
+    assert_eq!(result.reason, ResolutionReason::Error);
+}
+
+#[test]
+fn test_fractional_with_computed_bucketing_key() {
+    use flagd_evaluator::operators::bucket_of;
+    use serde_json::json;
+
+    let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+    let config = r#"{
+        "flags": {
+            "orgRollout": {
+                "state": "ENABLED",
+                "defaultVariant": "a",
+                "variants": {"a": "variant-a", "b": "variant-b"},
+                "targeting": {
+                    "fractional": [
+                        {"cat": [{"var": "org"}, {"var": "user"}]},
+                        ["a", 50],
+                        ["b", 50]
+                    ]
+                }
+            }
+        }
+    }"#;
+
+    evaluator
+        .update_state(config)
+        .expect("state should be updated");
+
+    let buckets = vec![json!("a"), json!(50), json!("b"), json!(50)];
+    let mut seen = std::collections::HashSet::new();
+    for org in ["acme", "globex"] {
+        for i in 0..20 {
+            let user = format!("user-{}", i);
+            let context = json!({"org": org, "user": user});
+
+            // Bucketing uses the concatenation as the key and is stable across calls
+            let (expected, _) = bucket_of(&format!("{}{}", org, user), "", &buckets).unwrap();
+            for _ in 0..3 {
+                let result = evaluator.evaluate_flag("orgRollout", context.clone());
+                assert_eq!(result.variant.as_deref(), Some(expected.as_str()));
+            }
+            seen.insert(expected);
+        }
+    }
+    assert_eq!(seen.len(), 2, "both buckets should be reachable");
+}
+
+#[test]
+fn test_sem_ver_wildcard_target() {
+    use serde_json::json;
+
+    let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+    // Wildcard targets pass strict schema validation
+    let config = r#"{
+        "flags": {
+            "wildcardFlag": {
+                "state": "ENABLED",
+                "defaultVariant": "outside",
+                "variants": {"inside": true, "outside": false},
+                "targeting": {
+                    "if": [
+                        {"sem_ver": [{"var": "appVersion"}, "=", "1.2.x"]},
+                        "inside",
+                        "outside"
+                    ]
+                }
+            }
+        }
+    }"#;
+    let response = evaluator.update_state(config).unwrap();
+    assert!(response.success, "{:?}", response.error);
+
+    let test_cases = vec![
+        ("1.1.9", "outside"),
+        ("1.2.0", "inside"),
+        ("1.2.17", "inside"),
+        ("1.3.0-alpha", "outside"),
+        ("1.3.0", "outside"),
+    ];
+
+    for (version, expected) in test_cases {
+        let result = evaluator.evaluate_flag("wildcardFlag", json!({"appVersion": version}));
+        assert_eq!(
+            result.variant,
+            Some(expected.to_string()),
+            "Version {} should map to variant {}",
+            version,
+            expected
+        );
+    }
+}
+
+#[test]
+fn test_sem_ver_with_computed_version() {
+    use serde_json::json;
+
+    let mut evaluator = FlagEvaluator::new(ValidationMode::Permissive);
+
+    let config = r#"{
+        "flags": {
+            "majorFlag": {
+                "state": "ENABLED",
+                "defaultVariant": "off",
+                "variants": {"on": true, "off": false},
+                "targeting": {
+                    "if": [
+                        {"sem_ver": [{"cat": [{"var": "major"}, ".0.0"]}, ">=", "2.0.0"]},
+                        "on",
+                        "off"
+                    ]
+                }
+            }
+        }
+    }"#;
+
+    evaluator
+        .update_state(config)
+        .expect("state should be updated");
+
+    let result = evaluator.evaluate_flag("majorFlag", json!({"major": 3}));
+    assert_eq!(result.value, json!(true));
+    let result = evaluator.evaluate_flag("majorFlag", json!({"major": 1}));
+    assert_eq!(result.value, json!(false));
+}
+
+#[test]
+fn test_has_and_is_type_operators() {
+    use flagd_evaluator::ResolutionReason;
+    use serde_json::json;
+
+    let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+    let config = r#"{
+        "flags": {
+            "planFlag": {
+                "state": "ENABLED",
+                "defaultVariant": "off",
+                "variants": {"on": true, "off": false},
+                "targeting": {"if": [{"has": [{"var": "account.plan"}]}, "on", "off"]}
+            },
+            "ageFlag": {
+                "state": "ENABLED",
+                "defaultVariant": "off",
+                "variants": {"on": true, "off": false},
+                "targeting": {"if": [{"is_type": [{"var": "age"}, "number"]}, "on", "off"]}
+            }
+        }
+    }"#;
+
+    let response = evaluator.update_state(config).unwrap();
+    assert!(response.success, "{:?}", response.error);
+
+    let has_plan = |context| evaluator.evaluate_flag("planFlag", context).value;
+    assert_eq!(has_plan(json!({"account": {"plan": "pro"}})), json!(true));
+    assert_eq!(has_plan(json!({"account": {"plan": ""}})), json!(true));
+    assert_eq!(has_plan(json!({"account": {}})), json!(false));
+    assert_eq!(has_plan(json!({"account": {"plan": null}})), json!(false));
+    assert_eq!(has_plan(json!({})), json!(false));
+
+    let result = evaluator.evaluate_flag("ageFlag", json!({"age": 42}));
+    assert_eq!(result.value, json!(true));
+    assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+    let result = evaluator.evaluate_flag("ageFlag", json!({"age": "42"}));
+    assert_eq!(result.value, json!(false));
+    let result = evaluator.evaluate_flag("ageFlag", json!({}));
+    assert_eq!(result.value, json!(false));
+
+    // Unknown type names are rejected by the schema
+    let config = r#"{
+        "flags": {
+            "badType": {
+                "state": "ENABLED",
+                "defaultVariant": "off",
+                "variants": {"on": true, "off": false},
+                "targeting": {"if": [{"is_type": [{"var": "age"}, "integer"]}, "on", "off"]}
+            }
+        }
+    }"#;
+    assert!(!evaluator.update_state(config).unwrap().success);
+}
+
+#[test]
+fn test_matches_named_operator() {
+    use flagd_evaluator::ResolutionReason;
+    use serde_json::json;
+
+    let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+    let config = r#"{
+        "patterns": {
+            "corpEmailPattern": "@(corp|staff)\\.example\\.com$"
+        },
+        "flags": {
+            "internalFlag": {
+                "state": "ENABLED",
+                "defaultVariant": "off",
+                "variants": {"on": true, "off": false},
+                "targeting": {"if": [{"matches_named": [{"var": "email"}, "corpEmailPattern"]}, "on", "off"]}
+            }
+        }
+    }"#;
+
+    let response = evaluator.update_state(config).unwrap();
+    assert!(response.success, "{:?}", response.error);
+
+    let result = evaluator.evaluate_flag("internalFlag", json!({"email": "ada@corp.example.com"}));
+    assert_eq!(result.value, json!(true));
+    assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+
+    let is_internal = |context| evaluator.evaluate_flag("internalFlag", context).value;
+    assert_eq!(
+        is_internal(json!({"email": "ada@staff.example.com"})),
+        json!(true)
+    );
+    assert_eq!(
+        is_internal(json!({"email": "ada@example.com"})),
+        json!(false)
+    );
+    assert_eq!(
+        is_internal(json!({"email": "ada@corp.example.com.evil"})),
+        json!(false)
+    );
+    assert_eq!(is_internal(json!({"email": 42})), json!(false));
+    assert_eq!(is_internal(json!({})), json!(false));
+
+    // Changing a pattern changes the flags that use it
+    let changed = config.replace("(corp|staff)", "corp");
+    let response = evaluator.update_state(&changed).unwrap();
+    assert_eq!(
+        response.changed_flags,
+        Some(vec!["internalFlag".to_string()])
+    );
+    let result = evaluator.evaluate_flag("internalFlag", json!({"email": "ada@staff.example.com"}));
+    assert_eq!(result.value, json!(false));
+
+    // Targeting may only name defined patterns
+    let undefined = config.replace(r#""corpEmailPattern"]"#, r#""partnerEmailPattern"]"#);
+    let response = evaluator.update_state(&undefined).unwrap();
+    assert!(!response.success);
+    assert!(response
+        .error
+        .unwrap()
+        .contains("Unknown pattern 'partnerEmailPattern' in flag 'internalFlag'"));
+
+    // Invalid regular expressions are rejected at load
+    let invalid = config.replace("(corp|staff)", "(corp|staff");
+    assert!(!evaluator.update_state(&invalid).unwrap().success);
+}
+
+#[test]
+fn test_date_comparison_operators() {
+    use flagd_evaluator::{ErrorCode, ResolutionReason};
+    use serde_json::json;
+
+    let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+    let config = r#"{
+        "flags": {
+            "earlyAdopter": {
+                "state": "ENABLED",
+                "defaultVariant": "off",
+                "variants": {"on": true, "off": false},
+                "targeting": {"if": [{"date_before": [{"var": "signedUpAt"}, "2024-03-01T12:00:00Z"]}, "on", "off"]}
+            },
+            "afterLaunch": {
+                "state": "ENABLED",
+                "defaultVariant": "off",
+                "variants": {"on": true, "off": false},
+                "targeting": {"if": [{"date_after": [{"var": "signedUpAt"}, "2024-03-01"]}, "on", "off"]}
+            },
+            "launchDay": {
+                "state": "ENABLED",
+                "defaultVariant": "off",
+                "variants": {"on": true, "off": false},
+                "targeting": {"if": [{"date_eq": [{"var": "signedUpAt"}, "2024-03-01"]}, "on", "off"]}
+            }
+        }
+    }"#;
+
+    let response = evaluator.update_state(config).unwrap();
+    assert!(response.success, "{:?}", response.error);
+
+    let evaluate = |flag: &str, signed_up_at| {
+        evaluator
+            .evaluate_flag(flag, json!({"signedUpAt": signed_up_at}))
+            .value
+    };
+
+    // 13:30 in Paris is 11:30 UTC, before noon UTC; 07:30 in New York is 12:30 UTC
+    assert_eq!(
+        evaluate("earlyAdopter", json!("2024-03-01T13:30:00+02:00")),
+        json!(true)
+    );
+    assert_eq!(
+        evaluate("earlyAdopter", json!("2024-03-01T07:30:00-05:00")),
+        json!(false)
+    );
+    // Unix timestamps compare as instants too (2024-03-01T11:00:00Z)
+    assert_eq!(evaluate("earlyAdopter", json!(1_709_290_800)), json!(true));
+
+    // A date-only operand compares by UTC calendar date
+    assert_eq!(
+        evaluate("launchDay", json!("2024-03-01T23:30:00Z")),
+        json!(true)
+    );
+    assert_eq!(
+        evaluate("afterLaunch", json!("2024-03-01T23:30:00Z")),
+        json!(false)
+    );
+    // 00:30 in Berlin on March 2nd is still March 1st in UTC
+    assert_eq!(
+        evaluate("afterLaunch", json!("2024-03-02T00:30:00+01:00")),
+        json!(false)
+    );
+    assert_eq!(evaluate("afterLaunch", json!("2024-03-02")), json!(true));
+
+    // Unparsable dates are an evaluation error, not a silent mismatch
+    let result = evaluator.evaluate_flag("launchDay", json!({"signedUpAt": "March 1st"}));
+    assert_eq!(result.reason, ResolutionReason::Error);
+    assert_eq!(result.error_code, Some(ErrorCode::ParseError));
+    assert!(result
+        .error_message
+        .unwrap()
+        .contains("Invalid ISO-8601 date: 'March 1st'"));
+}
+
 #[test]
 fn test_sem_ver_edge_cases() {
     use serde_json::json;
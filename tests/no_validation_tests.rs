@@ -0,0 +1,55 @@
+//! Tests for builds without the `validation` feature.
+//!
+//! Run with `cargo test --test no_validation_tests --no-default-features`. Without
+//! the feature, `update_state` skips JSON schema validation but still parses the
+//! config and runs the semantic checks.
+
+#![cfg(not(feature = "validation"))]
+
+use flagd_evaluator::{FlagEvaluator, ResolutionReason, ValidationMode};
+use serde_json::json;
+
+#[test]
+fn test_update_state_parses_valid_config() {
+    let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+    let config = r#"{
+        "flags": {
+            "myFlag": {
+                "state": "ENABLED",
+                "variants": {"on": true, "off": false},
+                "defaultVariant": "off",
+                "targeting": {"if": [{"==": [{"var": "tier"}, "gold"]}, "on"]}
+            }
+        }
+    }"#;
+
+    let response = evaluator.update_state(config).unwrap();
+    assert!(response.success);
+
+    let result = evaluator.evaluate_bool("myFlag", json!({"tier": "gold"}));
+    assert_eq!(result.value, json!(true));
+    assert_eq!(result.reason, ResolutionReason::TargetingMatch);
+}
+
+#[test]
+fn test_semantic_checks_still_run() {
+    let mut evaluator = FlagEvaluator::new(ValidationMode::Strict);
+
+    let config = r#"{
+        "flags": {
+            "splitFlag": {
+                "state": "ENABLED",
+                "variants": {"control": false, "treatment": true},
+                "defaultVariant": "control",
+                "targeting": {"fractional": [["control", 50], ["treatmnet", 50]]}
+            }
+        }
+    }"#;
+
+    let response = evaluator.update_state(config).unwrap();
+    assert!(!response.success);
+
+    let response = evaluator.update_state("{not json").unwrap();
+    assert!(!response.success);
+}